@@ -0,0 +1,393 @@
+use makepad_widgets::*;
+use makepad_map::*;
+
+live_design! {
+    use link::theme::*;
+    use link::shaders::*;
+    use link::widgets::*;
+
+    use makepad_map::map_view::GeoMapView;
+    use makepad_map::search_box::MapSearchBox;
+    use makepad_map::aggregation::DensityLayer;
+    use makepad_map::geojson::GeoJsonLayer;
+
+    ToolButton = <Button> {
+        height: Fit,
+        padding: { top: 4, bottom: 4, left: 8, right: 8 }
+    }
+
+    PlaceList = {{PlaceList}} {
+        width: 220.0,
+        height: Fill,
+        flow: Down,
+
+        places = <PortalList> {
+            width: Fill,
+            height: Fill,
+
+            Place = <View> {
+                width: Fill,
+                height: Fit,
+                padding: { top: 2, bottom: 2, left: 6, right: 6 }
+
+                name = <Button> {
+                    width: Fill,
+                    height: Fit,
+                }
+            }
+        }
+    }
+
+    App = {{App}} {
+        ui: <Root> {
+            main_window = <Window> {
+                window: { title: "Makepad Map Explorer" },
+                body = <View> {
+                    flow: Down,
+
+                    // Toolbar
+                    <View> {
+                        width: Fill, height: Fit,
+                        flow: Right,
+                        spacing: 8,
+                        padding: { top: 6, bottom: 6, left: 10, right: 10 },
+                        show_bg: true
+                        draw_bg: { color: #2196F3 }
+                        align: { y: 0.5 }
+
+                        <Label> {
+                            width: Fill, height: Fit
+                            draw_text: {
+                                text_style: { font_size: 16.0 }
+                                color: #ffffff
+                            }
+                            text: "Map Explorer"
+                        }
+                        world_btn = <ToolButton> { text: "World" }
+                        north_btn = <ToolButton> { text: "North up" }
+                        grid_btn = <ToolButton> { text: "UTM grid: off" }
+                        quality_btn = <ToolButton> { text: "Tiles: full" }
+                        base_btn = <ToolButton> { text: "Base: streets" }
+                        density_btn = <ToolButton> { text: "Sightings: clusters" }
+                        download_btn = <ToolButton> { text: "Download area" }
+                        clear_pin_btn = <ToolButton> { text: "Clear pin" }
+                    }
+
+                    <View> {
+                        width: Fill,
+                        height: Fill,
+                        flow: Right,
+
+                        place_list = <PlaceList> {}
+
+                        <View> {
+                            width: Fill,
+                            height: Fill,
+                            flow: Overlay,
+
+                            geo_map = <GeoMapView> {
+                                width: Fill,
+                                height: Fill,
+                                long_press_adds_marker: true,
+                                show_layer_control: true,
+                                layer_control_corner: TopRight,
+
+                                // Earthquakes of the past day, reloaded every five minutes
+                                quakes = <GeoJsonLayer> {
+                                    url: "https://earthquake.usgs.gov/earthquakes/feed/v1.0/summary/all_day.geojson"
+                                    refresh_interval: 300.0
+                                    point_radius: 4.0
+                                    draw_point: { color: #ff5722 }
+                                }
+
+                                sightings = <DensityLayer> {
+                                    mode: Clusters
+                                    cell_size: 50.0
+                                }
+                            }
+
+                            search_box = <MapSearchBox> {
+                                margin: { top: 10, left: 10 }
+                            }
+                        }
+                    }
+
+                    // Status bar
+                    <View> {
+                        width: Fill, height: Fit,
+                        padding: { top: 4, bottom: 4, left: 10, right: 10 },
+                        show_bg: true
+                        draw_bg: { color: #333333 }
+
+                        status_label = <Label> {
+                            width: Fill, height: Fit,
+                            text: "Select a place, tap the map, or long press to drop a pin"
+                            draw_text: {
+                                text_style: { font_size: 11.0 }
+                                color: #fff
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+app_main!(App);
+
+/// A place shown both in the list and as a marker
+struct Place {
+    name: &'static str,
+    lng: f64,
+    lat: f64,
+    color: Vec4,
+}
+
+const PLACES: &[Place] = &[
+    Place { name: "Golden Gate Bridge", lng: -122.4785, lat: 37.8199, color: vec4(0.9, 0.2, 0.2, 1.0) },
+    Place { name: "Statue of Liberty", lng: -74.0445, lat: 40.6892, color: vec4(0.2, 0.7, 0.5, 1.0) },
+    Place { name: "Eiffel Tower", lng: 2.2945, lat: 48.8584, color: vec4(0.2, 0.5, 0.9, 1.0) },
+    Place { name: "Brandenburg Gate", lng: 13.3777, lat: 52.5163, color: vec4(0.6, 0.3, 0.8, 1.0) },
+    Place { name: "Pyramids of Giza", lng: 31.1342, lat: 29.9792, color: vec4(0.9, 0.6, 0.1, 1.0) },
+    Place { name: "Taj Mahal", lng: 78.0421, lat: 27.1751, color: vec4(0.9, 0.3, 0.6, 1.0) },
+    Place { name: "Mount Fuji", lng: 138.7274, lat: 35.3606, color: vec4(0.3, 0.6, 0.9, 1.0) },
+    Place { name: "Sydney Opera House", lng: 151.2153, lat: -33.8568, color: vec4(0.2, 0.8, 0.3, 1.0) },
+    Place { name: "Christ the Redeemer", lng: -43.2105, lat: -22.9519, color: vec4(0.8, 0.2, 0.4, 1.0) },
+    Place { name: "Svalbard Seed Vault", lng: 15.4911, lat: 78.2357, color: vec4(0.4, 0.4, 0.4, 1.0) },
+];
+
+fn place_id(index: usize) -> LiveId {
+    LiveId::from_str(PLACES[index].name)
+}
+
+/// Ways the sightings layer is shown, cycled by the toolbar
+const DENSITY_MODES: &[(AggregationMode, &str)] = &[
+    (AggregationMode::Clusters, "Sightings: clusters"),
+    (AggregationMode::Hexbin, "Sightings: hexbins"),
+    (AggregationMode::Heatmap, "Sightings: heatmap"),
+];
+
+/// Made-up sightings scattered around the places, for the density layer
+fn sightings() -> Vec<AggregatePoint> {
+    let mut seed: u32 = 0x2545_f491;
+    let mut random = move || {
+        seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        (seed >> 8) as f64 / (1u32 << 24) as f64
+    };
+    let mut points = Vec::new();
+    for place in PLACES {
+        for _ in 0..400 {
+            // Denser near the place, thinning out over a few degrees
+            let distance = random() * random() * 3.0;
+            let angle = random() * std::f64::consts::TAU;
+            points.push(AggregatePoint {
+                lng: place.lng + distance * angle.cos(),
+                lat: (place.lat + distance * angle.sin()).clamp(-85.0, 85.0),
+                weight: 1.0,
+            });
+        }
+    }
+    points
+}
+
+/// Sidebar listing the places, drawn through a PortalList
+#[derive(Live, LiveHook, Widget)]
+pub struct PlaceList {
+    #[deref] view: View,
+}
+
+impl Widget for PlaceList {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        while let Some(item) = self.view.draw_walk(cx, scope, walk).step() {
+            if let Some(mut list) = item.as_portal_list().borrow_mut() {
+                list.set_item_range(cx, 0, PLACES.len());
+                while let Some(index) = list.next_visible_item(cx) {
+                    if index >= PLACES.len() {
+                        continue;
+                    }
+                    let row = list.item(cx, index, live_id!(Place));
+                    row.button(ids!(name)).set_text(cx, PLACES[index].name);
+                    row.draw_all(cx, scope);
+                }
+            }
+        }
+        DrawStep::done()
+    }
+}
+
+#[derive(Live, LiveHook)]
+pub struct App {
+    #[live] ui: WidgetRef,
+    #[rust] list_sync: MapListSync,
+    #[rust] show_grid: bool,
+    #[rust] reduced_quality: bool,
+    #[rust] density_mode: usize,
+}
+
+impl LiveRegister for App {
+    fn live_register(cx: &mut Cx) {
+        makepad_widgets::live_design(cx);
+        makepad_map::live_design(cx);
+    }
+}
+
+impl App {
+    fn set_status(&self, cx: &mut Cx, text: &str) {
+        self.ui.label(ids!(status_label)).set_text(cx, text);
+    }
+
+    fn set_base_button(&self, cx: &mut Cx, id: LiveId) {
+        let text = if id == live_id!(topo) { "Base: topo" } else { "Base: streets" };
+        self.ui.button(ids!(base_btn)).set_text(cx, text);
+    }
+}
+
+impl MatchEvent for App {
+    fn handle_startup(&mut self, cx: &mut Cx) {
+        let map = self.ui.geo_map_view(ids!(geo_map));
+
+        for (index, place) in PLACES.iter().enumerate() {
+            map.add_marker_with_label(cx, place_id(index), place.lng, place.lat, place.name, place.color);
+        }
+        self.list_sync.set_items((0..PLACES.len()).map(place_id));
+        self.list_sync.focus.zoom = Some(14.0);
+
+        // Picking a search result drops a marker and flies there
+        self.ui.map_search_box(ids!(search_box)).set_map(map.clone());
+
+        // Base maps, switched from the toolbar or the layer control
+        map.set_base_layers(cx, vec![
+            BaseLayer { id: live_id!(streets), title: "Streets".into(), source: TileSource::default(), refresh_interval: 0.0 },
+            BaseLayer {
+                id: live_id!(topo),
+                title: "Topographic".into(),
+                source: TileSource::Xyz("https://tile.opentopomap.org/{z}/{x}/{y}.png".into()),
+                refresh_interval: 0.0,
+            },
+        ]);
+        map.set_layer_title(cx, live_id!(quakes), "Earthquakes (24h)");
+        map.set_layer_title(cx, live_id!(sightings), "Sightings");
+        map.with_layer(cx, live_id!(sightings), |layer: &mut DensityLayer| {
+            layer.set_points(sightings());
+        });
+
+        // Report taps as MGRS grid references
+        map.set_coordinate_provider(Some(Box::new(MgrsProvider::default())));
+        map.set_center(cx, 0.0, 30.0);
+        map.set_zoom(cx, 2.0);
+    }
+
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions) {
+        let map = self.ui.geo_map_view(ids!(geo_map));
+
+        // List rows select their marker
+        let list = self.ui.portal_list(ids!(places));
+        for (index, row) in list.items_with_actions(actions) {
+            if row.button(ids!(name)).clicked(actions) && self.list_sync.select_row(cx, &map, index) {
+                self.set_status(cx, &format!("Flying to {}", PLACES[index].name));
+            }
+        }
+
+        // Toolbar
+        if self.ui.button(ids!(world_btn)).clicked(actions) {
+            self.list_sync.clear_selection(cx, &map);
+            map.fly_to(cx, 0.0, 30.0, 2.0);
+        }
+        if self.ui.button(ids!(north_btn)).clicked(actions) {
+            map.set_bearing(cx, 0.0);
+        }
+        if self.ui.button(ids!(grid_btn)).clicked(actions) {
+            self.show_grid = !self.show_grid;
+            if let Some(mut inner) = map.borrow_mut() {
+                inner.show_utm_grid = self.show_grid;
+            }
+            map.redraw(cx);
+            let text = if self.show_grid { "UTM grid: on" } else { "UTM grid: off" };
+            self.ui.button(ids!(grid_btn)).set_text(cx, text);
+        }
+        if self.ui.button(ids!(quality_btn)).clicked(actions) {
+            self.reduced_quality = !self.reduced_quality;
+            let level = self.reduced_quality.then_some(QualityLevel::ReducedTiles);
+            map.set_quality_override(cx, level);
+            let text = if self.reduced_quality { "Tiles: reduced" } else { "Tiles: full" };
+            self.ui.button(ids!(quality_btn)).set_text(cx, text);
+        }
+        if self.ui.button(ids!(base_btn)).clicked(actions) {
+            let active = map.borrow().and_then(|inner| inner.active_base_layer());
+            let next = if active == Some(live_id!(topo)) { live_id!(streets) } else { live_id!(topo) };
+            map.select_base_layer(cx, next);
+        }
+        if self.ui.button(ids!(density_btn)).clicked(actions) {
+            self.density_mode = (self.density_mode + 1) % DENSITY_MODES.len();
+            let (mode, text) = DENSITY_MODES[self.density_mode];
+            map.with_layer(cx, live_id!(sightings), |layer: &mut DensityLayer| {
+                layer.mode = mode;
+            });
+            self.ui.button(ids!(density_btn)).set_text(cx, text);
+        }
+        if self.ui.button(ids!(download_btn)).clicked(actions) {
+            // The view's surroundings, two zoom levels deeper, for offline use
+            if let Some(camera) = map.camera() {
+                let zoom = camera.zoom.round().clamp(0.0, 16.0) as u8;
+                let tiles = map.prefetch_route(cx, &[camera.center], zoom..=zoom + 2, 5000.0);
+                self.set_status(cx, &format!("Downloading {} tiles around the center", tiles));
+            }
+        }
+        if self.ui.button(ids!(clear_pin_btn)).clicked(actions) {
+            map.clear_dropped_pin(cx);
+        }
+
+        // Markers select their list row
+        if let Some(index) = self.list_sync.handle_actions(cx, &map, actions) {
+            list.set_first_id_and_scroll(index, 0.0);
+            self.set_status(cx, PLACES[index].name);
+        }
+
+        if let Some(result) = self.ui.map_search_box(ids!(search_box)).result_selected(actions) {
+            self.list_sync.clear_selection(cx, &map);
+            self.set_status(cx, &result.address);
+        }
+
+        // Map feedback
+        if let Some((_, lng, lat)) = map.pin_dropped(actions) {
+            self.set_status(cx, &format!("Pin dropped at {:.5}, {:.5}", lat, lng));
+        } else if let Some((lng, lat, text)) = map.coordinate_looked_up(actions) {
+            self.set_status(cx, &format!("{:.5}, {:.5} - MGRS {}", lat, lng, text));
+        } else if let Some((lng, lat, zoom)) = map.region_changed(actions) {
+            self.set_status(cx, &format!("Lat: {:.4}, Lng: {:.4}, Zoom: {:.1}", lat, lng, zoom));
+        }
+        if let Some(bearing) = map.bearing_changed(actions) {
+            self.set_status(cx, &format!("Bearing: {:.0}°", bearing));
+        }
+        if let Some((id, true)) = map.layer_toggled(actions) {
+            if id == live_id!(streets) || id == live_id!(topo) {
+                self.set_base_button(cx, id);
+            }
+        }
+        if let Some(progress) = map.download_progress_changed(actions) {
+            let text = format!("Downloaded {}/{} tiles ({} failed)", progress.completed, progress.total, progress.failed);
+            self.set_status(cx, &text);
+            let label = if progress.is_finished() {
+                "Download area".to_string()
+            } else {
+                format!("Downloading {}%", (progress.completed + progress.failed) * 100 / progress.total.max(1))
+            };
+            self.ui.button(ids!(download_btn)).set_text(cx, &label);
+        }
+        if let Some((warning, duration)) = map.tile_requests_backed_off(actions) {
+            self.set_status(cx, &format!("Tile requests paused for {:.0}s ({:?})", duration, warning));
+        }
+    }
+}
+
+impl AppMain for App {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event) {
+        self.match_event(cx, event);
+        self.ui.handle_event(cx, event, &mut Scope::empty());
+    }
+}
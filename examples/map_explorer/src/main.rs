@@ -0,0 +1,3 @@
+fn main() {
+    makepad_map_explorer::app_main()
+}
@@ -107,34 +107,20 @@ impl MatchEvent for App {
     }
 
     fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions) {
-        // Handle zoom in button
-        if self.ui.button(ids!(zoom_in_btn)).clicked(actions) {
-            let map_ref = self.ui.geo_map_view(ids!(geo_map));
-            let new_zoom = if let Some(mut map) = map_ref.borrow_mut() {
-                let z = (map.zoom + 1.0).min(map.max_zoom);
-                map.set_zoom(cx, z);
-                Some(z)
-            } else { None };
-            if let Some(z) = new_zoom {
-                self.ui.label(ids!(status_label)).set_text(cx, &format!("Zoom: {:.1}", z));
-            }
+        // Zoom buttons: tap for one level, hold to keep zooming.
+        // The new zoom is shown when the map reports the region below.
+        let map = self.ui.geo_map_view(ids!(geo_map));
+        if self.ui.button(ids!(zoom_in_btn)).pressed(actions) {
+            map.start_zoom_in(cx);
         }
-
-        // Handle zoom out button
-        if self.ui.button(ids!(zoom_out_btn)).clicked(actions) {
-            let map_ref = self.ui.geo_map_view(ids!(geo_map));
-            let new_zoom = if let Some(mut map) = map_ref.borrow_mut() {
-                let z = (map.zoom - 1.0).max(map.min_zoom);
-                map.set_zoom(cx, z);
-                Some(z)
-            } else { None };
-            if let Some(z) = new_zoom {
-                self.ui.label(ids!(status_label)).set_text(cx, &format!("Zoom: {:.1}", z));
-            }
+        if self.ui.button(ids!(zoom_out_btn)).pressed(actions) {
+            map.start_zoom_out(cx);
+        }
+        if self.ui.button(ids!(zoom_in_btn)).released(actions) || self.ui.button(ids!(zoom_out_btn)).released(actions) {
+            map.stop_zoom(cx);
         }
 
         // Handle map actions
-        let map = self.ui.geo_map_view(ids!(geo_map));
 
         if let Some(id) = map.marker_tapped(actions) {
             let name = if id == live_id!(golden_gate) {
@@ -0,0 +1,470 @@
+use makepad_widgets::*;
+use std::collections::HashMap;
+use std::sync::mpsc::{channel, Sender};
+use std::sync::Arc;
+
+use crate::layers::{DrawMapCircle, MapLayer, MapViewport};
+use crate::projection::{world_size, Projection};
+use crate::quality::QualityLevel;
+
+live_design! {
+    link widgets;
+    use link::theme::*;
+    use link::shaders::*;
+    use link::widgets::*;
+
+    // Pointy-top hexagon filling its rect (width = sqrt(3) / 2 * height)
+    pub DrawMapHex = {{DrawMapHex}} {
+        color_low: #2196f322
+        color_high: #d32f2fdd
+
+        fn pixel(self) -> vec4 {
+            let p = abs(self.pos * 2.0 - 1.0) * vec2(0.866, 1.0);
+            let d = max(p.x, p.x * 0.5 + p.y * 0.866) - 0.866;
+            let alpha = clamp(-d * self.rect_size.y * 0.5, 0.0, 1.0);
+            let color = mix(self.color_low, self.color_high, self.intensity);
+            return vec4(color.rgb * color.a * alpha, color.a * alpha);
+        }
+    }
+
+    // Soft blob of a heatmap cell, colored along a ramp by intensity
+    pub DrawMapHeat = {{DrawMapHeat}} {
+        color_low: #2196f300
+        color_high: #ff3d00ff
+
+        fn pixel(self) -> vec4 {
+            let d = length(self.pos * 2.0 - 1.0);
+            let color = mix(self.color_low, self.color_high, self.intensity);
+            let alpha = color.a * (1.0 - smoothstep(0.0, 1.0, d)) * min(1.0, self.intensity * 2.0);
+            return vec4(color.rgb * alpha, alpha);
+        }
+    }
+
+    // Clusters, hexagonal bins or a heatmap of many points
+    pub DensityLayer = {{DensityLayer}} {
+        draw_cluster: {
+            color: #1e88e5cc
+            border_color: #ffffff
+            border_width: 2.0
+        }
+        draw_count: {
+            color: #ffffff
+            text_style: <THEME_FONT_BOLD> {
+                font_size: 10.0
+            }
+        }
+    }
+}
+
+#[derive(Live, LiveRegister, LiveHook)]
+#[repr(C)]
+pub struct DrawMapHex {
+    #[deref] pub draw_super: DrawQuad,
+    #[live] pub color_low: Vec4,
+    #[live] pub color_high: Vec4,
+    #[live] pub intensity: f32,
+}
+
+#[derive(Live, LiveRegister, LiveHook)]
+#[repr(C)]
+pub struct DrawMapHeat {
+    #[deref] pub draw_super: DrawQuad,
+    #[live] pub color_low: Vec4,
+    #[live] pub color_high: Vec4,
+    #[live] pub intensity: f32,
+}
+
+/// A weighted point to aggregate
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AggregatePoint {
+    pub lng: f64,
+    pub lat: f64,
+    pub weight: f32,
+}
+
+/// How a `DensityLayer` summarizes its points
+#[derive(Copy, Clone, Debug, Live, LiveHook, PartialEq)]
+#[live_ignore]
+pub enum AggregationMode {
+    /// Grid-based clusters drawn as circles with a count
+    #[pick] Clusters,
+    /// Hexagonal bins shaded by total weight
+    Hexbin,
+    /// Blurred density grid
+    Heatmap,
+}
+
+/// Parameters of one aggregation run. Results are computed per integer zoom
+/// level and scaled for fractional zooms, so panning never re-aggregates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AggregationParams {
+    pub mode: AggregationMode,
+    pub zoom: u8,
+    /// Cluster cell, hexagon or heatmap cell size in screen pixels
+    pub cell_size: f64,
+    /// Heatmap blur radius in screen pixels
+    pub heat_radius: f64,
+}
+
+/// A cluster, hexagonal bin or heatmap cell
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AggregateCell {
+    /// Position in the projection's unit square (see `Projection::project`)
+    pub center: DVec2,
+    pub count: usize,
+    pub weight: f64,
+}
+
+/// Output of an aggregation run
+#[derive(Clone, Debug)]
+pub struct AggregationResult {
+    /// Submission counter, to drop results that were superseded
+    pub generation: u64,
+    pub params: AggregationParams,
+    pub cells: Vec<AggregateCell>,
+    /// Largest cell weight, for normalizing colors
+    pub max_weight: f64,
+}
+
+/// Aggregate projected points (unit square position and weight).
+///
+/// This is what the worker thread runs; it can also be called directly.
+pub fn aggregate(points: &[(DVec2, f32)], params: &AggregationParams) -> Vec<AggregateCell> {
+    let cell = params.cell_size / world_size(params.zoom as f64);
+    if cell <= 0.0 {
+        return Vec::new();
+    }
+    match params.mode {
+        AggregationMode::Clusters => aggregate_clusters(points, cell),
+        AggregationMode::Hexbin => aggregate_hexbin(points, cell / 2.0),
+        AggregationMode::Heatmap => {
+            let radius = (params.heat_radius / params.cell_size).ceil().max(0.0) as i64;
+            aggregate_heatmap(points, cell, radius)
+        }
+    }
+}
+
+/// Grid clustering: points in the same cell form a cluster at their weighted mean
+fn aggregate_clusters(points: &[(DVec2, f32)], cell: f64) -> Vec<AggregateCell> {
+    let mut bins: HashMap<(i64, i64), (DVec2, f64, usize)> = HashMap::new();
+    for &(pos, weight) in points {
+        let key = ((pos.x / cell).floor() as i64, (pos.y / cell).floor() as i64);
+        let bin = bins.entry(key).or_insert((DVec2::default(), 0.0, 0));
+        // Weight the position by at least a tiny amount so zero-weight points still place the cluster
+        let w = (weight as f64).max(1e-9);
+        bin.0 += pos * w;
+        bin.1 += w;
+        bin.2 += 1;
+    }
+    bins.into_values()
+        .map(|(sum, weight, count)| AggregateCell { center: sum / weight, count, weight })
+        .collect()
+}
+
+/// Hexagonal binning with pointy-top hexagons of circumradius `size`
+fn aggregate_hexbin(points: &[(DVec2, f32)], size: f64) -> Vec<AggregateCell> {
+    const SQRT_3: f64 = 1.7320508075688772;
+    let mut bins: HashMap<(i64, i64), (f64, usize)> = HashMap::new();
+    for &(pos, weight) in points {
+        // Axial coordinates, rounded through cube coordinates
+        let q = (SQRT_3 / 3.0 * pos.x - pos.y / 3.0) / size;
+        let r = (2.0 / 3.0 * pos.y) / size;
+        let (x, z) = (q, r);
+        let y = -x - z;
+        let (mut rx, ry, mut rz) = (x.round(), y.round(), z.round());
+        let (dx, dy, dz) = ((rx - x).abs(), (ry - y).abs(), (rz - z).abs());
+        if dx > dy && dx > dz {
+            rx = -ry - rz;
+        } else if dy <= dz {
+            rz = -rx - ry;
+        }
+        let bin = bins.entry((rx as i64, rz as i64)).or_insert((0.0, 0));
+        bin.0 += weight as f64;
+        bin.1 += 1;
+    }
+    bins.into_iter()
+        .map(|((q, r), (weight, count))| AggregateCell {
+            center: dvec2(size * SQRT_3 * (q as f64 + r as f64 / 2.0), size * 1.5 * r as f64),
+            count,
+            weight,
+        })
+        .collect()
+}
+
+/// Density grid blurred with a Gaussian kernel of `radius` cells
+fn aggregate_heatmap(points: &[(DVec2, f32)], cell: f64, radius: i64) -> Vec<AggregateCell> {
+    let mut grid: HashMap<(i64, i64), (f64, usize)> = HashMap::new();
+    for &(pos, weight) in points {
+        let bin = grid.entry(((pos.x / cell).floor() as i64, (pos.y / cell).floor() as i64)).or_insert((0.0, 0));
+        bin.0 += weight as f64;
+        bin.1 += 1;
+    }
+
+    let sigma = (radius as f64 / 2.0).max(0.5);
+    let kernel: Vec<(i64, i64, f64)> = (-radius..=radius)
+        .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+        .filter(|(dx, dy)| dx * dx + dy * dy <= radius * radius)
+        .map(|(dx, dy)| (dx, dy, (-((dx * dx + dy * dy) as f64) / (2.0 * sigma * sigma)).exp()))
+        .collect();
+
+    let mut blurred: HashMap<(i64, i64), (f64, usize)> = HashMap::with_capacity(grid.len() * kernel.len());
+    for (&(x, y), &(weight, count)) in &grid {
+        for &(dx, dy, k) in &kernel {
+            let out = blurred.entry((x + dx, y + dy)).or_insert((0.0, 0));
+            out.0 += weight * k;
+            if dx == 0 && dy == 0 {
+                out.1 += count;
+            }
+        }
+    }
+    blurred.into_iter()
+        .filter(|(_, (weight, _))| *weight > 1e-6)
+        .map(|((x, y), (weight, count))| AggregateCell {
+            center: dvec2((x as f64 + 0.5) * cell, (y as f64 + 0.5) * cell),
+            count,
+            weight,
+        })
+        .collect()
+}
+
+struct AggregationJob {
+    generation: u64,
+    points: Arc<Vec<(DVec2, f32)>>,
+    params: AggregationParams,
+}
+
+/// Runs aggregations on a background thread.
+///
+/// Jobs are coalesced: if several are queued while one is running, only the
+/// newest is computed. Results arrive on the UI thread with an `Event::Signal`
+/// and are picked up with `poll`.
+#[derive(Default)]
+pub struct AggregationWorker {
+    jobs: Option<Sender<AggregationJob>>,
+    results: ToUIReceiver<AggregationResult>,
+    generation: u64,
+    pending: bool,
+}
+
+impl AggregationWorker {
+    /// Queue an aggregation, starting the worker thread on first use.
+    /// Returns the generation the result will carry.
+    pub fn submit(&mut self, cx: &mut Cx, points: Arc<Vec<(DVec2, f32)>>, params: AggregationParams) -> u64 {
+        let results = &self.results;
+        let jobs = self.jobs.get_or_insert_with(|| {
+            let (sender, receiver) = channel::<AggregationJob>();
+            let to_ui = results.sender();
+            cx.spawn_thread(move || {
+                // The thread ends when the worker (and its sender) is dropped
+                while let Ok(mut job) = receiver.recv() {
+                    while let Ok(newer) = receiver.try_recv() {
+                        job = newer;
+                    }
+                    let cells = aggregate(&job.points, &job.params);
+                    let max_weight = cells.iter().map(|c| c.weight).fold(0.0, f64::max);
+                    let result = AggregationResult { generation: job.generation, params: job.params, cells, max_weight };
+                    if to_ui.send(result).is_err() {
+                        break;
+                    }
+                }
+            });
+            sender
+        });
+        self.generation += 1;
+        self.pending = true;
+        let _ = jobs.send(AggregationJob { generation: self.generation, points, params });
+        self.generation
+    }
+
+    /// Take the newest finished result, if any. Results of superseded jobs are
+    /// still returned (they're better than nothing) until the latest one arrives.
+    pub fn poll(&mut self) -> Option<AggregationResult> {
+        let mut latest = None;
+        while let Ok(result) = self.results.try_recv() {
+            latest = Some(result);
+        }
+        if latest.as_ref().is_some_and(|r| r.generation == self.generation) {
+            self.pending = false;
+        }
+        latest
+    }
+
+    /// Whether a submitted job hasn't finished yet
+    pub fn is_pending(&self) -> bool {
+        self.pending
+    }
+}
+
+/// Summarizes large point sets (hundreds of thousands of points) as clusters,
+/// hexagonal bins or a heatmap.
+///
+/// Aggregation runs on a worker thread whenever the integer zoom level or the
+/// data changes. The previous result stays on screen (scaled to the current
+/// zoom) until the new one is swapped in, so gestures never wait for it.
+#[derive(Live, LiveHook, LiveRegister)]
+pub struct DensityLayer {
+    #[live] draw_cluster: DrawMapCircle,
+    #[live] draw_count: DrawText,
+    #[live] draw_hex: DrawMapHex,
+    #[live] draw_heat: DrawMapHeat,
+    #[live] pub mode: AggregationMode,
+    #[live(60.0)] pub cell_size: f64,
+    #[live(24.0)] pub heat_radius: f64,
+    /// Radius of single-point clusters
+    #[live(6.0)] pub point_radius: f64,
+    #[live(true)] pub visible: bool,
+    #[rust] points: Vec<AggregatePoint>,
+    // Points in the unit square of the projection they were projected with
+    #[rust] projected: Option<(String, Arc<Vec<(DVec2, f32)>>)>,
+    #[rust] worker: AggregationWorker,
+    // Front buffer: the result being drawn
+    #[rust] front: Option<AggregationResult>,
+    #[rust] requested: Option<AggregationParams>,
+    #[rust] quality: QualityLevel,
+}
+
+impl DensityLayer {
+    /// Replace the points and re-aggregate in the background
+    pub fn set_points(&mut self, points: Vec<AggregatePoint>) {
+        self.points = points;
+        self.projected = None;
+        self.requested = None;
+    }
+
+    pub fn points(&self) -> &[AggregatePoint] {
+        &self.points
+    }
+
+    /// The result currently drawn
+    pub fn result(&self) -> Option<&AggregationResult> {
+        self.front.as_ref()
+    }
+
+    /// Whether a re-aggregation is running in the background
+    pub fn is_aggregating(&self) -> bool {
+        self.worker.is_pending()
+    }
+
+    /// Points projected for `projection`, reprojected when the projection changes
+    fn projected_points(&mut self, projection: &dyn Projection) -> Arc<Vec<(DVec2, f32)>> {
+        if let Some((code, points)) = &self.projected {
+            if code == projection.code() {
+                return points.clone();
+            }
+        }
+        let points = Arc::new(
+            self.points.iter().map(|p| (projection.project(p.lng, p.lat), p.weight)).collect::<Vec<_>>()
+        );
+        self.projected = Some((projection.code().to_string(), points.clone()));
+        self.requested = None;
+        points
+    }
+}
+
+impl MapLayer for DensityLayer {
+    fn draw_layer(&mut self, cx: &mut Cx2d, viewport: &MapViewport) {
+        // Aggregate at half resolution when the quality governor asks for coarse overlays
+        let resolution = if self.quality >= QualityLevel::CoarseOverlays { 2.0 } else { 1.0 };
+        let params = AggregationParams {
+            mode: self.mode,
+            zoom: viewport.zoom.floor().clamp(0.0, 24.0) as u8,
+            cell_size: self.cell_size * resolution,
+            heat_radius: self.heat_radius * resolution,
+        };
+        let projection_changed = self.projected.as_ref().is_some_and(|(code, _)| code != viewport.projection.code());
+        if self.requested != Some(params) || projection_changed {
+            let points = self.projected_points(viewport.projection.as_ref());
+            self.worker.submit(cx, points, params);
+            self.requested = Some(params);
+        }
+
+        let Some(front) = &self.front else {
+            return;
+        };
+        // Results of another zoom level are scaled until the new one arrives
+        let scale = 2.0_f64.powf(viewport.zoom - front.params.zoom as f64);
+        let max_weight = front.max_weight.max(f64::EPSILON);
+
+        match front.params.mode {
+            AggregationMode::Clusters => {
+                let font_size = self.draw_count.text_style.font_size as f64;
+                for cell in &front.cells {
+                    let pos = viewport.unit_to_screen(cell.center);
+                    let radius = if cell.count > 1 {
+                        self.point_radius + 4.0 * (cell.count as f64).log10() + 6.0
+                    } else {
+                        self.point_radius
+                    };
+                    if !viewport.is_visible(pos, radius) {
+                        continue;
+                    }
+                    self.draw_cluster.draw_circle(cx, pos, radius);
+                    if cell.count > 1 {
+                        let text = format_count(cell.count);
+                        let width = text.len() as f64 * font_size * 0.6;
+                        self.draw_count.draw_abs(cx, pos - dvec2(width / 2.0, font_size * 0.65), &text);
+                    }
+                }
+            }
+            AggregationMode::Hexbin => {
+                let radius = front.params.cell_size / 2.0 * scale;
+                let size = dvec2(radius * 1.7320508075688772, radius * 2.0);
+                for cell in &front.cells {
+                    let pos = viewport.unit_to_screen(cell.center);
+                    if !viewport.is_visible(pos, radius) {
+                        continue;
+                    }
+                    self.draw_hex.intensity = (cell.weight / max_weight) as f32;
+                    self.draw_hex.draw_abs(cx, Rect { pos: pos - size / 2.0, size });
+                }
+            }
+            AggregationMode::Heatmap => {
+                // Blobs overlap their neighbors so the grid doesn't show
+                let radius = front.params.cell_size * scale;
+                for cell in &front.cells {
+                    let pos = viewport.unit_to_screen(cell.center);
+                    if !viewport.is_visible(pos, radius) {
+                        continue;
+                    }
+                    self.draw_heat.intensity = (cell.weight / max_weight) as f32;
+                    self.draw_heat.draw_abs(cx, Rect {
+                        pos: pos - dvec2(radius, radius),
+                        size: dvec2(radius * 2.0, radius * 2.0),
+                    });
+                }
+            }
+        }
+    }
+
+    fn handle_layer_event(&mut self, _cx: &mut Cx, event: &Event, _viewport: &MapViewport) -> bool {
+        if let Event::Signal = event {
+            if let Some(result) = self.worker.poll() {
+                // Swap the finished back buffer in
+                self.front = Some(result);
+                return true;
+            }
+        }
+        false
+    }
+
+    fn set_quality(&mut self, level: QualityLevel) {
+        self.quality = level;
+    }
+
+    fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+}
+
+/// Short cluster count label, e.g. "12", "3.4k", "120k"
+fn format_count(count: usize) -> String {
+    match count {
+        0..=999 => count.to_string(),
+        1000..=9999 => format!("{:.1}k", count as f64 / 1000.0),
+        _ => format!("{}k", count / 1000),
+    }
+}
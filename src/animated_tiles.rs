@@ -0,0 +1,316 @@
+use makepad_widgets::*;
+use std::collections::HashMap;
+
+use crate::camera_math::TileGrid;
+use crate::disk_cache::DiskCacheConfig;
+use crate::layers::{MapLayer, MapViewport};
+use crate::legend::MapCorner;
+use crate::map_view::{measure_text, DrawMapTile, TileBlendMode};
+use crate::tiles::{TileCache, TileCoord};
+
+live_design! {
+    link widgets;
+    use link::theme::*;
+    use link::shaders::*;
+    use link::widgets::*;
+
+    // Tile frames shown one after another, e.g. a weather radar loop
+    pub AnimatedTileLayer = {{AnimatedTileLayer}} {
+        label_corner: TopLeft
+        draw_label_bg: {
+            color: #ffffffe6
+        }
+        draw_label: {
+            color: #333333
+            text_style: <THEME_FONT_REGULAR> {
+                font_size: 9.0
+            }
+        }
+    }
+}
+
+/// Requests the frames of an `AnimatedTileLayer` may start per draw, each
+const FRAME_REQUEST_BUDGET: usize = 16;
+
+/// One time step of an `AnimatedTileLayer`
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileFrame {
+    /// XYZ URL template of the frame's tiles, usually with its timestamp in it
+    pub url: String,
+    /// Time of the frame in seconds (e.g. Unix time), for `GeoMapView::seek`
+    pub time: f64,
+    /// Shown in a corner of the map with the frame, e.g. "14:20" (empty for none)
+    pub label: String,
+}
+
+/// A frame and the tiles loaded for it
+struct LoadedFrame {
+    frame: TileFrame,
+    tile_cache: TileCache,
+}
+
+/// Raster tiles that change over time, shown as a loop of frames: the
+/// classic weather radar animation.
+///
+/// Every frame is its own tile URL template (see `set_frames`). The tiles of
+/// all frames are loaded for the viewport, the current frame first, so the
+/// loop plays smoothly; while playing, the next frame is only shown once its
+/// visible tiles finished loading. `play`, `pause` and `step` control the
+/// loop, and `GeoMapView::seek` shows the frame at a timeline time.
+#[derive(Live, LiveHook, LiveRegister)]
+pub struct AnimatedTileLayer {
+    #[live] draw_tile: DrawMapTile,
+    #[live] draw_label_bg: DrawColor,
+    #[live] draw_label: DrawText,
+    #[live] pub blend_mode: TileBlendMode,
+    /// Seconds each frame is shown while playing
+    #[live(0.5)] pub frame_duration: f64,
+    /// Extra seconds the last frame is held before the loop starts over
+    #[live(1.5)] pub end_pause: f64,
+    /// Deepest zoom of the frame tiles, deeper zooms scale them up
+    #[live(12)] pub max_zoom: usize,
+    /// Tile requests in flight over all frames
+    #[live(24)] pub max_pending_tiles: usize,
+    /// GPU memory for the tiles of all frames in megabytes
+    #[live(96.0)] pub memory_mb: f64,
+    #[live(true)] pub show_label: bool,
+    #[live] pub label_corner: MapCorner,
+    #[live(true)] pub visible: bool,
+    #[rust] frames: Vec<LoadedFrame>,
+    #[rust] current: usize,
+    #[rust] playing: bool,
+    #[rust] frame_timer: Timer,
+    #[rust] next_frame: NextFrame,
+    // Tiles of the viewport at the last draw, which a frame needs to be shown
+    #[rust] visible_tiles: Vec<TileCoord>,
+    // Tiles of the last request pass and the frames' request generations then,
+    // requests are only issued again when either changes
+    #[rust] requested_tiles: Option<(Vec<TileCoord>, Vec<u64>)>,
+    #[rust(1.0)] opacity: f64,
+}
+
+impl AnimatedTileLayer {
+    /// Replace the frames, sorted by time. Frames whose URL template was
+    /// already shown keep their tiles, so moving the loop forward when a
+    /// provider publishes a new frame only loads that frame. The current frame
+    /// stays if it's still there.
+    pub fn set_frames(&mut self, mut frames: Vec<TileFrame>) {
+        frames.sort_by(|a, b| a.time.total_cmp(&b.time));
+        let current_url = self.current_frame().map(|frame| frame.url.clone());
+        let mut caches: HashMap<String, TileCache> = self.frames.drain(..)
+            .map(|loaded| (loaded.frame.url, loaded.tile_cache))
+            .collect();
+        self.frames = frames.into_iter().map(|frame| {
+            let tile_cache = caches.remove(&frame.url).unwrap_or_else(|| {
+                let mut tile_cache = TileCache::new();
+                // Frames are short-lived, they'd only push base map tiles out of the disk cache
+                tile_cache.set_disk_cache_config(DiskCacheConfig { enabled: false, ..Default::default() });
+                tile_cache.set_tile_server(&frame.url);
+                tile_cache
+            });
+            LoadedFrame { frame, tile_cache }
+        }).collect();
+        self.requested_tiles = None;
+        self.current = current_url
+            .and_then(|url| self.frames.iter().position(|loaded| loaded.frame.url == url))
+            .unwrap_or(self.current)
+            .min(self.frames.len().saturating_sub(1));
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// The frame shown
+    pub fn current_frame(&self) -> Option<&TileFrame> {
+        self.frames.get(self.current).map(|loaded| &loaded.frame)
+    }
+
+    /// Index of the frame shown
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    /// Show a frame by index, clamped to the frames
+    pub fn set_frame(&mut self, index: usize) {
+        self.current = index.min(self.frames.len().saturating_sub(1));
+    }
+
+    /// Move `delta` frames forward (negative goes back), wrapping around
+    pub fn step(&mut self, delta: isize) {
+        if !self.frames.is_empty() {
+            let count = self.frames.len() as isize;
+            self.current = (self.current as isize + delta).rem_euclid(count) as usize;
+        }
+    }
+
+    /// Start the loop from the frame shown
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Frames whose visible tiles all finished loading (or failed), and the
+    /// number of frames, e.g. for a progress bar while the loop preloads
+    pub fn loaded_frames(&self) -> (usize, usize) {
+        let loaded = (0..self.frames.len()).filter(|index| self.is_frame_loaded(*index)).count();
+        (loaded, self.frames.len())
+    }
+
+    fn is_frame_loaded(&self, index: usize) -> bool {
+        let cache = &self.frames[index].tile_cache;
+        self.visible_tiles.iter().all(|coord| cache.is_finished(coord))
+    }
+
+    /// Show the next frame once it's loaded, and time the one after it
+    fn advance(&mut self, cx: &mut Cx) {
+        if self.frames.is_empty() {
+            return;
+        }
+        let next = (self.current + 1) % self.frames.len();
+        if self.is_frame_loaded(next) {
+            self.current = next;
+        }
+        let last = self.current + 1 == self.frames.len();
+        let duration = self.frame_duration + if last { self.end_pause } else { 0.0 };
+        self.frame_timer = cx.start_timeout(duration.max(0.05));
+    }
+
+    /// Request the viewport's tiles for every frame, starting with the current
+    /// one and then in playback order, within one limit of requests in flight.
+    /// Only done when the viewport moved onto other tiles or tiles left a
+    /// frame's cache, not on every redraw of the loop.
+    fn request_tiles(&mut self, cx: &mut Cx, zoom: u8) {
+        let count = self.frames.len();
+        let memory = (self.memory_mb.max(0.0) * 1024.0 * 1024.0) as usize / count;
+        for loaded in &mut self.frames {
+            loaded.tile_cache.set_memory_budget(memory);
+            loaded.tile_cache.trim_memory(cx, zoom);
+        }
+        let generations: Vec<u64> = self.frames.iter().map(|loaded| loaded.tile_cache.request_generation()).collect();
+        if self.requested_tiles.as_ref().is_some_and(|(requested, requested_generations)| {
+            *requested == self.visible_tiles && *requested_generations == generations
+        }) {
+            return;
+        }
+
+        let mut pending: usize = self.frames.iter().map(|loaded| loaded.tile_cache.pending_request_count()).sum();
+        let mut deferred = false;
+        for offset in 0..count {
+            let cache = &mut self.frames[(self.current + offset) % count].tile_cache;
+            let others = pending - cache.pending_request_count();
+            cache.set_request_budget(FRAME_REQUEST_BUDGET, self.max_pending_tiles.saturating_sub(others));
+            cache.begin_frame();
+            for &coord in &self.visible_tiles {
+                cache.request_tile(cx, coord);
+            }
+            deferred |= cache.has_deferred_requests();
+            pending = others + cache.pending_request_count();
+        }
+        if deferred {
+            // Requests held back by the limit in flight go out when a response
+            // frees a slot, those over the per-draw budget on the next frame
+            self.requested_tiles = None;
+            if pending < self.max_pending_tiles {
+                self.next_frame = cx.new_next_frame();
+            }
+        } else {
+            let generations = self.frames.iter().map(|loaded| loaded.tile_cache.request_generation()).collect();
+            self.requested_tiles = Some((self.visible_tiles.clone(), generations));
+        }
+    }
+
+    fn draw_label(&mut self, cx: &mut Cx2d, viewport: &MapViewport, label: &str) {
+        let padding = dvec2(6.0, 4.0);
+        let size = measure_text(cx, &self.draw_label, label) + padding * 2.0;
+        let pos = self.label_corner.place(viewport.rect, size, 10.0);
+        self.draw_label_bg.draw_abs(cx, Rect { pos, size });
+        self.draw_label.draw_abs(cx, pos + padding, label);
+    }
+}
+
+impl MapLayer for AnimatedTileLayer {
+    fn draw_layer(&mut self, cx: &mut Cx2d, viewport: &MapViewport) {
+        if self.frames.is_empty() || viewport.projection.code() != "EPSG:3857" {
+            return;
+        }
+        let grid = TileGrid::new(viewport, self.max_zoom as u8, 0);
+        self.visible_tiles = grid.tiles().into_iter().map(|(coord, _)| coord).collect();
+        self.request_tiles(cx.cx.cx, grid.zoom);
+        if self.playing && self.frame_timer.is_empty() {
+            self.frame_timer = cx.start_timeout(self.frame_duration.max(0.05));
+        }
+
+        self.draw_tile.set_viewport(viewport);
+        self.draw_tile.set_blend_mode(self.blend_mode);
+        self.draw_tile.opacity = self.opacity as f32;
+        let loaded = &self.frames[self.current];
+        grid.draw(cx, &mut self.draw_tile, &loaded.tile_cache, viewport.rect, false);
+
+        if self.show_label && !loaded.frame.label.is_empty() {
+            let label = loaded.frame.label.clone();
+            self.draw_label(cx, viewport, &label);
+        }
+    }
+
+    fn handle_layer_event(&mut self, cx: &mut Cx, event: &Event, _viewport: &MapViewport) -> bool {
+        let mut redraw = false;
+        if let Event::NetworkResponses(responses) = event {
+            for response in responses {
+                for loaded in &mut self.frames {
+                    let cache = &mut loaded.tile_cache;
+                    let pending = cache.pending_request_count();
+                    match &response.response {
+                        NetworkResponse::HttpResponse(http_response) => {
+                            redraw |= cache.handle_response(cx, response.request_id, http_response);
+                        }
+                        NetworkResponse::HttpRequestError(error) => {
+                            cache.handle_error(response.request_id, error);
+                        }
+                        _ => {}
+                    }
+                    // A freed slot lets held back requests go out, even if the tile failed
+                    redraw |= self.requested_tiles.is_none() && cache.pending_request_count() < pending;
+                }
+            }
+        }
+        if self.frame_timer.is_event(event).is_some() {
+            self.frame_timer = Timer::empty();
+            // A hidden loop stops here and goes on when it's drawn again
+            if self.playing && self.visible {
+                self.advance(cx);
+                redraw = true;
+            }
+        }
+        // Spend the next frame's request budget on the tiles skipped last frame
+        if self.next_frame.is_event(event).is_some() {
+            redraw = true;
+        }
+        redraw
+    }
+
+    fn seek(&mut self, time: f64) {
+        self.current = self.frames.iter()
+            .rposition(|loaded| loaded.frame.time <= time)
+            .unwrap_or(0);
+    }
+
+    fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    fn set_opacity(&mut self, opacity: f64) {
+        self.opacity = opacity;
+    }
+}
@@ -0,0 +1,380 @@
+use makepad_widgets::*;
+
+use crate::geo::{bearing, haversine_distance};
+use crate::projection::Projection;
+
+/// Ease-out cubic: fast start, gentle arrival
+pub(crate) fn ease_out_cubic(t: f64) -> f64 {
+    1.0 - (1.0 - t).powi(3)
+}
+
+/// Ease-in-out cubic: gentle start and arrival
+pub(crate) fn ease_in_out_cubic(t: f64) -> f64 {
+    if t < 0.5 {
+        4.0 * t * t * t
+    } else {
+        1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+    }
+}
+
+/// What a camera animation interpolates
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum CameraAnimationKind {
+    /// Zoom while keeping a geographic point fixed on screen (double-tap zoom)
+    AnchoredZoom {
+        from_zoom: f64,
+        to_zoom: f64,
+        anchor_lng: f64,
+        anchor_lat: f64,
+        anchor_offset: DVec2,
+    },
+    /// Fly from the current camera to a target shown at `target_offset` from
+    /// the viewport center, optionally turning and tilting the map on the way
+    FlyTo {
+        from_lng: f64,
+        from_lat: f64,
+        from_zoom: f64,
+        to_lng: f64,
+        to_lat: f64,
+        to_zoom: f64,
+        target_offset: DVec2,
+        /// (from, to) bearing in degrees
+        bearing: Option<(f64, f64)>,
+        /// (from, to) pitch in degrees
+        pitch: Option<(f64, f64)>,
+    },
+    /// Turn the map around the viewport center (reset to north)
+    Rotate {
+        from_bearing: f64,
+        to_bearing: f64,
+        lng: f64,
+        lat: f64,
+        zoom: f64,
+    },
+}
+
+/// Rates of change of the camera, per second
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct CameraVelocity {
+    /// Center in unit square positions (see `Projection::project`)
+    pub unit: DVec2,
+    pub zoom: f64,
+    pub bearing: f64,
+    pub pitch: f64,
+}
+
+/// A time-based camera transition, driven by NextFrame events
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct CameraAnimation {
+    pub kind: CameraAnimationKind,
+    pub duration: f64,
+    pub start_time: Option<f64>,
+    /// Marker whose popup is opened when the animation arrives
+    pub open_popup: Option<LiveId>,
+    /// Velocity of the transition this one replaced, faded out over the
+    /// first part of a `FlyTo` so a retarget doesn't jerk
+    pub velocity: CameraVelocity,
+}
+
+/// Camera for one animation frame: the zoom, plus a geographic point and the
+/// offset from the viewport center where that point must appear
+pub(crate) struct CameraFrame {
+    pub zoom: f64,
+    pub lng: f64,
+    pub lat: f64,
+    pub offset: DVec2,
+    /// Bearing for animations that turn the map
+    pub bearing: Option<f64>,
+    /// Pitch for animations that tilt the map
+    pub pitch: Option<f64>,
+    pub finished: bool,
+}
+
+impl CameraAnimation {
+    pub fn new(kind: CameraAnimationKind, duration: f64) -> Self {
+        Self {
+            kind,
+            duration,
+            start_time: None,
+            open_popup: None,
+            velocity: CameraVelocity::default(),
+        }
+    }
+
+    /// Whether the transition continues the motion of one it replaces
+    fn carries_velocity(&self) -> bool {
+        matches!(self.kind, CameraAnimationKind::FlyTo { .. })
+    }
+
+    /// Compute the camera at `time` (seconds), starting the clock on the first frame
+    pub fn frame(&mut self, time: f64, projection: &dyn Projection) -> CameraFrame {
+        let start_time = *self.start_time.get_or_insert(time);
+        let t = if self.duration > 0.0 {
+            ((time - start_time) / self.duration).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        let finished = t >= 1.0;
+
+        match self.kind {
+            CameraAnimationKind::AnchoredZoom { from_zoom, to_zoom, anchor_lng, anchor_lat, anchor_offset } => {
+                let eased = ease_out_cubic(t);
+                CameraFrame {
+                    zoom: from_zoom + (to_zoom - from_zoom) * eased,
+                    lng: anchor_lng,
+                    lat: anchor_lat,
+                    offset: anchor_offset,
+                    bearing: None,
+                    pitch: None,
+                    finished,
+                }
+            }
+            CameraAnimationKind::FlyTo { from_lng, from_lat, from_zoom, to_lng, to_lat, to_zoom, target_offset, bearing, pitch } => {
+                let eased = ease_in_out_cubic(t);
+                // Hermite basis starting at slope 1 and ending flat: adds the
+                // replaced transition's velocity at the start, nothing on arrival
+                let carried = t * (1.0 - t) * (1.0 - t) * self.duration;
+                let velocity = self.velocity;
+
+                // Interpolate in projected space, taking the short way around the antimeridian
+                let to_lng = from_lng + (to_lng - from_lng + 180.0).rem_euclid(360.0) - 180.0;
+                let from_world = projection.project(from_lng, from_lat);
+                let to_world = projection.project(to_lng, to_lat);
+                let (lng, lat) = projection.unproject(from_world + (to_world - from_world) * eased + velocity.unit * carried);
+
+                CameraFrame {
+                    zoom: from_zoom + (to_zoom - from_zoom) * eased + velocity.zoom * carried,
+                    lng,
+                    lat,
+                    offset: target_offset * eased,
+                    bearing: bearing.map(|(from, to)| {
+                        from + ((to - from + 180.0).rem_euclid(360.0) - 180.0) * eased + velocity.bearing * carried
+                    }),
+                    pitch: pitch.map(|(from, to)| from + (to - from) * eased + velocity.pitch * carried),
+                    finished,
+                }
+            }
+            CameraAnimationKind::Rotate { from_bearing, to_bearing, lng, lat, zoom } => {
+                // The short way around
+                let turn = (to_bearing - from_bearing + 180.0).rem_euclid(360.0) - 180.0;
+                CameraFrame {
+                    zoom,
+                    lng,
+                    lat,
+                    offset: DVec2::default(),
+                    bearing: Some(from_bearing + turn * ease_out_cubic(t)),
+                    pitch: None,
+                    finished,
+                }
+            }
+        }
+    }
+}
+
+/// Camera of one animation frame, for measuring velocity
+#[derive(Clone, Copy, Debug)]
+struct CameraSample {
+    time: f64,
+    unit: DVec2,
+    zoom: f64,
+    bearing: f64,
+    pitch: f64,
+}
+
+/// Owns the camera transition in flight and the rules for interrupting it:
+///
+/// - User gestures (touch, scroll, pinch) and instant camera changes cancel
+///   it, leaving the camera where the transition had brought it.
+/// - A new transition replaces it, starting from the current camera. A
+///   `FlyTo` (`fly_to`, `ease_to`, `focus_marker`) continues at the velocity
+///   the camera had, so retargeting a flight in progress curves smoothly
+///   toward the new target instead of stopping and starting again.
+#[derive(Default)]
+pub(crate) struct CameraController {
+    animation: Option<CameraAnimation>,
+    /// The last two animated cameras
+    samples: [Option<CameraSample>; 2],
+}
+
+impl CameraController {
+    pub fn is_active(&self) -> bool {
+        self.animation.is_some()
+    }
+
+    /// Start a transition, replacing (and retargeting from) the one in flight
+    pub fn start(&mut self, mut animation: CameraAnimation) {
+        if self.animation.is_some() && animation.carries_velocity() {
+            animation.velocity = self.velocity();
+        } else {
+            self.samples = [None, None];
+        }
+        self.animation = Some(animation);
+    }
+
+    /// Drop the transition in flight. Returns true if there was one.
+    pub fn cancel(&mut self) -> bool {
+        self.samples = [None, None];
+        self.animation.take().is_some()
+    }
+
+    /// Marker whose popup the transition in flight opens on arrival
+    pub fn open_popup(&self) -> Option<LiveId> {
+        self.animation.as_ref().and_then(|animation| animation.open_popup)
+    }
+
+    /// Camera of the transition at `time`, ending it on its last frame
+    pub fn frame(&mut self, time: f64, projection: &dyn Projection) -> Option<CameraFrame> {
+        let frame = self.animation.as_mut()?.frame(time, projection);
+        if frame.finished {
+            self.cancel();
+        }
+        Some(frame)
+    }
+
+    /// Note where a frame put the camera (center in unit square positions)
+    pub fn record(&mut self, time: f64, unit: DVec2, zoom: f64, bearing: f64, pitch: f64) {
+        if self.animation.is_some() {
+            self.samples = [self.samples[1], Some(CameraSample { time, unit, zoom, bearing, pitch })];
+        }
+    }
+
+    /// Velocity between the last two animated cameras
+    fn velocity(&self) -> CameraVelocity {
+        let [Some(previous), Some(last)] = self.samples else {
+            return CameraVelocity::default();
+        };
+        let dt = last.time - previous.time;
+        if dt <= 0.0 {
+            return CameraVelocity::default();
+        }
+        // A step across the antimeridian is a small step, not most of the world
+        let mut unit = last.unit - previous.unit;
+        unit.x = (unit.x + 0.5).rem_euclid(1.0) - 0.5;
+        CameraVelocity {
+            unit: unit / dt,
+            zoom: (last.zoom - previous.zoom) / dt,
+            bearing: ((last.bearing - previous.bearing + 180.0).rem_euclid(360.0) - 180.0) / dt,
+            pitch: (last.pitch - previous.pitch) / dt,
+        }
+    }
+}
+
+/// Fraction of the shorter neighbouring segment over which the heading turns at a vertex
+const TURN_FRACTION: f64 = 0.2;
+
+/// Interpolate between two headings (degrees) the short way around
+fn lerp_heading(from: f64, to: f64, t: f64) -> f64 {
+    (from + ((to - from + 180.0).rem_euclid(360.0) - 180.0) * t).rem_euclid(360.0)
+}
+
+/// A marker moving along a path at constant ground speed, driven by NextFrame events
+pub(crate) struct MarkerPathAnimation {
+    pub id: LiveId,
+    points: Vec<(f64, f64)>,
+    /// Ground distance from the start to each point, in meters
+    distances: Vec<f64>,
+    /// Heading of each segment, degrees clockwise from north
+    headings: Vec<f64>,
+    pub duration: f64,
+    pub start_time: Option<f64>,
+}
+
+/// Marker position for one animation frame
+pub(crate) struct MarkerPathFrame {
+    pub lng: f64,
+    pub lat: f64,
+    /// Degrees clockwise from north, turning smoothly at the path's vertices
+    pub heading: f64,
+    /// Fraction of the path travelled
+    pub progress: f64,
+    pub finished: bool,
+}
+
+impl MarkerPathAnimation {
+    pub fn new(id: LiveId, points: Vec<(f64, f64)>, duration: f64) -> Self {
+        let mut distances = vec![0.0];
+        let mut headings: Vec<f64> = Vec::new();
+        for pair in points.windows(2) {
+            let ((lng1, lat1), (lng2, lat2)) = (pair[0], pair[1]);
+            let length = haversine_distance(lng1, lat1, lng2, lat2);
+            distances.push(distances.last().copied().unwrap_or_default() + length);
+            // Zero-length segments keep the previous heading
+            let heading = if length > 0.0 { bearing(lng1, lat1, lng2, lat2) } else { headings.last().copied().unwrap_or_default() };
+            headings.push(heading);
+        }
+        Self { id, points, distances, headings, duration, start_time: None }
+    }
+
+    /// Ground length of the path in meters
+    pub fn length(&self) -> f64 {
+        self.distances.last().copied().unwrap_or_default()
+    }
+
+    fn segment_length(&self, segment: usize) -> f64 {
+        self.distances[segment + 1] - self.distances[segment]
+    }
+
+    /// Heading at a distance along segment `segment`, blended with the
+    /// neighbouring segment's heading near either end
+    fn heading_at(&self, segment: usize, distance: f64) -> f64 {
+        let heading = self.headings[segment];
+        let turn_width = |vertex: usize| {
+            TURN_FRACTION * self.segment_length(vertex - 1).min(self.segment_length(vertex))
+        };
+        let start = self.distances[segment];
+        let end = self.distances[segment + 1];
+        if segment > 0 {
+            let width = turn_width(segment);
+            if width > 0.0 && distance - start < width {
+                let t = (distance - start + width) / (2.0 * width);
+                return lerp_heading(self.headings[segment - 1], heading, t);
+            }
+        }
+        if segment + 1 < self.headings.len() {
+            let width = turn_width(segment + 1);
+            if width > 0.0 && end - distance < width {
+                let t = (distance - end + width) / (2.0 * width);
+                return lerp_heading(heading, self.headings[segment + 1], t);
+            }
+        }
+        heading
+    }
+
+    /// Compute the marker at `time` (seconds), starting the clock on the first frame
+    pub fn frame(&mut self, time: f64, projection: &dyn Projection) -> MarkerPathFrame {
+        let start_time = *self.start_time.get_or_insert(time);
+        let progress = if self.duration > 0.0 {
+            ((time - start_time) / self.duration).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        let finished = progress >= 1.0;
+        let Some(&(last_lng, last_lat)) = self.points.last() else {
+            return MarkerPathFrame { lng: 0.0, lat: 0.0, heading: 0.0, progress, finished: true };
+        };
+        if self.headings.is_empty() {
+            return MarkerPathFrame { lng: last_lng, lat: last_lat, heading: 0.0, progress, finished: true };
+        }
+
+        let distance = self.length() * progress;
+        let segment = self.distances[1..].partition_point(|d| *d < distance).min(self.headings.len() - 1);
+        let length = self.segment_length(segment);
+        let t = if length > 0.0 { (distance - self.distances[segment]) / length } else { 1.0 };
+
+        // Straight in the map projection like a polyline, the short way around the antimeridian
+        let (from_lng, from_lat) = self.points[segment];
+        let (to_lng, to_lat) = self.points[segment + 1];
+        let to_lng = from_lng + (to_lng - from_lng + 180.0).rem_euclid(360.0) - 180.0;
+        let from_unit = projection.project(from_lng, from_lat);
+        let to_unit = projection.project(to_lng, to_lat);
+        let (lng, lat) = projection.unproject(from_unit + (to_unit - from_unit) * t);
+
+        MarkerPathFrame {
+            lng: (lng + 180.0).rem_euclid(360.0) - 180.0,
+            lat,
+            heading: self.heading_at(segment, distance),
+            progress,
+            finished,
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use std::fmt;
+
+use crate::json::{JsonError, JsonValue};
+
+/// Center, zoom, bearing and pitch of a map: read with `GeoMapView::camera`,
+/// applied in one step with `set_camera`, e.g. `MapCamera { zoom: 15.0,
+/// ..map.camera() }`, and persisted with `to_json` or the `serde` feature
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "crate::serde_impls::SavedCamera", into = "crate::serde_impls::SavedCamera"))]
+pub struct MapCamera {
+    /// (lng, lat)
+    pub center: (f64, f64),
+    pub zoom: f64,
+    /// Degrees clockwise from north
+    pub bearing: f64,
+    /// Degrees the map plane is tilted back
+    pub pitch: f64,
+}
+
+/// A saved camera, e.g. the last viewed region persisted across sessions
+/// with `GeoMapView::save_camera` and `restore_camera`
+pub type CameraState = MapCamera;
+
+/// Why a saved camera couldn't be read
+#[derive(Clone, Debug, PartialEq)]
+pub enum CameraStateError {
+    /// The text isn't valid JSON
+    Json(JsonError),
+    /// Valid JSON, but not a saved camera
+    Invalid(String),
+}
+
+impl fmt::Display for CameraStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CameraStateError::Json(error) => write!(f, "invalid JSON: {}", error),
+            CameraStateError::Invalid(message) => write!(f, "invalid camera state: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for CameraStateError {}
+
+impl MapCamera {
+    /// JSON object with the fields by name, e.g.
+    /// `{"lng":-122.41,"lat":37.77,"zoom":14,"bearing":0,"pitch":0}`
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"lng\":{},\"lat\":{},\"zoom\":{},\"bearing\":{},\"pitch\":{}}}",
+            json_number(self.center.0),
+            json_number(self.center.1),
+            json_number(self.zoom),
+            json_number(self.bearing),
+            json_number(self.pitch),
+        )
+    }
+
+    /// Read a camera written by `to_json`. `bearing` and `pitch` may be
+    /// missing (0), so hand-written or older states still load.
+    pub fn from_json(text: &str) -> Result<Self, CameraStateError> {
+        let root = JsonValue::parse(text).map_err(CameraStateError::Json)?;
+        let number = |key: &str| root.get(key).and_then(JsonValue::as_f64).filter(|n| n.is_finite());
+        let required = |key: &str| number(key).ok_or_else(|| CameraStateError::Invalid(format!("missing number \"{}\"", key)));
+        Ok(MapCamera {
+            center: (required("lng")?, required("lat")?),
+            zoom: required("zoom")?,
+            bearing: number("bearing").unwrap_or_default(),
+            pitch: number("pitch").unwrap_or_default(),
+        })
+    }
+}
+
+/// JSON has no NaN or infinity, write those as 0
+fn json_number(value: f64) -> f64 {
+    if value.is_finite() { value } else { 0.0 }
+}
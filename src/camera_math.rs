@@ -0,0 +1,399 @@
+//! Camera and tile math of the map, free of `Cx` so it runs (and is tested)
+//! headless: screen offsets under bearing and pitch, the tiles covering a
+//! viewport and the camera framing bounds. `MapViewport` converts between
+//! screen and geographic positions with these.
+
+use makepad_widgets::*;
+
+use crate::geo::GeoBounds;
+use crate::layers::MapViewport;
+use crate::projection::{Projection, TILE_SIZE};
+use crate::tiles::TileCoord;
+
+/// Fraction of the viewport `camera_for_bounds` fills, leaving a margin
+const FIT_FILL: f64 = 0.9;
+
+/// Rotate a vector by an angle in radians (screen coordinates, y down)
+pub fn rotate_vec(v: DVec2, angle: f64) -> DVec2 {
+    let (sin, cos) = angle.sin_cos();
+    dvec2(v.x * cos - v.y * sin, v.x * sin + v.y * cos)
+}
+
+/// Distance of the eye from the screen for a viewport height, in pixels
+/// (a vertical field of view of about 37 degrees)
+pub fn camera_distance(viewport_height: f64) -> f64 {
+    1.5 * viewport_height.max(1.0)
+}
+
+/// Screen offset from the center of a point on the map plane, when the plane
+/// is tilted back by `pitch` radians and seen from `distance` pixels.
+/// Must match the tilt in the `DrawMapTile` vertex shader.
+pub fn tilt_vec(v: DVec2, pitch: f64, distance: f64) -> DVec2 {
+    if pitch == 0.0 {
+        return v;
+    }
+    let (sin, cos) = pitch.sin_cos();
+    let scale = distance / (distance - v.y * sin).max(distance * 0.01);
+    dvec2(v.x * scale, v.y * cos * scale)
+}
+
+/// Point on the map plane under a screen offset from the center (inverse of `tilt_vec`)
+pub fn untilt_vec(v: DVec2, pitch: f64, distance: f64) -> DVec2 {
+    if pitch == 0.0 {
+        return v;
+    }
+    let (sin, cos) = pitch.sin_cos();
+    // Positions above the horizon are pushed to the far end of the plane
+    let y = v.y * distance / (distance * cos + v.y * sin).max(distance * 0.01);
+    let scale = distance / (distance - y * sin);
+    dvec2(v.x / scale, y)
+}
+
+/// Center `(lng, lat)` and zoom showing `bounds` in a north-up viewport of
+/// `size` pixels, with the zoom clamped to `min_zoom..=max_zoom`. The center
+/// is halfway between the edges as projected, so bounds far from the equator
+/// aren't pushed off screen. None if the bounds or the viewport are empty.
+pub fn camera_for_bounds(
+    projection: &dyn Projection,
+    bounds: &GeoBounds,
+    size: DVec2,
+    min_zoom: f64,
+    max_zoom: f64,
+) -> Option<(f64, f64, f64)> {
+    let (center_lng, _) = bounds.center();
+    let north = projection.project(center_lng, bounds.north);
+    let south = projection.project(center_lng, bounds.south);
+    // Unit square widths and heights, so the world size is the scale
+    let width = bounds.lng_span() / 360.0;
+    let height = (south.y - north.y).abs();
+    if !(width > 0.0 && height > 0.0 && size.x > 0.0 && size.y > 0.0) {
+        return None;
+    }
+    let scale = (size.x / width).min(size.y / height) * FIT_FILL;
+    let zoom = (scale / TILE_SIZE).log2().clamp(min_zoom, max_zoom);
+    let (_, center_lat) = projection.unproject(dvec2(north.x, (north.y + south.y) / 2.0));
+    Some((center_lng, center_lat, zoom))
+}
+
+/// Visible tile grid of one frame, around the tile containing the center
+pub(crate) struct TileGrid {
+    pub(crate) zoom: u8,
+    center_tile: (i32, i32),
+    /// Tiles drawn on each side of the center tile
+    extent: (i32, i32),
+    max_tile: i32,
+    /// Position of the center tile's top-left corner relative to the viewport, in scaled pixels
+    center_tile_pos: DVec2,
+    pub(crate) scaled_tile_size: f64,
+    world_repeat: bool,
+}
+
+impl TileGrid {
+    /// Tiles covering a viewport at its integer zoom, at most `max_zoom`, and
+    /// `overscan` rings of tiles around it
+    pub(crate) fn new(viewport: &MapViewport, max_zoom: u8, overscan: usize) -> Self {
+        let zoom = (viewport.zoom.floor().max(0.0) as u8).min(max_zoom);
+        let zoom_scale = 2.0_f64.powf(viewport.zoom - zoom as f64);
+        let center_world = viewport.projection.geo_to_world(viewport.center_lng, viewport.center_lat, zoom as f64);
+        let center_tile = ((center_world.x / TILE_SIZE).floor() as i32, (center_world.y / TILE_SIZE).floor() as i32);
+        let offset = (center_world - dvec2(center_tile.0 as f64, center_tile.1 as f64) * TILE_SIZE) * zoom_scale;
+
+        // The north-up extent of the map area under the viewport corners
+        // (larger when rotated or tilted)
+        let scaled_tile_size = TILE_SIZE * zoom_scale;
+        let half_size = viewport.rect.size / 2.0;
+        let center_pos = half_size + viewport.center_offset;
+        let (pitch, distance) = (viewport.pitch.to_radians(), camera_distance(viewport.rect.size.y));
+        let mut cover_half = DVec2::default();
+        for corner in [dvec2(-1.0, -1.0), dvec2(1.0, -1.0), dvec2(-1.0, 1.0), dvec2(1.0, 1.0)] {
+            let corner_offset = half_size * corner - viewport.center_offset;
+            let ground = rotate_vec(untilt_vec(corner_offset, pitch, distance), viewport.bearing.to_radians());
+            cover_half = dvec2(cover_half.x.max(ground.x.abs()), cover_half.y.max(ground.y.abs()));
+        }
+
+        Self {
+            zoom,
+            center_tile,
+            extent: (
+                (cover_half.x / scaled_tile_size).ceil() as i32 + overscan as i32,
+                (cover_half.y / scaled_tile_size).ceil() as i32 + overscan as i32,
+            ),
+            max_tile: 2_i32.pow(zoom as u32),
+            center_tile_pos: center_pos - offset,
+            scaled_tile_size,
+            // Without world repeat only the world copy containing the center is drawn
+            world_repeat: viewport.world_repeat,
+        }
+    }
+
+    /// Visible tiles with their top-left corners relative to the viewport, nearest the center first
+    pub(crate) fn tiles(&self) -> Vec<(TileCoord, DVec2)> {
+        let (center_x, center_y) = self.center_tile;
+        let (extent_x, extent_y) = self.extent;
+        let mut tiles = Vec::new();
+        for dy in -extent_y..=extent_y {
+            for dx in -extent_x..=extent_x {
+                let tile_y = center_y + dy;
+                if tile_y < 0 || tile_y >= self.max_tile
+                    || !(self.world_repeat || (0..self.max_tile).contains(&(center_x + dx))) {
+                    continue;
+                }
+                let coord = TileCoord {
+                    x: (center_x + dx).rem_euclid(self.max_tile) as u32,
+                    y: tile_y as u32,
+                    z: self.zoom,
+                };
+                let pos = self.center_tile_pos + dvec2(dx as f64, dy as f64) * self.scaled_tile_size;
+                tiles.push((dx * dx + dy * dy, coord, pos));
+            }
+        }
+        tiles.sort_by_key(|(distance, _, _)| *distance);
+        tiles.into_iter().map(|(_, coord, pos)| (coord, pos)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::projection::{world_size, WebMercator};
+    use std::collections::BTreeSet;
+    use std::rc::Rc;
+
+    fn map_viewport(lng: f64, lat: f64, zoom: f64, bearing: f64, pitch: f64) -> MapViewport {
+        MapViewport {
+            rect: Rect { pos: dvec2(10.0, 20.0), size: dvec2(800.0, 600.0) },
+            center_lng: lng,
+            center_lat: lat,
+            zoom,
+            bearing,
+            pitch,
+            world_repeat: true,
+            center_offset: DVec2::default(),
+            projection: Rc::new(WebMercator),
+        }
+    }
+
+    fn assert_near(actual: f64, expected: f64, tolerance: f64) {
+        assert!((actual - expected).abs() <= tolerance, "{actual} is not within {tolerance} of {expected}");
+    }
+
+    fn assert_geo_near(actual: (f64, f64), expected: (f64, f64)) {
+        assert_near(actual.0, expected.0, 1e-6);
+        assert_near(actual.1, expected.1, 1e-6);
+    }
+
+    fn screen_center(viewport: &MapViewport) -> DVec2 {
+        viewport.rect.pos + viewport.rect.size / 2.0 + viewport.center_offset
+    }
+
+    #[test]
+    fn untilt_inverts_tilt() {
+        let distance = camera_distance(600.0);
+        for pitch in [0.0_f64, 30.0, 60.0] {
+            for v in [dvec2(0.0, 0.0), dvec2(120.0, -250.0), dvec2(-300.0, 280.0)] {
+                let back = untilt_vec(tilt_vec(v, pitch.to_radians(), distance), pitch.to_radians(), distance);
+                assert_near(back.x, v.x, 1e-6);
+                assert_near(back.y, v.y, 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn center_is_at_screen_center() {
+        let mut viewport = map_viewport(13.4, 52.5, 11.3, 40.0, 45.0);
+        viewport.center_offset = dvec2(30.0, -12.0);
+        let pos = viewport.geo_to_screen(13.4, 52.5);
+        let expected = screen_center(&viewport);
+        assert_near(pos.x, expected.x, 1e-6);
+        assert_near(pos.y, expected.y, 1e-6);
+        assert_geo_near(viewport.screen_to_geo(expected), (13.4, 52.5));
+    }
+
+    #[test]
+    fn screen_to_geo_inverts_geo_to_screen() {
+        for (bearing, pitch) in [(0.0, 0.0), (35.0, 0.0), (-120.0, 50.0)] {
+            let mut viewport = map_viewport(-73.98, 40.75, 12.6, bearing, pitch);
+            viewport.center_offset = dvec2(-40.0, 25.0);
+            for (lng, lat) in [(-73.99, 40.76), (-73.97, 40.74), (-73.985, 40.745)] {
+                let pos = viewport.geo_to_screen(lng, lat);
+                assert_geo_near(viewport.screen_to_geo(pos), (lng, lat));
+            }
+        }
+    }
+
+    #[test]
+    fn fractional_zoom_scales_distances() {
+        let (lng, lat) = (2.35, 48.85);
+        let offset = |zoom: f64| {
+            let viewport = map_viewport(lng, lat, zoom, 0.0, 0.0);
+            viewport.geo_to_screen(lng + 0.01, lat) - screen_center(&viewport)
+        };
+        assert_near(offset(10.5).x / offset(10.0).x, 2.0_f64.sqrt(), 1e-9);
+        assert_near(offset(11.0).x / offset(10.0).x, 2.0, 1e-9);
+        assert_near(offset(10.0).x, 0.01 / 360.0 * world_size(10.0), 1e-9);
+    }
+
+    #[test]
+    fn antimeridian_uses_nearest_world_copy() {
+        let viewport = map_viewport(179.9, 0.0, 8.0, 0.0, 0.0);
+        let east = viewport.geo_to_screen(-179.9, 0.0);
+        let center = screen_center(&viewport);
+        // 0.2 degrees east of the center, not almost a whole world to the west
+        assert_near(east.x - center.x, 0.2 / 360.0 * world_size(8.0), 1e-6);
+        // Screen positions east of the antimeridian come back past 180 degrees
+        let (lng, _) = viewport.screen_to_geo(east);
+        assert_near(lng, 180.1, 1e-6);
+        assert_near(viewport.geo_to_screen_unwrapped(-179.9, 0.0).x - center.x, -359.8 / 360.0 * world_size(8.0), 1e-6);
+    }
+
+    #[test]
+    fn poles_are_at_world_edges() {
+        let viewport = map_viewport(0.0, 0.0, 0.0, 0.0, 0.0);
+        let top = viewport.rect.pos.y + viewport.rect.size.y / 2.0 - world_size(0.0) / 2.0;
+        // Web Mercator's square world ends at about 85.05 degrees
+        assert_near(viewport.geo_to_screen(0.0, 85.05112878).y, top, 1e-6);
+        assert_near(viewport.geo_to_screen(0.0, -85.05112878).y, top + world_size(0.0), 1e-6);
+        let (_, lat) = viewport.screen_to_geo(dvec2(viewport.rect.pos.x + 400.0, top));
+        assert_near(lat, 85.05112878, 1e-6);
+        // The top row of tiles starts at the edge
+        let tiles = TileGrid::new(&viewport, 19, 0).tiles();
+        let (_, pos) = tiles.iter().find(|(coord, _)| coord.y == 0).unwrap();
+        assert_near(pos.y + viewport.rect.pos.y, top, 1e-6);
+    }
+
+    #[test]
+    fn tile_grid_covers_viewport() {
+        let viewport = map_viewport(-0.12, 51.5, 10.4, 0.0, 0.0);
+        let grid = TileGrid::new(&viewport, 19, 0);
+        assert_eq!(grid.zoom, 10);
+        let tiles = grid.tiles();
+        let size = grid.scaled_tile_size;
+        // Tile positions are relative to the viewport
+        for corner in [dvec2(0.0, 0.0), dvec2(799.0, 0.0), dvec2(0.0, 599.0), dvec2(799.0, 599.0)] {
+            assert!(tiles.iter().any(|(_, pos)| {
+                corner.x >= pos.x && corner.x < pos.x + size && corner.y >= pos.y && corner.y < pos.y + size
+            }), "no tile covers {corner:?}");
+        }
+        // Nearest the center first, and each tile where the projection puts its corner
+        let (first, _) = tiles[0];
+        let center_world = viewport.projection.geo_to_world(-0.12, 51.5, 10.0);
+        assert_eq!((first.x, first.y), ((center_world.x / TILE_SIZE) as u32, (center_world.y / TILE_SIZE) as u32));
+        for (coord, pos) in &tiles {
+            let unit = dvec2(coord.x as f64, coord.y as f64) / 2.0_f64.powi(coord.z as i32);
+            let expected = viewport.unit_to_screen_near(unit, unit + dvec2(0.5, 0.5) / 2.0_f64.powi(coord.z as i32)) - viewport.rect.pos;
+            assert_near(pos.x, expected.x, 1e-6);
+            assert_near(pos.y, expected.y, 1e-6);
+        }
+    }
+
+    #[test]
+    fn tile_grid_wraps_across_antimeridian() {
+        let viewport = map_viewport(180.0, 0.0, 4.0, 0.0, 0.0);
+        let tiles = TileGrid::new(&viewport, 19, 0).tiles();
+        assert!(tiles.iter().any(|(coord, _)| coord.x == 0));
+        assert!(tiles.iter().any(|(coord, _)| coord.x == 15));
+        assert!(tiles.iter().all(|(coord, _)| coord.x < 16 && coord.z == 4));
+
+        // Without world repeat the tiles east of the antimeridian are left out
+        let mut single = map_viewport(170.0, 0.0, 4.0, 0.0, 0.0);
+        assert!(TileGrid::new(&single, 19, 0).tiles().iter().any(|(coord, _)| coord.x == 0));
+        single.world_repeat = false;
+        let tiles = TileGrid::new(&single, 19, 0).tiles();
+        assert!(!tiles.is_empty());
+        assert!(tiles.iter().all(|(coord, _)| coord.x >= 13));
+    }
+
+    #[test]
+    fn tile_grid_stops_at_poles() {
+        let viewport = map_viewport(0.0, 85.0, 3.0, 0.0, 0.0);
+        let tiles = TileGrid::new(&viewport, 19, 0).tiles();
+        assert!(tiles.iter().any(|(coord, _)| coord.y == 0));
+        assert!(tiles.iter().all(|(coord, _)| coord.y < 8));
+        assert!(tiles.iter().all(|(_, pos)| pos.y >= 300.0 - 8.0 * TILE_SIZE));
+    }
+
+    #[test]
+    fn tile_grid_clamps_to_max_zoom() {
+        let grid = TileGrid::new(&map_viewport(0.0, 0.0, 20.5, 0.0, 0.0), 18, 0);
+        assert_eq!(grid.zoom, 18);
+        assert_near(grid.scaled_tile_size, TILE_SIZE * 2.0_f64.powf(2.5), 1e-9);
+    }
+
+    #[test]
+    fn overscan_adds_a_ring_of_tiles() {
+        let viewport = map_viewport(10.0, 45.0, 9.0, 0.0, 0.0);
+        let inner = TileGrid::new(&viewport, 19, 0).tiles();
+        let outer = TileGrid::new(&viewport, 19, 1).tiles();
+        let count = |tiles: &[(TileCoord, DVec2)], axis: fn(&TileCoord) -> u32| {
+            tiles.iter().map(|(coord, _)| axis(coord)).collect::<BTreeSet<_>>().len()
+        };
+        assert_eq!(count(&outer, |coord| coord.x), count(&inner, |coord| coord.x) + 2);
+        assert_eq!(count(&outer, |coord| coord.y), count(&inner, |coord| coord.y) + 2);
+    }
+
+    #[test]
+    fn rotated_and_tilted_grid_covers_corners() {
+        let viewport = map_viewport(139.7, 35.7, 13.0, 45.0, 60.0);
+        let flat = TileGrid::new(&map_viewport(139.7, 35.7, 13.0, 0.0, 0.0), 19, 0).tiles();
+        let tiles = TileGrid::new(&viewport, 19, 0).tiles();
+        assert!(tiles.len() > flat.len());
+        let zoom = 13.0;
+        for corner in [dvec2(0.0, 0.0), dvec2(800.0, 0.0), dvec2(0.0, 600.0), dvec2(800.0, 600.0)] {
+            let (lng, lat) = viewport.screen_to_geo(viewport.rect.pos + corner);
+            let world = viewport.projection.geo_to_world(lng, lat, zoom) / TILE_SIZE;
+            let (x, y) = (world.x.floor() as u32, world.y.floor() as u32);
+            assert!(tiles.iter().any(|(coord, _)| coord.x == x && coord.y == y), "no tile under corner {corner:?}");
+        }
+    }
+
+    fn fits(bounds: &GeoBounds, camera: (f64, f64, f64)) -> bool {
+        let (lng, lat, zoom) = camera;
+        let viewport = MapViewport { rect: Rect { pos: DVec2::default(), size: dvec2(800.0, 600.0) }, ..map_viewport(lng, lat, zoom, 0.0, 0.0) };
+        [(bounds.west, bounds.north), (bounds.east, bounds.north), (bounds.west, bounds.south), (bounds.east, bounds.south)]
+            .into_iter()
+            .all(|(lng, lat)| viewport.is_visible(viewport.geo_to_screen(lng, lat), 1e-6))
+    }
+
+    #[test]
+    fn camera_for_bounds_frames_bounds() {
+        let size = dvec2(800.0, 600.0);
+        let bounds = GeoBounds { west: 5.9, south: 45.8, east: 10.5, north: 47.8 };
+        let camera = camera_for_bounds(&WebMercator, &bounds, size, 0.0, 22.0).unwrap();
+        assert!(fits(&bounds, camera));
+        // Tight: one more zoom level doesn't fit
+        assert!(!fits(&bounds, (camera.0, camera.1, camera.2 + 0.5)));
+        assert_near(camera.0, 8.2, 1e-9);
+    }
+
+    #[test]
+    fn camera_for_bounds_centers_high_latitudes_as_projected() {
+        let size = dvec2(800.0, 600.0);
+        let bounds = GeoBounds { west: -30.0, south: 0.0, east: 30.0, north: 80.0 };
+        let (lng, lat, zoom) = camera_for_bounds(&WebMercator, &bounds, size, 0.0, 22.0).unwrap();
+        // Mercator stretches the north, so the projected middle is well above 40 degrees
+        assert!(lat > 50.0);
+        assert!(fits(&bounds, (lng, lat, zoom)));
+    }
+
+    #[test]
+    fn camera_for_bounds_across_antimeridian() {
+        let size = dvec2(800.0, 600.0);
+        let bounds = GeoBounds { west: 170.0, south: -20.0, east: -170.0, north: 0.0 };
+        let (lng, lat, zoom) = camera_for_bounds(&WebMercator, &bounds, size, 0.0, 22.0).unwrap();
+        assert_near(lng.abs(), 180.0, 1e-9);
+        // Framing 20 degrees, not the 340 degrees the other way around
+        assert!(zoom > 3.0);
+        assert!(fits(&bounds, (lng, lat, zoom)));
+    }
+
+    #[test]
+    fn camera_for_bounds_clamps_and_rejects_empty() {
+        let size = dvec2(800.0, 600.0);
+        let tiny = GeoBounds { west: 8.5, south: 47.37, east: 8.5001, north: 47.3701 };
+        assert_eq!(camera_for_bounds(&WebMercator, &tiny, size, 0.0, 17.0).unwrap().2, 17.0);
+        let point = GeoBounds { west: 8.5, south: 47.37, east: 8.5, north: 47.37 };
+        assert!(camera_for_bounds(&WebMercator, &point, size, 0.0, 22.0).is_none());
+        let bounds = GeoBounds { west: 5.9, south: 45.8, east: 10.5, north: 47.8 };
+        assert!(camera_for_bounds(&WebMercator, &bounds, DVec2::default(), 0.0, 22.0).is_none());
+    }
+}
@@ -0,0 +1,73 @@
+use makepad_widgets::*;
+
+use crate::camera::MapCamera;
+use crate::map_view::GeoMapViewRef;
+
+/// Center (lng, lat), zoom and bearing of a map
+type Camera = (f64, f64, f64, f64);
+
+/// Keeps the cameras of several maps locked together, e.g. for comparing
+/// before/after imagery side by side, or stacked with a swipe divider
+/// (see `GeoMapView::swipe_position`).
+///
+/// Call `sync` after the UI handled an event. Whichever map's camera moved
+/// since the last sync leads, and the others are set to its camera.
+#[derive(Clone, Default)]
+pub struct MapCameraSync {
+    maps: Vec<GeoMapViewRef>,
+    camera: Option<Camera>,
+}
+
+impl MapCameraSync {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a map to the group. The first map's camera is the starting camera.
+    pub fn link(&mut self, map: GeoMapViewRef) {
+        self.maps.push(map);
+    }
+
+    /// Remove all maps from the group
+    pub fn unlink_all(&mut self) {
+        self.maps.clear();
+        self.camera = None;
+    }
+
+    /// Number of linked maps
+    pub fn len(&self) -> usize {
+        self.maps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.maps.is_empty()
+    }
+
+    /// Copy the camera of the map that moved to the others.
+    /// Returns true if any map was updated.
+    pub fn sync(&mut self, cx: &mut Cx) -> bool {
+        let cameras: Vec<Option<Camera>> = self.maps.iter()
+            .map(|map| map.borrow().map(|inner| (inner.center_lng, inner.center_lat, inner.zoom, inner.bearing)))
+            .collect();
+        let leader = match self.camera {
+            None => cameras.iter().flatten().next().copied(),
+            Some(last) => cameras.iter().flatten().find(|camera| **camera != last).copied(),
+        };
+        let Some(camera) = leader else {
+            return false;
+        };
+        self.camera = Some(camera);
+
+        let mut updated = false;
+        for (map, current) in self.maps.iter().zip(cameras) {
+            if current.is_some_and(|current| current != camera) {
+                let (lng, lat, zoom, bearing) = camera;
+                // Each map keeps its own pitch
+                let follower = map.camera().unwrap_or_default();
+                map.set_camera(cx, MapCamera { center: (lng, lat), zoom, bearing, ..follower });
+                updated = true;
+            }
+        }
+        updated
+    }
+}
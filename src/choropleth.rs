@@ -0,0 +1,327 @@
+use makepad_widgets::*;
+use std::collections::HashMap;
+
+use crate::legend::MapLegend;
+use crate::layers::{MapLayer, MapViewport, OverlayHit, ProjectedShape};
+use crate::spatial_index::SpatialIndex;
+use crate::map_view::DrawMapLine;
+use crate::quality::QualityLevel;
+
+live_design! {
+    link widgets;
+    use link::theme::*;
+    use link::shaders::*;
+    use link::widgets::*;
+    use crate::map_view::DrawMapLine;
+    use crate::legend::MapLegend;
+
+    // Solid triangle given by its corners relative to the rect, used to fill
+    // tessellated polygons. No anti-aliasing, so shared edges leave no seams.
+    pub DrawMapTriangle = {{DrawMapTriangle}} {
+        color: #1e88e5
+
+        fn pixel(self) -> vec4 {
+            let p = self.pos * self.rect_size;
+            let d0 = (self.p1.x - self.p0.x) * (p.y - self.p0.y) - (self.p1.y - self.p0.y) * (p.x - self.p0.x);
+            let d1 = (self.p2.x - self.p1.x) * (p.y - self.p1.y) - (self.p2.y - self.p1.y) * (p.x - self.p1.x);
+            let d2 = (self.p0.x - self.p2.x) * (p.y - self.p2.y) - (self.p0.y - self.p2.y) * (p.x - self.p2.x);
+            if (d0 < 0.0 || d1 < 0.0 || d2 < 0.0) && (d0 > 0.0 || d1 > 0.0 || d2 > 0.0) {
+                return vec4(0.0);
+            }
+            return vec4(self.color.rgb * self.color.a, self.color.a);
+        }
+    }
+
+    // Regions filled by a numeric property along a color ramp, with a legend
+    pub ChoroplethLayer = {{ChoroplethLayer}} {
+        draw_outline: {
+            color: #ffffffcc
+            line_width: 1.0
+        }
+        legend: <MapLegend> {}
+    }
+}
+
+#[derive(Live, LiveRegister, LiveHook)]
+#[repr(C)]
+pub struct DrawMapTriangle {
+    #[deref] pub draw_super: DrawQuad,
+    #[live] pub color: Vec4,
+    #[live] pub p0: Vec2,
+    #[live] pub p1: Vec2,
+    #[live] pub p2: Vec2,
+}
+
+impl DrawMapTriangle {
+    /// Fill a triangle between absolute positions
+    pub fn draw_triangle(&mut self, cx: &mut Cx2d, a: DVec2, b: DVec2, c: DVec2) {
+        let min = dvec2(a.x.min(b.x).min(c.x), a.y.min(b.y).min(c.y)).floor();
+        let max = dvec2(a.x.max(b.x).max(c.x), a.y.max(b.y).max(c.y)).ceil();
+        self.p0 = (a - min).into_vec2();
+        self.p1 = (b - min).into_vec2();
+        self.p2 = (c - min).into_vec2();
+        self.draw_abs(cx, Rect { pos: min, size: max - min });
+    }
+}
+
+/// Classes of values mapped to colors: values below `breaks[0]` get
+/// `colors[0]`, values from `breaks[i - 1]` up to `breaks[i]` get `colors[i]`,
+/// and so on, so there is one more color than breaks.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorRamp {
+    /// Ascending class boundaries
+    pub breaks: Vec<f64>,
+    pub colors: Vec<Vec4>,
+    /// Color for regions without the property (or NaN values)
+    pub no_data: Vec4,
+}
+
+impl Default for ColorRamp {
+    /// Five classes from 0 to 100 in a yellow-orange-red sequential palette
+    fn default() -> Self {
+        Self::equal_interval(0.0, 100.0, &Self::yellow_orange_red())
+    }
+}
+
+impl ColorRamp {
+    pub fn new(breaks: Vec<f64>, colors: Vec<Vec4>) -> Self {
+        Self { breaks, colors, no_data: vec4(0.8, 0.8, 0.8, 0.6) }
+    }
+
+    /// Classes of equal width between `min` and `max`, one per color
+    pub fn equal_interval(min: f64, max: f64, colors: &[Vec4]) -> Self {
+        let classes = colors.len().max(1);
+        let breaks = (1..classes).map(|i| min + (max - min) * i as f64 / classes as f64).collect();
+        Self::new(breaks, colors.to_vec())
+    }
+
+    /// Classes holding roughly the same number of values, one per color
+    pub fn quantiles(values: impl IntoIterator<Item = f64>, colors: &[Vec4]) -> Self {
+        let mut values: Vec<f64> = values.into_iter().filter(|v| v.is_finite()).collect();
+        values.sort_by(|a, b| a.total_cmp(b));
+        let classes = colors.len().max(1);
+        let mut breaks: Vec<f64> = (1..classes)
+            .filter_map(|i| values.get(i * values.len() / classes).copied())
+            .collect();
+        breaks.dedup();
+        Self::new(breaks, colors[..(breaks.len() + 1).min(colors.len())].to_vec())
+    }
+
+    /// The default five-class palette (ColorBrewer YlOrRd)
+    pub fn yellow_orange_red() -> Vec<Vec4> {
+        vec![
+            vec4(1.0, 1.0, 0.698, 1.0),
+            vec4(0.996, 0.8, 0.361, 1.0),
+            vec4(0.992, 0.553, 0.235, 1.0),
+            vec4(0.941, 0.231, 0.125, 1.0),
+            vec4(0.741, 0.0, 0.149, 1.0),
+        ]
+    }
+
+    /// Color of the class a value falls in
+    pub fn color_for(&self, value: f64) -> Vec4 {
+        if value.is_nan() || self.colors.is_empty() {
+            return self.no_data;
+        }
+        let class = self.breaks.iter().take_while(|limit| value >= **limit).count();
+        self.colors[class.min(self.colors.len() - 1)]
+    }
+
+    /// Legend entries: each class's color and label, e.g. "< 20", "20 – 40", "≥ 80"
+    pub fn legend_entries(&self) -> Vec<(Vec4, String)> {
+        self.colors.iter().enumerate().map(|(i, &color)| {
+            let label = match (i.checked_sub(1).and_then(|i| self.breaks.get(i)), self.breaks.get(i)) {
+                (None, Some(upper)) => format!("< {}", format_value(*upper)),
+                (Some(lower), Some(upper)) => format!("{} \u{2013} {}", format_value(*lower), format_value(*upper)),
+                (Some(lower), None) => format!("\u{2265} {}", format_value(*lower)),
+                (None, None) => "all".to_string(),
+            };
+            (color, label)
+        }).collect()
+    }
+}
+
+/// A polygon (first ring exterior, rest holes) with numeric properties, e.g. a
+/// country or census district. Multipolygons are several regions with one ID.
+#[derive(Clone, Debug, Default)]
+pub struct ChoroplethRegion {
+    pub id: LiveId,
+    /// Shown with the region's value in its hover tooltip
+    pub name: String,
+    /// Rings of (lng, lat) positions, closing points optional
+    pub rings: Vec<Vec<(f64, f64)>>,
+    pub properties: HashMap<String, f64>,
+}
+
+/// Fills polygons by the value of a numeric property along a color ramp,
+/// and shows a legend of the classes (a `MapLegend`, top-right by default).
+///
+/// Regions are tessellated once per projection, so restyling (another
+/// property or ramp) is cheap and panning never re-tessellates.
+#[derive(Live, LiveHook, LiveRegister)]
+pub struct ChoroplethLayer {
+    #[live] draw_fill: DrawMapTriangle,
+    #[live] draw_outline: DrawMapLine,
+    /// Property the regions are colored by
+    #[live] pub property: String,
+    #[live(0.75)] pub fill_opacity: f64,
+    /// Legend of the ramp's classes, e.g. `legend: { title: "Density", corner: BottomLeft }`
+    #[live] pub legend: MapLegend,
+    #[live(true)] pub visible: bool,
+    #[rust] regions: Vec<ChoroplethRegion>,
+    #[rust] ramp: ColorRamp,
+    // Tessellation for the projection with this code
+    #[rust] geometry: Option<(String, Vec<ProjectedShape>)>,
+    // Regions by their box, to draw only those in view
+    #[rust] index: SpatialIndex,
+    #[rust] quality: QualityLevel,
+    // Layer opacity from the map, on top of fill_opacity
+    #[rust(1.0)] opacity: f64,
+}
+
+impl ChoroplethLayer {
+    /// Replace the regions (tessellated on the next draw)
+    pub fn set_regions(&mut self, regions: Vec<ChoroplethRegion>) {
+        self.regions = regions;
+        self.geometry = None;
+    }
+
+    pub fn regions(&self) -> &[ChoroplethRegion] {
+        &self.regions
+    }
+
+    /// Set the classes and colors
+    pub fn set_ramp(&mut self, ramp: ColorRamp) {
+        self.legend.set_ramp(&ramp);
+        self.ramp = ramp;
+    }
+
+    pub fn ramp(&self) -> &ColorRamp {
+        &self.ramp
+    }
+
+    /// Color the regions by another property
+    pub fn set_property(&mut self, property: &str) {
+        self.property = property.to_string();
+    }
+
+    /// Value of the styled property for a region (NaN if it doesn't have it)
+    pub fn value_of(&self, region: &ChoroplethRegion) -> f64 {
+        region.properties.get(&self.property).copied().unwrap_or(f64::NAN)
+    }
+
+    /// Regions projected and tessellated for the viewport's projection
+    fn update_geometry(&mut self, viewport: &MapViewport) {
+        let projection = viewport.projection.as_ref();
+        if self.geometry.as_ref().is_some_and(|(code, _)| code == projection.code()) {
+            return;
+        }
+        let geometry: Vec<_> = self.regions.iter()
+            .map(|region| ProjectedShape::polygon(projection, &region.rings))
+            .collect();
+        self.index = SpatialIndex::from_boxes(geometry.iter().map(ProjectedShape::bounds));
+        self.geometry = Some((projection.code().to_string(), geometry));
+    }
+
+    /// Hover text for a region, e.g. "Mission: 1.2k" or "Mission: no data"
+    fn tooltip(&self, region: &ChoroplethRegion) -> String {
+        let value = self.value_of(region);
+        let value = if value.is_nan() { "no data".to_string() } else { format_value(value) };
+        if region.name.is_empty() {
+            value
+        } else {
+            format!("{}: {}", region.name, value)
+        }
+    }
+
+    fn fill_color(&self, region: &ChoroplethRegion) -> Vec4 {
+        let mut color = self.ramp.color_for(self.value_of(region));
+        color.w *= (self.fill_opacity * self.opacity) as f32;
+        color
+    }
+}
+
+impl MapLayer for ChoroplethLayer {
+    fn draw_layer(&mut self, cx: &mut Cx2d, viewport: &MapViewport) {
+        self.update_geometry(viewport);
+        let Some((_, geometry)) = self.geometry.take() else {
+            return;
+        };
+        // Outlines are the first thing to go when frames are slow
+        let outlines = self.quality < QualityLevel::CoarseOverlays;
+        let outline_alpha = self.draw_outline.color.w;
+        self.draw_outline.color.w = outline_alpha * self.opacity as f32;
+        for i in self.index.query_viewport(viewport, 0.0) {
+            let (region, shape) = (&self.regions[i], &geometry[i]);
+            if !shape.is_on_screen(viewport) {
+                continue;
+            }
+            let to_screen = |unit: DVec2| shape.to_screen(viewport, unit);
+            self.draw_fill.color = self.fill_color(region);
+            for [a, b, c] in &shape.triangles {
+                self.draw_fill.draw_triangle(cx, to_screen(*a), to_screen(*b), to_screen(*c));
+            }
+            if outlines {
+                for (from, to) in shape.segments() {
+                    let (from, to) = (to_screen(from), to_screen(to));
+                    if from != to {
+                        self.draw_outline.draw_line(cx, from, to);
+                    }
+                }
+            }
+        }
+        self.draw_outline.color.w = outline_alpha;
+        self.geometry = Some((viewport.projection.code().to_string(), geometry));
+
+        if self.legend.is_visible() {
+            if self.legend.is_empty() {
+                self.legend.set_ramp(&self.ramp);
+            }
+            self.legend.draw_layer(cx, viewport);
+        }
+    }
+
+    fn set_quality(&mut self, level: QualityLevel) {
+        self.quality = level;
+    }
+
+    fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    fn set_opacity(&mut self, opacity: f64) {
+        self.opacity = opacity;
+    }
+
+    fn overlay_at(&self, viewport: &MapViewport, pos: DVec2) -> Option<OverlayHit> {
+        let (code, geometry) = self.geometry.as_ref()?;
+        if code != viewport.projection.code() {
+            return None;
+        }
+        let (min, max) = viewport.unit_bounds(Rect { pos, size: dvec2(1.0, 1.0) });
+        // Later regions are drawn on top
+        self.index.query_wrapped(min, max, viewport.projection.wraps_longitude()).into_iter().rev()
+            .find(|&i| geometry[i].hit_test(viewport, pos, 0.0))
+            .map(|i| OverlayHit { id: self.regions[i].id, tooltip: self.tooltip(&self.regions[i]) })
+    }
+}
+
+/// Short label for a class boundary, e.g. "12", "3.5", "0.25", "1.2M"
+fn format_value(value: f64) -> String {
+    let abs = value.abs();
+    if abs >= 1e6 {
+        format!("{:.1}M", value / 1e6)
+    } else if abs >= 1e4 {
+        format!("{:.0}k", value / 1e3)
+    } else if abs >= 100.0 || value.fract() == 0.0 {
+        format!("{:.0}", value)
+    } else if abs >= 1.0 {
+        format!("{:.1}", value)
+    } else {
+        format!("{:.2}", value)
+    }
+}
@@ -0,0 +1,101 @@
+use makepad_widgets::*;
+
+use crate::locale::MapLocale;
+use crate::utm;
+
+/// How the map writes positions, in the cursor readout and `format_position`
+#[derive(Copy, Clone, Debug, Live, LiveHook, PartialEq)]
+#[live_ignore]
+pub enum CoordinateFormat {
+    /// Decimal degrees, e.g. "37.77490° N, 122.41940° W"
+    #[pick] Decimal,
+    /// Degrees, minutes and seconds, e.g. "37°46′29.6″ N, 122°25′09.8″ W"
+    DegreesMinutesSeconds,
+    /// UTM zone, band, easting and northing, e.g. "10S 551130 4180998"
+    Utm,
+    /// MGRS grid reference to the meter, e.g. "10SEG 51130 80998"
+    Mgrs,
+}
+
+impl CoordinateFormat {
+    /// Write a position in this format. Grid formats fall back to decimal
+    /// degrees outside the UTM range (north of 84°N and south of 80°S).
+    pub fn format(self, lng: f64, lat: f64, locale: &MapLocale) -> String {
+        let grid = match self {
+            CoordinateFormat::Decimal => None,
+            CoordinateFormat::DegreesMinutesSeconds => {
+                return format!("{}, {}", dms(lat, 'N', 'S', locale), dms(lng, 'E', 'W', locale));
+            }
+            CoordinateFormat::Utm => utm::to_utm(lng, lat).map(|utm| utm.to_string()),
+            CoordinateFormat::Mgrs => utm::to_mgrs(lng, lat, 5),
+        };
+        grid.unwrap_or_else(|| locale.format_position(lng, lat))
+    }
+}
+
+/// An angle as degrees, minutes and seconds to a tenth, e.g. "122°25′09.8″ W"
+fn dms(value: f64, positive: char, negative: char, locale: &MapLocale) -> String {
+    let tenths = (value.abs() * 36000.0).round() as u64;
+    let (degrees, minutes, seconds) = (tenths / 36000, tenths / 600 % 60, (tenths % 600) as f64 / 10.0);
+    let hemisphere = if value < 0.0 { negative } else { positive };
+    let seconds = locale.format_number(seconds, 1);
+    let padding = if seconds.len() < 4 { "0" } else { "" };
+    format!("{}°{:02}′{}{}″ {}", degrees, minutes, padding, seconds, hemisphere)
+}
+
+/// Result of looking up a position with a [`CoordinateProvider`]
+#[derive(Clone, Debug)]
+pub struct CoordinateLookup {
+    pub lng: f64,
+    pub lat: f64,
+    pub text: String,
+}
+
+/// Hook for third-party coordinate and addressing systems (grid references,
+/// what3words-style word addresses, plus codes, ...).
+///
+/// A provider contributes a synchronous readout for a position and a tap
+/// lookup, which may complete immediately or later via an HTTP response.
+/// The map routes network responses to the provider, so it can issue its own
+/// `cx.http_request` calls without the crate knowing about its API.
+pub trait CoordinateProvider {
+    /// Short name of the coordinate system (e.g. "MGRS")
+    fn name(&self) -> &str;
+
+    /// Format a position for display, if possible without a network round trip
+    fn readout(&self, lng: f64, lat: f64) -> Option<String>;
+
+    /// Look up a tapped position. Return the result directly if it is available
+    /// immediately, or None if it will arrive through `handle_response`.
+    fn lookup(&mut self, _cx: &mut Cx, lng: f64, lat: f64) -> Option<CoordinateLookup> {
+        self.readout(lng, lat).map(|text| CoordinateLookup { lng, lat, text })
+    }
+
+    /// Handle an HTTP response, returning a finished lookup if the response
+    /// belonged to one of this provider's requests
+    fn handle_response(&mut self, _cx: &mut Cx, _request_id: LiveId, _response: &HttpResponse) -> Option<CoordinateLookup> {
+        None
+    }
+}
+
+/// Built-in provider reporting positions as MGRS grid references
+pub struct MgrsProvider {
+    /// Digits per axis (1 = 10km, 5 = 1m precision)
+    pub digits: usize,
+}
+
+impl Default for MgrsProvider {
+    fn default() -> Self {
+        Self { digits: 5 }
+    }
+}
+
+impl CoordinateProvider for MgrsProvider {
+    fn name(&self) -> &str {
+        "MGRS"
+    }
+
+    fn readout(&self, lng: f64, lat: f64) -> Option<String> {
+        utm::to_mgrs(lng, lat, self.digits)
+    }
+}
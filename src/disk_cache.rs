@@ -1,11 +1,56 @@
+use makepad_widgets::*;
+use makepad_widgets::image_cache::ImageBuffer;
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
-use std::time::SystemTime;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use crate::tiles::TileCoord;
+use crate::tiles::{decode_tile, TileCoord};
 
-/// Maximum cache size in bytes (50MB)
-const MAX_CACHE_SIZE: u64 = 50 * 1024 * 1024;
+/// Default maximum cache size in bytes (50MB)
+pub const DEFAULT_MAX_CACHE_SIZE: u64 = 50 * 1024 * 1024;
+
+/// Default age after which cached tiles are revalidated with the server (7 days)
+pub const DEFAULT_TILE_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Where tiles are cached on disk and how much space they may take
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiskCacheConfig {
+    /// Cache root directory, tiles are stored in `tiles/` below it.
+    /// None uses the platform cache directory (see [`cache_dir`]).
+    pub dir: Option<PathBuf>,
+    /// Size limit in bytes, the oldest tiles are evicted above it
+    pub max_bytes: u64,
+    /// With disk caching disabled tiles are only kept in GPU memory
+    /// and nothing is read from or written to disk (e.g. privacy mode)
+    pub enabled: bool,
+    /// Cached tiles older than this are still shown, but revalidated with a
+    /// conditional request (a 304 response just refreshes them)
+    pub ttl: Duration,
+}
+
+impl Default for DiskCacheConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            max_bytes: DEFAULT_MAX_CACHE_SIZE,
+            enabled: true,
+            ttl: DEFAULT_TILE_TTL,
+        }
+    }
+}
+
+impl DiskCacheConfig {
+    /// Directory holding the cached tiles (None if disabled or no cache directory is available)
+    pub fn tiles_dir(&self) -> Option<PathBuf> {
+        if !self.enabled {
+            return None;
+        }
+        self.dir.clone().or_else(cache_dir).map(|base| base.join("tiles"))
+    }
+}
 
 /// Get platform-specific cache directory
 pub fn cache_dir() -> Option<PathBuf> {
@@ -61,130 +106,497 @@ pub fn cache_dir() -> Option<PathBuf> {
     }
 }
 
-/// Generate cache file path for a tile
-/// Format: {cache_dir}/tiles/{z}/{x}/{y}.png
-pub fn tile_path(coord: &TileCoord) -> Option<PathBuf> {
-    cache_dir().map(|base| {
-        base.join("tiles")
-            .join(coord.z.to_string())
-            .join(coord.x.to_string())
-            .join(format!("{}.png", coord.y))
-    })
+/// HTTP validators of a cached tile, sent back as `If-None-Match` and
+/// `If-Modified-Since` when revalidating it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TileValidators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
 }
 
-/// Save tile PNG data to disk
-pub fn save_tile(coord: &TileCoord, data: &[u8]) -> bool {
-    let Some(path) = tile_path(coord) else { return false };
-    path.parent()
-        .and_then(|p| fs::create_dir_all(p).ok())
-        .and_then(|_| fs::write(&path, data).ok())
-        .is_some()
+impl TileValidators {
+    /// Read the `ETag` and `Last-Modified` headers of a response
+    pub fn from_response(response: &HttpResponse) -> Self {
+        let header = |name: &str| {
+            response.headers.iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .and_then(|(_, values)| values.first())
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty() && !value.contains(['\t', '\n']))
+        };
+        Self {
+            etag: header("ETag"),
+            last_modified: header("Last-Modified"),
+        }
+    }
+
+    /// Add the conditional request headers for these validators
+    pub fn apply(&self, request: &mut HttpRequest) {
+        if let Some(etag) = &self.etag {
+            request.set_header("If-None-Match".to_string(), etag.clone());
+        }
+        if let Some(last_modified) = &self.last_modified {
+            request.set_header("If-Modified-Since".to_string(), last_modified.clone());
+        }
+    }
 }
 
-/// Load tile PNG data from disk
-pub fn load_tile(coord: &TileCoord) -> Option<Vec<u8>> {
-    fs::read(tile_path(coord)?).ok()
+/// Generate cache file path for a tile, optionally in a per-source namespace
+/// Format: {cache_dir}/tiles/[{namespace}/]{z}/{x}/{y}.png
+pub fn tile_path(config: &DiskCacheConfig, namespace: Option<&str>, coord: &TileCoord) -> Option<PathBuf> {
+    config.tiles_dir().map(|dir| dir.join(relative_tile_path(namespace, coord)))
 }
 
-/// Get total size of cache directory in bytes
-pub fn cache_size() -> u64 {
-    let Some(base) = cache_dir() else {
-        return 0;
-    };
+/// Path of a tile relative to the tiles directory, as stored in the index
+fn relative_tile_path(namespace: Option<&str>, coord: &TileCoord) -> PathBuf {
+    let mut path = PathBuf::new();
+    if let Some(namespace) = namespace {
+        path.push(namespace);
+    }
+    path.join(coord.z.to_string())
+        .join(coord.x.to_string())
+        .join(format!("{}.png", coord.y))
+}
 
-    let tiles_dir = base.join("tiles");
-    if !tiles_dir.exists() {
-        return 0;
+/// Save tile image data to disk, with the validators of the response it came from
+pub fn save_tile(
+    config: &DiskCacheConfig,
+    namespace: Option<&str>,
+    coord: &TileCoord,
+    data: &[u8],
+    validators: &TileValidators,
+) -> bool {
+    let Some(tiles_dir) = config.tiles_dir() else { return false };
+    let relative = relative_tile_path(namespace, coord);
+    let path = tiles_dir.join(&relative);
+    let saved = path.parent()
+        .and_then(|p| fs::create_dir_all(p).ok())
+        .and_then(|_| fs::write(&path, data).ok())
+        .is_some();
+    if saved {
+        with_index(&tiles_dir, |index| index.insert(relative, data.len() as u64, validators.clone()));
     }
+    saved
+}
+
+/// Check if a tile is in the disk cache without reading it
+pub fn has_tile(config: &DiskCacheConfig, namespace: Option<&str>, coord: &TileCoord) -> bool {
+    tile_path(config, namespace, coord).is_some_and(|path| path.is_file())
+}
 
-    calculate_dir_size(&tiles_dir)
+/// Load tile image data from disk
+pub fn load_tile(config: &DiskCacheConfig, namespace: Option<&str>, coord: &TileCoord) -> Option<Vec<u8>> {
+    let tiles_dir = config.tiles_dir()?;
+    let relative = relative_tile_path(namespace, coord);
+    let data = fs::read(tiles_dir.join(&relative)).ok();
+    with_index(&tiles_dir, |index| match &data {
+        Some(data) => index.touch(relative, data.len() as u64),
+        // Deleted behind the index's back
+        None => index.remove(&relative),
+    });
+    data
 }
 
-fn calculate_dir_size(path: &PathBuf) -> u64 {
-    fs::read_dir(path).into_iter().flatten().flatten().fold(0, |acc, entry| {
-        let p = entry.path();
-        acc + if p.is_dir() { calculate_dir_size(&p) } else { entry.metadata().map(|m| m.len()).unwrap_or(0) }
+/// Validators of a cached tile if it's older than the configured TTL and
+/// should be revalidated (None if it's fresh or not cached)
+pub fn stale_validators(config: &DiskCacheConfig, namespace: Option<&str>, coord: &TileCoord) -> Option<TileValidators> {
+    let tiles_dir = config.tiles_dir()?;
+    let relative = relative_tile_path(namespace, coord);
+    with_index(&tiles_dir, |index| {
+        let entry = index.entries.get(&relative)?;
+        let age = unix_time().saturating_sub(entry.fetched);
+        (age >= config.ttl.as_secs()).then(|| entry.validators.clone())
     })
 }
 
-/// Evict oldest files until cache is under MAX_CACHE_SIZE
-/// Call this periodically (e.g., on app startup or after saving tiles)
-pub fn evict_if_needed() {
-    let current_size = cache_size();
-    if current_size <= MAX_CACHE_SIZE {
+/// Mark a cached tile as fresh after the server confirmed it (HTTP 304)
+pub fn refresh_tile(config: &DiskCacheConfig, namespace: Option<&str>, coord: &TileCoord) {
+    let Some(tiles_dir) = config.tiles_dir() else {
         return;
-    }
+    };
+    let relative = relative_tile_path(namespace, coord);
+    with_index(&tiles_dir, |index| {
+        if let Some(entry) = index.entries.get_mut(&relative) {
+            entry.fetched = unix_time();
+            index.unsaved += 1;
+        }
+    });
+}
 
-    let Some(base) = cache_dir() else {
-        return;
+/// Get total size of cached tiles in bytes (from the index, no directory walk)
+pub fn cache_size(config: &DiskCacheConfig) -> u64 {
+    let Some(tiles_dir) = config.tiles_dir() else {
+        return 0;
     };
+    with_index(&tiles_dir, |index| index.total)
+}
 
-    let tiles_dir = base.join("tiles");
-    if !tiles_dir.exists() {
-        return;
-    }
+/// Evict least recently used tiles until the cache is under the configured
+/// size limit, returns the resulting cache size in bytes.
+/// Call this periodically (e.g., on app startup or after saving tiles)
+pub fn evict_if_needed(config: &DiskCacheConfig) -> u64 {
+    let Some(tiles_dir) = config.tiles_dir() else {
+        return 0;
+    };
+    with_index(&tiles_dir, |index| {
+        if index.total <= config.max_bytes {
+            index.save_if_dirty(&tiles_dir);
+            return index.total;
+        }
 
-    // Collect all tile files with their modification times
-    let mut files: Vec<(PathBuf, SystemTime)> = Vec::new();
-    collect_files_with_times(&tiles_dir, &mut files);
+        // Oldest access first
+        let mut entries: Vec<(PathBuf, u64)> = index.entries
+            .iter()
+            .map(|(path, entry)| (path.clone(), entry.accessed))
+            .collect();
+        entries.sort_by_key(|(_, accessed)| *accessed);
 
-    // Sort by modification time (oldest first)
-    files.sort_by(|a, b| a.1.cmp(&b.1));
+        for (relative, _) in entries {
+            if index.total <= config.max_bytes {
+                break;
+            }
+            // Files already gone are dropped from the index all the same
+            let path = tiles_dir.join(&relative);
+            let _ = fs::remove_file(&path);
+            index.remove(&relative);
 
-    // Delete oldest files until under limit
-    let mut size = current_size;
-    for (path, _) in files {
-        if size <= MAX_CACHE_SIZE {
-            break;
-        }
-        if let Ok(metadata) = fs::metadata(&path) {
-            let file_size = metadata.len();
-            if fs::remove_file(&path).is_ok() {
-                size = size.saturating_sub(file_size);
+            // Remove the tile's directories once empty (fails silently if not empty)
+            let mut dir = path.parent();
+            while let Some(parent) = dir.filter(|d| *d != tiles_dir.as_path()) {
+                if fs::remove_dir(parent).is_err() {
+                    break;
+                }
+                dir = parent.parent();
             }
         }
-    }
+        index.save(&tiles_dir);
+        index.total
+    })
+}
+
+/// Name of the persisted index in the tiles directory
+const INDEX_FILE: &str = "index.txt";
+
+/// Size, last access and download time (seconds since the epoch) and HTTP
+/// validators of a cached tile
+#[derive(Clone, Debug)]
+struct IndexEntry {
+    size: u64,
+    accessed: u64,
+    fetched: u64,
+    validators: TileValidators,
+}
 
-    // Clean up empty directories
-    cleanup_empty_dirs(&tiles_dir);
+/// Sizes and access times of the cached tiles, so size checks and eviction
+/// don't have to stat every file in the cache directory.
+///
+/// Loaded from `index.txt` on first use (rebuilt from a directory walk if it's
+/// missing or unreadable) and written back by `evict_if_needed`.
+#[derive(Default)]
+struct CacheIndex {
+    entries: HashMap<PathBuf, IndexEntry>,
+    total: u64,
+    unsaved: usize,
 }
 
-fn collect_files_with_times(dir: &PathBuf, files: &mut Vec<(PathBuf, SystemTime)>) {
-    if let Ok(entries) = fs::read_dir(dir) {
+/// Indexes of all cache directories in use, shared by every tile cache in the process
+static INDEXES: Mutex<Option<HashMap<PathBuf, CacheIndex>>> = Mutex::new(None);
+
+fn with_index<R>(tiles_dir: &Path, f: impl FnOnce(&mut CacheIndex) -> R) -> R {
+    let mut indexes = INDEXES.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let index = indexes
+        .get_or_insert_with(HashMap::new)
+        .entry(tiles_dir.to_path_buf())
+        .or_insert_with(|| CacheIndex::open(tiles_dir));
+    f(index)
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+impl CacheIndex {
+    /// Read the persisted index, or rebuild it from the files on disk
+    fn open(tiles_dir: &Path) -> Self {
+        Self::load(tiles_dir).unwrap_or_else(|| {
+            let mut index = Self::default();
+            index.rebuild(tiles_dir, tiles_dir);
+            if !index.entries.is_empty() {
+                index.save(tiles_dir);
+            }
+            index
+        })
+    }
+
+    /// Parse `index.txt`: one `size<TAB>accessed<TAB>fetched<TAB>etag<TAB>last_modified<TAB>path`
+    /// line per tile, with empty fields for missing validators
+    fn load(tiles_dir: &Path) -> Option<Self> {
+        let text = fs::read_to_string(tiles_dir.join(INDEX_FILE)).ok()?;
+        let mut index = Self::default();
+        let optional = |field: &str| (!field.is_empty()).then(|| field.to_string());
+        for line in text.lines() {
+            let mut fields = line.splitn(6, '\t');
+            let size = fields.next()?.parse().ok()?;
+            let accessed = fields.next()?.parse().ok()?;
+            let fetched = fields.next()?.parse().ok()?;
+            let validators = TileValidators {
+                etag: optional(fields.next()?),
+                last_modified: optional(fields.next()?),
+            };
+            let path = PathBuf::from(fields.next()?);
+            index.total += size;
+            index.entries.insert(path, IndexEntry { size, accessed, fetched, validators });
+        }
+        Some(index)
+    }
+
+    /// Index the tiles in a directory, using modification times as access and download times
+    fn rebuild(&mut self, tiles_dir: &Path, dir: &Path) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
         for entry in entries.flatten() {
             let path = entry.path();
             if path.is_dir() {
-                collect_files_with_times(&path, files);
-            } else if let Ok(metadata) = entry.metadata() {
-                if let Ok(modified) = metadata.modified() {
-                    files.push((path, modified));
+                self.rebuild(tiles_dir, &path);
+            } else if let (Ok(metadata), Ok(relative)) = (entry.metadata(), path.strip_prefix(tiles_dir)) {
+                // The index itself and its temporary file
+                if relative.to_str().is_some_and(|p| p.starts_with(INDEX_FILE)) {
+                    continue;
                 }
+                let accessed = metadata.modified().ok()
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0);
+                self.total += metadata.len();
+                self.entries.insert(relative.to_path_buf(), IndexEntry {
+                    size: metadata.len(),
+                    accessed,
+                    fetched: accessed,
+                    validators: TileValidators::default(),
+                });
             }
         }
     }
-}
 
-fn cleanup_empty_dirs(dir: &PathBuf) {
-    if let Ok(entries) = fs::read_dir(dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.is_dir() {
-                cleanup_empty_dirs(&path);
-                // Try to remove if empty (will fail silently if not empty)
-                let _ = fs::remove_dir(&path);
+    fn insert(&mut self, relative: PathBuf, size: u64, validators: TileValidators) {
+        let now = unix_time();
+        let entry = IndexEntry { size, accessed: now, fetched: now, validators };
+        if let Some(old) = self.entries.insert(relative, entry) {
+            self.total -= old.size;
+        }
+        self.total += size;
+        self.unsaved += 1;
+    }
+
+    /// Record a read, indexing the file if it wasn't known
+    fn touch(&mut self, relative: PathBuf, size: u64) {
+        match self.entries.get_mut(&relative) {
+            Some(entry) => {
+                entry.accessed = unix_time();
+                self.unsaved += 1;
             }
+            None => self.insert(relative, size, TileValidators::default()),
+        }
+    }
+
+    fn remove(&mut self, relative: &Path) {
+        if let Some(old) = self.entries.remove(relative) {
+            self.total -= old.size;
+            self.unsaved += 1;
+        }
+    }
+
+    fn save_if_dirty(&mut self, tiles_dir: &Path) {
+        if self.unsaved > 0 {
+            self.save(tiles_dir);
+        }
+    }
+
+    /// Write the index next to the tiles, via a temporary file so a crash
+    /// never leaves a truncated index behind
+    fn save(&mut self, tiles_dir: &Path) {
+        let mut text = String::with_capacity(self.entries.len() * 32);
+        for (path, entry) in &self.entries {
+            let Some(path) = path.to_str() else { continue };
+            text.push_str(&format!(
+                "{}\t{}\t{}\t{}\t{}\t{}\n",
+                entry.size,
+                entry.accessed,
+                entry.fetched,
+                entry.validators.etag.as_deref().unwrap_or(""),
+                entry.validators.last_modified.as_deref().unwrap_or(""),
+                path,
+            ));
+        }
+        let temp = tiles_dir.join(format!("{}.tmp", INDEX_FILE));
+        if fs::create_dir_all(tiles_dir).is_ok()
+            && fs::write(&temp, text).is_ok()
+            && fs::rename(&temp, tiles_dir.join(INDEX_FILE)).is_ok()
+        {
+            self.unsaved = 0;
         }
     }
 }
 
 /// Clear all cached tiles
-pub fn clear_cache() {
-    let Some(base) = cache_dir() else {
+pub fn clear_cache(config: &DiskCacheConfig) {
+    let Some(tiles_dir) = config.tiles_dir() else {
         return;
     };
-
-    let tiles_dir = base.join("tiles");
     if tiles_dir.exists() {
         let _ = fs::remove_dir_all(&tiles_dir);
     }
+    with_index(&tiles_dir, |index| *index = CacheIndex::default());
+}
+
+/// Disk cache work queued for the I/O thread
+pub(crate) enum DiskJob {
+    /// Use a new location and size limit for the following jobs
+    Configure(DiskCacheConfig),
+    /// Read and decode a tile. The result carries the tile's validators if
+    /// it's due for revalidation.
+    Load {
+        namespace: Option<String>,
+        coord: TileCoord,
+    },
+    Save {
+        namespace: Option<String>,
+        coord: TileCoord,
+        data: Vec<u8>,
+        validators: TileValidators,
+    },
+    /// Read and decode a tile of a `TileSource::Files` basemap
+    ReadFile {
+        path: String,
+        coord: TileCoord,
+    },
+    /// Check whether a tile is cached without reading it
+    Exists {
+        namespace: Option<String>,
+        coord: TileCoord,
+    },
+    /// Mark a tile fresh after a 304 response
+    Refresh {
+        namespace: Option<String>,
+        coord: TileCoord,
+    },
+    Evict,
+    Clear,
+}
+
+/// Outcome of a `DiskJob`, delivered to the UI thread
+pub(crate) enum DiskResult {
+    /// A tile read (None if it isn't cached or failed to decode)
+    Loaded {
+        namespace: Option<String>,
+        coord: TileCoord,
+        image: Option<ImageBuffer>,
+        decode_time: f64,
+        stale: Option<TileValidators>,
+    },
+    /// A basemap file read, answering `DiskJob::ReadFile`
+    FileRead {
+        path: String,
+        coord: TileCoord,
+        image: Result<ImageBuffer, String>,
+        decode_time: f64,
+    },
+    /// Whether a tile is cached, answering `DiskJob::Exists`
+    Exists {
+        namespace: Option<String>,
+        coord: TileCoord,
+        cached: bool,
+    },
+    /// A tile write finished, with the bytes written if it succeeded
+    Saved {
+        coord: TileCoord,
+        bytes: Option<u64>,
+    },
+    /// Cache size in bytes after configuring, evicting or clearing
+    Size(u64),
+}
+
+/// Runs disk cache reads (including decoding), writes and eviction on a
+/// background thread so slow storage never stalls drawing.
+///
+/// Jobs run in submission order. Results arrive on the UI thread with an
+/// `Event::Signal` and are picked up with `poll`.
+#[derive(Default)]
+pub(crate) struct DiskWorker {
+    jobs: Option<Sender<DiskJob>>,
+    results: ToUIReceiver<DiskResult>,
+}
+
+impl DiskWorker {
+    /// Queue a job, starting the I/O thread with `config` on first use
+    pub fn submit(&mut self, cx: &mut Cx, config: &DiskCacheConfig, job: DiskJob) {
+        let results = &self.results;
+        let jobs = self.jobs.get_or_insert_with(|| {
+            let (sender, receiver) = channel::<DiskJob>();
+            let to_ui = results.sender();
+            let _ = sender.send(DiskJob::Configure(config.clone()));
+            // The thread ends when the worker (and its sender) is dropped
+            cx.spawn_thread(move || run_disk_jobs(receiver, to_ui));
+            sender
+        });
+        let _ = jobs.send(job);
+    }
+
+    /// Queue a job if the I/O thread is running, returns false if it isn't
+    pub fn submit_if_running(&self, job: DiskJob) -> bool {
+        self.jobs.as_ref().is_some_and(|jobs| jobs.send(job).is_ok())
+    }
+
+    /// Take the next finished result, if any
+    pub fn poll(&self) -> Option<DiskResult> {
+        self.results.try_recv().ok()
+    }
+}
+
+fn run_disk_jobs(jobs: Receiver<DiskJob>, to_ui: ToUISender<DiskResult>) {
+    let mut config = DiskCacheConfig::default();
+    while let Ok(job) = jobs.recv() {
+        let result = match job {
+            DiskJob::Configure(new_config) => {
+                config = new_config;
+                DiskResult::Size(cache_size(&config))
+            }
+            DiskJob::Load { namespace, coord } => {
+                let data = load_tile(&config, namespace.as_deref(), &coord);
+                let start = Instant::now();
+                let image = data.and_then(|data| decode_tile(&data).ok());
+                let decode_time = start.elapsed().as_secs_f64();
+                let stale = image.as_ref().and_then(|_| stale_validators(&config, namespace.as_deref(), &coord));
+                DiskResult::Loaded { namespace, coord, image, decode_time, stale }
+            }
+            DiskJob::ReadFile { path, coord } => {
+                let data = fs::read(&path).map_err(|e| e.to_string());
+                let start = Instant::now();
+                let image = data.and_then(|data| decode_tile(&data).map_err(|e| format!("Image decode error: {:?}", e)));
+                let decode_time = start.elapsed().as_secs_f64();
+                DiskResult::FileRead { path, coord, image, decode_time }
+            }
+            DiskJob::Exists { namespace, coord } => {
+                let cached = has_tile(&config, namespace.as_deref(), &coord);
+                DiskResult::Exists { namespace, coord, cached }
+            }
+            DiskJob::Save { namespace, coord, data, validators } => {
+                let saved = save_tile(&config, namespace.as_deref(), &coord, &data, &validators);
+                DiskResult::Saved { coord, bytes: saved.then_some(data.len() as u64) }
+            }
+            DiskJob::Refresh { namespace, coord } => {
+                refresh_tile(&config, namespace.as_deref(), &coord);
+                continue;
+            }
+            DiskJob::Evict => DiskResult::Size(evict_if_needed(&config)),
+            DiskJob::Clear => {
+                clear_cache(&config);
+                DiskResult::Size(0)
+            }
+        };
+        if to_ui.send(result).is_err() {
+            break;
+        }
+    }
 }
@@ -0,0 +1,463 @@
+use makepad_widgets::*;
+
+use crate::choropleth::DrawMapTriangle;
+use crate::geo::haversine_distance;
+use crate::layers::DrawMapCircle;
+use crate::locale::MapLocale;
+use crate::map_view::{measure_text, DrawMapLine};
+use crate::terrain::ElevationSource;
+
+live_design! {
+    link widgets;
+    use link::theme::*;
+    use link::shaders::*;
+    use link::widgets::*;
+
+    pub ElevationProfileViewBase = {{ElevationProfileView}} {
+        draw_bg: {
+            color: #ffffff
+        }
+        draw_fill: {
+            color: #43a04755
+        }
+        draw_line: {
+            color: #2e7d32
+            line_width: 2.0
+        }
+        draw_grid: {
+            color: #e0e0e0
+        }
+        draw_label: {
+            color: #757575
+            text_style: {
+                font_size: 9.0
+            }
+        }
+        draw_cursor: {
+            color: #42424299
+        }
+        draw_dot: {
+            color: #e53935
+            border_color: #ffffff
+            border_width: 2.0
+        }
+        draw_readout: {
+            color: #212121
+            text_style: <THEME_FONT_BOLD> {
+                font_size: 10.0
+            }
+        }
+    }
+
+    // Distance/elevation chart of a path, e.g. below a map showing a hiking route
+    pub ElevationProfileView = <ElevationProfileViewBase> {
+        width: Fill,
+        height: 160,
+    }
+}
+
+/// Room left of the chart for elevation labels
+const ELEVATION_LABEL_WIDTH: f64 = 44.0;
+/// Room below the chart for distance labels
+const DISTANCE_LABEL_HEIGHT: f64 = 18.0;
+/// Space around the chart on the other sides
+const CHART_PADDING: f64 = 10.0;
+/// Smallest elevation range shown, so flat paths don't exaggerate small bumps
+const MIN_ELEVATION_RANGE: f64 = 50.0;
+
+/// A point of an elevation profile
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ProfilePoint {
+    /// Distance along the path from its start in meters
+    pub distance: f64,
+    pub lng: f64,
+    pub lat: f64,
+    /// Meters above sea level, None where it isn't known (yet)
+    pub elevation: Option<f64>,
+}
+
+#[derive(Clone, Debug, DefaultNone)]
+pub enum ElevationProfileViewAction {
+    /// The pointer is over the chart at this point of the path, by hover, tap or drag
+    PointHovered { point: ProfilePoint },
+    /// The pointer left the chart
+    HoverEnded,
+    None,
+}
+
+/// Distance/elevation chart of a path, for pairing with a `GeoMapView`.
+///
+/// Give it the path and an `ElevationSource` with `set_path` (elevations are
+/// sampled evenly along the path), or points that already carry elevations
+/// (e.g. from a GPX track) with `set_profile`. Hovering, tapping or dragging
+/// over the chart reports the point under the pointer with `PointHovered`, so
+/// the app can move a marker on the map to it; `set_highlight` goes the other
+/// way and marks a distance on the chart.
+#[derive(Live, LiveHook, Widget)]
+pub struct ElevationProfileView {
+    #[walk] walk: Walk,
+    #[redraw] #[live] draw_bg: DrawColor,
+    #[live] draw_fill: DrawMapTriangle,
+    #[live] draw_line: DrawMapLine,
+    #[live] draw_grid: DrawColor,
+    #[live] draw_label: DrawText,
+    #[live] draw_cursor: DrawColor,
+    #[live] draw_dot: DrawMapCircle,
+    #[live] draw_readout: DrawText,
+    /// Points sampled along paths given to `set_path`
+    #[live(200)] pub sample_count: usize,
+    #[live(5.0)] pub dot_radius: f64,
+    #[rust] points: Vec<ProfilePoint>,
+    #[rust] source: Option<Box<dyn ElevationSource>>,
+    // Distance marked on the chart
+    #[rust] highlight: Option<f64>,
+    // Absolute rect of the plot area from the last draw
+    #[rust] chart_rect: Rect,
+    #[rust] locale: MapLocale,
+}
+
+impl ElevationProfileView {
+    /// Show the profile of a path of (lng, lat) positions, with elevations
+    /// from `source` (loaded in the background if needed)
+    pub fn set_path(&mut self, cx: &mut Cx, path: &[(f64, f64)], mut source: Box<dyn ElevationSource>) {
+        self.points = resample(path, self.sample_count.max(2));
+        let positions: Vec<(f64, f64)> = self.points.iter().map(|p| (p.lng, p.lat)).collect();
+        source.request(cx, &positions);
+        self.source = Some(source);
+        self.highlight = None;
+        self.update_elevations();
+        self.draw_bg.redraw(cx);
+    }
+
+    /// Show the profile of (lng, lat, elevation) points, e.g. a recorded track
+    pub fn set_profile(&mut self, cx: &mut Cx, points: &[(f64, f64, f64)]) {
+        let mut distance = 0.0;
+        self.points = points.iter().enumerate().map(|(i, &(lng, lat, elevation))| {
+            if i > 0 {
+                let (prev_lng, prev_lat, _) = points[i - 1];
+                distance += haversine_distance(prev_lng, prev_lat, lng, lat);
+            }
+            ProfilePoint { distance, lng, lat, elevation: Some(elevation) }
+        }).collect();
+        self.source = None;
+        self.highlight = None;
+        self.draw_bg.redraw(cx);
+    }
+
+    /// Remove the profile
+    pub fn clear(&mut self, cx: &mut Cx) {
+        self.points.clear();
+        self.source = None;
+        self.highlight = None;
+        self.draw_bg.redraw(cx);
+    }
+
+    /// The profile's points, ordered by distance
+    pub fn points(&self) -> &[ProfilePoint] {
+        &self.points
+    }
+
+    /// Length of the path in meters
+    pub fn total_distance(&self) -> f64 {
+        self.points.last().map_or(0.0, |p| p.distance)
+    }
+
+    /// Lowest and highest known elevation
+    pub fn elevation_range(&self) -> Option<(f64, f64)> {
+        self.points.iter().filter_map(|p| p.elevation).fold(None, |range, e| match range {
+            None => Some((e, e)),
+            Some((low, high)) => Some((f64::min(low, e), f64::max(high, e))),
+        })
+    }
+
+    /// Total ascent and descent in meters over the known elevations
+    pub fn climb(&self) -> (f64, f64) {
+        let elevations: Vec<f64> = self.points.iter().filter_map(|p| p.elevation).collect();
+        elevations.windows(2).fold((0.0, 0.0), |(up, down), pair| {
+            let delta = pair[1] - pair[0];
+            (up + delta.max(0.0), down + (-delta).max(0.0))
+        })
+    }
+
+    /// Interpolated point at a distance along the path
+    pub fn point_at(&self, distance: f64) -> Option<ProfilePoint> {
+        let last = self.points.len().checked_sub(1)?;
+        let after = self.points.partition_point(|p| p.distance < distance).min(last);
+        let (a, b) = (self.points[after.saturating_sub(1)], self.points[after]);
+        let t = if b.distance > a.distance { ((distance - a.distance) / (b.distance - a.distance)).clamp(0.0, 1.0) } else { 0.0 };
+        let lng = a.lng + ((b.lng - a.lng + 180.0).rem_euclid(360.0) - 180.0) * t;
+        let elevation = match (a.elevation, b.elevation) {
+            (Some(ea), Some(eb)) => Some(ea + (eb - ea) * t),
+            (ea, eb) => if t < 0.5 { ea } else { eb },
+        };
+        Some(ProfilePoint {
+            distance: a.distance + (b.distance - a.distance) * t,
+            lng,
+            lat: a.lat + (b.lat - a.lat) * t,
+            elevation,
+        })
+    }
+
+    /// Mark a distance along the path on the chart (None to remove the mark),
+    /// e.g. the position of a marker moving along the route
+    pub fn set_highlight(&mut self, cx: &mut Cx, distance: Option<f64>) {
+        self.highlight = distance;
+        self.draw_bg.redraw(cx);
+    }
+
+    fn update_elevations(&mut self) {
+        if let Some(source) = &self.source {
+            for point in &mut self.points {
+                point.elevation = source.elevation(point.lng, point.lat);
+            }
+        }
+    }
+
+    /// Elevation range of the chart's vertical axis, widened to whole grid steps
+    fn axis_range(&self) -> Option<(f64, f64, f64)> {
+        // Round steps of the unit shown (meters or feet), returned in meters
+        let unit = self.locale.elevation_unit();
+        let (low, high) = self.elevation_range()?;
+        let (low, high) = (low / unit, high / unit);
+        let pad = (MIN_ELEVATION_RANGE / unit - (high - low)).max(0.0) / 2.0;
+        let step = nice_step(high - low + 2.0 * pad, 4.0);
+        let low = ((low - pad) / step).floor() * step;
+        let high = (((high + pad) / step).ceil() * step).max(low + step);
+        Some((low * unit, high * unit, step * unit))
+    }
+
+    /// Set the number format and units of the labels
+    pub fn set_locale(&mut self, cx: &mut Cx, locale: MapLocale) {
+        self.locale = locale;
+        self.draw_bg.redraw(cx);
+    }
+
+    fn hover_at(&mut self, cx: &mut Cx, uid: WidgetUid, path: &HeapLiveIdPath, abs: DVec2) {
+        if self.points.is_empty() || self.chart_rect.size.x <= 0.0 {
+            return;
+        }
+        let fraction = ((abs.x - self.chart_rect.pos.x) / self.chart_rect.size.x).clamp(0.0, 1.0);
+        if let Some(point) = self.point_at(fraction * self.total_distance()) {
+            self.highlight = Some(point.distance);
+            self.draw_bg.redraw(cx);
+            cx.widget_action(uid, path, ElevationProfileViewAction::PointHovered { point });
+        }
+    }
+}
+
+impl Widget for ElevationProfileView {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        let uid = self.widget_uid();
+
+        // Elevations loaded by the source
+        if let (Event::NetworkResponses(responses), Some(source)) = (event, &mut self.source) {
+            let mut updated = false;
+            for response in responses {
+                match &response.response {
+                    NetworkResponse::HttpResponse(http_response) => {
+                        updated |= source.handle_response(cx, response.request_id, http_response);
+                    }
+                    NetworkResponse::HttpRequestError(_) => {
+                        updated |= source.handle_error(response.request_id);
+                    }
+                    _ => {}
+                }
+            }
+            if updated {
+                self.update_elevations();
+                self.draw_bg.redraw(cx);
+            }
+        }
+
+        match event.hits(cx, self.draw_bg.area()) {
+            Hit::FingerHoverIn(fe) | Hit::FingerHoverOver(fe) => {
+                self.hover_at(cx, uid, &scope.path, fe.abs);
+            }
+            Hit::FingerDown(fe) if fe.is_primary_hit() => {
+                self.hover_at(cx, uid, &scope.path, fe.abs);
+            }
+            Hit::FingerMove(fe) => {
+                self.hover_at(cx, uid, &scope.path, fe.abs);
+            }
+            Hit::FingerHoverOut(_) => {
+                self.highlight = None;
+                self.draw_bg.redraw(cx);
+                cx.widget_action(uid, &scope.path, ElevationProfileViewAction::HoverEnded);
+            }
+            _ => {}
+        }
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, _scope: &mut Scope, walk: Walk) -> DrawStep {
+        cx.begin_turtle(walk, Layout::default());
+        let rect = cx.turtle().rect();
+        self.draw_bg.draw_abs(cx, rect);
+
+        let chart = Rect {
+            pos: rect.pos + dvec2(ELEVATION_LABEL_WIDTH, CHART_PADDING),
+            size: dvec2(
+                (rect.size.x - ELEVATION_LABEL_WIDTH - CHART_PADDING).max(0.0),
+                (rect.size.y - CHART_PADDING - DISTANCE_LABEL_HEIGHT).max(0.0),
+            ),
+        };
+        self.chart_rect = chart;
+
+        let total = self.total_distance();
+        if let Some((low, high, step)) = self.axis_range().filter(|_| total > 0.0) {
+            let to_screen = |distance: f64, elevation: f64| dvec2(
+                chart.pos.x + distance / total * chart.size.x,
+                chart.pos.y + (1.0 - (elevation - low) / (high - low)) * chart.size.y,
+            );
+            let bottom = chart.pos.y + chart.size.y;
+            let font_size = self.draw_label.text_style.font_size as f64;
+
+            // Elevation grid lines with labels on the left
+            for i in 0..=((high - low) / step).round() as usize {
+                let elevation = low + i as f64 * step;
+                let y = to_screen(0.0, elevation).y;
+                self.draw_grid.draw_abs(cx, Rect { pos: dvec2(chart.pos.x, y), size: dvec2(chart.size.x, 1.0) });
+                self.draw_label.draw_abs(cx, dvec2(rect.pos.x + 4.0, y - font_size * 0.6), &self.locale.format_elevation(elevation));
+            }
+
+            // Distance ticks below the chart, at round steps of the small or large unit
+            let (small, large, small_factor, large_factor, threshold) = self.locale.distance_units();
+            let tick_count = (chart.size.x / 80.0).max(1.0);
+            let distance_step = if total / tick_count < threshold {
+                nice_step(total / small_factor, tick_count) * small_factor
+            } else {
+                nice_step(total / large_factor, tick_count) * large_factor
+            };
+            for i in 0..=(total / distance_step).floor() as usize {
+                let distance = i as f64 * distance_step;
+                let x = to_screen(distance, low).x;
+                self.draw_grid.draw_abs(cx, Rect { pos: dvec2(x, chart.pos.y), size: dvec2(1.0, chart.size.y) });
+                let label = if distance_step >= large_factor {
+                    format!("{} {}", self.locale.format_number(distance / large_factor, 0), large)
+                } else if distance >= threshold {
+                    format!("{} {}", self.locale.format_number(distance / large_factor, 1), large)
+                } else {
+                    format!("{} {}", self.locale.format_number(distance / small_factor, 0), small)
+                };
+                self.draw_label.draw_abs(cx, dvec2(x - 4.0, bottom + 4.0), &label);
+            }
+
+            // Filled area and outline, with gaps where elevations are missing
+            for pair in self.points.windows(2) {
+                let (Some(e0), Some(e1)) = (pair[0].elevation, pair[1].elevation) else {
+                    continue;
+                };
+                let (a, b) = (to_screen(pair[0].distance, e0), to_screen(pair[1].distance, e1));
+                let (a_base, b_base) = (dvec2(a.x, bottom), dvec2(b.x, bottom));
+                self.draw_fill.draw_triangle(cx, a, b, b_base);
+                self.draw_fill.draw_triangle(cx, a, b_base, a_base);
+                self.draw_line.draw_line(cx, a, b);
+            }
+
+            // Highlighted point with a vertical line and its elevation and distance
+            if let Some(point) = self.highlight.and_then(|distance| self.point_at(distance)) {
+                let x = to_screen(point.distance, low).x;
+                self.draw_cursor.draw_abs(cx, Rect { pos: dvec2(x, chart.pos.y), size: dvec2(1.0, chart.size.y) });
+                if let Some(elevation) = point.elevation {
+                    self.draw_dot.draw_circle(cx, to_screen(point.distance, elevation), self.dot_radius);
+                    let text = format!("{} · {}", self.locale.format_elevation(elevation), self.locale.format_distance(point.distance));
+                    let text_width = measure_text(cx, &self.draw_readout, &text).x;
+                    let text_x = if x + 6.0 + text_width > chart.pos.x + chart.size.x { x - 6.0 - text_width } else { x + 6.0 };
+                    self.draw_readout.draw_abs(cx, dvec2(text_x, chart.pos.y), &text);
+                }
+            }
+        }
+
+        cx.end_turtle_with_area(&mut self.draw_bg.draw_vars.area);
+        DrawStep::done()
+    }
+}
+
+/// Points at `count` even distances along a path of (lng, lat) positions
+fn resample(path: &[(f64, f64)], count: usize) -> Vec<ProfilePoint> {
+    let mut distances = vec![0.0];
+    for pair in path.windows(2) {
+        let ((lng1, lat1), (lng2, lat2)) = (pair[0], pair[1]);
+        distances.push(distances[distances.len() - 1] + haversine_distance(lng1, lat1, lng2, lat2));
+    }
+    let (Some(&(lng, lat)), Some(&total)) = (path.first(), distances.last()) else {
+        return Vec::new();
+    };
+    if path.len() < 2 || total <= 0.0 {
+        return vec![ProfilePoint { distance: 0.0, lng, lat, elevation: None }];
+    }
+    let mut segment = 0;
+    (0..count).map(|i| {
+        let distance = total * i as f64 / (count - 1) as f64;
+        while segment + 2 < distances.len() && distances[segment + 1] < distance {
+            segment += 1;
+        }
+        let ((lng1, lat1), (lng2, lat2)) = (path[segment], path[segment + 1]);
+        let length = distances[segment + 1] - distances[segment];
+        let t = if length > 0.0 { ((distance - distances[segment]) / length).clamp(0.0, 1.0) } else { 0.0 };
+        ProfilePoint {
+            distance,
+            lng: lng1 + ((lng2 - lng1 + 180.0).rem_euclid(360.0) - 180.0) * t,
+            lat: lat1 + (lat2 - lat1) * t,
+            elevation: None,
+        }
+    }).collect()
+}
+
+/// Smallest 1, 2 or 5 times a power of ten that splits `range` into at most `count` steps
+fn nice_step(range: f64, count: f64) -> f64 {
+    let raw = (range / count).max(1e-9);
+    let magnitude = 10f64.powf(raw.log10().floor());
+    [1.0, 2.0, 5.0, 10.0].into_iter().map(|f| f * magnitude).find(|step| *step >= raw).unwrap_or(10.0 * magnitude)
+}
+
+impl ElevationProfileViewRef {
+    /// Show the profile of a path with elevations from a source
+    pub fn set_path(&self, cx: &mut Cx, path: &[(f64, f64)], source: Box<dyn ElevationSource>) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_path(cx, path, source);
+        }
+    }
+
+    /// Set the number format and units of the labels
+    pub fn set_locale(&self, cx: &mut Cx, locale: MapLocale) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_locale(cx, locale);
+        }
+    }
+
+    /// Show the profile of (lng, lat, elevation) points
+    pub fn set_profile(&self, cx: &mut Cx, points: &[(f64, f64, f64)]) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_profile(cx, points);
+        }
+    }
+
+    /// Mark a distance along the path on the chart
+    pub fn set_highlight(&self, cx: &mut Cx, distance: Option<f64>) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_highlight(cx, distance);
+        }
+    }
+
+    /// Total ascent and descent in meters
+    pub fn climb(&self) -> (f64, f64) {
+        self.borrow().map(|inner| inner.climb()).unwrap_or_default()
+    }
+
+    /// Check if the pointer moved over the chart (returns the latest point under it)
+    pub fn point_hovered(&self, actions: &Actions) -> Option<ProfilePoint> {
+        actions.filter_widget_actions(self.widget_uid())
+            .map(|action| action.cast::<ElevationProfileViewAction>())
+            .filter_map(|action| match action {
+                ElevationProfileViewAction::PointHovered { point } => Some(point),
+                _ => None,
+            })
+            .last()
+    }
+
+    /// Check if the pointer left the chart
+    pub fn hover_ended(&self, actions: &Actions) -> bool {
+        actions.filter_widget_actions(self.widget_uid())
+            .any(|action| matches!(action.cast::<ElevationProfileViewAction>(), ElevationProfileViewAction::HoverEnded))
+    }
+}
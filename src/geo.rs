@@ -0,0 +1,181 @@
+//! Geodesy helpers on (lng, lat) positions in degrees, using a spherical earth.
+//!
+//! Good to about 0.5% against the WGS84 ellipsoid, which is plenty for scale
+//! bars, measurements and routing previews.
+
+/// Grid coordinate conversions, on the WGS84 ellipsoid (see the `utm` module)
+pub use crate::utm::{from_mgrs, from_utm, parse_utm, to_mgrs, to_utm, UtmCoord};
+
+/// Mean earth radius in meters
+pub const EARTH_RADIUS: f64 = 6371008.8;
+
+/// A geographic bounding box in degrees. `west > east` means the box crosses
+/// the antimeridian.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GeoBounds {
+    pub west: f64,
+    pub south: f64,
+    pub east: f64,
+    pub north: f64,
+}
+
+impl GeoBounds {
+    /// Check if a position lies within the bounds (edges included)
+    pub fn contains(&self, lng: f64, lat: f64) -> bool {
+        if lat < self.south || lat > self.north {
+            return false;
+        }
+        let lng = wrap_lng(lng);
+        if self.west <= self.east {
+            lng >= self.west && lng <= self.east
+        } else {
+            lng >= self.west || lng <= self.east
+        }
+    }
+
+    /// Center of the bounds as (lng, lat)
+    pub fn center(&self) -> (f64, f64) {
+        let mut east = self.east;
+        if east < self.west {
+            east += 360.0;
+        }
+        (wrap_lng((self.west + east) / 2.0), (self.south + self.north) / 2.0)
+    }
+
+    /// Width in degrees of longitude
+    pub fn lng_span(&self) -> f64 {
+        (self.east - self.west).rem_euclid(360.0)
+    }
+}
+
+/// Great-circle distance in meters between two positions
+pub fn haversine_distance(lng1: f64, lat1: f64, lng2: f64, lat2: f64) -> f64 {
+    let dlat = (lat2 - lat1).to_radians();
+    let dlng = (lng2 - lng1).to_radians();
+    let a = (dlat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos() * lat2.to_radians().cos() * (dlng / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * a.sqrt().asin()
+}
+
+/// Initial bearing from the first position towards the second, in degrees
+/// clockwise from north (0..360)
+pub fn bearing(lng1: f64, lat1: f64, lng2: f64, lat2: f64) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let dlng = (lng2 - lng1).to_radians();
+    let y = dlng.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlng.cos();
+    y.atan2(x).to_degrees().rem_euclid(360.0)
+}
+
+/// Position reached by travelling `distance` meters from a position along a
+/// great circle starting at `bearing` degrees clockwise from north
+pub fn destination(lng: f64, lat: f64, bearing: f64, distance: f64) -> (f64, f64) {
+    let angle = distance / EARTH_RADIUS;
+    let bearing = bearing.to_radians();
+    let lat1 = lat.to_radians();
+    let lat2 = (lat1.sin() * angle.cos() + lat1.cos() * angle.sin() * bearing.cos()).asin();
+    let dlng = (bearing.sin() * angle.sin() * lat1.cos())
+        .atan2(angle.cos() - lat1.sin() * lat2.sin());
+    (wrap_lng(lng + dlng.to_degrees()), lat2.to_degrees())
+}
+
+/// Halfway point along the great circle between two positions
+pub fn midpoint(lng1: f64, lat1: f64, lng2: f64, lat2: f64) -> (f64, f64) {
+    great_circle_point(lng1, lat1, lng2, lat2, 0.5)
+}
+
+/// Point at fraction `t` (0..1) along the great circle between two positions
+pub fn great_circle_point(lng1: f64, lat1: f64, lng2: f64, lat2: f64, t: f64) -> (f64, f64) {
+    let to_vec = |lng: f64, lat: f64| {
+        let (lng, lat) = (lng.to_radians(), lat.to_radians());
+        [lat.cos() * lng.cos(), lat.cos() * lng.sin(), lat.sin()]
+    };
+    let a = to_vec(lng1, lat1);
+    let b = to_vec(lng2, lat2);
+    let dot = (a[0] * b[0] + a[1] * b[1] + a[2] * b[2]).clamp(-1.0, 1.0);
+    let angle = dot.acos();
+    // Coincident or antipodal points have no unique great circle, interpolate linearly
+    if angle.sin().abs() < 1e-12 {
+        return (lng1 + (lng2 - lng1) * t, lat1 + (lat2 - lat1) * t);
+    }
+    let wa = ((1.0 - t) * angle).sin() / angle.sin();
+    let wb = (t * angle).sin() / angle.sin();
+    let v = [wa * a[0] + wb * b[0], wa * a[1] + wb * b[1], wa * a[2] + wb * b[2]];
+    (v[1].atan2(v[0]).to_degrees(), v[2].atan2(v[0].hypot(v[1])).to_degrees())
+}
+
+/// Points along the great circle between two positions, at most `max_step`
+/// degrees of arc apart, including both ends. Longitudes are in [-180, 180].
+pub fn great_circle_path(lng1: f64, lat1: f64, lng2: f64, lat2: f64, max_step: f64) -> Vec<(f64, f64)> {
+    let arc = (haversine_distance(lng1, lat1, lng2, lat2) / EARTH_RADIUS).to_degrees();
+    let steps = ((arc / max_step.max(0.01)).ceil() as usize).max(1);
+    (0..=steps)
+        .map(|i| great_circle_point(lng1, lat1, lng2, lat2, i as f64 / steps as f64))
+        .collect()
+}
+
+/// Ring of `segments` positions at `radius` meters around a position, without
+/// a closing point (a geodesic circle, which Mercator shows stretched toward the poles)
+pub fn circle_polygon(lng: f64, lat: f64, radius: f64, segments: usize) -> Vec<(f64, f64)> {
+    let segments = segments.max(3);
+    (0..segments)
+        .map(|i| destination(lng, lat, i as f64 * 360.0 / segments as f64, radius))
+        .collect()
+}
+
+/// Length in meters of a path along great circles between its positions
+pub fn path_length(points: &[(f64, f64)]) -> f64 {
+    points.windows(2)
+        .map(|pair| haversine_distance(pair[0].0, pair[0].1, pair[1].0, pair[1].1))
+        .sum()
+}
+
+/// Area in square meters of a polygon on the sphere, given as a ring of (lng, lat)
+/// positions (closing point optional). Self-intersecting rings give meaningless results.
+pub fn polygon_area(ring: &[(f64, f64)]) -> f64 {
+    if ring.len() < 3 {
+        return 0.0;
+    }
+    let mut sum = 0.0;
+    for i in 0..ring.len() {
+        let (lng1, lat1) = ring[i];
+        let (lng2, lat2) = ring[(i + 1) % ring.len()];
+        let dlng = ((lng2 - lng1 + 180.0).rem_euclid(360.0) - 180.0).to_radians();
+        sum += dlng * (2.0 + lat1.to_radians().sin() + lat2.to_radians().sin());
+    }
+    (sum * EARTH_RADIUS * EARTH_RADIUS / 2.0).abs()
+}
+
+/// Smallest bounds containing all positions (None for an empty slice).
+/// Point sets spanning the antimeridian get bounds crossing it rather than
+/// bounds wrapping the long way around the world.
+pub fn bounding_box(points: &[(f64, f64)]) -> Option<GeoBounds> {
+    let (mut south, mut north) = (f64::INFINITY, f64::NEG_INFINITY);
+    let mut lngs = Vec::with_capacity(points.len());
+    for &(lng, lat) in points {
+        south = south.min(lat);
+        north = north.max(lat);
+        lngs.push(wrap_lng(lng));
+    }
+    if lngs.is_empty() {
+        return None;
+    }
+    lngs.sort_by(|a, b| a.total_cmp(b));
+
+    // The bounds are the complement of the widest gap between longitudes
+    let (mut west, mut east) = (lngs[0], lngs[lngs.len() - 1]);
+    let mut widest_gap = lngs[0] + 360.0 - east;
+    for pair in lngs.windows(2) {
+        if pair[1] - pair[0] > widest_gap {
+            widest_gap = pair[1] - pair[0];
+            west = pair[1];
+            east = pair[0];
+        }
+    }
+    Some(GeoBounds { west, south, east, north })
+}
+
+/// Wrap a longitude into [-180, 180)
+fn wrap_lng(lng: f64) -> f64 {
+    (lng + 180.0).rem_euclid(360.0) - 180.0
+}
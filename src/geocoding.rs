@@ -0,0 +1,281 @@
+use makepad_widgets::*;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::geo::GeoBounds;
+use crate::json::{JsonError, JsonValue};
+
+/// Public Nominatim server of OpenStreetMap. Its usage policy asks for at most
+/// one request per second and no autocomplete-as-you-type.
+pub const NOMINATIM_SERVER: &str = "https://nominatim.openstreetmap.org";
+
+/// Public Photon server of Komoot, suited to search-as-you-type
+pub const PHOTON_SERVER: &str = "https://photon.komoot.io";
+
+/// API a [`Geocoder`] talks to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GeocoderService {
+    Nominatim,
+    Photon,
+}
+
+/// A place found by a search or reverse lookup
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeocodeResult {
+    /// Short name (e.g. "Brandenburg Gate"), the first part of `address` if the place has none
+    pub name: String,
+    /// Full address for display
+    pub address: String,
+    pub lng: f64,
+    pub lat: f64,
+    /// Extent of the place (a city, a country), if the service reports one
+    pub bounds: Option<GeoBounds>,
+}
+
+/// What a geocoding request asked for
+#[derive(Clone, Debug, PartialEq)]
+pub enum GeocodeQuery {
+    Search(String),
+    Reverse { lng: f64, lat: f64 },
+}
+
+/// Why a geocoding request failed
+#[derive(Clone, Debug, PartialEq)]
+pub enum GeocodeError {
+    /// The response isn't valid JSON
+    Json(JsonError),
+    /// Valid JSON, but not what the service returns
+    Invalid(String),
+    /// The server answered with a non-200 status
+    Status(u16),
+    /// The request failed (offline, DNS, TLS, ...)
+    Network(String),
+}
+
+impl fmt::Display for GeocodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeocodeError::Json(error) => write!(f, "invalid JSON: {}", error),
+            GeocodeError::Invalid(message) => write!(f, "invalid geocoder response: {}", message),
+            GeocodeError::Status(status) => write!(f, "HTTP status {}", status),
+            GeocodeError::Network(message) => write!(f, "request failed: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for GeocodeError {}
+
+/// A finished geocoding request
+#[derive(Clone, Debug)]
+pub struct GeocodeResponse {
+    pub request_id: LiveId,
+    pub query: GeocodeQuery,
+    /// Places best match first; a reverse lookup has at most one
+    pub result: Result<Vec<GeocodeResult>, GeocodeError>,
+}
+
+/// Minimal client for Nominatim and Photon geocoding.
+///
+/// Requests go out through `cx.http_request`; the owner routes network
+/// responses back through `handle_response` and `handle_error`, like a
+/// `CoordinateProvider`. `GeoMapView` does this for its own geocoder.
+#[derive(Clone, Debug)]
+pub struct Geocoder {
+    service: GeocoderService,
+    url: String,
+    /// Preferred language of the results (e.g. "de"), the server's default if None
+    pub language: Option<String>,
+    /// Most results returned by a search
+    pub limit: usize,
+    pending: HashMap<LiveId, GeocodeQuery>,
+}
+
+impl Default for Geocoder {
+    fn default() -> Self {
+        Self::nominatim()
+    }
+}
+
+impl Geocoder {
+    /// Client for a server at `url` (without a trailing slash), e.g. a self-hosted instance
+    pub fn new(service: GeocoderService, url: &str) -> Self {
+        Self {
+            service,
+            url: url.trim_end_matches('/').to_string(),
+            language: None,
+            limit: 5,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Client for the public Nominatim server
+    pub fn nominatim() -> Self {
+        Self::new(GeocoderService::Nominatim, NOMINATIM_SERVER)
+    }
+
+    /// Client for the public Photon server
+    pub fn photon() -> Self {
+        Self::new(GeocoderService::Photon, PHOTON_SERVER)
+    }
+
+    pub fn service(&self) -> GeocoderService {
+        self.service
+    }
+
+    /// Whether any request is waiting for its response
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Search for places matching free text, returning the request's id
+    pub fn search(&mut self, cx: &mut Cx, query: &str) -> LiveId {
+        let query_param = encode_query_component(query);
+        let url = match self.service {
+            GeocoderService::Nominatim => format!("{}/search?q={}&format=jsonv2&limit={}", self.url, query_param, self.limit),
+            GeocoderService::Photon => format!("{}/api/?q={}&limit={}", self.url, query_param, self.limit),
+        };
+        self.send(cx, url, GeocodeQuery::Search(query.to_string()))
+    }
+
+    /// Look up the address at a position, returning the request's id
+    pub fn reverse(&mut self, cx: &mut Cx, lng: f64, lat: f64) -> LiveId {
+        let url = match self.service {
+            GeocoderService::Nominatim => format!("{}/reverse?lat={}&lon={}&format=jsonv2", self.url, lat, lng),
+            GeocoderService::Photon => format!("{}/reverse?lat={}&lon={}&limit=1", self.url, lat, lng),
+        };
+        self.send(cx, url, GeocodeQuery::Reverse { lng, lat })
+    }
+
+    fn send(&mut self, cx: &mut Cx, mut url: String, query: GeocodeQuery) -> LiveId {
+        if let Some(language) = &self.language {
+            let key = match self.service {
+                GeocoderService::Nominatim => "accept-language",
+                GeocoderService::Photon => "lang",
+            };
+            url.push_str(&format!("&{}={}", key, encode_query_component(language)));
+        }
+        let request_id = LiveId::unique();
+        let mut request = HttpRequest::new(url, HttpMethod::GET);
+        // Nominatim's usage policy requires an identifying User-Agent
+        request.set_header("User-Agent".to_string(), "MakepadMap/0.1".to_string());
+        request.set_header("Accept".to_string(), "application/json".to_string());
+        cx.http_request(request_id, request);
+        self.pending.insert(request_id, query);
+        request_id
+    }
+
+    /// Handle an HTTP response, returning the finished request if it was one of this geocoder's
+    pub fn handle_response(&mut self, request_id: LiveId, response: &HttpResponse) -> Option<GeocodeResponse> {
+        let query = self.pending.remove(&request_id)?;
+        let result = if response.status_code != 200 {
+            Err(GeocodeError::Status(response.status_code))
+        } else {
+            let body = response.body.as_deref().unwrap_or_default();
+            self.parse(&String::from_utf8_lossy(body))
+        };
+        Some(GeocodeResponse { request_id, query, result })
+    }
+
+    /// Handle a failed HTTP request, returning the failed request if it was one of this geocoder's
+    pub fn handle_error(&mut self, request_id: LiveId, error: &HttpError) -> Option<GeocodeResponse> {
+        let query = self.pending.remove(&request_id)?;
+        let result = Err(GeocodeError::Network(format!("{:?}", error)));
+        Some(GeocodeResponse { request_id, query, result })
+    }
+
+    /// Parse a search or reverse response of this geocoder's service
+    pub fn parse(&self, text: &str) -> Result<Vec<GeocodeResult>, GeocodeError> {
+        let root = JsonValue::parse(text).map_err(GeocodeError::Json)?;
+        match self.service {
+            GeocoderService::Nominatim => parse_nominatim(&root),
+            GeocoderService::Photon => parse_photon(&root),
+        }
+    }
+}
+
+/// Nominatim answers searches with an array of places and reverse lookups
+/// with a single place, or an object with an `error` if nothing is there
+fn parse_nominatim(root: &JsonValue) -> Result<Vec<GeocodeResult>, GeocodeError> {
+    let places = match root {
+        JsonValue::Array(places) => places.as_slice(),
+        JsonValue::Object(_) if root.get("error").is_some() => return Ok(Vec::new()),
+        JsonValue::Object(_) => std::slice::from_ref(root),
+        _ => return Err(GeocodeError::Invalid("expected an array or object".to_string())),
+    };
+    places.iter().map(|place| {
+        // Coordinates and bounding boxes are strings in Nominatim's output
+        let number = |value: &JsonValue| value.as_str().and_then(|s| s.parse::<f64>().ok()).or_else(|| value.as_f64());
+        let lng = place.get("lon").and_then(number);
+        let lat = place.get("lat").and_then(number);
+        let (Some(lng), Some(lat)) = (lng, lat) else {
+            return Err(GeocodeError::Invalid("place without lat/lon".to_string()));
+        };
+        let address = place.get("display_name").and_then(JsonValue::as_str).unwrap_or_default().to_string();
+        let name = place.get("name").and_then(JsonValue::as_str).filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| first_part(&address));
+        // [south, north, west, east]
+        let bounds = place.get("boundingbox").and_then(JsonValue::as_array).and_then(|b| {
+            let b: Vec<f64> = b.iter().filter_map(number).collect();
+            (b.len() == 4).then(|| GeoBounds { south: b[0], north: b[1], west: b[2], east: b[3] })
+        });
+        Ok(GeocodeResult { name, address, lng, lat, bounds })
+    }).collect()
+}
+
+/// Photon answers with a GeoJSON FeatureCollection of points
+fn parse_photon(root: &JsonValue) -> Result<Vec<GeocodeResult>, GeocodeError> {
+    let features = root.get("features")
+        .and_then(JsonValue::as_array)
+        .ok_or_else(|| GeocodeError::Invalid("expected a FeatureCollection".to_string()))?;
+    features.iter().map(|feature| {
+        let coordinates = feature.get("geometry")
+            .and_then(|geometry| geometry.get("coordinates"))
+            .and_then(JsonValue::as_array)
+            .unwrap_or_default();
+        let (Some(lng), Some(lat)) = (
+            coordinates.first().and_then(JsonValue::as_f64),
+            coordinates.get(1).and_then(JsonValue::as_f64),
+        ) else {
+            return Err(GeocodeError::Invalid("feature without point coordinates".to_string()));
+        };
+        let properties = feature.get("properties");
+        let text = |key: &str| properties.and_then(|p| p.get(key)).and_then(JsonValue::as_str).filter(|s| !s.is_empty());
+
+        // Photon has no display name, build one from the address parts
+        let street = match (text("street"), text("housenumber")) {
+            (Some(street), Some(number)) => Some(format!("{} {}", street, number)),
+            (street, _) => street.map(str::to_string),
+        };
+        let parts: Vec<String> = [text("name").map(str::to_string), street]
+            .into_iter()
+            .chain(["postcode", "city", "state", "country"].iter().map(|key| text(key).map(str::to_string)))
+            .flatten()
+            .collect();
+        let address = parts.join(", ");
+        let name = text("name").map(str::to_string).unwrap_or_else(|| first_part(&address));
+        // [west, north, east, south]
+        let bounds = properties.and_then(|p| p.get("extent")).and_then(JsonValue::as_array).and_then(|e| {
+            let e: Vec<f64> = e.iter().filter_map(JsonValue::as_f64).collect();
+            (e.len() == 4).then(|| GeoBounds { west: e[0], north: e[1], east: e[2], south: e[3] })
+        });
+        Ok(GeocodeResult { name, address, lng, lat, bounds })
+    }).collect()
+}
+
+fn first_part(address: &str) -> String {
+    address.split(',').next().unwrap_or_default().trim().to_string()
+}
+
+/// Percent-encode text for a URL query parameter
+fn encode_query_component(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => out.push(byte as char),
+            b' ' => out.push('+'),
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
@@ -0,0 +1,524 @@
+use makepad_widgets::*;
+use std::fmt;
+
+use crate::choropleth::DrawMapTriangle;
+use crate::geometry::{repair_polygon, GeometryWarning};
+use crate::json::{JsonError, JsonValue};
+use crate::layers::{DrawMapCircle, MapLayer, MapLayerEvent, MapViewport, ProjectedShape};
+use crate::spatial_index::SpatialIndex;
+use crate::map_view::DrawMapLine;
+use crate::projection::Projection;
+use crate::quality::QualityLevel;
+
+live_design! {
+    link widgets;
+    use link::theme::*;
+    use link::shaders::*;
+    use link::widgets::*;
+
+    // Features of a GeoJSON document or feed, styled uniformly
+    pub GeoJsonLayer = {{GeoJsonLayer}} {
+        draw_fill: {
+            color: #1e88e566
+        }
+        draw_line: {
+            color: #1e88e5
+            line_width: 2.0
+        }
+        draw_point: {
+            color: #e53935
+            border_color: #ffffff
+            border_width: 1.5
+        }
+    }
+}
+
+/// A GeoJSON geometry, positions as (lng, lat)
+#[derive(Clone, Debug, PartialEq)]
+pub enum GeoJsonGeometry {
+    Point((f64, f64)),
+    MultiPoint(Vec<(f64, f64)>),
+    LineString(Vec<(f64, f64)>),
+    MultiLineString(Vec<Vec<(f64, f64)>>),
+    /// Rings, first exterior, rest holes
+    Polygon(Vec<Vec<(f64, f64)>>),
+    MultiPolygon(Vec<Vec<Vec<(f64, f64)>>>),
+    GeometryCollection(Vec<GeoJsonGeometry>),
+}
+
+/// A feature with its geometry (None for unlocated features) and properties
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeoJsonFeature {
+    /// The feature's `id`, numbers formatted as text
+    pub id: Option<String>,
+    pub geometry: Option<GeoJsonGeometry>,
+    /// The `properties` object (or `Null`)
+    pub properties: JsonValue,
+}
+
+impl GeoJsonFeature {
+    /// Numeric property, e.g. an earthquake's magnitude
+    pub fn number(&self, key: &str) -> Option<f64> {
+        self.properties.get(key)?.as_f64()
+    }
+
+    /// Text property, e.g. a vehicle's name
+    pub fn text(&self, key: &str) -> Option<&str> {
+        self.properties.get(key)?.as_str()
+    }
+}
+
+/// Why GeoJSON couldn't be loaded
+#[derive(Clone, Debug, PartialEq)]
+pub enum GeoJsonError {
+    /// The document isn't valid JSON
+    Json(JsonError),
+    /// Valid JSON, but not a GeoJSON object
+    Invalid(String),
+    /// The server answered with a non-200 status
+    Status(u16),
+    /// The request failed (offline, DNS, TLS, ...)
+    Network(String),
+}
+
+impl fmt::Display for GeoJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeoJsonError::Json(error) => write!(f, "invalid JSON: {}", error),
+            GeoJsonError::Invalid(message) => write!(f, "invalid GeoJSON: {}", message),
+            GeoJsonError::Status(status) => write!(f, "HTTP status {}", status),
+            GeoJsonError::Network(message) => write!(f, "request failed: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for GeoJsonError {}
+
+/// Features parsed from a GeoJSON document, with the repairs made to their
+/// polygons as (feature index, warning)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GeoJsonData {
+    pub features: Vec<GeoJsonFeature>,
+    pub warnings: Vec<(usize, GeometryWarning)>,
+    /// Features of a FeatureCollection that couldn't be parsed and were left
+    /// out, as (index in the document's `features`, why)
+    pub skipped: Vec<(usize, GeoJsonError)>,
+}
+
+impl GeoJsonData {
+    /// Parse a FeatureCollection, a single Feature or a bare geometry.
+    /// Invalid features of a collection are skipped (listed in `skipped`)
+    /// rather than failing the whole document. Polygon rings are repaired
+    /// (winding, duplicate and closing points); polygons without a valid
+    /// exterior ring are dropped.
+    pub fn parse(text: &str) -> Result<Self, GeoJsonError> {
+        let root = JsonValue::parse(text).map_err(GeoJsonError::Json)?;
+        let mut skipped = Vec::new();
+        let features = match root.get("type").and_then(JsonValue::as_str) {
+            Some("FeatureCollection") => root.get("features")
+                .and_then(JsonValue::as_array)
+                .ok_or_else(|| GeoJsonError::Invalid("FeatureCollection without features".to_string()))?
+                .iter()
+                .enumerate()
+                .filter_map(|(index, feature)| match parse_feature(feature) {
+                    Ok(feature) => Some(feature),
+                    Err(error) => {
+                        skipped.push((index, error));
+                        None
+                    }
+                })
+                .collect(),
+            Some("Feature") => vec![parse_feature(&root)?],
+            Some(_) => vec![GeoJsonFeature { id: None, geometry: Some(parse_geometry(&root)?), properties: JsonValue::Null }],
+            None => return Err(GeoJsonError::Invalid("missing type".to_string())),
+        };
+
+        let mut data = GeoJsonData { features, warnings: Vec::new(), skipped };
+        for (index, feature) in data.features.iter_mut().enumerate() {
+            if let Some(geometry) = &mut feature.geometry {
+                let mut warnings = Vec::new();
+                repair_geometry(geometry, &mut warnings);
+                data.warnings.extend(warnings.into_iter().map(|warning| (index, warning)));
+            }
+        }
+        Ok(data)
+    }
+}
+
+fn parse_feature(value: &JsonValue) -> Result<GeoJsonFeature, GeoJsonError> {
+    if value.get("type").and_then(JsonValue::as_str) != Some("Feature") {
+        return Err(GeoJsonError::Invalid("expected a Feature".to_string()));
+    }
+    let id = match value.get("id") {
+        Some(JsonValue::String(id)) => Some(id.clone()),
+        Some(JsonValue::Number(id)) => Some(id.to_string()),
+        _ => None,
+    };
+    let geometry = match value.get("geometry") {
+        None | Some(JsonValue::Null) => None,
+        Some(geometry) => Some(parse_geometry(geometry)?),
+    };
+    Ok(GeoJsonFeature {
+        id,
+        geometry,
+        properties: value.get("properties").cloned().unwrap_or_default(),
+    })
+}
+
+fn parse_geometry(value: &JsonValue) -> Result<GeoJsonGeometry, GeoJsonError> {
+    let kind = value.get("type").and_then(JsonValue::as_str).unwrap_or_default();
+    if kind == "GeometryCollection" {
+        let geometries = value.get("geometries")
+            .and_then(JsonValue::as_array)
+            .ok_or_else(|| GeoJsonError::Invalid("GeometryCollection without geometries".to_string()))?;
+        return geometries.iter().map(parse_geometry).collect::<Result<_, _>>().map(GeoJsonGeometry::GeometryCollection);
+    }
+    let coordinates = value.get("coordinates")
+        .ok_or_else(|| GeoJsonError::Invalid(format!("{} without coordinates", kind)))?;
+    Ok(match kind {
+        "Point" => GeoJsonGeometry::Point(position(coordinates)?),
+        "MultiPoint" => GeoJsonGeometry::MultiPoint(positions(coordinates)?),
+        "LineString" => GeoJsonGeometry::LineString(positions(coordinates)?),
+        "MultiLineString" => GeoJsonGeometry::MultiLineString(nested(coordinates, positions)?),
+        "Polygon" => GeoJsonGeometry::Polygon(nested(coordinates, positions)?),
+        "MultiPolygon" => GeoJsonGeometry::MultiPolygon(nested(coordinates, |rings| nested(rings, positions))?),
+        _ => return Err(GeoJsonError::Invalid(format!("unknown geometry type {:?}", kind))),
+    })
+}
+
+/// A [lng, lat] position (altitude is ignored)
+fn position(value: &JsonValue) -> Result<(f64, f64), GeoJsonError> {
+    let numbers = value.as_array().unwrap_or_default();
+    match (numbers.first().and_then(JsonValue::as_f64), numbers.get(1).and_then(JsonValue::as_f64)) {
+        (Some(lng), Some(lat)) => Ok((lng, lat)),
+        _ => Err(GeoJsonError::Invalid("invalid position".to_string())),
+    }
+}
+
+fn positions(value: &JsonValue) -> Result<Vec<(f64, f64)>, GeoJsonError> {
+    nested(value, position)
+}
+
+fn nested<T>(value: &JsonValue, parse: impl Fn(&JsonValue) -> Result<T, GeoJsonError>) -> Result<Vec<T>, GeoJsonError> {
+    value.as_array()
+        .ok_or_else(|| GeoJsonError::Invalid("expected an array of coordinates".to_string()))?
+        .iter()
+        .map(parse)
+        .collect()
+}
+
+fn repair_geometry(geometry: &mut GeoJsonGeometry, warnings: &mut Vec<GeometryWarning>) {
+    match geometry {
+        GeoJsonGeometry::Polygon(rings) => {
+            if !repair_polygon(rings, warnings) {
+                rings.clear();
+            }
+        }
+        GeoJsonGeometry::MultiPolygon(polygons) => {
+            polygons.retain_mut(|rings| repair_polygon(rings, warnings));
+        }
+        GeoJsonGeometry::GeometryCollection(geometries) => {
+            for geometry in geometries {
+                repair_geometry(geometry, warnings);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Where a `GeoJsonLayer` gets its features from
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GeoJsonSource {
+    pub url: String,
+    /// Seconds between reloads, 0 to load once
+    pub refresh_interval: f64,
+}
+
+impl GeoJsonSource {
+    /// A FeatureCollection fetched over HTTP and reloaded every
+    /// `refresh_interval` seconds (0 to load it once), e.g. a live feed of
+    /// earthquakes or vehicle positions
+    pub fn from_url(url: impl Into<String>, refresh_interval: f64) -> Self {
+        Self { url: url.into(), refresh_interval }
+    }
+}
+
+/// Features projected for one projection
+#[derive(Default)]
+struct FeatureGeometry {
+    shapes: Vec<ProjectedShape>,
+    points: Vec<DVec2>,
+    // Shapes and points by position, to draw only those in view
+    shape_index: SpatialIndex,
+    point_index: SpatialIndex,
+}
+
+/// Draws GeoJSON features: polygons filled and outlined, lines, and points as
+/// dots. Data comes from `url` (reloaded every `refresh_interval` seconds when
+/// set) or from Rust with `set_data`.
+///
+/// The previous features stay on the map while a reload is in flight, and
+/// after a failed one; `error` tells what went wrong.
+#[derive(Live, LiveHook, LiveRegister)]
+pub struct GeoJsonLayer {
+    #[live] draw_fill: DrawMapTriangle,
+    #[live] draw_line: DrawMapLine,
+    #[live] draw_point: DrawMapCircle,
+    /// Address of the GeoJSON document, empty for data set from Rust
+    #[live] pub url: String,
+    /// Seconds between reloads of `url`, 0 to load it once
+    #[live(0.0)] pub refresh_interval: f64,
+    #[live(5.0)] pub point_radius: f64,
+    #[live(true)] pub visible: bool,
+    #[rust] data: GeoJsonData,
+    #[rust] error: Option<GeoJsonError>,
+    // Url the current data was (or is being) loaded from
+    #[rust] loaded_url: String,
+    #[rust] request: Option<LiveId>,
+    #[rust] refresh_timer: Timer,
+    #[rust] events: Vec<MapLayerEvent>,
+    // Projected features for the projection with this code
+    #[rust] geometry: Option<(String, FeatureGeometry)>,
+    #[rust] quality: QualityLevel,
+    #[rust(1.0)] opacity: f64,
+}
+
+impl GeoJsonLayer {
+    /// Load features from a source; the request goes out with the next event
+    pub fn set_source(&mut self, source: GeoJsonSource) {
+        self.url = source.url;
+        self.refresh_interval = source.refresh_interval;
+        self.loaded_url.clear();
+    }
+
+    pub fn source(&self) -> GeoJsonSource {
+        GeoJsonSource::from_url(self.url.clone(), self.refresh_interval)
+    }
+
+    /// Show features loaded elsewhere (e.g. a bundled file). Stops loading from `url`.
+    pub fn set_data(&mut self, data: GeoJsonData) {
+        self.url.clear();
+        self.loaded_url.clear();
+        self.request = None;
+        self.error = None;
+        self.set_loaded(data);
+    }
+
+    /// Reload `url` now instead of waiting for the refresh interval
+    pub fn reload(&mut self) {
+        self.loaded_url.clear();
+    }
+
+    pub fn features(&self) -> &[GeoJsonFeature] {
+        &self.data.features
+    }
+
+    /// Repairs made to the polygons of the current features
+    pub fn warnings(&self) -> &[(usize, GeometryWarning)] {
+        &self.data.warnings
+    }
+
+    /// Features of the current document that couldn't be parsed, as (index in
+    /// the document, why)
+    pub fn skipped(&self) -> &[(usize, GeoJsonError)] {
+        &self.data.skipped
+    }
+
+    /// Why the last load failed (cleared by the next successful one)
+    pub fn error(&self) -> Option<&GeoJsonError> {
+        self.error.as_ref()
+    }
+
+    /// Whether a request for `url` is in flight
+    pub fn is_loading(&self) -> bool {
+        self.request.is_some()
+    }
+
+    fn set_loaded(&mut self, data: GeoJsonData) {
+        self.data = data;
+        self.geometry = None;
+    }
+
+    fn fetch(&mut self, cx: &mut Cx) {
+        cx.stop_timer(self.refresh_timer);
+        self.refresh_timer = Timer::empty();
+        self.loaded_url = self.url.clone();
+        if self.url.is_empty() {
+            self.request = None;
+            return;
+        }
+        let request_id = LiveId::unique();
+        let mut request = HttpRequest::new(self.url.clone(), HttpMethod::GET);
+        request.set_header("User-Agent".to_string(), "MakepadMap/0.1".to_string());
+        request.set_header("Accept".to_string(), "application/geo+json, application/json".to_string());
+        cx.http_request(request_id, request);
+        self.request = Some(request_id);
+    }
+
+    /// Apply the result of the pending request and schedule the next reload
+    fn finish_request(&mut self, cx: &mut Cx, result: Result<GeoJsonData, GeoJsonError>) {
+        self.request = None;
+        match result {
+            Ok(data) => {
+                self.error = None;
+                if !data.warnings.is_empty() {
+                    self.events.push(MapLayerEvent::GeometryRepaired { warnings: data.warnings.clone() });
+                }
+                self.set_loaded(data);
+            }
+            Err(error) => {
+                self.events.push(MapLayerEvent::GeoJsonFailed { url: self.loaded_url.clone(), error: error.clone() });
+                self.error = Some(error);
+            }
+        }
+        if self.refresh_interval > 0.0 {
+            self.refresh_timer = cx.start_timeout(self.refresh_interval);
+        }
+    }
+
+    /// Features projected for the viewport's projection
+    fn update_geometry(&mut self, viewport: &MapViewport) {
+        let projection = viewport.projection.as_ref();
+        if self.geometry.as_ref().is_some_and(|(code, _)| code == projection.code()) {
+            return;
+        }
+        fn add(geometry: &GeoJsonGeometry, projection: &dyn Projection, out: &mut FeatureGeometry) {
+            match geometry {
+                GeoJsonGeometry::Point((lng, lat)) => out.points.push(projection.project(*lng, *lat)),
+                GeoJsonGeometry::MultiPoint(points) => {
+                    out.points.extend(points.iter().map(|(lng, lat)| projection.project(*lng, *lat)));
+                }
+                GeoJsonGeometry::LineString(points) => out.shapes.push(ProjectedShape::path(projection, points)),
+                GeoJsonGeometry::MultiLineString(lines) => {
+                    out.shapes.extend(lines.iter().map(|points| ProjectedShape::path(projection, points)));
+                }
+                GeoJsonGeometry::Polygon(rings) => out.shapes.push(ProjectedShape::polygon(projection, rings)),
+                GeoJsonGeometry::MultiPolygon(polygons) => {
+                    out.shapes.extend(polygons.iter().map(|rings| ProjectedShape::polygon(projection, rings)));
+                }
+                GeoJsonGeometry::GeometryCollection(geometries) => {
+                    for geometry in geometries {
+                        add(geometry, projection, out);
+                    }
+                }
+            }
+        }
+        let mut geometry = FeatureGeometry::default();
+        for feature in &self.data.features {
+            if let Some(feature_geometry) = &feature.geometry {
+                add(feature_geometry, projection, &mut geometry);
+            }
+        }
+        geometry.shape_index = SpatialIndex::from_boxes(geometry.shapes.iter().map(ProjectedShape::bounds));
+        geometry.point_index = SpatialIndex::from_boxes(geometry.points.iter().map(|&unit| (unit, unit)));
+        self.geometry = Some((projection.code().to_string(), geometry));
+    }
+
+    fn faded(&self, color: Vec4) -> Vec4 {
+        vec4(color.x, color.y, color.z, color.w * self.opacity as f32)
+    }
+}
+
+impl MapLayer for GeoJsonLayer {
+    fn draw_layer(&mut self, cx: &mut Cx2d, viewport: &MapViewport) {
+        self.update_geometry(viewport);
+        let Some((code, geometry)) = self.geometry.take() else {
+            return;
+        };
+        let colors = (self.draw_fill.color, self.draw_line.color, self.draw_point.color, self.draw_point.border_color);
+        self.draw_fill.color = self.faded(colors.0);
+        self.draw_line.color = self.faded(colors.1);
+        self.draw_point.color = self.faded(colors.2);
+        self.draw_point.border_color = self.faded(colors.3);
+        // Polygon outlines are the first thing to go when frames are slow
+        let outlines = self.quality < QualityLevel::CoarseOverlays;
+
+        for i in geometry.shape_index.query_viewport(viewport, 0.0) {
+            let shape = &geometry.shapes[i];
+            if !shape.is_on_screen(viewport) {
+                continue;
+            }
+            let to_screen = |unit: DVec2| shape.to_screen(viewport, unit);
+            for [a, b, c] in &shape.triangles {
+                self.draw_fill.draw_triangle(cx, to_screen(*a), to_screen(*b), to_screen(*c));
+            }
+            if outlines || !shape.closed {
+                for (from, to) in shape.segments() {
+                    let (from, to) = (to_screen(from), to_screen(to));
+                    if from != to {
+                        self.draw_line.draw_line(cx, from, to);
+                    }
+                }
+            }
+        }
+        for i in geometry.point_index.query_viewport(viewport, self.point_radius) {
+            let pos = viewport.unit_to_screen(geometry.points[i]);
+            if viewport.is_visible(pos, self.point_radius) {
+                self.draw_point.draw_circle(cx, pos, self.point_radius);
+            }
+        }
+
+        self.draw_fill.color = colors.0;
+        self.draw_line.color = colors.1;
+        self.draw_point.color = colors.2;
+        self.draw_point.border_color = colors.3;
+        self.geometry = Some((code, geometry));
+    }
+
+    fn handle_layer_event(&mut self, cx: &mut Cx, event: &Event, _viewport: &MapViewport) -> bool {
+        // A new url (from live_design, set_source or reload) is fetched right away
+        if self.url != self.loaded_url {
+            self.fetch(cx);
+        }
+        if self.refresh_timer.is_event(event).is_some() {
+            self.refresh_timer = Timer::empty();
+            self.fetch(cx);
+        }
+        let Event::NetworkResponses(responses) = event else {
+            return false;
+        };
+        let mut redraw = false;
+        for response in responses {
+            if Some(response.request_id) != self.request {
+                continue;
+            }
+            match &response.response {
+                NetworkResponse::HttpResponse(http_response) => {
+                    let result = if http_response.status_code != 200 {
+                        Err(GeoJsonError::Status(http_response.status_code))
+                    } else {
+                        let body = http_response.body.as_deref().unwrap_or_default();
+                        GeoJsonData::parse(&String::from_utf8_lossy(body))
+                    };
+                    redraw |= result.is_ok();
+                    self.finish_request(cx, result);
+                }
+                NetworkResponse::HttpRequestError(error) => {
+                    self.finish_request(cx, Err(GeoJsonError::Network(format!("{:?}", error))));
+                }
+                _ => {}
+            }
+        }
+        redraw
+    }
+
+    fn set_quality(&mut self, level: QualityLevel) {
+        self.quality = level;
+    }
+
+    fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    fn set_opacity(&mut self, opacity: f64) {
+        self.opacity = opacity;
+    }
+
+    fn take_layer_events(&mut self) -> Vec<MapLayerEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
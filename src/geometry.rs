@@ -0,0 +1,540 @@
+//! Geometry validity repair for imported polygon data
+//!
+//! Real-world GeoJSON/KML often has rings with the wrong winding order,
+//! repeated vertices or missing closing points. These helpers fix such
+//! problems so the data still renders correctly, reporting what was changed.
+//! Coordinates are `(lng, lat)` pairs, matching the rest of the crate.
+
+/// A problem found (and fixed) while repairing a geometry
+#[derive(Clone, Debug, PartialEq)]
+pub enum GeometryWarning {
+    /// Ring had the wrong winding order and was reversed
+    WindingReversed { ring: usize },
+    /// Consecutive duplicate points were removed from a ring
+    DuplicatePointsRemoved { ring: usize, count: usize },
+    /// Ring was not closed, the first point was appended
+    RingClosed { ring: usize },
+    /// Ring has fewer than 3 distinct points and was dropped
+    DegenerateRingDropped { ring: usize },
+}
+
+/// Signed area of a ring using the shoelace formula (positive = counter-clockwise)
+pub fn ring_signed_area(ring: &[(f64, f64)]) -> f64 {
+    if ring.len() < 3 {
+        return 0.0;
+    }
+    let mut sum = 0.0;
+    for i in 0..ring.len() {
+        let (x0, y0) = ring[i];
+        let (x1, y1) = ring[(i + 1) % ring.len()];
+        sum += x0 * y1 - x1 * y0;
+    }
+    sum / 2.0
+}
+
+/// Check if a ring is wound counter-clockwise
+pub fn is_ccw(ring: &[(f64, f64)]) -> bool {
+    ring_signed_area(ring) > 0.0
+}
+
+/// Repair a single ring in place: remove consecutive duplicates, close it and
+/// enforce winding (counter-clockwise for exterior rings, clockwise for holes,
+/// as in RFC 7946). Returns false if the ring is degenerate and should be dropped.
+pub fn repair_ring(ring: &mut Vec<(f64, f64)>, index: usize, exterior: bool, warnings: &mut Vec<GeometryWarning>) -> bool {
+    // Remove consecutive duplicate points
+    let before = ring.len();
+    ring.dedup();
+    let removed = before - ring.len();
+    if removed > 0 {
+        warnings.push(GeometryWarning::DuplicatePointsRemoved { ring: index, count: removed });
+    }
+
+    // Work on the open form of the ring
+    let was_closed = ring.len() > 1 && ring.first() == ring.last();
+    if was_closed {
+        ring.pop();
+    }
+
+    if ring.len() < 3 {
+        warnings.push(GeometryWarning::DegenerateRingDropped { ring: index });
+        return false;
+    }
+
+    if is_ccw(ring) != exterior {
+        ring.reverse();
+        warnings.push(GeometryWarning::WindingReversed { ring: index });
+    }
+
+    // Close the ring again
+    if !was_closed {
+        warnings.push(GeometryWarning::RingClosed { ring: index });
+    }
+    ring.push(ring[0]);
+    true
+}
+
+/// Repair a polygon given as rings (first ring exterior, rest holes).
+/// Degenerate holes are dropped; returns false if the exterior ring is degenerate.
+pub fn repair_polygon(rings: &mut Vec<Vec<(f64, f64)>>, warnings: &mut Vec<GeometryWarning>) -> bool {
+    let mut index = 0;
+    let mut exterior_ok = true;
+    rings.retain_mut(|ring| {
+        let exterior = index == 0;
+        let keep = repair_ring(ring, index, exterior, warnings);
+        if exterior && !keep {
+            exterior_ok = false;
+        }
+        index += 1;
+        keep
+    });
+    exterior_ok
+}
+
+/// Douglas–Peucker simplification, run once per line: for each point the
+/// largest tolerance (in the points' units) the simplification keeps it at.
+/// The end points are always kept (infinity). `simplify_line` then gives the
+/// line at any tolerance without running the algorithm again.
+pub fn simplification_thresholds(points: &[(f64, f64)]) -> Vec<f64> {
+    let mut thresholds = vec![0.0; points.len()];
+    let Some(last) = points.len().checked_sub(1) else {
+        return thresholds;
+    };
+    thresholds[0] = f64::INFINITY;
+    thresholds[last] = f64::INFINITY;
+    // Spans still to split, with the threshold of the point that bounds them.
+    // A stack instead of recursion, long GPS tracks would overflow the call stack.
+    let mut spans = vec![(0, last, f64::INFINITY)];
+    while let Some((first, last, limit)) = spans.pop() {
+        if last <= first + 1 {
+            continue;
+        }
+        let (mut farthest, mut max_distance) = (first + 1, -1.0);
+        for i in first + 1..last {
+            let distance = segment_distance(points[i], points[first], points[last]);
+            if distance > max_distance {
+                farthest = i;
+                max_distance = distance;
+            }
+        }
+        // A point is only kept while the point that split off its span is
+        let threshold = max_distance.min(limit);
+        thresholds[farthest] = threshold;
+        spans.push((first, farthest, threshold));
+        spans.push((farthest, last, threshold));
+    }
+    thresholds
+}
+
+/// Points of a line that Douglas–Peucker simplification at `tolerance`
+/// keeps, given the line's `simplification_thresholds`
+pub fn simplify_line(points: &[(f64, f64)], thresholds: &[f64], tolerance: f64) -> Vec<(f64, f64)> {
+    points.iter().zip(thresholds)
+        .filter(|(_, &threshold)| threshold > tolerance)
+        .map(|(&point, _)| point)
+        .collect()
+}
+
+/// Triangulate a polygon given as rings (first ring exterior, rest holes,
+/// closing points optional) by ear clipping, with each hole bridged into the
+/// exterior ring. Works in any planar coordinates, e.g. projected positions.
+/// Self-intersecting rings give a best-effort result.
+pub fn triangulate(rings: &[Vec<(f64, f64)>]) -> Vec<[(f64, f64); 3]> {
+    let open = |ring: &Vec<(f64, f64)>| {
+        let mut ring = ring.clone();
+        ring.dedup();
+        if ring.len() > 1 && ring.first() == ring.last() {
+            ring.pop();
+        }
+        ring
+    };
+    let Some(exterior) = rings.first().map(open).filter(|ring| ring.len() >= 3) else {
+        return Vec::new();
+    };
+    let mut outline = exterior;
+    if ring_signed_area(&outline) < 0.0 {
+        outline.reverse();
+    }
+
+    // Bridge holes into the outline, rightmost hole first, holes wound opposite the exterior
+    let mut holes: Vec<Vec<(f64, f64)>> = rings[1..].iter().map(open).filter(|ring| ring.len() >= 3).collect();
+    for hole in &mut holes {
+        if ring_signed_area(hole) > 0.0 {
+            hole.reverse();
+        }
+    }
+    let max_x = |ring: &Vec<(f64, f64)>| ring.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+    holes.sort_by(|a, b| max_x(b).total_cmp(&max_x(a)));
+    for hole in holes {
+        bridge_hole(&mut outline, &hole);
+    }
+
+    ear_clip(outline)
+}
+
+/// Splice a hole into the outline through a bridge from the hole's rightmost
+/// vertex to an outline vertex it can see. A hole outside the outline is left out.
+fn bridge_hole(outline: &mut Vec<(f64, f64)>, hole: &[(f64, f64)]) {
+    let (hole_index, &from) = hole.iter().enumerate().max_by(|a, b| a.1.0.total_cmp(&b.1.0)).unwrap();
+    let Some(target) = bridge_target(outline, from) else {
+        return;
+    };
+
+    let mut bridged = Vec::with_capacity(outline.len() + hole.len() + 2);
+    bridged.extend_from_slice(&outline[..=target]);
+    bridged.extend(hole[hole_index..].iter().chain(&hole[..=hole_index]));
+    bridged.extend_from_slice(&outline[target..]);
+    *outline = bridged;
+}
+
+/// Outline vertex visible from `m`, a point inside the counter-clockwise
+/// outline, by casting a ray from `m` to the right (Eberly, "Triangulation by
+/// Ear Clipping"). Holes are bridged right to left, so the bridge, which lies
+/// right of `m`, can't cross a hole still to come.
+fn bridge_target(outline: &[(f64, f64)], m: (f64, f64)) -> Option<usize> {
+    let n = outline.len();
+    // Nearest upward edge the ray hits, at x
+    let mut hit: Option<(f64, usize)> = None;
+    for i in 0..n {
+        let (a, b) = (outline[i], outline[(i + 1) % n]);
+        if a.1 <= m.1 && b.1 > m.1 {
+            let x = a.0 + (m.1 - a.1) / (b.1 - a.1) * (b.0 - a.0);
+            if x >= m.0 && hit.is_none_or(|(best, _)| x < best) {
+                hit = Some((x, i));
+            }
+        }
+    }
+    let (x, edge) = hit?;
+    let (a, b) = (edge, (edge + 1) % n);
+    let hit_point = (x, m.1);
+    let mut target = if outline[a] == hit_point || outline[a].0 > outline[b].0 { a } else { b };
+
+    // Reflex vertices inside the triangle from m to the hit and the edge's
+    // vertex may block the view; then the one at the smallest angle to the
+    // ray is visible instead
+    let corner = outline[target];
+    if corner.1 != m.1 {
+        let angle = |p: (f64, f64)| (p.1 - m.1).abs() / (p.0 - m.0);
+        let mut best: Option<(f64, f64, usize)> = None;
+        for i in 0..n {
+            let p = outline[i];
+            let (prev, next) = (outline[(i + n - 1) % n], outline[(i + 1) % n]);
+            if p == corner || p.0 <= m.0 || cross(prev, p, next) >= 0.0 {
+                continue;
+            }
+            let sides = [cross(m, hit_point, p), cross(hit_point, corner, p), cross(corner, m, p)];
+            let inside = sides.iter().all(|&side| side >= 0.0) || sides.iter().all(|&side| side <= 0.0);
+            let distance = (p.0 - m.0).powi(2) + (p.1 - m.1).powi(2);
+            if inside && best.is_none_or(|(best_angle, best_distance, _)| {
+                angle(p) < best_angle || (angle(p) == best_angle && distance < best_distance)
+            }) {
+                best = Some((angle(p), distance, i));
+            }
+        }
+        if let Some((_, _, i)) = best {
+            target = i;
+        }
+    }
+
+    // Earlier bridges repeat vertices; use the copy whose corner faces m
+    let point = outline[target];
+    Some((0..n).filter(|&i| outline[i] == point).find(|&i| faces(outline, i, m)).unwrap_or(target))
+}
+
+/// Whether `p` lies within the interior angle of a counter-clockwise ring at vertex `i`
+fn faces(ring: &[(f64, f64)], i: usize, p: (f64, f64)) -> bool {
+    let n = ring.len();
+    let (prev, cur, next) = (ring[(i + n - 1) % n], ring[i], ring[(i + 1) % n]);
+    if cross(prev, cur, next) >= 0.0 {
+        cross(prev, cur, p) >= 0.0 && cross(cur, next, p) >= 0.0
+    } else {
+        cross(prev, cur, p) >= 0.0 || cross(cur, next, p) >= 0.0
+    }
+}
+
+/// Whether `p` is inside a polygon (first ring exterior, rest holes, closing
+/// points optional), by the even-odd rule so holes and winding don't matter.
+/// A point on an edge shared by two polygons is in exactly one of them.
+pub fn point_in_polygon(p: (f64, f64), rings: &[Vec<(f64, f64)>]) -> bool {
+    let mut inside = false;
+    for ring in rings {
+        for i in 0..ring.len() {
+            let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+            // Count crossings of a ray from p towards +x
+            if (a.1 > p.1) != (b.1 > p.1) && p.0 < a.0 + (p.1 - a.1) / (b.1 - a.1) * (b.0 - a.0) {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+/// Distance from `p` to the segment from `a` to `b`
+pub fn segment_distance(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_sq = dx * dx + dy * dy;
+    let t = if length_sq > 0.0 {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length_sq).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    ((p.0 - a.0 - dx * t).powi(2) + (p.1 - a.1 - dy * t).powi(2)).sqrt()
+}
+
+/// Z component of (b - a) x (p - a): positive when p is left of a -> b
+fn cross(a: (f64, f64), b: (f64, f64), p: (f64, f64)) -> f64 {
+    (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0)
+}
+
+/// Ear clipping of a counter-clockwise ring
+fn ear_clip(ring: Vec<(f64, f64)>) -> Vec<[(f64, f64); 3]> {
+    let mut indices: Vec<usize> = (0..ring.len()).collect();
+    let mut triangles = Vec::with_capacity(ring.len().saturating_sub(2));
+    let mut misses = 0;
+    let mut i = 0;
+    while indices.len() > 3 {
+        let n = indices.len();
+        let (prev, cur, next) = (ring[indices[(i + n - 1) % n]], ring[indices[i % n]], ring[indices[(i + 1) % n]]);
+        let convex = cross(prev, cur, next) > 0.0;
+        let is_ear = convex && !indices.iter().any(|&j| {
+            let p = ring[j];
+            p != prev && p != cur && p != next
+                && cross(prev, cur, p) >= 0.0 && cross(cur, next, p) >= 0.0 && cross(next, prev, p) >= 0.0
+        });
+        // Without an ear in a full pass the ring is degenerate, clip anyway so it terminates
+        if is_ear || misses >= n {
+            if convex || misses >= n {
+                triangles.push([prev, cur, next]);
+            }
+            indices.remove(i % n);
+            misses = 0;
+            i %= indices.len();
+        } else {
+            misses += 1;
+            i = (i + 1) % n;
+        }
+    }
+    if let [a, b, c] = indices[..] {
+        if cross(ring[a], ring[b], ring[c]) > 0.0 {
+            triangles.push([ring[a], ring[b], ring[c]]);
+        }
+    }
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: f64, expected: f64, tolerance: f64) {
+        assert!((actual - expected).abs() <= tolerance, "{actual} is not within {tolerance} of {expected}");
+    }
+
+    fn rect(x0: f64, y0: f64, x1: f64, y1: f64) -> Vec<(f64, f64)> {
+        vec![(x0, y0), (x1, y0), (x1, y1), (x0, y1)]
+    }
+
+    /// Total area of the triangles, checking that none is wound clockwise
+    fn triangles_area(triangles: &[[(f64, f64); 3]]) -> f64 {
+        triangles.iter().map(|&[a, b, c]| {
+            let area = cross(a, b, c) / 2.0;
+            assert!(area >= -1e-9, "clockwise triangle {a:?} {b:?} {c:?}");
+            area
+        }).sum()
+    }
+
+    fn polygon_area(rings: &[Vec<(f64, f64)>]) -> f64 {
+        let exterior = ring_signed_area(&rings[0]).abs();
+        exterior - rings[1..].iter().map(|hole| ring_signed_area(hole).abs()).sum::<f64>()
+    }
+
+    /// Check that the triangles add up to the polygon's area and, sampled on
+    /// a grid, cover each point of the polygon once and no point outside it
+    fn assert_covers(rings: &[Vec<(f64, f64)>]) {
+        let triangles = triangulate(rings);
+        assert_near(triangles_area(&triangles), polygon_area(rings), 1e-9);
+        let (min, max) = rings[0].iter().fold(
+            ((f64::INFINITY, f64::INFINITY), (f64::NEG_INFINITY, f64::NEG_INFINITY)),
+            |(min, max), p| ((min.0.min(p.0), min.1.min(p.1)), (max.0.max(p.0), max.1.max(p.1))),
+        );
+        for i in 0..50 {
+            for j in 0..50 {
+                // Offsets that keep samples off the integer edges of the test shapes
+                let p = (min.0 + (max.0 - min.0) * (i as f64 + 0.37) / 50.0, min.1 + (max.1 - min.1) * (j as f64 + 0.61) / 50.0);
+                let covered = triangles.iter()
+                    .filter(|&&[a, b, c]| cross(a, b, p) >= 0.0 && cross(b, c, p) >= 0.0 && cross(c, a, p) >= 0.0)
+                    .count();
+                assert_eq!(covered, usize::from(point_in_polygon(p, rings)), "{p:?} covered {covered} times");
+            }
+        }
+    }
+
+    #[test]
+    fn triangulates_square() {
+        let triangles = triangulate(&[rect(0.0, 0.0, 10.0, 10.0)]);
+        assert_eq!(triangles.len(), 2);
+        assert_near(triangles_area(&triangles), 100.0, 1e-9);
+    }
+
+    #[test]
+    fn square_with_one_hole() {
+        let rings = [rect(0.0, 0.0, 10.0, 10.0), rect(3.0, 3.0, 7.0, 7.0)];
+        assert_near(polygon_area(&rings), 84.0, 1e-9);
+        assert_covers(&rings);
+    }
+
+    #[test]
+    fn square_with_two_holes() {
+        assert_covers(&[rect(0.0, 0.0, 10.0, 10.0), rect(2.0, 2.0, 4.0, 4.0), rect(6.0, 6.0, 8.0, 8.0)]);
+        assert_covers(&[rect(0.0, 0.0, 10.0, 10.0), rect(2.0, 4.0, 4.0, 6.0), rect(6.0, 4.0, 8.0, 6.0)]);
+        assert_covers(&[rect(0.0, 0.0, 10.0, 10.0), rect(2.0, 2.0, 8.0, 4.0), rect(2.0, 6.0, 8.0, 8.0)]);
+    }
+
+    #[test]
+    fn bridges_do_not_cross_later_holes() {
+        // The outline vertex nearest to the right hole lies beyond the left
+        // hole, a bridge to it would cut through that hole
+        let exterior = vec![(0.0, 0.0), (100.0, 0.0), (100.0, 10.0), (30.0, 10.0), (0.0, 10.0)];
+        assert_covers(&[exterior, rect(35.0, 3.0, 45.0, 8.0), vec![(50.0, 4.0), (56.0, 5.0), (50.0, 6.0)]]);
+    }
+
+    #[test]
+    fn bridges_around_reflex_vertices() {
+        // A notch from the top blocks the view from the hole to the corner
+        // of the edge right of it
+        let exterior = vec![
+            (0.0, 0.0), (20.0, 0.0), (20.0, 20.0), (14.0, 20.0),
+            (14.0, 14.0), (12.0, 14.0), (12.0, 20.0), (0.0, 20.0),
+        ];
+        assert_covers(&[exterior, rect(4.0, 9.0, 10.0, 11.0)]);
+    }
+
+    #[test]
+    fn clockwise_input_with_closing_points() {
+        let mut exterior = rect(0.0, 0.0, 10.0, 10.0);
+        exterior.reverse();
+        exterior.push(exterior[0]);
+        let mut hole = rect(3.0, 3.0, 7.0, 7.0);
+        hole.push(hole[0]);
+        let rings = [exterior, hole];
+        assert_near(triangles_area(&triangulate(&rings)), 84.0, 1e-9);
+    }
+
+    #[test]
+    fn degenerate_rings() {
+        // Exterior without three distinct points
+        assert!(triangulate(&[vec![(0.0, 0.0), (1.0, 1.0), (1.0, 1.0), (0.0, 0.0)]]).is_empty());
+        assert!(triangulate(&[]).is_empty());
+        // Collinear exterior has no area
+        assert_near(triangles_area(&triangulate(&[vec![(0.0, 0.0), (1.0, 0.0), (2.0, 0.0)]])), 0.0, 1e-9);
+        // Degenerate holes are ignored
+        let rings = [rect(0.0, 0.0, 10.0, 10.0), vec![(5.0, 5.0), (6.0, 6.0), (5.0, 5.0)]];
+        assert_near(triangles_area(&triangulate(&rings)), 100.0, 1e-9);
+        // Repeated points don't change the result
+        let rings = [vec![(0.0, 0.0), (10.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0), (0.0, 10.0)]];
+        assert_near(triangles_area(&triangulate(&rings)), 100.0, 1e-9);
+    }
+
+    /// A zigzag with shrinking teeth, so each tolerance keeps a different number of points
+    fn zigzag() -> Vec<(f64, f64)> {
+        (0..12).map(|i| (i as f64, if i % 2 == 0 { 0.0 } else { 12.0 - i as f64 })).collect()
+    }
+
+    /// Plain recursive Douglas–Peucker, to check the thresholds against
+    fn douglas_peucker(points: &[(f64, f64)], tolerance: f64) -> Vec<(f64, f64)> {
+        let last = points.len() - 1;
+        let (farthest, distance) = (1..last)
+            .map(|i| (i, segment_distance(points[i], points[0], points[last])))
+            .fold((0, -1.0), |best, candidate| if candidate.1 > best.1 { candidate } else { best });
+        if last < 2 || distance <= tolerance {
+            return vec![points[0], points[last]];
+        }
+        let mut kept = douglas_peucker(&points[..=farthest], tolerance);
+        kept.pop();
+        kept.extend(douglas_peucker(&points[farthest..], tolerance));
+        kept
+    }
+
+    #[test]
+    fn collinear_run_collapses_to_endpoints() {
+        let points: Vec<(f64, f64)> = (0..6).map(|i| (i as f64, 2.0 * i as f64)).collect();
+        let thresholds = simplification_thresholds(&points);
+        assert_eq!(simplify_line(&points, &thresholds, 0.0), [(0.0, 0.0), (5.0, 10.0)]);
+    }
+
+    #[test]
+    fn simplification_keeps_endpoints() {
+        let points = zigzag();
+        let thresholds = simplification_thresholds(&points);
+        assert_eq!((thresholds[0], thresholds[points.len() - 1]), (f64::INFINITY, f64::INFINITY));
+        for tolerance in [0.0, 1.0, 5.0, 1e9] {
+            let kept = simplify_line(&points, &thresholds, tolerance);
+            assert_eq!((kept[0], kept[kept.len() - 1]), (points[0], points[points.len() - 1]), "tolerance {tolerance}");
+        }
+        assert_eq!(simplify_line(&points, &thresholds, 1e9).len(), 2);
+        // Lines too short to simplify are kept as they are
+        for short in [vec![], vec![(1.0, 2.0)], vec![(1.0, 2.0), (3.0, 4.0)]] {
+            assert_eq!(simplify_line(&short, &simplification_thresholds(&short), 1e9), short);
+        }
+    }
+
+    #[test]
+    fn simplification_is_monotonic_in_tolerance() {
+        let points = zigzag();
+        let thresholds = simplification_thresholds(&points);
+        let mut previous = simplify_line(&points, &thresholds, 0.0);
+        assert_eq!(previous, points);
+        for step in 1..=24 {
+            let tolerance = step as f64 * 0.5;
+            let kept = simplify_line(&points, &thresholds, tolerance);
+            assert!(kept.iter().all(|point| previous.contains(point)), "tolerance {tolerance} kept a dropped point");
+            assert_eq!(kept, douglas_peucker(&points, tolerance), "tolerance {tolerance}");
+            previous = kept;
+        }
+    }
+
+    #[test]
+    fn point_in_polygon_with_hole() {
+        let rings = [rect(0.0, 0.0, 10.0, 10.0), rect(3.0, 3.0, 7.0, 7.0)];
+        assert!(point_in_polygon((1.0, 1.0), &rings));
+        assert!(point_in_polygon((8.0, 5.0), &rings));
+        assert!(!point_in_polygon((5.0, 5.0), &rings));
+        assert!(!point_in_polygon((12.0, 5.0), &rings));
+        // Winding and closing points don't matter
+        let mut exterior = rings[0].clone();
+        exterior.reverse();
+        exterior.push(exterior[0]);
+        assert!(!point_in_polygon((5.0, 5.0), &[exterior.clone(), rings[1].clone()]));
+        assert!(point_in_polygon((5.0, 5.0), &[exterior]));
+    }
+
+    #[test]
+    fn point_on_shared_edge_is_in_one_polygon() {
+        // Four squares meeting at (10, 10): points on their edges and on the
+        // common corner belong to exactly one of them
+        let squares = [
+            rect(0.0, 0.0, 10.0, 10.0),
+            rect(10.0, 0.0, 20.0, 10.0),
+            rect(0.0, 10.0, 10.0, 20.0),
+            rect(10.0, 10.0, 20.0, 20.0),
+        ];
+        for p in [(10.0, 5.0), (10.0, 15.0), (5.0, 10.0), (15.0, 10.0), (10.0, 10.0)] {
+            let count = squares.iter().filter(|square| point_in_polygon(p, &[square.to_vec()])).count();
+            assert_eq!(count, 1, "{p:?} is in {count} squares");
+        }
+    }
+
+    #[test]
+    fn segment_distances() {
+        assert_near(segment_distance((5.0, 3.0), (0.0, 0.0), (10.0, 0.0)), 3.0, 1e-12);
+        assert_near(segment_distance((5.0, 0.0), (0.0, 0.0), (10.0, 0.0)), 0.0, 1e-12);
+        // Beyond the ends the nearest end point counts
+        assert_near(segment_distance((13.0, 4.0), (0.0, 0.0), (10.0, 0.0)), 5.0, 1e-12);
+        assert_near(segment_distance((-3.0, -4.0), (0.0, 0.0), (10.0, 0.0)), 5.0, 1e-12);
+    }
+
+    #[test]
+    fn zero_length_segment_distance() {
+        assert_near(segment_distance((4.0, 5.0), (1.0, 1.0), (1.0, 1.0)), 5.0, 1e-12);
+        assert_near(segment_distance((1.0, 1.0), (1.0, 1.0), (1.0, 1.0)), 0.0, 1e-12);
+    }
+}
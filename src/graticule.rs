@@ -0,0 +1,245 @@
+use makepad_widgets::*;
+
+use crate::layers::{MapLayer, MapViewport};
+use crate::map_view::DrawMapLine;
+use crate::quality::QualityLevel;
+
+live_design! {
+    link widgets;
+    use link::theme::*;
+    use link::shaders::*;
+    use link::widgets::*;
+
+    // Latitude/longitude grid with degree labels along the top and left edges
+    pub GraticuleLayer = {{GraticuleLayer}} {
+        draw_line: {
+            color: #33557777
+            line_width: 1.0
+        }
+        draw_label: {
+            color: #335577
+            text_style: <THEME_FONT_REGULAR> {
+                font_size: 9.0
+            }
+        }
+    }
+}
+
+/// Grid spacings in degrees, finest first: whole degrees down to 1°, then
+/// minutes and seconds, so labels stay round numbers in either notation
+const STEPS: [f64; 20] = [
+    1.0 / 3600.0, 2.0 / 3600.0, 5.0 / 3600.0, 10.0 / 3600.0, 15.0 / 3600.0, 30.0 / 3600.0,
+    1.0 / 60.0, 2.0 / 60.0, 5.0 / 60.0, 10.0 / 60.0, 15.0 / 60.0, 30.0 / 60.0,
+    1.0, 2.0, 5.0, 10.0, 15.0, 20.0, 30.0, 45.0,
+];
+
+/// Ground meters per degree of latitude, to turn the map scale into degrees
+const METERS_PER_DEGREE: f64 = 111_320.0;
+
+/// More lines than this are not drawn (e.g. a spacing far too fine for the view)
+const MAX_LINES: usize = 200;
+
+/// Latitude/longitude lines (a graticule) over the map, spaced by the zoom
+/// level so neighbouring lines are at least `min_spacing` pixels apart.
+///
+/// Lines are sampled through the map projection, so they curve where the
+/// projection does (e.g. `PolarStereographic`). Parallels are labeled at the
+/// left edge and meridians at the top, in degrees, minutes and seconds as the
+/// spacing needs.
+#[derive(Live, LiveHook, LiveRegister)]
+pub struct GraticuleLayer {
+    #[live] draw_line: DrawMapLine,
+    #[live] draw_label: DrawText,
+    /// Least distance in pixels between neighbouring lines
+    #[live(80.0)] pub min_spacing: f64,
+    #[live(true)] pub show_labels: bool,
+    #[live(true)] pub visible: bool,
+    #[rust] quality: QualityLevel,
+    #[rust(1.0)] opacity: f64,
+}
+
+/// A line of the grid: its value in degrees and screen positions along it
+struct GraticuleLine {
+    degrees: f64,
+    points: Vec<DVec2>,
+}
+
+impl GraticuleLayer {
+    /// Spacing in degrees for the viewport's scale
+    pub fn step(&self, viewport: &MapViewport) -> f64 {
+        let degrees_per_pixel = viewport.meters_per_pixel() / METERS_PER_DEGREE;
+        let wanted = degrees_per_pixel * self.min_spacing;
+        STEPS.iter().copied().find(|step| *step >= wanted).unwrap_or(STEPS[STEPS.len() - 1])
+    }
+
+    /// Geographic range under the viewport as (min_lng, max_lng, min_lat, max_lat).
+    /// Longitudes are continuous past ±180 for projections that wrap.
+    fn visible_range(viewport: &MapViewport) -> (f64, f64, f64, f64) {
+        let projection = viewport.projection.as_ref();
+        if projection.wraps_longitude() {
+            let (min, max) = viewport.unit_bounds(viewport.rect);
+            let center = projection.project(viewport.center_lng, viewport.center_lat);
+            let (min_lng, _) = projection.unproject(dvec2(min.x, center.y));
+            let (max_lng, _) = projection.unproject(dvec2(max.x, center.y));
+            // y points down, so the bottom of the view is the southern edge
+            let (_, min_lat) = projection.unproject(dvec2(center.x, max.y.clamp(0.0, 1.0)));
+            let (_, max_lat) = projection.unproject(dvec2(center.x, min.y.clamp(0.0, 1.0)));
+            return (min_lng, max_lng, min_lat, max_lat);
+        }
+
+        // Azimuthal projections: every longitude may be in view, latitudes
+        // from the screen edges, or up to a pole on screen
+        let rect = viewport.rect;
+        let (mut min_lat, mut max_lat) = (f64::INFINITY, f64::NEG_INFINITY);
+        for fx in [0.0, 0.5, 1.0] {
+            for fy in [0.0, 0.5, 1.0] {
+                let (_, lat) = viewport.screen_to_geo(rect.pos + rect.size * dvec2(fx, fy));
+                min_lat = min_lat.min(lat);
+                max_lat = max_lat.max(lat);
+            }
+        }
+        for pole in [90.0, -90.0] {
+            if viewport.is_visible(viewport.unit_to_screen(projection.project(0.0, pole)), 0.0) {
+                min_lat = min_lat.min(pole);
+                max_lat = max_lat.max(pole);
+            }
+        }
+        (-180.0, 180.0, min_lat.max(-90.0), max_lat.min(90.0))
+    }
+
+    /// Meridians and parallels at `step` degrees over the visible range, as
+    /// screen polylines sampled finely enough to follow curved projections
+    fn lines(&self, viewport: &MapViewport, step: f64) -> (Vec<GraticuleLine>, Vec<GraticuleLine>) {
+        let (min_lng, max_lng, min_lat, max_lat) = Self::visible_range(viewport);
+        let projection = viewport.projection.as_ref();
+        let center = projection.project(viewport.center_lng, viewport.center_lat);
+        let to_screen = |lng: f64, lat: f64| viewport.unit_to_screen_near(projection.project(lng, lat), center);
+
+        let multiples = |min: f64, max: f64| {
+            let (first, last) = ((min / step).ceil() as i64, (max / step).floor() as i64);
+            let count = (last - first + 1).max(0) as usize;
+            (count <= MAX_LINES).then(|| (first..=last).map(move |i| i as f64 * step))
+        };
+        // Fewer samples when frames are slow
+        let samples = if self.quality >= QualityLevel::CoarseOverlays { 8 } else { 32 };
+
+        let meridians = multiples(min_lng, max_lng).into_iter().flatten().map(|lng| GraticuleLine {
+            degrees: lng,
+            points: (0..=samples).map(|i| {
+                let lat = min_lat + (max_lat - min_lat) * i as f64 / samples as f64;
+                to_screen(lng, lat)
+            }).collect(),
+        }).collect();
+        let parallels = multiples(min_lat, max_lat).into_iter().flatten().map(|lat| GraticuleLine {
+            degrees: lat,
+            points: (0..=samples * 2).map(|i| {
+                let lng = min_lng + (max_lng - min_lng) * i as f64 / (samples * 2) as f64;
+                to_screen(lng, lat)
+            }).collect(),
+        }).collect();
+        (meridians, parallels)
+    }
+
+    fn faded(&self, color: Vec4) -> Vec4 {
+        vec4(color.x, color.y, color.z, color.w * self.opacity as f32)
+    }
+}
+
+impl MapLayer for GraticuleLayer {
+    fn draw_layer(&mut self, cx: &mut Cx2d, viewport: &MapViewport) {
+        let step = self.step(viewport);
+        let (meridians, parallels) = self.lines(viewport, step);
+        let colors = (self.draw_line.color, self.draw_label.color);
+        self.draw_line.color = self.faded(colors.0);
+        self.draw_label.color = self.faded(colors.1);
+
+        for line in meridians.iter().chain(&parallels) {
+            for pair in line.points.windows(2) {
+                let (from, to) = (pair[0], pair[1]);
+                if viewport.is_visible((from + to) / 2.0, (to - from).length() / 2.0) {
+                    self.draw_line.draw_line(cx, from, to);
+                }
+            }
+        }
+
+        if self.show_labels {
+            let rect = viewport.rect;
+            let inset = 4.0;
+            let font_size = self.draw_label.text_style.font_size as f64;
+            for line in &meridians {
+                if let Some(pos) = edge_crossing(&line.points, rect.pos.y + inset, true) {
+                    if pos.x > rect.pos.x + font_size * 6.0 && pos.x < rect.pos.x + rect.size.x - inset {
+                        let label = format_degrees(wrap_degrees(line.degrees), step, 'E', 'W');
+                        self.draw_label.draw_abs(cx, dvec2(pos.x + 3.0, pos.y), &label);
+                    }
+                }
+            }
+            for line in &parallels {
+                if let Some(pos) = edge_crossing(&line.points, rect.pos.x + inset, false) {
+                    if pos.y > rect.pos.y + font_size * 2.5 && pos.y < rect.pos.y + rect.size.y - font_size * 2.0 {
+                        let label = format_degrees(line.degrees, step, 'N', 'S');
+                        self.draw_label.draw_abs(cx, dvec2(pos.x, pos.y - font_size * 1.4), &label);
+                    }
+                }
+            }
+        }
+
+        self.draw_line.color = colors.0;
+        self.draw_label.color = colors.1;
+    }
+
+    fn set_quality(&mut self, level: QualityLevel) {
+        self.quality = level;
+    }
+
+    fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    fn set_opacity(&mut self, opacity: f64) {
+        self.opacity = opacity;
+    }
+}
+
+/// First point where a polyline crosses the horizontal line `y = value`
+/// (`horizontal`) or the vertical line `x = value`
+fn edge_crossing(points: &[DVec2], value: f64, horizontal: bool) -> Option<DVec2> {
+    let coordinate = |p: DVec2| if horizontal { p.y } else { p.x };
+    points.windows(2).find_map(|pair| {
+        let (a, b) = (coordinate(pair[0]), coordinate(pair[1]));
+        if (a - value) * (b - value) > 0.0 || a == b {
+            return None;
+        }
+        let t = (value - a) / (b - a);
+        Some(pair[0] + (pair[1] - pair[0]) * t)
+    })
+}
+
+/// Longitude in -180..=180
+fn wrap_degrees(lng: f64) -> f64 {
+    let wrapped = (lng + 180.0).rem_euclid(360.0) - 180.0;
+    if wrapped == -180.0 && lng > 0.0 { 180.0 } else { wrapped }
+}
+
+/// A grid value as "30°N", "37°45′N" or "122°27′30″W", with as much
+/// precision as the spacing needs. The equator, prime meridian and
+/// antimeridian get no hemisphere.
+pub fn format_degrees(value: f64, step: f64, positive: char, negative: char) -> String {
+    let seconds = (value.abs() * 3600.0).round() as i64;
+    let (degrees, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    let mut text = format!("{}°", degrees);
+    if step < 1.0 && (minutes > 0 || seconds > 0) {
+        text.push_str(&format!("{:02}′", minutes));
+    }
+    if step < 1.0 / 60.0 && seconds > 0 {
+        text.push_str(&format!("{:02}″", seconds));
+    }
+    if seconds > 0 || minutes > 0 || (degrees > 0 && degrees < 180) {
+        text.push(if value < 0.0 { negative } else { positive });
+    }
+    text
+}
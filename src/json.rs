@@ -0,0 +1,361 @@
+//! Minimal JSON reader for web service responses (GeoJSON feeds, geocoders,
+//! routers). Objects keep their key order; numbers are f64.
+
+use std::fmt;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum JsonValue {
+    #[default]
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+/// Where and why a document failed to parse
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonError {
+    /// Byte offset of the problem
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+impl JsonValue {
+    /// Parse a complete JSON document
+    pub fn parse(text: &str) -> Result<JsonValue, JsonError> {
+        let mut parser = Parser { bytes: text.as_bytes(), pos: 0 };
+        let value = parser.value(0)?;
+        parser.skip_whitespace();
+        if parser.pos < parser.bytes.len() {
+            return Err(parser.error("trailing characters"));
+        }
+        Ok(value)
+    }
+
+    /// Member of an object (None for other values or missing keys)
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonValue::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            JsonValue::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, JsonValue::Null)
+    }
+}
+
+/// Nesting limit, so hostile documents can't overflow the stack
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> JsonError {
+        JsonError { offset: self.pos, message: message.to_string() }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.bytes.get(self.pos), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, literal: &str, value: JsonValue) -> Result<JsonValue, JsonError> {
+        if self.bytes[self.pos..].starts_with(literal.as_bytes()) {
+            self.pos += literal.len();
+            Ok(value)
+        } else {
+            Err(self.error("invalid literal"))
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<JsonValue, JsonError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("nesting too deep"));
+        }
+        self.skip_whitespace();
+        match self.bytes.get(self.pos) {
+            Some(b'{') => self.object(depth),
+            Some(b'[') => self.array(depth),
+            Some(b'"') => self.string().map(JsonValue::String),
+            Some(b't') => self.expect("true", JsonValue::Bool(true)),
+            Some(b'f') => self.expect("false", JsonValue::Bool(false)),
+            Some(b'n') => self.expect("null", JsonValue::Null),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> Result<JsonValue, JsonError> {
+        self.pos += 1;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.bytes.get(self.pos) == Some(&b'}') {
+            self.pos += 1;
+            return Ok(JsonValue::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.bytes.get(self.pos) != Some(&b'"') {
+                return Err(self.error("expected key"));
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            if self.bytes.get(self.pos) != Some(&b':') {
+                return Err(self.error("expected ':'"));
+            }
+            self.pos += 1;
+            members.push((key, self.value(depth + 1)?));
+            self.skip_whitespace();
+            match self.bytes.get(self.pos) {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(JsonValue::Object(members));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<JsonValue, JsonError> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.bytes.get(self.pos) == Some(&b']') {
+            self.pos += 1;
+            return Ok(JsonValue::Array(items));
+        }
+        loop {
+            items.push(self.value(depth + 1)?);
+            self.skip_whitespace();
+            match self.bytes.get(self.pos) {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(JsonValue::Array(items));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<JsonValue, JsonError> {
+        let start = self.pos;
+        while matches!(self.bytes.get(self.pos), Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos])
+            .ok()
+            .and_then(|text| text.parse::<f64>().ok())
+            .map(JsonValue::Number)
+            .ok_or_else(|| JsonError { offset: start, message: "invalid number".to_string() })
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let digits = self.bytes.get(self.pos..self.pos + 4).ok_or_else(|| self.error("truncated escape"))?;
+        let code = std::str::from_utf8(digits)
+            .ok()
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| self.error("invalid escape"))?;
+        self.pos += 4;
+        Ok(code)
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            // Copy the run of plain characters up to the next quote or escape
+            let start = self.pos;
+            while matches!(self.bytes.get(self.pos), Some(b) if *b != b'"' && *b != b'\\') {
+                self.pos += 1;
+            }
+            out.push_str(std::str::from_utf8(&self.bytes[start..self.pos]).map_err(|_| self.error("invalid UTF-8"))?);
+            match self.bytes.get(self.pos) {
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(out);
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    let escape = *self.bytes.get(self.pos).ok_or_else(|| self.error("truncated escape"))?;
+                    self.pos += 1;
+                    match escape {
+                        b'"' => out.push('"'),
+                        b'\\' => out.push('\\'),
+                        b'/' => out.push('/'),
+                        b'b' => out.push('\u{8}'),
+                        b'f' => out.push('\u{c}'),
+                        b'n' => out.push('\n'),
+                        b'r' => out.push('\r'),
+                        b't' => out.push('\t'),
+                        b'u' => {
+                            let mut code = self.hex4()?;
+                            // Surrogate pair for characters outside the basic plane. A high
+                            // surrogate without a low one becomes U+FFFD and the following
+                            // escape is read on its own.
+                            if (0xd800..0xdc00).contains(&code) && self.bytes[self.pos..].starts_with(b"\\u") {
+                                let high_end = self.pos;
+                                self.pos += 2;
+                                match self.hex4() {
+                                    Ok(low) if (0xdc00..0xe000).contains(&low) => {
+                                        code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                                    }
+                                    _ => self.pos = high_end,
+                                }
+                            }
+                            out.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    }
+                }
+                _ => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_string(json: &str) -> String {
+        match JsonValue::parse(json) {
+            Ok(JsonValue::String(text)) => text,
+            other => panic!("{json} parsed to {other:?}"),
+        }
+    }
+
+    fn parse_error(json: &str) -> String {
+        JsonValue::parse(json).expect_err(json).message
+    }
+
+    fn nested_arrays(depth: usize) -> String {
+        "[".repeat(depth) + &"]".repeat(depth)
+    }
+
+    #[test]
+    fn parses_documents() {
+        let value = JsonValue::parse(r#" {"b": [1, true, null], "a": {"c": "d"}} "#).unwrap();
+        let JsonValue::Object(members) = &value else {
+            panic!("expected an object, got {value:?}");
+        };
+        let keys: Vec<&str> = members.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, ["b", "a"]);
+        assert_eq!(
+            value.get("b").and_then(JsonValue::as_array).unwrap(),
+            [JsonValue::Number(1.0), JsonValue::Bool(true), JsonValue::Null]
+        );
+        assert_eq!(value.get("a").and_then(|a| a.get("c")).and_then(JsonValue::as_str), Some("d"));
+    }
+
+    #[test]
+    fn simple_escapes() {
+        assert_eq!(parse_string(r#""a\"b\\c\/d\b\f\n\r\t""#), "a\"b\\c/d\u{8}\u{c}\n\r\t");
+        assert_eq!(parse_string(r#""caf\u00e9 \u00E9""#), "caf\u{e9} \u{e9}");
+        assert_eq!(parse_error(r#""\x""#), "invalid escape");
+        assert_eq!(parse_error(r#""\u12g4""#), "invalid escape");
+    }
+
+    #[test]
+    fn surrogate_pairs() {
+        assert_eq!(parse_string(r#""\ud83d\ude00""#), "\u{1f600}");
+        assert_eq!(parse_string(r#""\uD834\uDD1E clef""#), "\u{1d11e} clef");
+    }
+
+    #[test]
+    fn unpaired_surrogates_become_replacement_characters() {
+        // A high surrogate followed by an escape that isn't a low surrogate
+        // leaves that escape to be read on its own
+        assert_eq!(parse_string(r#""\ud83d\u0041""#), "\u{fffd}A");
+        assert_eq!(parse_string(r#""\ud83d\ud83d\ude00""#), "\u{fffd}\u{1f600}");
+        assert_eq!(parse_string(r#""\ud83d\n""#), "\u{fffd}\n");
+        assert_eq!(parse_string(r#""\ud83d""#), "\u{fffd}");
+        assert_eq!(parse_string(r#""\ude00x""#), "\u{fffd}x");
+        assert_eq!(parse_error(r#""\ud83d\u00zz""#), "invalid escape");
+    }
+
+    #[test]
+    fn numbers() {
+        for (json, expected) in [("0", 0.0), ("-12", -12.0), ("3.25", 3.25), ("1e3", 1000.0), ("-2.5E-2", -0.025)] {
+            assert_eq!(JsonValue::parse(json), Ok(JsonValue::Number(expected)), "{json}");
+        }
+        for json in ["-", "1.2.3", "1e", "--1"] {
+            assert_eq!(parse_error(json), "invalid number", "{json}");
+        }
+        assert_eq!(JsonValue::parse("-1").unwrap().as_f64(), Some(-1.0));
+    }
+
+    #[test]
+    fn depth_limit() {
+        assert!(JsonValue::parse(&nested_arrays(MAX_DEPTH + 1)).is_ok());
+        assert_eq!(parse_error(&nested_arrays(MAX_DEPTH + 2)), "nesting too deep");
+        let objects = r#"{"a":"#.repeat(MAX_DEPTH + 2) + "1" + &"}".repeat(MAX_DEPTH + 2);
+        assert_eq!(parse_error(&objects), "nesting too deep");
+    }
+
+    #[test]
+    fn trailing_characters() {
+        let error = JsonValue::parse("[1] 2").unwrap_err();
+        assert_eq!(error, JsonError { offset: 4, message: "trailing characters".to_string() });
+        assert_eq!(parse_error(r#"{"a": 1}}"#), "trailing characters");
+        assert_eq!(JsonValue::parse(" true \n"), Ok(JsonValue::Bool(true)));
+    }
+
+    #[test]
+    fn truncated_input() {
+        assert_eq!(parse_error(""), "unexpected end of input");
+        assert_eq!(parse_error("[1, 2"), "expected ',' or ']'");
+        assert_eq!(parse_error("[1,"), "unexpected end of input");
+        assert_eq!(parse_error(r#"{"a""#), "expected ':'");
+        assert_eq!(parse_error(r#"{"a": 1"#), "expected ',' or '}'");
+        assert_eq!(parse_error(r#""abc"#), "unterminated string");
+        assert_eq!(parse_error(r#""abc\"#), "truncated escape");
+        assert_eq!(parse_error(r#""\u12"#), "truncated escape");
+        assert_eq!(parse_error("tru"), "invalid literal");
+    }
+}
@@ -0,0 +1,440 @@
+use makepad_widgets::*;
+use std::any::Any;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::geometry::{point_in_polygon, segment_distance, triangulate, GeometryWarning};
+use crate::camera_math::{camera_distance, rotate_vec, tilt_vec, untilt_vec};
+use crate::geojson::GeoJsonError;
+use crate::projection::{world_size, Projection};
+use crate::quality::QualityLevel;
+
+live_design! {
+    link widgets;
+    use link::theme::*;
+    use link::shaders::*;
+    use link::widgets::*;
+
+    // Shader for a filled circle with a border (location dots, accuracy rings)
+    pub DrawMapCircle = {{DrawMapCircle}} {
+        color: #2196f3
+        border_color: #ffffff
+        border_width: 2.0
+
+        fn pixel(self) -> vec4 {
+            let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+            let r = min(self.rect_size.x, self.rect_size.y) * 0.5;
+            sdf.circle(r, r, max(r - self.border_width, 0.0));
+            sdf.fill_keep(self.color);
+            sdf.stroke(self.border_color, self.border_width);
+            return sdf.result;
+        }
+    }
+
+    // Device position as a dot with an accuracy circle
+    pub UserLocationLayer = {{UserLocationLayer}} {
+        draw_accuracy: {
+            color: #2196f322
+            border_color: #2196f366
+            border_width: 1.0
+        }
+        draw_dot: {
+            color: #2196f3
+            border_color: #ffffff
+            border_width: 3.0
+        }
+    }
+}
+
+#[derive(Live, LiveRegister, LiveHook)]
+#[repr(C)]
+pub struct DrawMapCircle {
+    #[deref] pub draw_super: DrawQuad,
+    #[live] pub color: Vec4,
+    #[live] pub border_color: Vec4,
+    #[live(1.0)] pub border_width: f32,
+}
+
+impl DrawMapCircle {
+    /// Draw a circle around an absolute position
+    pub fn draw_circle(&mut self, cx: &mut Cx2d, center: DVec2, radius: f64) {
+        self.draw_abs(cx, Rect {
+            pos: center - dvec2(radius, radius),
+            size: dvec2(radius * 2.0, radius * 2.0),
+        });
+    }
+}
+
+/// The map's camera for one frame, handed to layers to position their content
+pub struct MapViewport {
+    /// Absolute rect of the map on screen
+    pub rect: Rect,
+    pub center_lng: f64,
+    pub center_lat: f64,
+    pub zoom: f64,
+    /// Degrees clockwise from north
+    pub bearing: f64,
+    /// Degrees the map plane is tilted back
+    pub pitch: f64,
+    /// Whether copies of the world are shown left and right of it
+    pub world_repeat: bool,
+    /// Offset of the center's screen position from the middle of `rect`, from the map's padding
+    pub center_offset: DVec2,
+    pub projection: Rc<dyn Projection>,
+}
+
+impl MapViewport {
+    /// Absolute screen position of a geographic point, using the world copy nearest the center
+    pub fn geo_to_screen(&self, lng: f64, lat: f64) -> DVec2 {
+        let lng = if self.projection.wraps_longitude() {
+            self.center_lng + (lng - self.center_lng + 180.0).rem_euclid(360.0) - 180.0
+        } else {
+            lng
+        };
+        self.geo_to_screen_unwrapped(lng, lat)
+    }
+
+    /// Absolute screen position of a geographic point, with the longitude taken as is
+    pub fn geo_to_screen_unwrapped(&self, lng: f64, lat: f64) -> DVec2 {
+        let center = self.projection.geo_to_world(self.center_lng, self.center_lat, self.zoom);
+        self.world_offset_to_screen(self.projection.geo_to_world(lng, lat, self.zoom) - center)
+    }
+
+    /// Absolute screen position of a unit square position (see `Projection::project`),
+    /// using the world copy nearest the center
+    pub fn unit_to_screen(&self, unit: DVec2) -> DVec2 {
+        self.unit_to_screen_near(unit, unit)
+    }
+
+    /// Absolute screen position of a unit square position, on the same world
+    /// copy as `near` (the copy of `near` nearest the center), e.g. to place a
+    /// shape's vertices relative to its center so it stays in one piece
+    pub fn unit_to_screen_near(&self, unit: DVec2, near: DVec2) -> DVec2 {
+        let size = world_size(self.zoom);
+        let center = self.projection.geo_to_world(self.center_lng, self.center_lat, self.zoom);
+        let mut offset = unit * size - center;
+        if self.projection.wraps_longitude() {
+            let near_x = near.x * size - center.x;
+            offset.x += (near_x + size / 2.0).rem_euclid(size) - size / 2.0 - near_x;
+        }
+        self.world_offset_to_screen(offset)
+    }
+
+    /// Absolute screen position of a north-up offset from the center in world pixels
+    pub fn world_offset_to_screen(&self, offset: DVec2) -> DVec2 {
+        let rotated = rotate_vec(offset, -self.bearing.to_radians());
+        self.rect.pos + self.rect.size / 2.0 + self.center_offset + tilt_vec(rotated, self.pitch.to_radians(), camera_distance(self.rect.size.y))
+    }
+
+    /// Geographic position of an absolute screen position
+    pub fn screen_to_geo(&self, pos: DVec2) -> (f64, f64) {
+        let center = self.projection.geo_to_world(self.center_lng, self.center_lat, self.zoom);
+        let ground = untilt_vec(pos - self.rect.pos - self.rect.size / 2.0 - self.center_offset, self.pitch.to_radians(), camera_distance(self.rect.size.y));
+        self.projection.world_to_geo(center + rotate_vec(ground, self.bearing.to_radians()), self.zoom)
+    }
+
+    /// Bounding box in unit square positions (see `Projection::project`) of
+    /// the ground under an absolute screen rect. x runs past 0..1 where the
+    /// rect crosses the antimeridian.
+    pub fn unit_bounds(&self, rect: Rect) -> (DVec2, DVec2) {
+        let size = world_size(self.zoom);
+        let center = self.projection.geo_to_world(self.center_lng, self.center_lat, self.zoom) / size;
+        let screen_center = self.rect.pos + self.rect.size / 2.0 + self.center_offset;
+        let (pitch, distance) = (self.pitch.to_radians(), camera_distance(self.rect.size.y));
+        let (lo, hi) = (rect.pos, rect.pos + rect.size);
+        let (mut min, mut max) = (dvec2(f64::INFINITY, f64::INFINITY), dvec2(f64::NEG_INFINITY, f64::NEG_INFINITY));
+        // The ground under a rect is a quadrilateral through its corners, even when tilted
+        for corner in [lo, dvec2(hi.x, lo.y), dvec2(lo.x, hi.y), hi] {
+            let ground = rotate_vec(untilt_vec(corner - screen_center, pitch, distance), self.bearing.to_radians());
+            let unit = center + ground / size;
+            min = dvec2(min.x.min(unit.x), min.y.min(unit.y));
+            max = dvec2(max.x.max(unit.x), max.y.max(unit.y));
+        }
+        (min, max)
+    }
+
+    /// Ground meters per pixel at the map center
+    pub fn meters_per_pixel(&self) -> f64 {
+        self.projection.meters_per_unit(self.center_lng, self.center_lat) / world_size(self.zoom)
+    }
+
+    /// Check if an absolute position is on screen, within `margin` pixels
+    pub fn is_visible(&self, pos: DVec2, margin: f64) -> bool {
+        pos.x >= self.rect.pos.x - margin
+            && pos.y >= self.rect.pos.y - margin
+            && pos.x <= self.rect.pos.x + self.rect.size.x + margin
+            && pos.y <= self.rect.pos.y + self.rect.size.y + margin
+    }
+}
+
+/// A polygon or path projected into the unit square once, then placed on
+/// screen relative to its center so it stays on one world copy and panning
+/// never re-projects
+pub(crate) struct ProjectedShape {
+    /// Fill triangles (polygons only)
+    pub triangles: Vec<[DVec2; 3]>,
+    /// Rings of a polygon, or the points of a path
+    pub outlines: Vec<Vec<DVec2>>,
+    /// Whether the outlines are closed rings
+    pub closed: bool,
+    /// Bounding box center and half diagonal length, for culling
+    center: DVec2,
+    radius: f64,
+}
+
+impl ProjectedShape {
+    /// A polygon (first ring exterior, rest holes, closing points optional), tessellated for filling
+    pub fn polygon(projection: &dyn Projection, rings: &[Vec<(f64, f64)>]) -> Self {
+        let rings = Self::project(projection, rings);
+        let triangles = triangulate(&rings).into_iter().map(|t| t.map(|(x, y)| dvec2(x, y))).collect();
+        Self::new(rings, triangles, true)
+    }
+
+    /// An open path, e.g. a GeoJSON line string
+    pub fn path(projection: &dyn Projection, points: &[(f64, f64)]) -> Self {
+        let rings = Self::project(projection, std::slice::from_ref(&points.to_vec()));
+        Self::new(rings, Vec::new(), false)
+    }
+
+    /// Unit positions with continuous longitudes, so shapes crossing the antimeridian stay in one piece
+    fn project(projection: &dyn Projection, rings: &[Vec<(f64, f64)>]) -> Vec<Vec<(f64, f64)>> {
+        let first_lng = rings.first().and_then(|ring| ring.first()).map_or(0.0, |p| p.0);
+        rings.iter().map(|ring| {
+            ring.iter().map(|&(lng, lat)| {
+                let lng = if projection.wraps_longitude() {
+                    first_lng + (lng - first_lng + 180.0).rem_euclid(360.0) - 180.0
+                } else {
+                    lng
+                };
+                let unit = projection.project(lng, lat);
+                (unit.x, unit.y)
+            }).collect()
+        }).collect()
+    }
+
+    fn new(rings: Vec<Vec<(f64, f64)>>, triangles: Vec<[DVec2; 3]>, closed: bool) -> Self {
+        let (mut min, mut max) = (dvec2(f64::INFINITY, f64::INFINITY), dvec2(f64::NEG_INFINITY, f64::NEG_INFINITY));
+        for &(x, y) in rings.iter().flatten() {
+            min = dvec2(min.x.min(x), min.y.min(y));
+            max = dvec2(max.x.max(x), max.y.max(y));
+        }
+        Self {
+            triangles,
+            outlines: rings.iter().map(|ring| ring.iter().map(|&(x, y)| dvec2(x, y)).collect()).collect(),
+            closed,
+            center: (min + max) / 2.0,
+            radius: (max - min).length() / 2.0,
+        }
+    }
+
+    /// Box around the shape in unit positions, for a `SpatialIndex`
+    pub fn bounds(&self) -> (DVec2, DVec2) {
+        let radius = dvec2(self.radius, self.radius);
+        (self.center - radius, self.center + radius)
+    }
+
+    /// Whether any of the shape may be on screen
+    pub fn is_on_screen(&self, viewport: &MapViewport) -> bool {
+        if self.outlines.iter().all(|ring| ring.is_empty()) {
+            return false;
+        }
+        // Tilted shapes grow towards the bottom of the view, so cull loosely
+        let margin = if viewport.pitch > 0.0 { 2.0 } else { 1.0 };
+        viewport.is_visible(viewport.unit_to_screen(self.center), self.radius * world_size(viewport.zoom) * margin)
+    }
+
+    /// Screen position of one of the shape's unit positions, on the world copy of its center
+    pub fn to_screen(&self, viewport: &MapViewport, unit: DVec2) -> DVec2 {
+        viewport.unit_to_screen_near(unit, self.center)
+    }
+
+    /// Outline segments in unit positions, including the closing segment of rings
+    pub fn segments(&self) -> impl Iterator<Item = (DVec2, DVec2)> + '_ {
+        self.outlines.iter().flat_map(move |ring| {
+            let count = if self.closed { ring.len() } else { ring.len().saturating_sub(1) };
+            (0..count).map(move |i| (ring[i], ring[(i + 1) % ring.len()]))
+        })
+    }
+
+    /// Whether an absolute screen position is inside the polygon or within
+    /// `tolerance` pixels of its outline. Tested on screen, so it holds for
+    /// rotated and tilted maps.
+    pub fn hit_test(&self, viewport: &MapViewport, pos: DVec2, tolerance: f64) -> bool {
+        let to_screen = |unit: DVec2| {
+            let p = self.to_screen(viewport, unit);
+            (p.x, p.y)
+        };
+        let p = (pos.x, pos.y);
+        if self.segments().any(|(from, to)| segment_distance(p, to_screen(from), to_screen(to)) <= tolerance) {
+            return true;
+        }
+        if !self.closed {
+            return false;
+        }
+        let rings: Vec<Vec<(f64, f64)>> = self.outlines.iter()
+            .map(|ring| ring.iter().map(|&unit| to_screen(unit)).collect())
+            .collect();
+        point_in_polygon(p, &rings)
+    }
+}
+
+/// A line or shape under the pointer, reported with `GeoMapViewAction::OverlayHovered`
+#[derive(Clone, Debug, PartialEq)]
+pub struct OverlayHit {
+    pub id: LiveId,
+    /// Text shown next to the pointer, empty for none
+    pub tooltip: String,
+}
+
+/// Something a layer reports to the app, emitted by the map as a `GeoMapViewAction`
+#[derive(Clone, Debug, PartialEq)]
+pub enum MapLayerEvent {
+    /// Loaded data had polygons repaired, as (feature index, warning)
+    GeometryRepaired {
+        warnings: Vec<(usize, GeometryWarning)>,
+    },
+    /// Loading or refreshing a GeoJSON document failed; the previous data stays shown
+    GeoJsonFailed {
+        url: String,
+        error: GeoJsonError,
+    },
+}
+
+/// An overlay drawn by `GeoMapView` above the tiles and below the markers.
+///
+/// Layers are live components: once registered with [`register_map_layer`]
+/// they can be declared as children of a `GeoMapView` in live_design,
+/// e.g. `me = <UserLocationLayer> {}`, and are drawn in declaration order.
+pub trait MapLayer: LiveApply + Any {
+    /// Draw the layer
+    fn draw_layer(&mut self, cx: &mut Cx2d, viewport: &MapViewport);
+
+    /// Handle an event (e.g. network responses for remote data).
+    /// Return true if the map needs to redraw.
+    fn handle_layer_event(&mut self, _cx: &mut Cx, _event: &Event, _viewport: &MapViewport) -> bool {
+        false
+    }
+
+    /// Whether the layer is currently drawn
+    fn is_visible(&self) -> bool {
+        true
+    }
+
+    /// Show or hide the layer (e.g. from the built-in layer control)
+    fn set_visible(&mut self, _visible: bool) {}
+
+    /// Fade the layer (0..1), set by `GeoMapView::set_layer_opacity`.
+    /// Layers that don't implement it ignore the opacity.
+    fn set_opacity(&mut self, _opacity: f64) {}
+
+    /// Position the map keeps centered while the layer is visible (e.g. the
+    /// newest point of a followed track), None to leave the camera alone
+    fn follow_position(&self) -> Option<(f64, f64)> {
+        None
+    }
+
+    /// The user panned the map away, stop following (see `follow_position`)
+    fn stop_following(&mut self) {}
+
+    /// Whether the layer is listed in the built-in layer control
+    fn in_layer_control(&self) -> bool {
+        true
+    }
+
+    /// Rendering quality chosen by the adaptive quality governor, set before each draw.
+    /// Expensive layers should get cheaper at `QualityLevel::CoarseOverlays` and below.
+    fn set_quality(&mut self, _level: QualityLevel) {}
+
+    /// Show the layer's state at a timeline time in seconds (track playback etc.).
+    /// Called by `GeoMapView::seek`; must only depend on `time`, not on earlier calls.
+    fn seek(&mut self, _time: f64) {}
+
+    /// The topmost shape at an absolute screen position, for hover tooltips
+    /// and `OverlayHovered`. Layers without hoverable shapes return None.
+    fn overlay_at(&self, _viewport: &MapViewport, _pos: DVec2) -> Option<OverlayHit> {
+        None
+    }
+
+    /// Reports queued since the last call (e.g. a finished load), taken by the
+    /// map after each event and emitted as actions
+    fn take_layer_events(&mut self) -> Vec<MapLayerEvent> {
+        Vec::new()
+    }
+}
+
+type MapLayerConstructor = fn(&mut Cx) -> Box<dyn MapLayer>;
+
+/// Factory for map layers declared in live_design, keyed by their live type
+#[derive(Default)]
+pub struct MapLayerRegistry {
+    constructors: HashMap<LiveType, MapLayerConstructor>,
+}
+
+/// Create a layer of a registered live type
+pub fn new_map_layer(cx: &mut Cx, live_type: LiveType) -> Option<Box<dyn MapLayer>> {
+    let constructor = *cx.global::<MapLayerRegistry>().constructors.get(&live_type)?;
+    Some(constructor(cx))
+}
+
+/// Register a layer type so it can be declared inside `GeoMapView` in live_design.
+/// Call it from `live_register` after the type's own `live_design`.
+pub fn register_map_layer<T: MapLayer + LiveNew>(cx: &mut Cx) {
+    fn construct<T: MapLayer + LiveNew>(cx: &mut Cx) -> Box<dyn MapLayer> {
+        Box::new(T::new(cx))
+    }
+    cx.global::<MapLayerRegistry>()
+        .constructors
+        .insert(LiveType::of::<T>(), construct::<T>);
+}
+
+/// Shows the device position as a dot with a circle for the accuracy radius
+#[derive(Live, LiveHook, LiveRegister)]
+pub struct UserLocationLayer {
+    #[live] draw_accuracy: DrawMapCircle,
+    #[live] draw_dot: DrawMapCircle,
+    #[live(16.0)] pub dot_size: f64,
+    #[live(true)] pub visible: bool,
+    #[rust] location: Option<(f64, f64, f64)>,
+}
+
+impl UserLocationLayer {
+    /// Set the device position and its accuracy radius in meters
+    pub fn set_location(&mut self, lng: f64, lat: f64, accuracy: f64) {
+        self.location = Some((lng, lat, accuracy));
+    }
+
+    pub fn clear_location(&mut self) {
+        self.location = None;
+    }
+
+    /// Current position as (lng, lat, accuracy in meters)
+    pub fn location(&self) -> Option<(f64, f64, f64)> {
+        self.location
+    }
+}
+
+impl MapLayer for UserLocationLayer {
+    fn draw_layer(&mut self, cx: &mut Cx2d, viewport: &MapViewport) {
+        let Some((lng, lat, accuracy)) = self.location else {
+            return;
+        };
+        let pos = viewport.geo_to_screen(lng, lat);
+        let accuracy_radius = accuracy / viewport.meters_per_pixel();
+        if !viewport.is_visible(pos, accuracy_radius.max(self.dot_size)) {
+            return;
+        }
+        // Only show the accuracy circle when it's larger than the dot
+        if accuracy_radius > self.dot_size / 2.0 {
+            self.draw_accuracy.draw_circle(cx, pos, accuracy_radius);
+        }
+        self.draw_dot.draw_circle(cx, pos, self.dot_size / 2.0);
+    }
+
+    fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+}
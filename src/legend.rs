@@ -0,0 +1,264 @@
+use makepad_widgets::*;
+
+use crate::choropleth::ColorRamp;
+use crate::layers::{MapLayer, MapViewport};
+
+live_design! {
+    link widgets;
+    use link::theme::*;
+    use link::shaders::*;
+    use link::widgets::*;
+
+    // Horizontal blend between two colors (one stop of a gradient bar)
+    pub DrawMapGradient = {{DrawMapGradient}} {
+        color_start: #2196f300
+        color_end: #ff3d00ff
+
+        fn pixel(self) -> vec4 {
+            let color = mix(self.color_start, self.color_end, self.pos.x);
+            return vec4(color.rgb * color.a, color.a);
+        }
+    }
+
+    // Legend box with swatches or a gradient bar, pinned to a corner of the map
+    pub MapLegend = {{MapLegend}} {
+        draw_bg: {
+            color: #ffffffe6
+        }
+        draw_text: {
+            color: #333333
+            text_style: {
+                font_size: 9.0
+            }
+        }
+    }
+}
+
+#[derive(Live, LiveRegister, LiveHook)]
+#[repr(C)]
+pub struct DrawMapGradient {
+    #[deref] pub draw_super: DrawQuad,
+    #[live] pub color_start: Vec4,
+    #[live] pub color_end: Vec4,
+}
+
+/// Corner of the map an overlay control is pinned to
+#[derive(Copy, Clone, Debug, Live, LiveHook, PartialEq)]
+#[live_ignore]
+pub enum MapCorner {
+    TopLeft,
+    #[pick] TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl MapCorner {
+    /// Position of a box of `size` in this corner of `rect`, `margin` pixels from the edges
+    pub fn place(self, rect: Rect, size: DVec2, margin: f64) -> DVec2 {
+        let left = rect.pos.x + margin;
+        let right = rect.pos.x + rect.size.x - margin - size.x;
+        let top = rect.pos.y + margin;
+        let bottom = rect.pos.y + rect.size.y - margin - size.y;
+        match self {
+            MapCorner::TopLeft => dvec2(left, top),
+            MapCorner::TopRight => dvec2(right, top),
+            MapCorner::BottomLeft => dvec2(left, bottom),
+            MapCorner::BottomRight => dvec2(right, bottom),
+        }
+    }
+}
+
+/// What a legend shows
+#[derive(Clone, Debug, PartialEq)]
+pub enum LegendContent {
+    /// Discrete classes: a color swatch and a label per row
+    Swatches(Vec<(Vec4, String)>),
+    /// A continuous bar through evenly spaced colors, with labels spread
+    /// evenly underneath (e.g. min, mid and max values)
+    Gradient { colors: Vec<Vec4>, labels: Vec<String> },
+}
+
+/// Legend box shown in a corner of the map, with discrete swatches (e.g. the
+/// classes of a choropleth) or a gradient bar (e.g. heatmap intensity).
+///
+/// Declared as a map layer it can be configured from live_design alone: with
+/// `min_label`/`max_label` set it shows a gradient between the colors of
+/// `draw_gradient`. Content set from Rust replaces that.
+#[derive(Live, LiveHook, LiveRegister)]
+pub struct MapLegend {
+    #[live] draw_bg: DrawColor,
+    #[live] draw_text: DrawText,
+    #[live] draw_swatch: DrawColor,
+    #[live] draw_gradient: DrawMapGradient,
+    #[live] pub title: String,
+    #[live] pub corner: MapCorner,
+    /// Labels of the live_design gradient's ends
+    #[live] pub min_label: String,
+    #[live] pub max_label: String,
+    /// Distance from the map edges
+    #[live(10.0)] pub margin: f64,
+    #[live(120.0)] pub gradient_width: f64,
+    #[live(true)] pub visible: bool,
+    #[rust] content: Option<LegendContent>,
+}
+
+/// Positions of a legend's parts for one draw
+struct LegendLayout {
+    rect: Rect,
+    title: Option<DVec2>,
+    /// Colored rects (swatches or gradient stops, as start and end colors)
+    fills: Vec<(Rect, Vec4, Vec4)>,
+    labels: Vec<(DVec2, String)>,
+}
+
+impl MapLegend {
+    /// Show discrete classes
+    pub fn set_swatches(&mut self, entries: Vec<(Vec4, String)>) {
+        self.content = Some(LegendContent::Swatches(entries));
+    }
+
+    /// Show a gradient bar through `colors`, with `labels` spread evenly underneath
+    pub fn set_gradient(&mut self, colors: Vec<Vec4>, labels: Vec<String>) {
+        self.content = Some(LegendContent::Gradient { colors, labels });
+    }
+
+    /// Show the classes of a color ramp
+    pub fn set_ramp(&mut self, ramp: &ColorRamp) {
+        self.set_swatches(ramp.legend_entries());
+    }
+
+    /// Go back to the content configured in live_design
+    pub fn clear_content(&mut self) {
+        self.content = None;
+    }
+
+    /// Whether there is nothing to show, neither from Rust nor from live_design
+    pub fn is_empty(&self) -> bool {
+        self.content.is_none() && self.min_label.is_empty() && self.max_label.is_empty()
+    }
+
+    /// What the legend currently shows (None if nothing is configured)
+    pub fn content(&self) -> Option<LegendContent> {
+        if let Some(content) = &self.content {
+            return Some(content.clone());
+        }
+        if self.min_label.is_empty() && self.max_label.is_empty() {
+            return None;
+        }
+        Some(LegendContent::Gradient {
+            colors: vec![self.draw_gradient.color_start, self.draw_gradient.color_end],
+            labels: vec![self.min_label.clone(), self.max_label.clone()],
+        })
+    }
+
+    fn layout(&self, viewport: &MapViewport) -> Option<LegendLayout> {
+        let content = self.content()?;
+        // Text is measured by character count, close enough for short labels
+        let font_size = self.draw_text.text_style.font_size as f64;
+        let char_width = font_size * 0.55;
+        let line_height = font_size * 1.6;
+        let padding = 6.0;
+        let text_width = |text: &str| text.chars().count() as f64 * char_width;
+
+        let mut size = dvec2(text_width(&self.title), 0.0);
+        let title_height = if self.title.is_empty() { 0.0 } else { line_height };
+        match &content {
+            LegendContent::Swatches(entries) => {
+                let swatch = font_size * 1.2;
+                for (_, label) in entries {
+                    size.x = size.x.max(swatch + padding + text_width(label));
+                }
+                size.y = entries.len() as f64 * line_height;
+            }
+            LegendContent::Gradient { labels, .. } => {
+                let labels_width: f64 = labels.iter().map(|label| text_width(label) + padding).sum();
+                size.x = size.x.max(self.gradient_width).max(labels_width);
+                size.y = font_size + line_height;
+            }
+        }
+        let size = size + dvec2(padding * 2.0, padding * 2.0 + title_height);
+
+        let pos = self.corner.place(viewport.rect, size, self.margin);
+        let inner = pos + dvec2(padding, padding + title_height);
+        let inner_width = size.x - padding * 2.0;
+        let mut fills = Vec::new();
+        let mut labels = Vec::new();
+        match content {
+            LegendContent::Swatches(entries) => {
+                let swatch = font_size * 1.2;
+                for (i, (color, label)) in entries.into_iter().enumerate() {
+                    let y = inner.y + i as f64 * line_height;
+                    fills.push((Rect {
+                        pos: dvec2(inner.x, y + (line_height - swatch) / 2.0 - 1.0),
+                        size: dvec2(swatch, swatch),
+                    }, color, color));
+                    labels.push((dvec2(inner.x + swatch + padding, y), label));
+                }
+            }
+            LegendContent::Gradient { colors, labels: texts } => {
+                let stops = colors.len().saturating_sub(1).max(1);
+                let stop_width = inner_width / stops as f64;
+                for i in 0..stops {
+                    let start = colors.get(i).copied().unwrap_or_default();
+                    let end = colors.get(i + 1).copied().unwrap_or(start);
+                    fills.push((Rect {
+                        pos: dvec2(inner.x + i as f64 * stop_width, inner.y),
+                        size: dvec2(stop_width, font_size),
+                    }, start, end));
+                }
+                // Labels centered on evenly spaced ticks, the outer ones aligned to the bar's ends
+                let count = texts.len();
+                for (i, text) in texts.into_iter().enumerate() {
+                    let t = if count > 1 { i as f64 / (count - 1) as f64 } else { 0.5 };
+                    let x = inner.x + inner_width * t - text_width(&text) * t;
+                    labels.push((dvec2(x, inner.y + font_size + padding / 2.0), text));
+                }
+            }
+        }
+        Some(LegendLayout {
+            rect: Rect { pos, size },
+            title: (!self.title.is_empty()).then(|| pos + dvec2(padding, padding)),
+            fills,
+            labels,
+        })
+    }
+}
+
+impl MapLayer for MapLegend {
+    fn draw_layer(&mut self, cx: &mut Cx2d, viewport: &MapViewport) {
+        let Some(layout) = self.layout(viewport) else {
+            return;
+        };
+        self.draw_bg.draw_abs(cx, layout.rect);
+        if let Some(pos) = layout.title {
+            let title = self.title.clone();
+            self.draw_text.draw_abs(cx, pos, &title);
+        }
+        for (rect, start, end) in layout.fills {
+            if start == end {
+                self.draw_swatch.color = start;
+                self.draw_swatch.draw_abs(cx, rect);
+            } else {
+                self.draw_gradient.color_start = start;
+                self.draw_gradient.color_end = end;
+                self.draw_gradient.draw_abs(cx, rect);
+            }
+        }
+        for (pos, label) in layout.labels {
+            self.draw_text.draw_abs(cx, pos, &label);
+        }
+    }
+
+    fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    // A legend follows the layer it explains rather than being toggled itself
+    fn in_layer_control(&self) -> bool {
+        false
+    }
+}
@@ -1,13 +1,99 @@
 pub use makepad_widgets;
 pub use makepad_widgets::*;
 
+pub mod aggregation;
+pub mod animated_tiles;
+pub mod camera;
+pub mod camera_math;
+pub(crate) mod animation;
+pub mod camera_sync;
+pub mod choropleth;
+pub mod coordinates;
 pub mod disk_cache;
+pub mod elevation_profile;
+pub mod geo;
+pub mod geocoding;
+pub mod geojson;
+pub mod geometry;
+pub mod graticule;
+pub mod json;
+pub mod layers;
+pub mod legend;
+pub mod locale;
+pub mod list_sync;
 pub mod map_view;
+pub mod navigation;
+pub mod offline;
+pub mod permalink;
+pub mod projection;
+pub mod quality;
+pub mod routing;
+pub mod search_box;
+#[cfg(feature = "serde")]
+mod serde_impls;
+pub mod spatial_index;
+pub mod terrain;
 pub mod tiles;
+pub mod timeline;
+pub mod tour;
+pub mod track;
+pub mod utm;
 
+pub use aggregation::*;
+pub use animated_tiles::*;
+pub use camera::*;
+pub use camera_math::camera_for_bounds;
+pub use camera_sync::*;
+pub use choropleth::*;
+pub use coordinates::*;
+pub use disk_cache::DiskCacheConfig;
+pub use elevation_profile::*;
+pub use geo::{great_circle_path, great_circle_point, haversine_distance, GeoBounds};
+pub use geocoding::*;
+pub use geojson::*;
+pub use graticule::*;
+pub use json::{JsonError, JsonValue};
+pub use layers::*;
+pub use legend::*;
+pub use locale::*;
+pub use list_sync::*;
 pub use map_view::*;
+pub use navigation::{NavigationFix, NavigationOptions};
+pub use offline::*;
+pub use permalink::*;
+pub use projection::*;
+pub use quality::*;
+pub use routing::*;
+pub use search_box::*;
+pub use spatial_index::SpatialIndex;
+pub use terrain::*;
 pub use tiles::*;
+pub use timeline::*;
+pub use tour::*;
+pub use track::*;
 
 pub fn live_design(cx: &mut Cx) {
+    crate::layers::live_design(cx);
+    crate::aggregation::live_design(cx);
     crate::map_view::live_design(cx);
+    crate::legend::live_design(cx);
+    crate::choropleth::live_design(cx);
+    crate::geojson::live_design(cx);
+    crate::graticule::live_design(cx);
+    crate::track::live_design(cx);
+    crate::timeline::live_design(cx);
+    crate::terrain::live_design(cx);
+    crate::animated_tiles::live_design(cx);
+    crate::elevation_profile::live_design(cx);
+    crate::search_box::live_design(cx);
+    layers::register_map_layer::<layers::UserLocationLayer>(cx);
+    layers::register_map_layer::<aggregation::DensityLayer>(cx);
+    layers::register_map_layer::<choropleth::ChoroplethLayer>(cx);
+    layers::register_map_layer::<legend::MapLegend>(cx);
+    layers::register_map_layer::<geojson::GeoJsonLayer>(cx);
+    layers::register_map_layer::<graticule::GraticuleLayer>(cx);
+    layers::register_map_layer::<track::TrackLayer>(cx);
+    layers::register_map_layer::<timeline::TimelineLayer>(cx);
+    layers::register_map_layer::<terrain::HillshadeLayer>(cx);
+    layers::register_map_layer::<animated_tiles::AnimatedTileLayer>(cx);
 }
@@ -0,0 +1,108 @@
+use makepad_widgets::*;
+
+use crate::map_view::{FocusOptions, GeoMapViewAction, GeoMapViewRef};
+
+/// Keeps an external list widget (typically a `PortalList` of places) and the
+/// map in sync.
+///
+/// The controller knows which marker belongs to which list row. Selecting a row
+/// flies to its marker, opens the popup and selects the marker on the map;
+/// tapping a marker on the map returns the row index so the app can scroll its
+/// list to it.
+#[derive(Clone, Debug, Default)]
+pub struct MapListSync {
+    ids: Vec<LiveId>,
+    selected: Option<usize>,
+    /// How the map moves to a selected row's marker
+    pub focus: FocusOptions,
+}
+
+impl MapListSync {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the marker IDs in list row order. The selection is kept if its marker is still listed.
+    pub fn set_items(&mut self, ids: impl IntoIterator<Item = LiveId>) {
+        let selected_id = self.selected_id();
+        self.ids = ids.into_iter().collect();
+        self.selected = selected_id.and_then(|id| self.index_of(id));
+    }
+
+    /// Number of list rows
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Marker ID shown in a list row
+    pub fn id_at(&self, index: usize) -> Option<LiveId> {
+        self.ids.get(index).copied()
+    }
+
+    /// List row of a marker
+    pub fn index_of(&self, id: LiveId) -> Option<usize> {
+        self.ids.iter().position(|&item| item == id)
+    }
+
+    /// Currently selected list row
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// Marker ID of the currently selected row
+    pub fn selected_id(&self) -> Option<LiveId> {
+        self.id_at(self.selected?)
+    }
+
+    /// Select a list row: fly to its marker, open the popup and select the
+    /// marker. Returns false if the row or its marker doesn't exist.
+    pub fn select_row(&mut self, cx: &mut Cx, map: &GeoMapViewRef, index: usize) -> bool {
+        let Some(id) = self.id_at(index) else {
+            return false;
+        };
+        if !map.focus_marker(cx, id, self.focus) {
+            return false;
+        }
+        map.select_marker(cx, id);
+        self.selected = Some(index);
+        true
+    }
+
+    /// Clear the selection, deselect the marker and close its popup
+    pub fn clear_selection(&mut self, cx: &mut Cx, map: &GeoMapViewRef) {
+        if self.selected.take().is_some() {
+            map.close_popup(cx);
+            map.deselect_marker(cx);
+        }
+    }
+
+    /// Process the map's actions. A tapped marker that belongs to the list is
+    /// selected and its popup opened; returns its list row so the list can
+    /// scroll to it. Tapping the empty map clears the selection.
+    pub fn handle_actions(&mut self, cx: &mut Cx, map: &GeoMapViewRef, actions: &Actions) -> Option<usize> {
+        let mut scroll_to = None;
+        for action in map.map_actions(actions) {
+            match action {
+                GeoMapViewAction::MarkerTapped { id } => {
+                    if let Some(index) = self.index_of(id) {
+                        map.open_popup(cx, id);
+                        map.select_marker(cx, id);
+                        self.selected = Some(index);
+                        scroll_to = Some(index);
+                    }
+                }
+                GeoMapViewAction::Tapped { .. } => {
+                    if self.selected.take().is_some() {
+                        map.deselect_marker(cx);
+                    }
+                }
+                _ => {}
+            }
+        }
+        scroll_to
+    }
+}
@@ -0,0 +1,176 @@
+/// Units distances and areas are shown in
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum UnitSystem {
+    /// Meters and kilometers, hectares
+    #[default]
+    Metric,
+    /// Feet and miles, acres
+    Imperial,
+}
+
+/// Number formats and unit symbols of the text the map draws itself (scale
+/// bar, measurement labels, elevation profile). Set with
+/// `GeoMapView::set_locale`; other built-in text (gesture hints, the
+/// attribution) is set in live_design.
+///
+/// The default is English with metric units. For another language set the
+/// separators and the symbols that differ:
+///
+/// ```ignore
+/// let german = MapLocale { decimal_separator: ',', group_separator: Some('.'), ..MapLocale::default() };
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapLocale {
+    pub decimal_separator: char,
+    /// Separator between groups of three digits (None for no grouping)
+    pub group_separator: Option<char>,
+    pub unit_system: UnitSystem,
+    pub meters: String,
+    pub kilometers: String,
+    pub feet: String,
+    pub miles: String,
+    pub square_meters: String,
+    pub hectares: String,
+    pub square_kilometers: String,
+    pub acres: String,
+    pub square_miles: String,
+}
+
+impl Default for MapLocale {
+    fn default() -> Self {
+        Self {
+            decimal_separator: '.',
+            group_separator: Some(','),
+            unit_system: UnitSystem::Metric,
+            meters: "m".to_string(),
+            kilometers: "km".to_string(),
+            feet: "ft".to_string(),
+            miles: "mi".to_string(),
+            square_meters: "m²".to_string(),
+            hectares: "ha".to_string(),
+            square_kilometers: "km²".to_string(),
+            acres: "ac".to_string(),
+            square_miles: "mi²".to_string(),
+        }
+    }
+}
+
+const METERS_PER_FOOT: f64 = 0.3048;
+const METERS_PER_MILE: f64 = 1609.344;
+const SQUARE_METERS_PER_ACRE: f64 = 4_046.856_422_4;
+
+impl MapLocale {
+    /// English with feet and miles
+    pub fn imperial() -> Self {
+        Self { unit_system: UnitSystem::Imperial, ..Self::default() }
+    }
+
+    /// A number with `decimals` decimals and the locale's separators, e.g. "12,345.6"
+    pub fn format_number(&self, value: f64, decimals: usize) -> String {
+        let text = format!("{:.*}", decimals, value.abs());
+        let (integer, fraction) = text.split_once('.').unwrap_or((&text, ""));
+        let mut out = String::with_capacity(text.len() + integer.len() / 3 + 1);
+        // Rounding can turn small negative values into zero, which has no sign
+        if value < 0.0 && text.bytes().any(|b| b.is_ascii_digit() && b != b'0') {
+            out.push('-');
+        }
+        for (i, digit) in integer.chars().enumerate() {
+            if let Some(separator) = self.group_separator {
+                if i > 0 && (integer.len() - i) % 3 == 0 {
+                    out.push(separator);
+                }
+            }
+            out.push(digit);
+        }
+        if !fraction.is_empty() {
+            out.push(self.decimal_separator);
+            out.push_str(fraction);
+        }
+        out
+    }
+
+    /// A distance in meters as "850 m" or "12.40 km" (feet and miles in imperial units)
+    pub fn format_distance(&self, meters: f64) -> String {
+        let (small, large, small_factor, large_factor, threshold) = self.distance_units();
+        if meters < threshold {
+            format!("{} {}", self.format_number(meters / small_factor, 0), small)
+        } else if meters < 100.0 * large_factor {
+            format!("{} {}", self.format_number(meters / large_factor, 2), large)
+        } else {
+            format!("{} {}", self.format_number(meters / large_factor, 0), large)
+        }
+    }
+
+    /// An area in square meters as m², hectares or km² (acres or square miles in imperial units)
+    pub fn format_area(&self, square_meters: f64) -> String {
+        match self.unit_system {
+            UnitSystem::Metric if square_meters < 10_000.0 => {
+                format!("{} {}", self.format_number(square_meters, 0), self.square_meters)
+            }
+            UnitSystem::Metric if square_meters < 1_000_000.0 => {
+                format!("{} {}", self.format_number(square_meters / 10_000.0, 2), self.hectares)
+            }
+            UnitSystem::Metric => {
+                format!("{} {}", self.format_number(square_meters / 1_000_000.0, 2), self.square_kilometers)
+            }
+            UnitSystem::Imperial if square_meters < 640.0 * SQUARE_METERS_PER_ACRE => {
+                format!("{} {}", self.format_number(square_meters / SQUARE_METERS_PER_ACRE, 2), self.acres)
+            }
+            UnitSystem::Imperial => {
+                let square_mile = METERS_PER_MILE * METERS_PER_MILE;
+                format!("{} {}", self.format_number(square_meters / square_mile, 2), self.square_miles)
+            }
+        }
+    }
+
+    /// A position in decimal degrees with hemispheres, e.g. "37.77490° N, 122.41940° W"
+    pub fn format_position(&self, lng: f64, lat: f64) -> String {
+        let lat_hemisphere = if lat < 0.0 { 'S' } else { 'N' };
+        let lng_hemisphere = if lng < 0.0 { 'W' } else { 'E' };
+        format!(
+            "{}° {}, {}° {}",
+            self.format_number(lat.abs(), 5), lat_hemisphere,
+            self.format_number(lng.abs(), 5), lng_hemisphere,
+        )
+    }
+
+    /// An elevation or height in meters, as whole meters or feet
+    pub fn format_elevation(&self, meters: f64) -> String {
+        match self.unit_system {
+            UnitSystem::Metric => format!("{} {}", self.format_number(meters, 0), self.meters),
+            UnitSystem::Imperial => format!("{} {}", self.format_number(meters / METERS_PER_FOOT, 0), self.feet),
+        }
+    }
+
+    /// Round lengths in meters for a scale bar, shortest first, with their labels
+    pub fn scale_steps(&self) -> Vec<(f64, String)> {
+        let (small, large, small_factor, large_factor, _) = self.distance_units();
+        let small_steps: &[f64] = match self.unit_system {
+            UnitSystem::Metric => &[10.0, 20.0, 50.0, 100.0, 200.0, 500.0],
+            UnitSystem::Imperial => &[10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0],
+        };
+        let large_steps = [1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0];
+        small_steps.iter()
+            .map(|&value| (value * small_factor, format!("{} {}", self.format_number(value, 0), small)))
+            .chain(large_steps.iter().map(|&value| (value * large_factor, format!("{} {}", self.format_number(value, 0), large))))
+            .collect()
+    }
+
+    /// Size in meters of the unit elevations are shown in
+    pub(crate) fn elevation_unit(&self) -> f64 {
+        match self.unit_system {
+            UnitSystem::Metric => 1.0,
+            UnitSystem::Imperial => METERS_PER_FOOT,
+        }
+    }
+
+    /// Small and large unit symbols, their sizes in meters, and the distance
+    /// from which the large unit is used
+    pub(crate) fn distance_units(&self) -> (&str, &str, f64, f64, f64) {
+        match self.unit_system {
+            UnitSystem::Metric => (&self.meters, &self.kilometers, 1.0, 1000.0, 1000.0),
+            // Feet below 1000 ft, as road signs do
+            UnitSystem::Imperial => (&self.feet, &self.miles, METERS_PER_FOOT, METERS_PER_MILE, 1000.0 * METERS_PER_FOOT),
+        }
+    }
+}
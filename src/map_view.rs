@@ -1,5 +1,36 @@
 use makepad_widgets::*;
-use crate::tiles::{TileCache, TileCoord};
+use crate::animation::{CameraAnimation, CameraAnimationKind, CameraController, MarkerPathAnimation};
+use crate::camera::{CameraState, MapCamera};
+use crate::camera_math::{camera_distance, camera_for_bounds, rotate_vec, tilt_vec, untilt_vec, TileGrid};
+use crate::coordinates::{CoordinateFormat, CoordinateLookup, CoordinateProvider};
+use crate::disk_cache::DiskCacheConfig;
+use crate::geocoding::{GeocodeError, GeocodeQuery, GeocodeResponse, GeocodeResult, Geocoder};
+use crate::geojson::GeoJsonError;
+use crate::layers::{new_map_layer, DrawMapCircle, MapLayer, MapLayerEvent, MapViewport, OverlayHit};
+use crate::legend::MapCorner;
+use crate::locale::MapLocale;
+use crate::choropleth::DrawMapTriangle;
+use crate::navigation::{NavigationCamera, NavigationFix, NavigationOptions};
+use crate::spatial_index::SpatialIndex;
+use crate::geometry::{point_in_polygon, segment_distance, simplification_thresholds, simplify_line, GeometryWarning};
+use crate::geo::{circle_polygon, destination, great_circle_path, haversine_distance, midpoint, path_length, polygon_area, GeoBounds};
+use crate::offline::DownloadProgress;
+use crate::permalink::parse_map_link;
+use crate::projection::{world_size, Projection, WebMercator, TILE_SIZE};
+use crate::quality::{QualityGovernor, QualityLevel};
+use crate::routing::{Route, RouteError, RouteResponse, RouteStyle, Router, ROUTE_END_MARKER, ROUTE_POLYLINE, ROUTE_START_MARKER};
+use crate::timeline::Timeline;
+use crate::tour::CameraTour;
+use crate::tiles::{ProviderAttribution, TileCache, TileCacheStats, TileCoord, TileQuality, TileRequestStats, TileRequestWarning, TileSource, TileTransport};
+use std::any::Any;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::utm;
 
 live_design! {
     link widgets;
@@ -7,56 +38,235 @@ live_design! {
     use link::shaders::*;
     use link::widgets::*;
 
-    // Shader for rendering map tiles with UV offset/scale for parent tile fallback
-    DrawMapTile = {{DrawMapTile}} {
+    // Shader for rendering map tiles with UV offset/scale for parent tile fallback,
+    // rotation around the viewport center (map bearing) and perspective tilt (pitch)
+    pub DrawMapTile = {{DrawMapTile}} {
         texture tile_texture: texture2d
         has_texture: 0.0
         uv_offset: vec2(0.0, 0.0)
         uv_scale: vec2(1.0, 1.0)
+        rotation: 0.0
+        rotation_center: vec2(0.0, 0.0)
+        pitch: 0.0
+        camera_distance: 1000.0
+        opacity: 1.0
+        blend_mode: 0.0
+        nearest: 0.0
+        terrain: 0.0
+        tile_texels: 256.0
+        meters_per_texel: 1.0
+        exaggeration: 1.0
+        sun_azimuth: 5.4978
+        sun_altitude: 0.7854
+
+        // Screen position times the perspective w, and w itself, so the pixel
+        // shader recovers the screen position interpolated linearly on screen
+        varying screen_pos_w: vec2
+        varying clip_w: float
+
+        fn vertex(self) -> vec4 {
+            // Rotate the unclipped quad, clipping happens per pixel instead
+            self.pos = self.geom_pos;
+            let p = self.rect_pos + self.geom_pos * self.rect_size - self.rotation_center;
+            let c = cos(self.rotation);
+            let s = sin(self.rotation);
+            let rotated = vec2(p.x * c - p.y * s, p.x * s + p.y * c);
+
+            // Tilt the map plane back around the horizontal axis through the center
+            let depth = max(self.camera_distance - rotated.y * sin(self.pitch), self.camera_distance * 0.01);
+            let scale = self.camera_distance / depth;
+            let tilted = vec2(rotated.x * scale, rotated.y * cos(self.pitch) * scale) + self.rotation_center;
+            let w = 1.0 / scale;
+            self.screen_pos_w = tilted * w;
+            self.clip_w = w;
+            // Scaling the whole clip position by w keeps it in place on screen
+            // but makes texture coordinates interpolate with perspective
+            return self.camera_projection * (self.camera_view * (self.view_transform * vec4(tilted.x, tilted.y, self.draw_depth + self.draw_zbias, 1.))) * w;
+        }
+
+        // Elevation in meters at a texel of a terrain tile (1: Terrarium, 2: Terrain-RGB)
+        fn elevation(self, uv: vec2) -> float {
+            // Sample texel centers, filtering between texels would mix the encoded bytes
+            let c = sample2d(self.tile_texture, (floor(uv * self.tile_texels) + 0.5) / self.tile_texels);
+            if self.terrain > 1.5 {
+                return (c.r * 65536.0 + c.g * 256.0 + c.b) * 25.5 - 10000.0;
+            }
+            return (c.r * 256.0 + c.g + c.b / 256.0) * 255.0 - 32768.0;
+        }
+
+        // Light falling on the terrain: 1 on flat ground, less on slopes facing
+        // away from the sun and more on slopes facing it
+        fn hillshade(self, uv: vec2) -> float {
+            let step = 1.0 / self.tile_texels;
+            // A parent tile's texels cover more ground
+            let spacing = 2.0 * self.meters_per_texel / self.uv_scale.x / self.exaggeration;
+            let east = (self.elevation(uv + vec2(step, 0.0)) - self.elevation(uv - vec2(step, 0.0))) / spacing;
+            let north = (self.elevation(uv - vec2(0.0, step)) - self.elevation(uv + vec2(0.0, step))) / spacing;
+            let normal = normalize(vec3(-east, -north, 1.0));
+            let sun = vec3(
+                sin(self.sun_azimuth) * cos(self.sun_altitude),
+                cos(self.sun_azimuth) * cos(self.sun_altitude),
+                sin(self.sun_altitude)
+            );
+            return max(dot(normal, sun), 0.0) / sun.z;
+        }
+
+        // Composite a premultiplied color with the blend mode (see TileBlendMode).
+        // Multiply and overlay only darken or lighten what is below, which
+        // premultiplied blending can do with black or white at some alpha.
+        fn blend(self, color: vec4) -> vec4 {
+            let luma = dot(color.rgb, vec3(0.299, 0.587, 0.114));
+            if self.blend_mode > 2.5 {
+                // Screen adds the color and dims what is below by its brightest
+                // channel: exact for gray, colors keep their hue
+                let brightest = max(color.r, max(color.g, color.b));
+                return vec4(color.rgb, brightest) * self.opacity;
+            }
+            if self.blend_mode > 1.5 {
+                if luma < 0.5 * color.a {
+                    return vec4(0.0, 0.0, 0.0, color.a - 2.0 * luma) * self.opacity;
+                }
+                let lighten = 2.0 * luma - color.a;
+                return vec4(lighten, lighten, lighten, lighten) * self.opacity;
+            }
+            if self.blend_mode > 0.5 {
+                return vec4(0.0, 0.0, 0.0, color.a - luma) * self.opacity;
+            }
+            return color * self.opacity;
+        }
 
         fn pixel(self) -> vec4 {
+            let screen_pos = self.screen_pos_w / self.clip_w;
+            if screen_pos.x < self.draw_clip.x || screen_pos.y < self.draw_clip.y
+                || screen_pos.x > self.draw_clip.z || screen_pos.y > self.draw_clip.w {
+                return vec4(0.0);
+            }
             if self.has_texture > 0.5 {
                 // Sample with UV offset and scale (for parent tile fallback)
                 let uv = self.uv_offset + self.pos * self.uv_scale;
-                return sample2d(self.tile_texture, uv)
+                if self.terrain > 0.5 {
+                    // Flat ground is white for multiply, black for screen and
+                    // mid-gray otherwise, so it leaves what is below unchanged
+                    let light = self.hillshade(uv);
+                    let shade = min(light * 0.5, 1.0);
+                    if self.blend_mode > 0.5 && self.blend_mode < 1.5 {
+                        shade = min(light, 1.0);
+                    }
+                    if self.blend_mode > 2.5 {
+                        shade = clamp(light - 1.0, 0.0, 1.0);
+                    }
+                    return self.blend(vec4(shade, shade, shade, 1.0));
+                }
+                // Parent tiles standing in for missing ones stay filtered,
+                // sampled nearest they would turn blocky
+                if self.nearest > 0.5 && self.uv_scale.x > 0.99 {
+                    uv = (floor(uv * self.tile_texels) + 0.5) / self.tile_texels;
+                }
+                return self.blend(sample2d(self.tile_texture, uv));
             }
             // Loading placeholder - subtle light gray
-            return vec4(0.95, 0.95, 0.95, 1.0)
+            return vec4(0.95, 0.95, 0.95, 1.0) * self.opacity
         }
     }
 
-    // Shader for rendering map markers (pin/teardrop shape)
+    // Shader for rendering map markers, shape selected by MarkerShape:
+    // 0 pin (teardrop), 1 circle, 2 square, 3 star, 4 arrow
     DrawMarker = {{DrawMarker}} {
         marker_color: #ff3333
-
-        fn pixel(self) -> vec4 {
-            // Anchor at bottom point (the pin tip)
-            let pos = self.pos - vec2(0.5, 0.7);
-
-            // Teardrop: circle on top, point at bottom
-            let circle_center = vec2(0.0, 0.0);
-            let circle_radius = 0.3;
-
-            // Distance to circle
-            let d_circle = length(pos - circle_center) - circle_radius;
-
+        opacity: 1.0
+        shape: 0.0
+        rotation: 0.0
+        selected: 0.0
+        outline_color: #1a1a1a
+
+        // Teardrop: circle on top, point at bottom
+        fn pin(self, pos: vec2) -> float {
+            let d_circle = length(pos) - 0.3;
             // Triangle/cone pointing down
             let tip = vec2(0.0, 0.35);
             let d_cone = dot(pos - tip, normalize(vec2(abs(pos.x), -0.5)));
+            return min(d_circle, d_cone);
+        }
+
+        // Five-pointed star with a point up
+        fn star(self, pos: vec2) -> float {
+            let k1 = vec2(0.809017, -0.587785);
+            let k2 = vec2(-k1.x, k1.y);
+            let p = vec2(abs(pos.x), -pos.y);
+            p = p - 2.0 * max(dot(k1, p), 0.0) * k1;
+            p = p - 2.0 * max(dot(k2, p), 0.0) * k2;
+            p = vec2(abs(p.x), p.y - 0.45);
+            let ba = 0.45 * vec2(-k1.y, k1.x) - vec2(0.0, 1.0);
+            let h = clamp(dot(p, ba) / dot(ba, ba), 0.0, 0.45);
+            return length(p - ba * h) * sign(p.y * ba.x - p.x * ba.y);
+        }
+
+        // Heading arrow pointing up, with a notch at the back
+        fn arrow(self, pos: vec2) -> float {
+            let p = vec2(abs(pos.x), pos.y);
+            let d_side = dot(p - vec2(0.0, -0.42), normalize(vec2(0.8, -0.32)));
+            let d_back = (p.y - 0.18 - 0.625 * p.x) / length(vec2(0.625, 1.0));
+            return max(d_side, d_back);
+        }
 
-            // Combine: inside if either shape
-            let d = min(d_circle, d_cone);
+        fn pixel(self) -> vec4 {
+            // The pin is anchored at its head, the other shapes at their center
+            let d = 1.0;
+            let d_body = 1.0;
+            if self.shape < 0.5 {
+                let pos = self.pos - vec2(0.5, 0.7);
+                d = self.pin(pos);
+                d_body = length(pos) - 0.3;
+            } else {
+                // Turn the shape clockwise by sampling it turned back
+                let p = self.pos - vec2(0.5, 0.5);
+                let c = cos(self.rotation);
+                let s = sin(self.rotation);
+                let pos = vec2(p.x * c + p.y * s, p.y * c - p.x * s);
+                if self.shape < 1.5 {
+                    d = length(pos) - 0.4;
+                } else if self.shape < 2.5 {
+                    let q = abs(pos) - vec2(0.3, 0.3);
+                    d = length(max(q, vec2(0.0, 0.0))) + min(max(q.x, q.y), 0.0) - 0.05;
+                } else if self.shape < 3.5 {
+                    d = self.star(pos);
+                } else {
+                    d = self.arrow(pos);
+                }
+                d_body = d;
+            }
 
             if d < 0.0 {
                 // Add subtle highlight for depth
-                let highlight = smoothstep(0.0, -0.15, d_circle - 0.1);
-                return mix(self.marker_color, vec4(1.0, 1.0, 1.0, 1.0), highlight * 0.3);
+                let highlight = smoothstep(0.0, -0.15, d_body - 0.1);
+                let color = mix(self.marker_color, vec4(1.0, 1.0, 1.0, 1.0), highlight * 0.3);
+                return vec4(color.rgb * self.opacity, color.a * self.opacity);
+            }
+            // The selected marker gets an outline around its shape
+            if self.selected > 0.5 && d < 0.05 {
+                return vec4(self.outline_color.rgb * self.opacity, self.outline_color.a * self.opacity);
             }
             return vec4(0.0);
         }
     }
 
+    // Shader for rendering a single line segment (grid lines and other overlays)
+    pub DrawMapLine = {{DrawMapLine}} {
+        color: #0055aa99
+        line_width: 1.0
+
+        fn pixel(self) -> vec4 {
+            let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+            sdf.move_to(self.line_start.x, self.line_start.y);
+            sdf.line_to(self.line_end.x, self.line_end.y);
+            return sdf.stroke(self.color, self.line_width);
+        }
+    }
+
     pub GeoMapViewBase = {{GeoMapView}} {
+        draw_background: {
+            color: #dde3e8
+        }
         draw_scale_bg: {
             color: #333333
         }
@@ -66,6 +276,9 @@ live_design! {
                 font_size: 10.0
             }
         }
+        draw_swipe_divider: {
+            color: #ffffff
+        }
         draw_attribution_bg: {
             color: #ffffffcc
         }
@@ -75,6 +288,69 @@ live_design! {
                 font_size: 9.0
             }
         }
+        attribution_text: "© OpenStreetMap contributors © CARTO"
+        attribution_url: "https://www.openstreetmap.org/copyright"
+        draw_hint_bg: {
+            color: #00000066
+        }
+        draw_hint_text: {
+            color: #ffffff
+            text_style: <THEME_FONT_BOLD> {
+                font_size: 14.0
+            }
+        }
+        layer_control_corner: TopLeft
+        draw_compass_bg: {
+            color: #ffffffe6
+            border_color: #00000026
+            border_width: 1.0
+        }
+        draw_compass_north: {
+            color: #e53935
+        }
+        draw_compass_south: {
+            color: #757575
+        }
+        draw_layer_control_bg: {
+            color: #ffffffe6
+        }
+        draw_layer_control_text: {
+            color: #333333
+            text_style: {
+                font_size: 10.0
+            }
+        }
+        draw_layer_control_box: {
+            color: #666666
+
+            fn pixel(self) -> vec4 {
+                let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+                sdf.box(1.0, 1.0, self.rect_size.x - 2.0, self.rect_size.y - 2.0, 2.0);
+                sdf.fill_keep(#ffffff);
+                sdf.stroke(self.color, 1.5);
+                return sdf.result;
+            }
+        }
+        draw_layer_control_check: {
+            color: #1e88e5
+        }
+        draw_layer_control_radio: {
+            color: #ffffff
+            border_color: #666666
+            border_width: 1.5
+        }
+        draw_layer_control_dot: {
+            color: #1e88e5
+            border_width: 0.0
+        }
+        cooperative_touch_hint: "Use two fingers to move the map"
+        cooperative_wheel_hint: "Use ctrl + scroll to zoom the map"
+        draw_stats_text: {
+            color: #333333
+            text_style: <THEME_FONT_CODE> {
+                font_size: 9.0
+            }
+        }
         draw_marker_label: {
             color: #333333
             text_style: <THEME_FONT_REGULAR> {
@@ -84,6 +360,96 @@ live_design! {
         draw_marker_label_bg: {
             color: #ffffffee
         }
+        draw_popup_bg: {
+            color: #ffffff
+        }
+        draw_popup_text: {
+            color: #222222
+            text_style: <THEME_FONT_REGULAR> {
+                font_size: 12.0
+            }
+        }
+        draw_tooltip_bg: {
+            color: #333333ee
+        }
+        cursor_readout_corner: BottomLeft
+        draw_crosshair: {
+            color: #222222cc
+            line_width: 1.5
+        }
+        draw_context_menu_bg: {
+            color: #ffffff
+        }
+        draw_context_menu_text: {
+            color: #222222
+            text_style: <THEME_FONT_REGULAR> {
+                font_size: 11.0
+            }
+        }
+        draw_tooltip_text: {
+            color: #ffffff
+            text_style: <THEME_FONT_REGULAR> {
+                font_size: 10.0
+            }
+        }
+        draw_grid_line: {
+            color: #0055aa99
+        }
+        draw_polyline: {
+            color: #1e88e5
+            line_width: 3.0
+        }
+        draw_measure_line: {
+            color: #ff6f00
+            line_width: 2.5
+        }
+        draw_measure_point: {
+            color: #ffffff
+            border_color: #ff6f00
+            border_width: 2.5
+        }
+        draw_sketch_line: {
+            color: #e53935
+            line_width: 2.5
+        }
+        draw_sketch_point: {
+            color: #ffffff
+            border_color: #e53935
+            border_width: 2.5
+        }
+        draw_selection: {
+            color: #1e88e533
+        }
+        draw_edit_handle: {
+            color: #ffffff
+            border_color: #1e88e5
+            border_width: 2.5
+        }
+        draw_edit_midpoint: {
+            color: #ffffffaa
+            border_color: #1e88e5aa
+            border_width: 1.5
+        }
+        draw_navigation_puck: {
+            color: #1e88e5
+            border_color: #ffffff
+            border_width: 2.5
+        }
+        draw_navigation_arrow: {
+            color: #ffffff
+        }
+        draw_measure_label: {
+            color: #333333
+            text_style: <THEME_FONT_BOLD> {
+                font_size: 11.0
+            }
+        }
+        draw_grid_label: {
+            color: #0055aa
+            text_style: {
+                font_size: 9.0
+            }
+        }
     }
 
     pub GeoMapView = <GeoMapViewBase> {
@@ -99,6 +465,80 @@ pub struct DrawMapTile {
     #[live] pub has_texture: f32,
     #[live] pub uv_offset: Vec2,
     #[live] pub uv_scale: Vec2,
+    #[live] pub rotation: f32,
+    #[live] pub rotation_center: Vec2,
+    /// Tilt of the map plane in radians
+    #[live] pub pitch: f32,
+    /// Distance of the eye from the screen in pixels, sets the strength of the perspective
+    #[live(1000.0)] pub camera_distance: f32,
+    #[live(1.0)] pub opacity: f32,
+    /// How tiles are composited, see `TileBlendMode` and `set_blend_mode`
+    #[live] pub blend_mode: f32,
+    /// Nonzero to sample the texel under each pixel instead of filtering, see `TileFilter`
+    #[live] pub nearest: f32,
+    /// Nonzero to shade elevation tiles as relief: 1 for Terrarium, 2 for Terrain-RGB encoding
+    #[live] pub terrain: f32,
+    /// Texture width of the tile in pixels, for terrain and nearest sampling
+    #[live(256.0)] pub tile_texels: f32,
+    /// Ground size of a terrain texel at the tile zoom
+    #[live(1.0)] pub meters_per_texel: f32,
+    /// Vertical exaggeration of the relief
+    #[live(1.0)] pub exaggeration: f32,
+    /// Direction of the light in radians clockwise from north
+    #[live] pub sun_azimuth: f32,
+    /// Height of the light above the horizon in radians
+    #[live] pub sun_altitude: f32,
+}
+
+/// How a tile layer is composited over what is drawn below it
+#[derive(Copy, Clone, Debug, Live, LiveHook, PartialEq)]
+#[live_ignore]
+pub enum TileBlendMode {
+    /// Cover what is below (transparent parts let it through)
+    #[pick] Normal,
+    /// Darken what is below by the tile's brightness, white leaves it unchanged
+    Multiply,
+    /// Darken what is below under mid-gray and lighten it above, mid-gray leaves it unchanged
+    Overlay,
+    /// Lighten what is below by the tile's brightness, black leaves it unchanged
+    /// (e.g. glowing heat or radar tiles on a dark base map)
+    Screen,
+}
+
+/// How tile textures are sampled when drawn at a fractional zoom
+#[derive(Copy, Clone, Debug, Live, LiveHook, PartialEq)]
+#[live_ignore]
+pub enum TileFilter {
+    /// Blend neighbouring texels: smooth, but scaled tiles look soft
+    #[pick] Linear,
+    /// The texel under each pixel: crisp text and lines, with blocky edges when scaled up
+    Nearest,
+}
+
+impl DrawMapTile {
+    pub fn set_blend_mode(&mut self, mode: TileBlendMode) {
+        self.blend_mode = match mode {
+            TileBlendMode::Normal => 0.0,
+            TileBlendMode::Multiply => 1.0,
+            TileBlendMode::Overlay => 2.0,
+            TileBlendMode::Screen => 3.0,
+        };
+    }
+
+    pub fn set_filter(&mut self, filter: TileFilter) {
+        self.nearest = match filter {
+            TileFilter::Linear => 0.0,
+            TileFilter::Nearest => 1.0,
+        };
+    }
+
+    /// Lay out tiles north-up, rotated around the viewport center and tilted like the viewport
+    pub(crate) fn set_viewport(&mut self, viewport: &MapViewport) {
+        self.rotation = (-viewport.bearing.to_radians()) as f32;
+        self.rotation_center = (viewport.rect.pos + viewport.rect.size / 2.0 + viewport.center_offset).into_vec2();
+        self.pitch = viewport.pitch.to_radians() as f32;
+        self.camera_distance = camera_distance(viewport.rect.size.y) as f32;
+    }
 }
 
 #[derive(Live, LiveRegister, LiveHook)]
@@ -106,828 +546,6030 @@ pub struct DrawMapTile {
 pub struct DrawMarker {
     #[deref] pub draw_super: DrawQuad,
     #[live] pub marker_color: Vec4,
+    #[live(1.0)] pub opacity: f32,
+    /// `MarkerShape::shader_id` of the shape drawn
+    #[live] pub shape: f32,
+    /// Clockwise rotation on screen in radians (not applied to pins)
+    #[live] pub rotation: f32,
+    /// 1.0 for the selected marker, drawn with an outline
+    #[live] pub selected: f32,
+    #[live] pub outline_color: Vec4,
+}
+
+#[derive(Live, LiveRegister, LiveHook)]
+#[repr(C)]
+pub struct DrawMapLine {
+    #[deref] pub draw_super: DrawQuad,
+    #[live] pub color: Vec4,
+    #[live] pub line_start: Vec2,
+    #[live] pub line_end: Vec2,
+    #[live(1.0)] pub line_width: f32,
+}
+
+impl DrawMapLine {
+    /// Draw a line segment between two absolute positions
+    pub fn draw_line(&mut self, cx: &mut Cx2d, from: DVec2, to: DVec2) {
+        // The quad covers the segment's bounding box, padded for the stroke width
+        let pad = self.line_width as f64 + 1.0;
+        let min = dvec2(from.x.min(to.x) - pad, from.y.min(to.y) - pad);
+        let max = dvec2(from.x.max(to.x) + pad, from.y.max(to.y) + pad);
+        self.line_start = (from - min).into_vec2();
+        self.line_end = (to - min).into_vec2();
+        self.draw_abs(cx, Rect { pos: min, size: max - min });
+    }
 }
 
 /// A marker that can be placed on the map at a geographic location
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MapMarker {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impls::live_id"))]
     pub id: LiveId,
     pub lng: f64,
     pub lat: f64,
+    #[cfg_attr(feature = "serde", serde(default))]
     pub label: String,
+    /// Text shown in the marker's popup (info window), falls back to the label
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub popup: String,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impls::color"))]
     pub color: Vec4,
+    /// Zoom range the marker is shown (and tappable) in, e.g. individual
+    /// stops only from zoom 15 (0 and infinity by default)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub min_zoom: f64,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impls::max_zoom", default = "crate::serde_impls::max_zoom::default"))]
+    pub max_zoom: f64,
+    /// Side of the pin the label is drawn on
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub label_placement: LabelPlacement,
+    /// Extra shift of the label in pixels, e.g. to move it off a neighbor
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impls::dvec2", default))]
+    pub label_offset: DVec2,
+    /// Symbol drawn for the marker
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub shape: MarkerShape,
+    /// Direction the marker points in, degrees clockwise from north. Turns
+    /// every shape but the pin, and stays geographic when the map is turned
+    /// or tilted. Markers moved by `animate_marker_along_path` follow their
+    /// heading of travel.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rotation_deg: f64,
+    /// The app's own object for the marker (e.g. the place it shows), to get
+    /// back with `data` when it's tapped instead of keeping a map by marker id.
+    /// Not saved with the marker.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub user_data: Option<MarkerData>,
 }
 
-#[derive(Clone, Debug, DefaultNone)]
-pub enum GeoMapViewAction {
-    None,
-    RegionChanged {
-        center_lng: f64,
-        center_lat: f64,
-        zoom: f64,
-    },
-    Tapped {
-        lng: f64,
-        lat: f64,
-    },
-    LongPressed {
-        lng: f64,
-        lat: f64,
-    },
-    MarkerTapped {
-        id: LiveId,
-    },
-}
+/// An app object attached to a marker, shared (not copied) by marker clones
+#[derive(Clone)]
+pub struct MarkerData(pub Arc<dyn Any + Send + Sync>);
 
-/// Tile size in pixels (standard OSM tile size)
-const TILE_SIZE: f64 = 256.0;
+impl fmt::Debug for MarkerData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MarkerData(..)")
+    }
+}
 
-/// Scale bar step values in meters (from 10m to 1000km)
-const SCALE_STEPS: &[f64] = &[
-    10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0,
-    10000.0, 20000.0, 50000.0, 100000.0, 200000.0, 500000.0, 1000000.0,
-];
+/// Built-in marker symbols, to tell categories apart without image assets
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MarkerShape {
+    /// Teardrop pin
+    #[default]
+    Pin,
+    Circle,
+    /// Square with rounded corners
+    Square,
+    Star,
+    /// Triangle pointing up, e.g. for a heading
+    Arrow,
+}
 
-#[derive(Live, LiveHook, Widget)]
-pub struct GeoMapView {
-    #[walk] walk: Walk,
-    #[redraw] #[live] pub draw_tile: DrawMapTile,
+impl MarkerShape {
+    /// Value of `DrawMarker::shape` for the shape
+    fn shader_id(self) -> f32 {
+        match self {
+            MarkerShape::Pin => 0.0,
+            MarkerShape::Circle => 1.0,
+            MarkerShape::Square => 2.0,
+            MarkerShape::Star => 3.0,
+            MarkerShape::Arrow => 4.0,
+        }
+    }
 
-    // Scale bar drawing
-    #[live] draw_scale_bg: DrawColor,
-    #[live] draw_scale_text: DrawText,
-    #[live(true)] pub show_scale_bar: bool,
+    /// How far the shape reaches above and below the marker's position, as
+    /// fractions of the marker size. The pin's quad ends below its drawn point.
+    fn extent(self) -> (f64, f64) {
+        match self {
+            MarkerShape::Pin => (0.7, 0.0),
+            _ => (0.5, 0.5),
+        }
+    }
 
-    // Attribution overlay
-    #[live] draw_attribution_bg: DrawColor,
-    #[live] draw_attribution_text: DrawText,
-    #[live(true)] pub show_attribution: bool,
+    /// Height of the shape's visual center above the marker's position, as a
+    /// fraction of the marker size
+    fn center_height(self) -> f64 {
+        match self {
+            MarkerShape::Pin => 0.35,
+            _ => 0.0,
+        }
+    }
+}
 
-    // Markers
-    #[live] draw_marker: DrawMarker,
-    #[live] draw_marker_label: DrawText,
-    #[live] draw_marker_label_bg: DrawColor,
-    #[live(32.0)] pub marker_size: f64,
-    #[rust] markers: Vec<MapMarker>,
+/// Where a marker's label is drawn relative to its pin
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LabelPlacement {
+    /// Under the pin's point, aligned by `marker_label_align`
+    #[default]
+    Below,
+    /// Over the pin's head, aligned by `marker_label_align`
+    Above,
+    /// Ending left of the pin, vertically centered on its head
+    Left,
+    /// Starting right of the pin, vertically centered on its head
+    Right,
+    /// Centered on the pin's head, for short labels like a number or letter
+    Center,
+}
 
-    // Map state (default: San Francisco at zoom 12)
-    #[live(-122.4194)] pub center_lng: f64,
-    #[live(37.7749)] pub center_lat: f64,
-    #[live(12.0)] pub zoom: f64,
+impl MapMarker {
+    /// Whether the marker is shown at a zoom level
+    pub fn is_shown_at(&self, zoom: f64) -> bool {
+        zoom >= self.min_zoom && zoom <= self.max_zoom
+    }
 
-    // Zoom constraints
-    #[live(1.0)] pub min_zoom: f64,
-    #[live(19.0)] pub max_zoom: f64,
+    /// Attach an app object to the marker, replacing any attached before
+    pub fn set_data<T: Any + Send + Sync>(&mut self, data: T) -> &mut Self {
+        self.user_data = Some(MarkerData(Arc::new(data)));
+        self
+    }
 
-    // Internal state
-    #[rust] drag_start: Option<DVec2>,
-    #[rust] drag_start_center: Option<(f64, f64)>,
-    #[rust] last_abs: DVec2,
-    #[rust] viewport_size: DVec2,
-    #[rust] viewport_pos: DVec2,  // Top-left position of viewport in absolute coords
+    /// The object attached with `set_data`, if it's a `T`
+    pub fn data<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        self.user_data.as_ref()?.0.clone().downcast::<T>().ok()
+    }
+}
 
-    // Pinch zoom state
-    #[rust] initial_pinch_distance: Option<f64>,
-    #[rust] pinch_zoom_start: Option<f64>,
+/// How fast `animate_marker_along_path` moves a marker
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathTiming {
+    /// Travel the whole path in this many seconds
+    Duration(f64),
+    /// Travel at this ground speed in meters per second
+    Speed(f64),
+}
 
-    // Momentum scrolling state
-    #[rust] velocity_samples: Vec<(DVec2, f64)>,  // (position, time in seconds)
-    #[rust] flick_velocity: DVec2,
-    #[rust] next_frame: NextFrame,
-    #[rust] is_flicking: bool,
+/// A line through geographic positions
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MapPolyline {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impls::live_id"))]
+    pub id: LiveId,
+    /// Positions as (lng, lat)
+    pub points: Vec<(f64, f64)>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impls::color"))]
+    pub color: Vec4,
+    /// Line width in pixels
+    pub width: f64,
+    /// Follow great circles between points (flight and shipping routes)
+    /// instead of straight lines in the map projection
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub geodesic: bool,
+    /// Connect the last point back to the first, outlining a polygon
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub closed: bool,
+    /// Show draggable vertex handles and midpoint handles that insert a
+    /// vertex; changes are reported with `OverlayEdited`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub editable: bool,
+    /// Zoom range the line is shown in (0 and infinity by default)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub min_zoom: f64,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impls::max_zoom", default = "crate::serde_impls::max_zoom::default"))]
+    pub max_zoom: f64,
+    /// Shown next to the pointer while it hovers the line (or the inside of a
+    /// closed outline), and where it's tapped on touch screens
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub tooltip: String,
+}
 
-    // Momentum tunable parameters
-    #[live(0.95)] pub momentum_decay: f64,
-    #[live(0.5)] pub momentum_threshold: f64,
+impl MapPolyline {
+    /// Whether the line is shown at a zoom level
+    pub fn is_shown_at(&self, zoom: f64) -> bool {
+        zoom >= self.min_zoom && zoom <= self.max_zoom
+    }
 
-    // Tile loading
-    #[rust] tile_cache: TileCache,
+    /// Positions to draw through, repeating the first one at the end of a closed outline
+    fn outline(&self) -> Vec<(f64, f64)> {
+        let mut points = self.points.clone();
+        if self.closed && points.len() > 2 {
+            points.push(points[0]);
+        }
+        points
+    }
 }
 
-impl Widget for GeoMapView {
-    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
-        let uid = self.widget_uid();
+/// Identifies a layer in the map's draw order: an overlay layer (declared in
+/// live_design or added with `add_layer`) or one of the built-in layers
+pub type LayerId = LiveId;
+
+/// The base map tiles
+pub const TILES_LAYER: LayerId = LiveId::from_str("tiles");
+/// The UTM/MGRS grid (see `show_utm_grid`)
+pub const UTM_GRID_LAYER: LayerId = LiveId::from_str("utm_grid");
+/// Polylines added with `add_polyline`
+pub const POLYLINES_LAYER: LayerId = LiveId::from_str("polylines");
+/// Markers added with `add_marker`
+pub const MARKERS_LAYER: LayerId = LiveId::from_str("markers");
+
+/// Draw order without changes: overlay layers go between the polylines and the markers
+const DEFAULT_LAYER_ORDER: [LayerId; 4] = [TILES_LAYER, UTM_GRID_LAYER, POLYLINES_LAYER, MARKERS_LAYER];
+
+/// Highest zoom level tiles are requested at, deeper zooms scale these up
+const MAX_TILE_ZOOM: u8 = 19;
+
+/// Rings of tiles loaded beyond the viewport edges, the most `overscan_tiles` allows
+const MAX_OVERSCAN_TILES: usize = 3;
+
+/// What the cached tile pass was drawn for. The tiles are only drawn again
+/// when any of it changes, or the pass is reset (e.g. a new projection).
+#[derive(Clone, Debug, PartialEq)]
+struct TilePassKey {
+    rect: Rect,
+    center: (f64, f64),
+    zoom: f64,
+    bearing: f64,
+    pitch: f64,
+    center_offset: DVec2,
+    world_repeat: bool,
+    opacity: f64,
+    swipe_position: f64,
+    filter: TileFilter,
+    content_generation: u64,
+}
 
-        // Handle HTTP responses for tile loading
-        if let Event::NetworkResponses(responses) = event {
-            for response in responses {
-                match &response.response {
-                    NetworkResponse::HttpResponse(http_response) => {
-                        if self.tile_cache.handle_response(cx, response.request_id, http_response) {
-                            // Tile loaded successfully, redraw
-                            self.draw_tile.redraw(cx);
-                        }
-                    }
-                    NetworkResponse::HttpRequestError(error) => {
-                        self.tile_cache.handle_error(response.request_id, error);
+impl TileGrid {
+    /// Draw the tiles from a cache, falling back to scaled parent tiles. Tiles
+    /// that aren't loaded show a placeholder if `placeholder` is set.
+    pub(crate) fn draw(&self, cx: &mut Cx2d, draw_tile: &mut DrawMapTile, cache: &TileCache, rect: Rect, placeholder: bool) {
+        for (coord, pos) in self.tiles() {
+            // Set up texture - try current tile, then fall back to parent tiles
+            if let Some(texture) = cache.get_tile(&coord) {
+                // Use the exact tile
+                draw_tile.draw_vars.set_texture(0, texture);
+                draw_tile.has_texture = 1.0;
+                draw_tile.uv_offset = Vec2 { x: 0.0, y: 0.0 };
+                draw_tile.uv_scale = Vec2 { x: 1.0, y: 1.0 };
+                if draw_tile.nearest > 0.5 {
+                    if let Some(width) = cache.texture_width(&coord) {
+                        draw_tile.tile_texels = width as f32;
                     }
-                    _ => {}
                 }
+            } else if let Some((parent_texture, uv_offset, uv_scale)) = cache.find_parent(&coord) {
+                // Use scaled parent tile as fallback
+                draw_tile.draw_vars.set_texture(0, parent_texture);
+                draw_tile.has_texture = 1.0;
+                draw_tile.uv_offset = uv_offset;
+                draw_tile.uv_scale = uv_scale;
+            } else if placeholder {
+                // No tile available, show placeholder
+                draw_tile.has_texture = 0.0;
+                draw_tile.uv_offset = Vec2 { x: 0.0, y: 0.0 };
+                draw_tile.uv_scale = Vec2 { x: 1.0, y: 1.0 };
+            } else {
+                continue;
             }
-        }
 
-        // Handle momentum animation frames
-        if self.next_frame.is_event(event).is_some() && self.is_flicking {
-            self.apply_momentum(cx, uid, &scope.path);
+            // Draw the tile
+            draw_tile.draw_abs(cx, Rect {
+                pos: rect.pos + pos,
+                size: dvec2(self.scaled_tile_size, self.scaled_tile_size),
+            });
         }
+    }
+}
 
-        // Handle touch events for pinch zoom
-        if let Event::TouchUpdate(te) = event {
-            // Check if we have multiple touches for pinch zoom
-            if te.touches.len() >= 2 {
-                // Calculate distance between first two touches
-                let t0 = &te.touches[0];
-                let t1 = &te.touches[1];
-                let dx = t1.abs.x - t0.abs.x;
-                let dy = t1.abs.y - t0.abs.y;
-                let distance = (dx * dx + dy * dy).sqrt();
+/// A tile source the layer control offers as a base map (one active at a time)
+#[derive(Clone, Debug)]
+pub struct BaseLayer {
+    pub id: LiveId,
+    pub title: String,
+    pub source: TileSource,
+    /// Seconds between refetches of its visible tiles while active, 0 never
+    /// (see `tile_refresh_interval`)
+    pub refresh_interval: f64,
+}
 
-                if let (Some(initial_distance), Some(start_zoom)) = (self.initial_pinch_distance, self.pinch_zoom_start) {
-                    // Calculate zoom change based on pinch ratio from initial
-                    let scale = distance / initial_distance;
-                    // Use log scale for more natural zoom feel
-                    let zoom_delta = scale.ln() / std::f64::consts::LN_2;
-                    let new_zoom = (start_zoom + zoom_delta).clamp(self.min_zoom, self.max_zoom);
+/// Horizontal alignment of marker labels to their pin
+#[derive(Copy, Clone, Debug, Live, LiveHook, PartialEq)]
+#[live_ignore]
+pub enum LabelAlign {
+    /// Centered under the pin
+    #[pick] Center,
+    /// Beginning at the pin in the label's reading direction: running right
+    /// for left-to-right text, left for Arabic or Hebrew
+    Start,
+    /// Running right from the pin
+    Left,
+    /// Ending at the pin
+    Right,
+}
 
-                    if (new_zoom - self.zoom).abs() > 0.01 {
-                        self.zoom = new_zoom;
-                        self.draw_tile.redraw(cx);
-                    }
-                } else {
-                    // Start of pinch - store initial state
-                    self.initial_pinch_distance = Some(distance);
-                    self.pinch_zoom_start = Some(self.zoom);
-                }
+/// An on-map control that takes taps instead of the map
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MapControl {
+    Compass,
+    /// The layer control, at one of its rows or between them
+    LayerControl(Option<LayerControlEntry>),
+    Attribution,
+    ScaleBar,
+    /// The open context menu, at one of its items or its padding
+    ContextMenu(Option<LiveId>),
+}
 
-                // Clear single-finger drag state during pinch
-                self.drag_start = None;
-                self.drag_start_center = None;
-            }
-        }
+/// A row of the layer control
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum LayerControlEntry {
+    /// Base map (radio button)
+    Base(LiveId),
+    /// Overlay layer (checkbox)
+    Overlay(LiveId),
+}
 
-        match event.hits(cx, self.draw_tile.area()) {
-            Hit::FingerDown(fe) if fe.is_primary_hit() => {
-                cx.set_key_focus(self.draw_tile.area());
+/// A vertex or midpoint handle of an editable polyline
+#[derive(Clone, Copy, Debug)]
+struct EditHandle {
+    /// Index into the polylines
+    line: usize,
+    /// Vertex index, or for midpoints the index the inserted vertex gets
+    index: usize,
+    midpoint: bool,
+    /// Position relative to the viewport
+    pos: DVec2,
+    lng: f64,
+    lat: f64,
+}
+
+/// Hint shown when a gesture is left to the page in cooperative mode
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum GestureHint {
+    TwoFingers,
+    ModifierScroll,
+}
+
+/// How the map responds to taps and drags
+#[derive(Copy, Clone, Debug, Live, LiveHook, PartialEq)]
+#[live_ignore]
+pub enum InteractionMode {
+    /// Pan, zoom and tap markers
+    #[pick] Pan,
+    /// Dragging selects a rectangle (emits `AreaSelected`), pinch still zooms
+    Select,
+    /// Taps add polygon vertices, tapping the first vertex finishes the polygon
+    /// (emits `PolygonDrawn`)
+    DrawPolygon,
+    /// Dragging from corner to corner adds a rectangle (emits `RectangleDrawn`)
+    DrawRectangle,
+    /// Dragging from the center outwards adds a circle (emits `CircleDrawn`)
+    DrawCircle,
+    /// Taps add measurement points, tapping the first point closes an area
+    Measure,
+}
+
+/// A distance/area measurement built in `InteractionMode::Measure`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Measurement {
+    /// Measured positions as (lng, lat)
+    pub points: Vec<(f64, f64)>,
+    /// The points form a closed polygon
+    pub closed: bool,
+}
+
+impl Measurement {
+    /// Great-circle length in meters, including the closing segment of a polygon
+    pub fn distance(&self) -> f64 {
+        let mut distance = path_length(&self.points);
+        if self.closed {
+            if let (Some(first), Some(last)) = (self.points.first(), self.points.last()) {
+                distance += haversine_distance(last.0, last.1, first.0, first.1);
+            }
+        }
+        distance
+    }
+
+    /// Enclosed area in square meters, if the polygon is closed
+    pub fn area(&self) -> Option<f64> {
+        self.closed.then(|| polygon_area(&self.points))
+    }
+}
+
+#[derive(Clone, Debug, DefaultNone)]
+pub enum GeoMapViewAction {
+    None,
+    RegionChanged {
+        center_lng: f64,
+        center_lat: f64,
+        zoom: f64,
+    },
+    Tapped {
+        lng: f64,
+        lat: f64,
+    },
+    LongPressed {
+        lng: f64,
+        lat: f64,
+    },
+    MarkerTapped {
+        id: LiveId,
+    },
+    /// A marker was selected by a tap (see `tap_selects_marker`)
+    MarkerSelected {
+        id: LiveId,
+    },
+    /// The selected marker was deselected by a tap on another marker or on the map
+    MarkerDeselected {
+        id: LiveId,
+    },
+    /// The map bearing changed (degrees clockwise from north)
+    BearingChanged {
+        bearing: f64,
+    },
+    /// A marker's popup was opened
+    PopupOpened {
+        id: LiveId,
+    },
+    /// A tapped position was looked up by the coordinate provider
+    CoordinateLookedUp {
+        lng: f64,
+        lat: f64,
+        text: String,
+    },
+    /// A temporary pin was dropped by a long press (see `long_press_adds_marker`)
+    PinDropped {
+        id: LiveId,
+        lng: f64,
+        lat: f64,
+    },
+    /// A measurement point was added or the measurement was closed or cleared.
+    /// Distance in meters, area in square meters once the polygon is closed.
+    MeasurementChanged {
+        distance: f64,
+        area: Option<f64>,
+    },
+    /// A rectangle was dragged out in `InteractionMode::Select`
+    AreaSelected {
+        bounds: GeoBounds,
+    },
+    /// The points of an editable polyline were changed by dragging its handles
+    OverlayEdited {
+        id: LiveId,
+        points: Vec<(f64, f64)>,
+    },
+    /// The pointer moved onto a polyline or a layer's shape (e.g. a choropleth
+    /// region); on touch screens a tap reports it
+    OverlayHovered {
+        id: LiveId,
+    },
+    /// A polygon was finished in `InteractionMode::DrawPolygon`, as (lng, lat)
+    /// vertices without a closing point
+    PolygonDrawn {
+        points: Vec<(f64, f64)>,
+    },
+    /// A rectangle was drawn in `InteractionMode::DrawRectangle` and added as
+    /// the closed polyline `id`
+    RectangleDrawn {
+        id: LiveId,
+        bounds: GeoBounds,
+    },
+    /// A circle was drawn in `InteractionMode::DrawCircle` and added as the
+    /// closed polyline `id`. Radius in meters.
+    CircleDrawn {
+        id: LiveId,
+        lng: f64,
+        lat: f64,
+        radius: f64,
+    },
+    /// A layer was shown or hidden from the layer control. Switching the base
+    /// map reports the previous base layer hidden and the new one visible.
+    LayerToggled {
+        id: LiveId,
+        visible: bool,
+    },
+    /// A layer finished loading data whose polygons had to be repaired (e.g.
+    /// a `GeoJsonLayer` feed), as (feature index, warning)
+    GeometryRepaired {
+        layer: LiveId,
+        warnings: Vec<(usize, GeometryWarning)>,
+    },
+    /// Loading or refreshing a `GeoJsonLayer`'s `url` failed. The layer keeps
+    /// showing its previous features.
+    GeoJsonFailed {
+        layer: LiveId,
+        url: String,
+        error: GeoJsonError,
+    },
+    /// A marker moved along its path (see `animate_marker_along_path`), once
+    /// per frame. Heading in degrees clockwise from north, progress 0..1.
+    MarkerAnimationProgress {
+        id: LiveId,
+        lng: f64,
+        lat: f64,
+        heading: f64,
+        progress: f64,
+    },
+    /// A marker reached the end of its path
+    MarkerAnimationFinished {
+        id: LiveId,
+    },
+    /// The playing timeline advanced (see `set_timeline`), once per frame
+    TimeChanged {
+        time: f64,
+    },
+    /// The swipe divider was dragged to a new position (fraction of the width)
+    SwipeMoved {
+        position: f64,
+    },
+    /// Tiles for the current view are loading: `pending` still loading out of
+    /// `total` started since the map was last idle
+    TilesLoading {
+        pending: usize,
+        total: usize,
+    },
+    /// All tiles for the current view have loaded (or failed)
+    TilesIdle,
+    /// Queued tile downloads progressed (see `prefetch_route`)
+    DownloadProgressChanged {
+        progress: DownloadProgress,
+    },
+    /// The tile server rate-limited (HTTP 429) or failed (5xx), requests to
+    /// `host` are paused for `duration` seconds
+    ProviderThrottled {
+        host: String,
+        status: u16,
+        duration: f64,
+    },
+    /// Tile requests were paused for `duration` seconds after a pathological request pattern
+    TileRequestsBackedOff {
+        warning: TileRequestWarning,
+        duration: f64,
+    },
+    /// A tile request got no response within `tile_timeout` seconds and is retried
+    TileTimeout {
+        coord: TileCoord,
+        timeout: f64,
+    },
+    /// The adaptive quality governor changed the rendering quality
+    QualityChanged {
+        level: QualityLevel,
+    },
+    /// A `geocode` search finished, best match first (empty if nothing matched)
+    GeocodeResults {
+        query: String,
+        results: Vec<GeocodeResult>,
+    },
+    /// A `reverse_geocode` lookup finished (None if there is no address there)
+    ReverseGeocoded {
+        lng: f64,
+        lat: f64,
+        result: Option<GeocodeResult>,
+    },
+    /// The address at a long-pressed position was looked up (see
+    /// `long_press_reverse_geocode`). None if there is no address there or
+    /// the lookup failed.
+    LongPressedWithAddress {
+        lng: f64,
+        lat: f64,
+        address: Option<String>,
+    },
+    /// A geocoding request failed
+    GeocodeFailed {
+        query: GeocodeQuery,
+        error: GeocodeError,
+    },
+    /// The user panned the map away from the navigation camera, which stopped
+    /// following until `resume_navigation`
+    NavigationPaused,
+    /// A route requested with `request_route` is shown. Distance in meters,
+    /// duration in seconds.
+    RouteReady {
+        distance: f64,
+        duration: f64,
+    },
+    /// A route request failed or the router found no route
+    RouteFailed {
+        error: RouteError,
+    },
+    /// The attribution was tapped, with `attribution_url` to open (e.g. with
+    /// `cx.open_url`), as tile providers' terms usually ask
+    AttributionTapped {
+        url: String,
+    },
+    /// The tile source changed to a provider whose usage policy requires
+    /// attribution. The map shows it regardless of `show_attribution`; this
+    /// lets the app credit the provider in its own UI too (e.g. an about screen).
+    AttributionRequired {
+        attribution: ProviderAttribution,
+    },
+    /// The scale bar was tapped, e.g. to switch between metric and imperial units
+    ScaleBarTapped,
+    /// The mouse moved over the map (see `track_cursor`), at most once per
+    /// `cursor_moved_interval` with the latest position
+    CursorMoved {
+        lng: f64,
+        lat: f64,
+    },
+    /// The map was right-clicked at a position, with the absolute pointer
+    /// position to place the app's own menu at
+    ContextRequested {
+        lng: f64,
+        lat: f64,
+        abs: DVec2,
+    },
+    /// An item of the built-in context menu (see `set_context_menu`) was
+    /// picked, with the position the menu was opened for
+    ContextMenuItemSelected {
+        id: LiveId,
+        lng: f64,
+        lat: f64,
+    },
+    /// User input tried to zoom out past the minimum zoom (e.g. to switch to
+    /// a wider data source)
+    MinZoomReached,
+    /// User input tried to zoom in past `max_zoom` (e.g. to show "Maximum
+    /// detail reached")
+    MaxZoomReached,
+}
+
+/// Zoom level from which the UTM grid lines of the center zone are drawn
+const UTM_GRID_MIN_ZOOM: f64 = 7.0;
+
+/// Number of segments used to draw each (slightly curved) UTM grid line
+const UTM_GRID_SEGMENTS: usize = 8;
+
+/// Frame rate that `momentum_decay` and `momentum_threshold` are expressed in
+const REFERENCE_FRAME_RATE: f64 = 60.0;
+
+/// Shortest time between animation frames and gesture redraws in low power mode (30 fps)
+const LOW_POWER_FRAME_TIME: f64 = 1.0 / 30.0;
+
+/// Only finger movement within this time before release (seconds) counts towards flick velocity
+const VELOCITY_WINDOW: f64 = 0.1;
+
+/// Finger rotation (degrees) needed before a pinch also rotates the map
+const ROTATE_THRESHOLD_DEG: f64 = 10.0;
+
+/// Bearings closer than this to north (degrees) snap back to north after a rotate gesture
+const NORTH_SNAP_DEG: f64 = 7.0;
+
+/// Largest map tilt in degrees; beyond it the horizon would come into view
+pub const MAX_PITCH: f64 = 60.0;
+
+/// Width of the swipe divider line and its drag handle in pixels
+const SWIPE_DIVIDER_WIDTH: f64 = 3.0;
+const SWIPE_HANDLE_WIDTH: f64 = 14.0;
+const SWIPE_HANDLE_HEIGHT: f64 = 44.0;
+
+/// Pixels beyond a polyline's stroke that still count as hovering it
+const OVERLAY_HIT_SLOP: f64 = 4.0;
+
+/// Duration of the animated double-tap zoom step in seconds
+const ZOOM_ANIMATION_DURATION: f64 = 0.25;
+
+/// Default duration of fly_to animations in seconds
+const FLY_TO_DURATION: f64 = 0.8;
+
+/// Zoom of `search_and_fly_to` for results without an extent (a street address)
+const SEARCH_RESULT_ZOOM: f64 = 16.0;
+
+/// How long the cooperative gesture hint stays up, in seconds
+const GESTURE_HINT_DURATION: f64 = 1.5;
+
+/// Tapping within this distance (pixels) of the first measurement point closes the polygon
+const MEASURE_CLOSE_DISTANCE: f64 = 14.0;
+
+/// Radius of measurement point handles in pixels
+const MEASURE_POINT_RADIUS: f64 = 5.0;
+
+/// Radius of the vertex handles of editable polylines
+const EDIT_HANDLE_RADIUS: f64 = 6.0;
+/// Radius of the midpoint handles that insert a vertex
+const EDIT_MIDPOINT_RADIUS: f64 = 4.0;
+
+/// Radius of the navigation position puck in pixels
+const NAVIGATION_PUCK_RADIUS: f64 = 10.0;
+
+/// Radius of the compass control in pixels
+const COMPASS_RADIUS: f64 = 16.0;
+
+/// Duration of the animated turn back to north in seconds
+const RESET_NORTH_DURATION: f64 = 0.4;
+/// Touches within this distance (pixels) of a handle grab it
+const EDIT_HANDLE_HIT_DISTANCE: f64 = 16.0;
+
+/// Row height and checkbox/radio button size of the layer control in pixels
+const LAYER_CONTROL_ROW_HEIGHT: f64 = 24.0;
+const CONTEXT_MENU_ROW_HEIGHT: f64 = 26.0;
+const LAYER_CONTROL_BOX_SIZE: f64 = 13.0;
+
+/// Vertices of circles drawn in `InteractionMode::DrawCircle`
+const CIRCLE_SEGMENTS: usize = 64;
+
+/// Smallest selection rectangle side in pixels, smaller drags count as taps
+const SELECT_MIN_SIZE: f64 = 8.0;
+
+/// Maximum arc between interpolated points of geodesic polylines (degrees)
+const GEODESIC_STEP_DEG: f64 = 1.0;
+
+/// A polyline's Douglas–Peucker thresholds, and its outline simplified for
+/// the whole zoom levels drawn so far
+struct SimplifiedPolyline {
+    thresholds: Vec<f64>,
+    levels: HashMap<i32, Vec<(f64, f64)>>,
+}
+
+/// Options for [`GeoMapView::focus_marker`]
+#[derive(Clone, Copy, Debug)]
+pub struct FocusOptions {
+    /// Target zoom (keeps the current zoom if None)
+    pub zoom: Option<f64>,
+    /// Viewport area covered by UI, the marker is centered in the remaining area
+    pub padding: Padding,
+    /// Open the marker's popup on arrival
+    pub open_popup: bool,
+    /// Animation duration in seconds
+    pub duration: f64,
+}
+
+impl Default for FocusOptions {
+    fn default() -> Self {
+        Self {
+            zoom: None,
+            padding: Padding::default(),
+            open_popup: true,
+            duration: FLY_TO_DURATION,
+        }
+    }
+}
+
+#[derive(Live, Widget)]
+pub struct GeoMapView {
+    #[walk] walk: Walk,
+    #[redraw] #[live] pub draw_tile: DrawMapTile,
+
+    // Outside the map extent (above/below the mercator square, beside a non-repeating world)
+    #[live] draw_background: DrawColor,
+    #[live(true)] pub world_repeat: bool,
+    #[live(false)] pub fit_world_zoom: bool,
+
+    // Scale bar drawing
+    #[live] draw_scale_bg: DrawColor,
+    #[live] draw_scale_text: DrawText,
+    #[live(true)] pub show_scale_bar: bool,
+    #[rust] scale_bar_rect: Option<Rect>,
+    // Number format and units of the scale bar and measurement labels
+    #[rust] locale: MapLocale,
+
+    // Attribution overlay, tapping it reports `AttributionTapped` with the url
+    #[live] draw_attribution_bg: DrawColor,
+    #[live] draw_attribution_text: DrawText,
+    #[live(true)] pub show_attribution: bool,
+    #[live] pub attribution_text: String,
+    #[live] pub attribution_url: String,
+    #[rust] attribution_rect: Option<Rect>,
+    // Attribution required by a custom tile source's terms, see `set_required_attribution`
+    #[rust] custom_attribution: Option<ProviderAttribution>,
+    #[rust] reported_attribution: Option<ProviderAttribution>,
+
+    // Control under the finger that went down on one, it takes the gesture instead of the map
+    #[rust] pressed_control: Option<MapControl>,
+
+    // Tile cache statistics overlay (top-left), for tuning cache sizes
+    #[live] draw_stats_text: DrawText,
+    #[live(false)] pub show_tile_stats: bool,
+
+    // Swipe comparison: only the part left of swipe_position (fraction of the
+    // width) is drawn, so a map stacked below shows through on the right
+    #[live] draw_swipe_divider: DrawColor,
+    #[live(1.0)] pub swipe_position: f64,
+    #[live(false)] pub show_swipe_divider: bool,
+    #[rust] dragging_divider: bool,
+
+    // UTM/MGRS grid overlay
+    #[live] draw_grid_line: DrawMapLine,
+    #[live] draw_grid_label: DrawText,
+    #[live(false)] pub show_utm_grid: bool,
+
+    // Markers
+    #[live] draw_marker: DrawMarker,
+    #[live] draw_marker_label: DrawText,
+    #[live] draw_marker_label_bg: DrawColor,
+    #[live(32.0)] pub marker_size: f64,
+    #[live] pub marker_label_align: LabelAlign,
+    /// Size of the selected marker relative to `marker_size`
+    #[live(1.3)] pub selected_marker_scale: f64,
+    /// Whether tapping a marker selects it and tapping the map deselects it
+    #[live(false)] pub tap_selects_marker: bool,
+    #[rust] markers: Vec<MapMarker>,
+    #[rust] selected_marker: Option<LiveId>,
+    // Markers by position, rebuilt on the next draw after they change
+    #[rust] marker_index: Option<SpatialIndex>,
+
+    // Polylines (routes, flight paths), drawn below the markers
+    #[live] draw_polyline: DrawMapLine,
+    #[rust] polylines: Vec<MapPolyline>,
+    /// Largest distance in pixels polylines may stray from their points when
+    /// simplified for drawing (Douglas–Peucker), 0 to draw every point
+    #[live(1.0)] pub polyline_tolerance: f64,
+    // Simplified outlines by polyline, dropped when a line changes
+    #[rust] simplified_polylines: HashMap<LiveId, SimplifiedPolyline>,
+
+    // Handles of editable polylines and the vertex being dragged (line, index)
+    #[live] draw_edit_handle: DrawMapCircle,
+    #[live] draw_edit_midpoint: DrawMapCircle,
+    #[rust] edit_drag: Option<(usize, usize)>,
+
+    // Navigation camera following a moving position, and its position puck
+    #[live] draw_navigation_puck: DrawMapCircle,
+    #[live] draw_navigation_arrow: DrawMapTriangle,
+    #[rust] navigation: Option<NavigationCamera>,
+
+    // What taps and drags do: pan as usual, select an area, draw a polygon or measure
+    #[live] pub interaction_mode: InteractionMode,
+    #[live] draw_sketch_line: DrawMapLine,
+    #[live] draw_sketch_point: DrawMapCircle,
+    #[live] draw_selection: DrawColor,
+    #[rust] sketch: Vec<(f64, f64)>,
+    #[rust] selection: Option<(DVec2, DVec2)>,  // Select/draw drag start and end relative to the viewport
+    #[live] draw_measure_line: DrawMapLine,
+    #[live] draw_measure_point: DrawMapCircle,
+    #[live] draw_measure_label: DrawText,
+    #[rust] measurement: Measurement,
+
+    // Marker popup (info window)
+    #[live] draw_popup_bg: DrawColor,
+    #[live] draw_popup_text: DrawText,
+    #[rust] open_popup: Option<LiveId>,
+
+    // Tooltip of the polyline or layer shape under the pointer, at its
+    // position relative to the viewport
+    #[live] draw_tooltip_bg: DrawColor,
+    #[live] draw_tooltip_text: DrawText,
+    #[rust] hovered_overlay: Option<(OverlayHit, DVec2)>,
+
+    // GIS tools: the position under the mouse (or at the crosshair) in a corner,
+    // a crosshair at the map center, and throttled CursorMoved actions
+    #[live(false)] pub show_cursor_readout: bool,
+    /// Format of the readout and `format_position`
+    #[live] pub coordinate_format: CoordinateFormat,
+    #[live] pub cursor_readout_corner: MapCorner,
+    #[live(false)] pub show_crosshair: bool,
+    #[live] draw_crosshair: DrawMapLine,
+    #[live(false)] pub track_cursor: bool,
+    #[live(0.1)] pub cursor_moved_interval: f64,
+    // Mouse position relative to the viewport while it hovers the map
+    #[rust] cursor_pos: Option<DVec2>,
+    #[rust] cursor_timer: Timer,
+    #[rust] cursor_moved_pending: bool,
+
+    // Built-in context menu shown on right-click when the app set items: the
+    // absolute position and (lng, lat) it was opened at, and its absolute rect
+    // and item rows from the last draw for hit testing
+    #[live] draw_context_menu_bg: DrawColor,
+    #[live] draw_context_menu_text: DrawText,
+    #[rust] context_menu_items: Vec<(LiveId, String)>,
+    #[rust] context_menu: Option<(DVec2, f64, f64)>,
+    #[rust] context_menu_rect: Option<Rect>,
+    #[rust] context_menu_rows: Vec<(Rect, LiveId)>,
+
+    // Long press drops a temporary pin (replacing the previous one)
+    #[live(false)] pub long_press_adds_marker: bool,
+    #[rust] dropped_pin: Option<LiveId>,
+
+    // Long press looks up the address there (with the geocoder)
+    #[live(false)] pub long_press_reverse_geocode: bool,
+    #[rust] long_press_lookup: Option<LiveId>,
+
+    // Map state (default: San Francisco at zoom 12)
+    #[live(-122.4194)] pub center_lng: f64,
+    #[live(37.7749)] pub center_lat: f64,
+    #[live(12.0)] pub zoom: f64,
+    #[live(0.0)] pub bearing: f64,  // Degrees clockwise from north, the direction facing up
+    #[live(0.0)] pub pitch: f64,  // Degrees the map plane is tilted back, 0 (top-down) to MAX_PITCH
+
+    // Zoom constraints
+    #[live(1.0)] pub min_zoom: f64,
+    #[live(19.0)] pub max_zoom: f64,
+    // Zoom step gestures settle on (e.g. 0.25 or 1.0), 0 to leave the zoom where the gesture ended
+    #[live(0.0)] pub zoom_snap: f64,
+
+    // Internal state
+    #[rust] drag_start: Option<DVec2>,
+    #[rust] drag_start_center: Option<(f64, f64)>,
+    #[rust] last_abs: DVec2,
+    #[rust] viewport_size: DVec2,
+    #[rust] viewport_pos: DVec2,  // Top-left position of viewport in absolute coords
+    // Viewport insets covered by UI, the center is the center of the remaining area
+    #[rust] padding: Padding,
+
+    // Pinch zoom state
+    #[rust] initial_pinch_distance: Option<f64>,
+    #[rust] pinch_zoom_start: Option<f64>,
+    #[rust] pinch_anchor: Option<(f64, f64)>,  // Geographic point under the initial pinch midpoint
+    #[rust] pinch_start_angle: Option<f64>,  // Angle between the touches at pinch start (degrees)
+    #[rust] pinch_bearing_start: Option<f64>,
+    #[rust] is_rotating: bool,
+    // Whether MinZoomReached/MaxZoomReached was reported for the current push against the limit
+    #[rust] zoom_limit_reported: bool,
+
+    // Momentum scrolling state
+    #[rust] velocity_samples: Vec<(DVec2, f64)>,  // (position, time in seconds)
+    #[rust] flick_velocity: DVec2,  // Pixels per second
+    #[rust] next_frame: NextFrame,
+    #[rust] is_flicking: bool,
+    #[rust] last_momentum_time: Option<f64>,
+
+    // Camera animation (double-tap zoom, fly_to)
+    #[rust] camera_controller: CameraController,
+    // Press-and-hold zoom: +1 zooming in, -1 out, 0 idle
+    #[rust] zoom_direction: f64,
+    #[rust] last_zoom_time: Option<f64>,
+    #[rust] zoom_hold_start: f64,
+    // Markers moving along paths
+    #[rust] marker_animations: Vec<MarkerPathAnimation>,
+
+    // Gestures: turn off individual interactions, e.g. for a static preview
+    // inside a scrolling list. zoom_enabled turns off all zoom gestures.
+    #[live(true)] pub pan_enabled: bool,
+    #[live(true)] pub zoom_enabled: bool,
+    #[live(true)] pub scroll_wheel_zoom: bool,
+    #[live(true)] pub double_tap_zoom: bool,
+    #[live(true)] pub pinch_enabled: bool,
+
+    // Layer control: base maps as radio buttons and overlay layers as
+    // checkboxes, rows (absolute rects) kept from the last draw for hit testing
+    #[live(false)] pub show_layer_control: bool,
+    #[live] pub layer_control_corner: MapCorner,
+    #[live] draw_layer_control_bg: DrawColor,
+    #[live] draw_layer_control_text: DrawText,
+    #[live] draw_layer_control_box: DrawColor,
+    #[live] draw_layer_control_check: DrawColor,
+    #[live] draw_layer_control_radio: DrawMapCircle,
+    #[live] draw_layer_control_dot: DrawMapCircle,
+    #[rust] base_layers: Vec<BaseLayer>,
+    #[rust] active_base_layer: Option<LiveId>,
+    #[rust] layer_titles: HashMap<LiveId, String>,
+    #[rust] layer_control_rect: Option<Rect>,
+    #[rust] layer_control_rows: Vec<(Rect, LayerControlEntry)>,
+
+    // Compass shown while the map is rotated, tapping it turns back to north
+    #[live(true)] pub show_compass: bool,
+    #[live] pub compass_corner: MapCorner,
+    #[live] draw_compass_bg: DrawMapCircle,
+    #[live] draw_compass_north: DrawMapTriangle,
+    #[live] draw_compass_south: DrawMapTriangle,
+    #[rust] compass_rect: Option<Rect>,
+
+    // Cooperative gestures for maps inside scrolling pages: one-finger drags
+    // and plain wheel scrolls are left to the page, showing a hint instead
+    #[live(false)] pub cooperative_gestures: bool,
+    #[live] pub cooperative_touch_hint: String,
+    #[live] pub cooperative_wheel_hint: String,
+    #[live] draw_hint_bg: DrawColor,
+    #[live] draw_hint_text: DrawText,
+    #[rust] gesture_hint: Option<GestureHint>,
+    #[rust] gesture_hint_timer: Timer,
+    #[rust] drag_blocked: bool,
+
+    // Momentum tunable parameters
+    // momentum_enabled: glide after a pan gesture (false = "snappy" mode)
+    // momentum_decay: fraction of velocity kept per 1/60s, scaled by real frame time
+    // momentum_threshold: minimum release velocity (pixels per 1/60s) to start a glide
+    #[live(true)] pub momentum_enabled: bool,
+    #[live(0.95)] pub momentum_decay: f64,
+    #[live(0.5)] pub momentum_threshold: f64,
+    // Zoom levels per second while a zoom button is held (start_zoom_in/out)
+    #[live(1.5)] pub zoom_rate: f64,
+
+    // Tile loading: caps of the request budget per frame and in flight, which
+    // adapts to the viewport's tiles and the response latency below them
+    #[live(16)] pub tile_request_budget: usize,
+    #[live(32)] pub max_pending_tiles: usize,
+    #[live] pub tile_filter: TileFilter,
+    #[live(128.0)] pub tile_memory_mb: f64,
+    // Rings of tiles loaded beyond the viewport edges (0-3), none in low power mode
+    #[live(1)] pub overscan_tiles: usize,
+    #[rust] tile_cache: TileCache,
+
+    // Disk cache: empty disk_cache_dir uses the platform cache directory,
+    // tiles older than tile_ttl_hours are revalidated with conditional requests
+    #[live(true)] pub disk_cache_enabled: bool,
+    #[live(50.0)] pub disk_cache_mb: f64,
+    #[live] pub disk_cache_dir: String,
+    #[live(168.0)] pub tile_ttl_hours: f64,
+    // Seconds between refetches of the visible tiles (0 never), for imagery
+    // that changes such as weather radar; base layers have their own
+    #[live(0.0)] pub tile_refresh_interval: f64,
+    #[rust] tile_refresh_timer: Timer,
+
+    // Adaptive quality: lower rendering quality while animation frames are slower than target_fps
+    #[live(false)] pub adaptive_quality: bool,
+    #[live(60.0)] pub target_fps: f64,
+    #[rust] quality: QualityGovernor,
+
+    // Low power: no momentum, animation frames and gesture redraws at most
+    // every LOW_POWER_FRAME_TIME, waiting on timers instead of every frame
+    #[live(false)] pub low_power: bool,
+    #[rust] frame_timer: Timer,
+    #[rust] gesture_redraw_timer: Timer,
+    #[rust] last_gesture_redraw: f64,
+
+    // Tile request guardrails (back off on repeated requests or client errors)
+    #[live(5)] pub max_tile_repeats_per_minute: usize,
+    #[live(20)] pub max_tile_client_errors_per_minute: usize,
+    #[rust] backoff_timer: Timer,
+    // Seconds without a response before a tile request is retried (0 waits forever)
+    #[live(30.0)] pub tile_timeout: f64,
+    #[rust] tile_timeout_timer: Timer,
+    // Tiles of the last request pass and the cache's request generation then,
+    // requests are only issued again when either changes
+    #[rust] requested_tiles: Option<(Vec<TileCoord>, u64)>,
+    // Tiles are drawn into their own draw list, reused while markers and
+    // overlays redraw on top until the camera or the loaded tiles change
+    #[rust] tile_draw_list: Option<DrawList2d>,
+    #[rust] tile_pass: Option<TilePassKey>,
+
+    // Offline download queue progress last reported to the app
+    #[rust] last_download_progress: DownloadProgress,
+
+    // Tile loading activity: finished load count when loading started and
+    // the last reported (pending, total), None while idle
+    #[rust] tile_loading: Option<(u64, usize, usize)>,
+    #[rust] tiles_idle_reported: bool,
+
+    // Third-party coordinate/addressing system (readout and tap lookup)
+    #[rust] coordinate_provider: Option<Box<dyn CoordinateProvider>>,
+
+    // Search and reverse lookup client (Nominatim once first used, if not set)
+    #[rust] geocoder: Option<Geocoder>,
+    // Search started by search_and_fly_to, flown to when it answers
+    #[rust] fly_to_search: Option<LiveId>,
+    /// `set_camera` moved the camera; reported with the next draw
+    #[rust] region_change_pending: bool,
+
+    // Routing client (the OSRM demo server once first used, if not set),
+    // the pending request and the route shown
+    #[rust] router: Option<Router>,
+    #[rust] route_request: Option<LiveId>,
+    #[rust] route: Option<Route>,
+    #[rust] route_style: RouteStyle,
+
+    // Projection of the tile pyramid (Web Mercator if None)
+    #[rust] projection: Option<Rc<dyn Projection>>,
+
+    // Overlay layers declared as children in live_design or added from Rust
+    #[rust] layers: Vec<(LiveId, Box<dyn MapLayer>)>,
+
+    // Draw order of built-in and overlay layers (bottom first, empty until changed),
+    // hidden built-in layers and layer opacities
+    #[rust] layer_order: Vec<LayerId>,
+    #[rust] hidden_layers: HashSet<LayerId>,
+    #[rust] layer_opacity: HashMap<LayerId, f64>,
+    // Zoom ranges layers are shown in, (min, max) inclusive
+    #[rust] layer_zoom_ranges: HashMap<LayerId, (f64, f64)>,
+
+    // External timeline: camera tour and the last seeked time
+    #[rust] tour: Option<CameraTour>,
+    #[rust] timeline_time: Option<f64>,
+    // Built-in playback clock driving `seek`
+    #[rust] timeline: Option<Timeline>,
+}
+
+impl LiveHook for GeoMapView {
+    fn apply_value_instance(&mut self, cx: &mut Cx, apply: &mut Apply, index: usize, nodes: &[LiveNode]) -> usize {
+        let id = nodes[index].id;
+        if let LiveValue::Class { live_type, .. } = nodes[index].value {
+            if let Some((_, layer)) = self.layers.iter_mut().find(|(layer_id, _)| *layer_id == id) {
+                return layer.apply(cx, apply, index, nodes);
+            }
+            if let Some(mut layer) = new_map_layer(cx, live_type) {
+                let next = layer.apply(cx, apply, index, nodes);
+                self.insert_layer(id, layer);
+                return next;
+            }
+        }
+        nodes.skip_node(index)
+    }
+
+    fn after_apply(&mut self, _cx: &mut Cx, _apply: &mut Apply, _index: usize, _nodes: &[LiveNode]) {
+        // The tile shader's properties may have changed
+        self.tile_pass = None;
+        self.tile_cache.set_disk_cache_config(DiskCacheConfig {
+            dir: (!self.disk_cache_dir.is_empty()).then(|| self.disk_cache_dir.clone().into()),
+            max_bytes: (self.disk_cache_mb.max(0.0) * 1024.0 * 1024.0) as u64,
+            enabled: self.disk_cache_enabled,
+            ttl: std::time::Duration::from_secs_f64(self.tile_ttl_hours.max(0.0) * 3600.0),
+        });
+    }
+}
+
+impl Widget for GeoMapView {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        let uid = self.widget_uid();
+
+        // Let overlay layers handle events (e.g. their own network responses)
+        if !self.layers.is_empty() {
+            let viewport = self.map_viewport(Rect { pos: self.viewport_pos, size: self.viewport_size });
+            let mut redraw = false;
+            for (id, layer) in &mut self.layers {
+                redraw |= layer.handle_layer_event(cx, event, &viewport);
+                for layer_event in layer.take_layer_events() {
+                    let action = match layer_event {
+                        MapLayerEvent::GeometryRepaired { warnings } => GeoMapViewAction::GeometryRepaired { layer: *id, warnings },
+                        MapLayerEvent::GeoJsonFailed { url, error } => GeoMapViewAction::GeoJsonFailed { layer: *id, url, error },
+                    };
+                    cx.widget_action(uid, &scope.path, action);
+                }
+            }
+            if redraw {
+                self.draw_tile.redraw(cx);
+            }
+        }
+
+        // Handle HTTP responses for tile loading
+        if let Event::NetworkResponses(responses) = event {
+            let pending = self.tile_cache.pending_request_count();
+            for response in responses {
+                match &response.response {
+                    NetworkResponse::HttpResponse(http_response) => {
+                        if self.tile_cache.handle_response(cx, response.request_id, http_response) {
+                            // Tile loaded successfully, redraw
+                            self.draw_tile.redraw(cx);
+                        }
+                        if let Some(provider) = &mut self.coordinate_provider {
+                            if let Some(lookup) = provider.handle_response(cx, response.request_id, http_response) {
+                                Self::emit_coordinate_lookup(cx, uid, &scope.path, lookup);
+                            }
+                        }
+                        if let Some(geocoded) = self.geocoder.as_mut().and_then(|g| g.handle_response(response.request_id, http_response)) {
+                            self.handle_geocode_response(cx, uid, &scope.path, geocoded);
+                        }
+                        if let Some(routed) = self.router.as_mut().and_then(|r| r.handle_response(response.request_id, http_response)) {
+                            self.handle_route_response(cx, uid, &scope.path, routed);
+                        }
+                    }
+                    NetworkResponse::HttpRequestError(error) => {
+                        self.tile_cache.handle_error(response.request_id, error);
+                        if let Some(geocoded) = self.geocoder.as_mut().and_then(|g| g.handle_error(response.request_id, error)) {
+                            self.handle_geocode_response(cx, uid, &scope.path, geocoded);
+                        }
+                        if let Some(routed) = self.router.as_mut().and_then(|r| r.handle_error(response.request_id, error)) {
+                            self.handle_route_response(cx, uid, &scope.path, routed);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            // A freed slot lets the tiles held back by the limit in flight go out
+            if self.requested_tiles.is_none() && self.tile_cache.pending_request_count() < pending {
+                self.draw_tile.redraw(cx);
+            }
+            self.tile_cache.resume_downloads(cx);
+            self.emit_tile_request_warnings(cx, uid, &scope.path);
+            self.emit_download_progress(cx, uid, &scope.path);
+            self.emit_tile_loading(cx, uid, &scope.path);
+        }
+
+        // Tiles read from the disk cache on the I/O thread
+        if let Event::Signal = event {
+            if self.tile_cache.handle_disk_results(cx) {
+                self.draw_tile.redraw(cx);
+            }
+            // Queued downloads found on disk complete here
+            self.emit_download_progress(cx, uid, &scope.path);
+        }
+
+        // Report the latest cursor position held back by the throttle
+        if self.cursor_timer.is_event(event).is_some() {
+            self.cursor_timer = Timer::empty();
+            if std::mem::take(&mut self.cursor_moved_pending) {
+                self.emit_cursor_moved(cx, uid, &scope.path);
+            }
+        }
+
+        if self.gesture_hint_timer.is_event(event).is_some() {
+            self.gesture_hint_timer = Timer::empty();
+            self.gesture_hint = None;
+            self.draw_tile.redraw(cx);
+        }
+
+        // Resume tile requests after a guardrail backoff
+        if self.backoff_timer.is_event(event).is_some() {
+            self.backoff_timer = Timer::empty();
+            self.requested_tiles = None;
+            self.tile_cache.resume_downloads(cx);
+            self.emit_download_progress(cx, uid, &scope.path);
+            self.draw_tile.redraw(cx);
+        }
+
+        // Low power mode waits out the frame time, then asks for a single frame
+        if self.frame_timer.is_event(event).is_some() {
+            self.frame_timer = Timer::empty();
+            self.next_frame = cx.new_next_frame();
+        }
+        if self.gesture_redraw_timer.is_event(event).is_some() {
+            self.gesture_redraw_timer = Timer::empty();
+            self.draw_tile.redraw(cx);
+        }
+
+        // Retry tile requests the network never answered
+        if self.tile_timeout_timer.is_event(event).is_some() {
+            self.tile_timeout_timer = Timer::empty();
+            for coord in self.tile_cache.expire_requests() {
+                cx.widget_action(uid, &scope.path, GeoMapViewAction::TileTimeout { coord, timeout: self.tile_timeout });
+            }
+            self.requested_tiles = None;
+            self.tile_cache.resume_downloads(cx);
+            self.draw_tile.redraw(cx);
+        }
+
+        // Refetch visible tiles that got older than the refresh interval on the next draw
+        if self.tile_refresh_timer.is_event(event).is_some() {
+            self.tile_refresh_timer = Timer::empty();
+            self.requested_tiles = None;
+            self.draw_tile.redraw(cx);
+        }
+
+        // Handle momentum and zoom animation frames
+        if let Some(ne) = self.next_frame.is_event(event) {
+            if self.adaptive_quality {
+                self.quality.set_target_fps(self.target_fps);
+                if let Some(level) = self.quality.record_frame(ne.time) {
+                    cx.widget_action(uid, &scope.path, GeoMapViewAction::QualityChanged { level });
+                    self.draw_tile.redraw(cx);
+                }
+            }
+            if self.is_flicking {
+                self.apply_momentum(cx, ne.time, uid, &scope.path);
+            }
+            if self.zoom_direction != 0.0 {
+                self.apply_continuous_zoom(cx, ne.time, uid, &scope.path);
+            }
+            if self.camera_controller.is_active() {
+                self.apply_camera_animation(cx, ne.time, uid, &scope.path);
+            }
+            if self.navigation.is_some() {
+                self.apply_navigation(cx, ne.time);
+            }
+            if !self.marker_animations.is_empty() {
+                self.apply_marker_animations(cx, ne.time, uid, &scope.path);
+            }
+            if self.timeline.as_ref().is_some_and(|timeline| timeline.is_playing()) {
+                self.apply_timeline(cx, ne.time, uid, &scope.path);
+            }
+            if self.tile_cache.needs_next_frame() {
+                // Spend the next frame's budget on the tiles skipped last frame
+                self.draw_tile.redraw(cx);
+            }
+        }
+
+        // Handle touch events for pinch zoom and rotate
+        if let Event::TouchUpdate(te) = event {
+            // Check if we have multiple touches for pinch zoom
+            if te.touches.len() >= 2 && self.pinch_enabled {
+                // Calculate distance, angle and midpoint of the first two touches
+                let t0 = &te.touches[0];
+                let t1 = &te.touches[1];
+                let dx = t1.abs.x - t0.abs.x;
+                let dy = t1.abs.y - t0.abs.y;
+                let distance = (dx * dx + dy * dy).sqrt();
+                let angle = dy.atan2(dx).to_degrees();
+                let midpoint = (t0.abs + t1.abs) / 2.0 - self.viewport_pos;
+
+                if let (Some(initial_distance), Some(start_zoom), Some((anchor_lng, anchor_lat))) =
+                    (self.initial_pinch_distance, self.pinch_zoom_start, self.pinch_anchor)
+                {
+                    // Calculate zoom change based on pinch ratio from initial
+                    let scale = distance / initial_distance;
+                    // Use log scale for more natural zoom feel
+                    let zoom_delta = scale.ln() / std::f64::consts::LN_2;
+                    if self.zoom_enabled {
+                        self.zoom = self.clamp_user_zoom(cx, uid, &scope.path, start_zoom + zoom_delta);
+                    }
+
+                    // Rotate once the fingers turned past the threshold; turning the
+                    // fingers clockwise turns the map clockwise (bearing decreases)
+                    if let (Some(start_angle), Some(start_bearing)) = (self.pinch_start_angle, self.pinch_bearing_start) {
+                        let rotation = (angle - start_angle + 180.0).rem_euclid(360.0) - 180.0;
+                        if rotation.abs() > ROTATE_THRESHOLD_DEG {
+                            self.is_rotating = true;
+                        }
+                        if self.is_rotating {
+                            self.bearing = normalize_bearing(start_bearing - rotation);
+                        }
+                    }
+
+                    // Keep the point under the initial midpoint under the current
+                    // midpoint, so moving both fingers also pans the map
+                    self.center_on_anchor(anchor_lng, anchor_lat, midpoint);
+                    self.redraw_gesture(cx, te.time);
+                } else if distance > 0.0 {
+                    // Start of pinch - store initial state
+                    self.initial_pinch_distance = Some(distance);
+                    self.pinch_zoom_start = Some(self.zoom);
+                    self.pinch_anchor = Some(self.screen_to_geo(midpoint));
+                    self.pinch_start_angle = Some(angle);
+                    self.pinch_bearing_start = Some(self.bearing);
+                    self.is_rotating = false;
+                    self.stop_animations(cx);
+                    self.hide_gesture_hint(cx);
+                }
+
+                // Clear single-finger drag state during pinch
+                self.drag_start = None;
+                self.drag_start_center = None;
+                self.selection = None;
+                self.edit_drag = None;
+            }
+        }
+
+        match event.hits(cx, self.draw_tile.area()) {
+            // On-map controls take the whole gesture, a tap activates the control
+            // it went down on and released over
+            Hit::FingerHoverOver(fe) if self.control_at(fe.abs).is_some() => {
+                cx.set_cursor(MouseCursor::Hand);
+            }
+            Hit::FingerDown(fe) if fe.is_primary_hit() && self.control_at(fe.abs).is_some() => {
+                self.pressed_control = self.control_at(fe.abs);
+            }
+            Hit::FingerMove(_) | Hit::FingerLongPress(_) if self.pressed_control.is_some() => {}
+            Hit::FingerUp(fe) if self.pressed_control.is_some() => {
+                let control = self.pressed_control.take();
+                if fe.is_over && self.control_at(fe.abs) == control {
+                    if let Some(control) = control {
+                        self.activate_control(cx, uid, &scope.path, control);
+                    }
+                }
+            }
+            // Right-click asks the app for a context menu at the clicked position
+            Hit::FingerDown(fe) if fe.mouse_button().is_some_and(|button| button.is_secondary()) => {
+                let (lng, lat) = self.screen_to_geo(fe.abs - self.viewport_pos);
+                let lng = wrap_lng(lng);
+                cx.widget_action(uid, &scope.path, GeoMapViewAction::ContextRequested { lng, lat, abs: fe.abs });
+                if !self.context_menu_items.is_empty() {
+                    self.context_menu = Some((fe.abs, lng, lat));
+                    self.draw_tile.redraw(cx);
+                }
+            }
+            // Dragging the swipe divider takes precedence over panning
+            Hit::FingerDown(fe) if fe.is_primary_hit() && self.hits_swipe_divider(fe.abs) => {
+                self.dragging_divider = true;
+            }
+            Hit::FingerMove(fe) if self.dragging_divider => {
+                if self.viewport_size.x > 0.0 {
+                    self.swipe_position = ((fe.abs.x - self.viewport_pos.x) / self.viewport_size.x).clamp(0.0, 1.0);
+                    self.draw_tile.redraw(cx);
+                    cx.widget_action(uid, &scope.path, GeoMapViewAction::SwipeMoved { position: self.swipe_position });
+                }
+            }
+            Hit::FingerUp(_) if self.dragging_divider => {
+                self.dragging_divider = false;
+            }
+            Hit::FingerDown(fe) if fe.is_primary_hit() => {
+                cx.set_key_focus(self.draw_tile.area());
+                self.close_context_menu(cx);
+                if fe.device.is_touch() {
+                    self.clear_hovered_overlay(cx);
+                }
                 self.drag_start = Some(fe.abs);
+                self.drag_blocked = false;
+                self.zoom_limit_reported = false;
+                if let Some(handle) = self.edit_handle_at(fe.abs - self.viewport_pos) {
+                    // Dragging a handle moves the vertex instead of panning,
+                    // a midpoint handle first inserts a vertex there
+                    if handle.midpoint {
+                        self.polylines[handle.line].points.insert(handle.index, (handle.lng, handle.lat));
+                    }
+                    self.edit_drag = Some((handle.line, handle.index));
+                    self.drag_blocked = true;
+                    self.draw_tile.redraw(cx);
+                } else if matches!(
+                    self.interaction_mode,
+                    InteractionMode::Select | InteractionMode::DrawRectangle | InteractionMode::DrawCircle
+                ) {
+                    // Dragging selects or draws instead of panning
+                    let pos = fe.abs - self.viewport_pos;
+                    self.selection = Some((pos, pos));
+                    self.drag_blocked = true;
+                }
                 self.drag_start_center = Some((self.center_lng, self.center_lat));
                 self.last_abs = fe.abs;
 
-                // Stop any ongoing flick and start collecting velocity samples
-                self.is_flicking = false;
-                self.velocity_samples.clear();
-                self.velocity_samples.push((fe.abs, fe.time));
-            }
-            Hit::FingerMove(fe) => {
-                // Only handle panning if not pinching
-                if self.initial_pinch_distance.is_none() {
-                    if let (Some(start), Some((start_lng, start_lat))) = (self.drag_start, self.drag_start_center) {
-                        let delta = fe.abs - start;
-                        let (deg_per_px_x, deg_per_px_y) = self.degrees_per_pixel();
+                // User touches always interrupt momentum and camera animations;
+                // report where the interrupted animation left the camera
+                if self.stop_animations(cx) {
+                    self.emit_region_changed(cx, uid, &scope.path);
+                }
+
+                // Start collecting velocity samples
+                self.velocity_samples.clear();
+                self.velocity_samples.push((fe.abs, fe.time));
+            }
+            Hit::FingerMove(fe) if self.edit_drag.is_some() => {
+                if let Some((line, index)) = self.edit_drag {
+                    let (lng, lat) = self.screen_to_geo(fe.abs - self.viewport_pos);
+                    if let Some(point) = self.polylines.get_mut(line).and_then(|line| line.points.get_mut(index)) {
+                        *point = (wrap_lng(lng), lat);
+                    }
+                    self.draw_tile.redraw(cx);
+                }
+            }
+            Hit::FingerMove(fe) if self.selection.is_some() => {
+                if let Some((_, end)) = &mut self.selection {
+                    *end = fe.abs - self.viewport_pos;
+                    self.draw_tile.redraw(cx);
+                }
+            }
+            Hit::FingerMove(fe) => {
+                // Only handle panning if not pinching
+                if self.initial_pinch_distance.is_none() && self.pan_enabled {
+                    // Cooperative mode pans with two fingers (the pinch), one finger scrolls the page
+                    if self.cooperative_gestures && fe.device.is_touch() {
+                        if let Some(start) = self.drag_start {
+                            if !self.drag_blocked && (fe.abs - start).length() >= 10.0 {
+                                self.drag_blocked = true;
+                                self.show_gesture_hint(cx, GestureHint::TwoFingers);
+                            }
+                        }
+                    } else if let (Some(start), Some((start_lng, start_lat))) = (self.drag_start, self.drag_start_center) {
+                        let delta = self.screen_delta_to_world(start - self.viewport_pos, fe.abs - self.viewport_pos);
+                        self.pan_from((start_lng, start_lat), delta);
+                        for (_, layer) in &mut self.layers {
+                            layer.stop_following();
+                        }
+                        if let Some(navigation) = self.navigation.as_mut().filter(|navigation| navigation.following) {
+                            navigation.following = false;
+                            cx.widget_action(uid, &scope.path, GeoMapViewAction::NavigationPaused);
+                        }
+
+                        self.last_abs = fe.abs;
+                        self.redraw_gesture(cx, fe.time);
+
+                        // Add velocity sample, keeping only the recent ones
+                        self.velocity_samples.push((fe.abs, fe.time));
+                        self.velocity_samples.retain(|(_, time)| fe.time - time <= VELOCITY_WINDOW);
+                    }
+                }
+            }
+            Hit::FingerUp(fe) if fe.is_primary_hit() => {
+                let was_pinching = self.initial_pinch_distance.is_some();
+                self.initial_pinch_distance = None;
+                self.pinch_zoom_start = None;
+                let pinch_anchor = self.pinch_anchor.take();
+                self.pinch_start_angle = None;
+                self.pinch_bearing_start = None;
+
+                // Finish a rotate gesture, snapping back to north when close to it
+                if std::mem::take(&mut self.is_rotating) {
+                    let from_north = (self.bearing + 180.0).rem_euclid(360.0) - 180.0;
+                    if from_north.abs() < NORTH_SNAP_DEG {
+                        self.bearing = 0.0;
+                        self.draw_tile.redraw(cx);
+                    }
+                    cx.widget_action(uid, &scope.path, GeoMapViewAction::BearingChanged { bearing: self.bearing });
+                }
+
+                if let Some((line, _)) = self.edit_drag.take() {
+                    if let Some(line) = self.polylines.get(line) {
+                        cx.widget_action(uid, &scope.path, GeoMapViewAction::OverlayEdited {
+                            id: line.id,
+                            points: line.points.clone(),
+                        });
+                    }
+                    self.drag_start = None;
+                    self.drag_start_center = None;
+                    self.drag_blocked = false;
+                    self.velocity_samples.clear();
+                    return;
+                }
+
+                if let Some((start, end)) = self.selection.take() {
+                    self.draw_tile.redraw(cx);
+                    let size = end - start;
+                    let large_enough = if self.interaction_mode == InteractionMode::DrawCircle {
+                        size.length() >= SELECT_MIN_SIZE
+                    } else {
+                        size.x.abs() >= SELECT_MIN_SIZE && size.y.abs() >= SELECT_MIN_SIZE
+                    };
+                    if large_enough {
+                        self.finish_drag_shape(cx, uid, &scope.path, start, end);
+                        self.drag_start = None;
+                        self.drag_start_center = None;
+                        self.drag_blocked = false;
+                        self.velocity_samples.clear();
+                        return;
+                    }
+                }
+
+                // Check if this was a tap (minimal movement from start)
+                let is_tap = if let Some(start) = self.drag_start {
+                    let dist = (fe.abs - start).length();
+                    dist < 10.0  // Less than 10px movement = tap
+                } else {
+                    false
+                };
+
+                if fe.is_over && is_tap && self.interaction_mode == InteractionMode::Measure {
+                    self.add_measure_point(fe.abs - self.viewport_pos);
+                    self.draw_tile.redraw(cx);
+                    cx.widget_action(uid, &scope.path, GeoMapViewAction::MeasurementChanged {
+                        distance: self.measurement.distance(),
+                        area: self.measurement.area(),
+                    });
+                } else if fe.is_over && is_tap && self.interaction_mode == InteractionMode::DrawPolygon {
+                    if self.add_sketch_point(fe.abs - self.viewport_pos) {
+                        let points = std::mem::take(&mut self.sketch);
+                        cx.widget_action(uid, &scope.path, GeoMapViewAction::PolygonDrawn { points });
+                    }
+                    self.draw_tile.redraw(cx);
+                } else if fe.is_over && is_tap && fe.tap_count == 2 && self.zoom_enabled && self.double_tap_zoom {
+                    // Double tap: zoom in one level, keeping the tapped point under the finger
+                    let target_zoom = self.clamp_user_zoom(cx, uid, &scope.path, self.snap_zoom(self.zoom + 1.0, 0.0));
+                    self.start_zoom_animation(cx, target_zoom, fe.abs - self.viewport_pos);
+                } else if fe.is_over && is_tap {
+                    // Touch screens have no hover, a tap shows the overlay's tooltip instead
+                    if fe.device.is_touch() {
+                        self.update_hovered_overlay(cx, uid, &scope.path, fe.abs);
+                    }
+
+                    // Check if a marker was tapped
+                    if let Some(marker_id) = self.find_marker_at_screen_pos(fe.abs) {
+                        cx.widget_action(uid, &scope.path, GeoMapViewAction::MarkerTapped { id: marker_id });
+                        if self.tap_selects_marker && self.selected_marker != Some(marker_id) {
+                            if let Some(id) = self.selected_marker.replace(marker_id) {
+                                cx.widget_action(uid, &scope.path, GeoMapViewAction::MarkerDeselected { id });
+                            }
+                            cx.widget_action(uid, &scope.path, GeoMapViewAction::MarkerSelected { id: marker_id });
+                            self.draw_tile.redraw(cx);
+                        }
+                    } else {
+                        let (lng, lat) = self.screen_to_geo(fe.abs - self.viewport_pos);
+                        let lng = wrap_lng(lng);
+                        cx.widget_action(uid, &scope.path, GeoMapViewAction::Tapped { lng, lat });
+
+                        if self.tap_selects_marker {
+                            if let Some(id) = self.selected_marker.take() {
+                                cx.widget_action(uid, &scope.path, GeoMapViewAction::MarkerDeselected { id });
+                                self.draw_tile.redraw(cx);
+                            }
+                        }
+
+                        // Tapping the map away from markers closes the popup
+                        if self.open_popup.take().is_some() {
+                            self.draw_tile.redraw(cx);
+                        }
+
+                        if let Some(provider) = &mut self.coordinate_provider {
+                            if let Some(lookup) = provider.lookup(cx, lng, lat) {
+                                Self::emit_coordinate_lookup(cx, uid, &scope.path, lookup);
+                            }
+                        }
+                    }
+                }
+
+                // Settle a pinch on the zoom snap, around the point between the fingers
+                let settling = match pinch_anchor {
+                    Some((lng, lat)) if was_pinching => {
+                        let target_zoom = self.snap_zoom(self.zoom, 0.0).clamp(self.effective_min_zoom(), self.max_zoom);
+                        self.start_zoom_animation(cx, target_zoom, self.geo_to_screen(lng, lat));
+                        self.camera_controller.is_active()
+                    }
+                    _ => false,
+                };
+
+                // Start momentum scrolling if above threshold (only for drags, not taps)
+                let drag_blocked = std::mem::take(&mut self.drag_blocked);
+                if self.momentum_enabled && !self.low_power && self.pan_enabled && !is_tap && !was_pinching && !drag_blocked {
+                    let velocity = self.calculate_flick_velocity(fe.abs, fe.time);
+                    if velocity.x.hypot(velocity.y) / REFERENCE_FRAME_RATE > self.momentum_threshold {
+                        self.flick_velocity = velocity;
+                        self.is_flicking = true;
+                        self.last_momentum_time = None;
+                        self.request_frame(cx);
+                    }
+                }
+
+                self.drag_start = None;
+                self.drag_start_center = None;
+                self.velocity_samples.clear();
+                // A settling pinch reports the region when it arrives
+                if !is_tap && !drag_blocked && !settling && (self.pan_enabled || was_pinching) {
+                    self.emit_region_changed(cx, uid, &scope.path);
+                }
+            }
+            Hit::FingerScroll(fe) if self.zoom_enabled && self.scroll_wheel_zoom
+                && self.cooperative_gestures && !(fe.modifiers.control || fe.modifiers.logo) =>
+            {
+                self.show_gesture_hint(cx, GestureHint::ModifierScroll);
+            }
+            Hit::FingerScroll(fe) if self.zoom_enabled && self.scroll_wheel_zoom => {
+                // Handle scroll wheel zoom (desktop), interrupting any animation
+                self.stop_animations(cx);
+                let zoom_delta = if fe.scroll.y > 0.0 { 0.5 } else { -0.5 };
+                let new_zoom = self.clamp_user_zoom(cx, uid, &scope.path, self.snap_zoom(self.zoom + zoom_delta, zoom_delta));
+
+                if new_zoom != self.zoom {
+                    self.zoom = new_zoom;
+                    self.redraw_gesture(cx, fe.time);
+                    self.emit_region_changed(cx, uid, &scope.path);
+                }
+            }
+            Hit::FingerLongPress(fe) => {
+                let (lng, lat) = self.screen_to_geo(fe.abs - self.viewport_pos);
+                let lng = wrap_lng(lng);
+                cx.widget_action(uid, &scope.path, GeoMapViewAction::LongPressed { lng, lat });
+
+                if self.long_press_adds_marker {
+                    let id = self.drop_pin(cx, lng, lat);
+                    cx.widget_action(uid, &scope.path, GeoMapViewAction::PinDropped { id, lng, lat });
+                }
+                // Only the latest long press is answered
+                if self.long_press_reverse_geocode {
+                    self.long_press_lookup = Some(self.reverse_geocode(cx, lng, lat));
+                }
+            }
+            Hit::FingerHoverIn(fe) | Hit::FingerHoverOver(fe) => {
+                self.update_hovered_overlay(cx, uid, &scope.path, fe.abs);
+                self.update_cursor(cx, uid, &scope.path, fe.abs);
+            }
+            Hit::FingerHoverOut(_) => {
+                self.clear_hovered_overlay(cx);
+                if self.cursor_pos.take().is_some() && self.show_cursor_readout {
+                    self.draw_tile.redraw(cx);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        // Begin drawing and get the rect
+        cx.begin_turtle(walk, Layout::default());
+        let rect = cx.turtle().rect();
+        self.viewport_size = rect.size;
+        self.viewport_pos = rect.pos;
+
+        // Clip everything to the left of the swipe divider
+        let swipe_clip = self.swipe_position < 1.0;
+        if swipe_clip {
+            let width = rect.size.x * self.swipe_position.max(0.0);
+            cx.begin_turtle(
+                Walk::size(Size::Fixed(width), Size::Fixed(rect.size.y)),
+                Layout { clip_x: true, clip_y: true, ..Layout::default() },
+            );
+        }
+
+        // Keep the world filling the viewport if requested
+        self.zoom = self.zoom.max(self.effective_min_zoom());
+        self.pitch = self.pitch.clamp(0.0, MAX_PITCH);
+
+        // Center on a followed layer (e.g. a live track), unless the camera is
+        // animating or navigating
+        if !self.is_animating() && !self.is_navigating() {
+            let follow = self.layers.iter()
+                .filter(|(_, layer)| layer.is_visible())
+                .find_map(|(_, layer)| layer.follow_position());
+            if let Some((lng, lat)) = follow {
+                self.center_lng = lng;
+                self.center_lat = self.clamp_lat(lat);
+            }
+        }
+
+        // Background shows wherever there is no map
+        self.draw_background.draw_abs(cx, rect);
+
+        // Tiles are laid out north-up, rotated around the viewport center and tilted in the shader
+        let viewport = self.map_viewport(rect);
+        let grid = TileGrid::new(&viewport, MAX_TILE_ZOOM, self.effective_overscan_tiles());
+        self.draw_tile.set_viewport(&viewport);
+        self.draw_tile.set_filter(self.tile_filter);
+
+        let quality = self.quality_level();
+        if quality >= QualityLevel::ReducedTiles {
+            self.tile_cache.set_quality(TileQuality::Reduced);
+        } else {
+            self.tile_cache.set_quality(TileQuality::Full);
+        }
+        self.tile_cache.set_request_timeout(Some(self.tile_timeout));
+        self.request_tiles(cx.cx.cx, &grid);
+        // Tiles a transport answered right away are shown this frame
+        self.tile_cache.handle_ready_responses(cx.cx.cx);
+        let required = self.required_attribution();
+        if required != self.reported_attribution {
+            self.reported_attribution = required.clone();
+            if let Some(attribution) = required {
+                cx.widget_action(self.widget_uid(), &scope.path, GeoMapViewAction::AttributionRequired { attribution });
+            }
+        }
+        if std::mem::take(&mut self.region_change_pending) {
+            self.emit_region_changed(cx.cx.cx, self.widget_uid(), &scope.path);
+        }
+        self.emit_tile_request_warnings(cx.cx.cx, self.widget_uid(), &scope.path);
+        self.emit_tile_loading(cx.cx.cx, self.widget_uid(), &scope.path);
+        self.schedule_tile_refresh(cx.cx.cx);
+        self.tile_cache.set_memory_budget((self.tile_memory_mb * 1024.0 * 1024.0) as usize);
+        self.tile_cache.trim_memory(cx.cx.cx, grid.zoom);
+
+        // Draw tiles, the UTM grid, polylines, overlay layers and markers in layer order
+        for id in self.layer_order() {
+            if !self.is_layer_shown(id) {
+                continue;
+            }
+            let opacity = self.layer_opacity(id);
+            if id == TILES_LAYER {
+                self.draw_tiles(cx, &viewport, &grid, opacity);
+            } else if id == UTM_GRID_LAYER {
+                if self.show_utm_grid && self.projection().wraps_longitude() {
+                    let (line_color, label_color) = (self.draw_grid_line.color, self.draw_grid_label.color);
+                    self.draw_grid_line.color.w *= opacity as f32;
+                    self.draw_grid_label.color.w *= opacity as f32;
+                    self.draw_utm_grid(cx, rect);
+                    self.draw_grid_line.color = line_color;
+                    self.draw_grid_label.color = label_color;
+                }
+            } else if id == POLYLINES_LAYER {
+                self.draw_polylines(cx, rect, opacity);
+            } else if id == MARKERS_LAYER {
+                self.draw_markers(cx, rect, quality, opacity);
+            } else if let Some((_, layer)) = self.layers.iter_mut().find(|(layer_id, _)| *layer_id == id) {
+                layer.set_quality(quality);
+                layer.draw_layer(cx, &viewport);
+            }
+        }
+
+        if !self.measurement.points.is_empty() {
+            self.draw_measurement(cx, rect);
+        }
+        if !self.sketch.is_empty() || self.selection.is_some() {
+            self.draw_sketch(cx, rect);
+        }
+        if self.polylines.iter().any(|line| line.editable) {
+            for handle in self.edit_handles() {
+                let pos = rect.pos + handle.pos;
+                if handle.midpoint {
+                    self.draw_edit_midpoint.draw_circle(cx, pos, EDIT_MIDPOINT_RADIUS);
+                } else {
+                    self.draw_edit_handle.draw_circle(cx, pos, EDIT_HANDLE_RADIUS);
+                }
+            }
+        }
+
+        if let Some(position) = self.navigation.as_ref().and_then(|navigation| navigation.position()) {
+            self.draw_navigation_position(cx, rect, position);
+        }
+
+        // Draw the open marker popup above its pin
+        let popup_marker = self.open_popup
+            .and_then(|id| self.get_marker(id))
+            .filter(|marker| marker.is_shown_at(self.zoom) && self.is_layer_shown(MARKERS_LAYER));
+        if let Some(marker) = popup_marker {
+            let text = if marker.popup.is_empty() { marker.label.clone() } else { marker.popup.clone() };
+            let screen_pos = self.geo_to_screen(marker.lng, marker.lat);
+            if !text.is_empty() {
+                self.draw_marker_popup(cx, rect.pos + screen_pos, &text);
+            }
+        }
+
+        // Tooltip of the hovered overlay, next to the pointer (hidden while dragging)
+        if let Some((hit, pos)) = self.hovered_overlay.as_ref().filter(|_| self.drag_start.is_none()) {
+            if !hit.tooltip.is_empty() {
+                let (text, pos) = (hit.tooltip.clone(), rect.pos + *pos);
+                self.draw_overlay_tooltip(cx, rect, pos, &text);
+            }
+        }
+
+        if self.show_crosshair {
+            self.draw_crosshair(cx, rect);
+        }
+        if self.show_cursor_readout {
+            self.draw_cursor_readout(cx, rect);
+        }
+
+        // Draw scale bar if enabled
+        self.scale_bar_rect = None;
+        if self.show_scale_bar {
+            let (bar_width, label) = self.calculate_scale_bar(100.0);
+            let margin = 10.0;
+            let bar_height = 4.0;
+            let bar_y = rect.pos.y + rect.size.y - margin - bar_height;
+            let bar_x = rect.pos.x + margin;
+            // Taps on the bar or its label are the scale bar's
+            self.scale_bar_rect = Some(Rect {
+                pos: dvec2(bar_x, bar_y - 14.0),
+                size: dvec2(bar_width, bar_height + 14.0),
+            });
+
+            // Draw the scale bar background (dark line)
+            self.draw_scale_bg.draw_abs(cx, Rect {
+                pos: dvec2(bar_x, bar_y),
+                size: dvec2(bar_width, bar_height),
+            });
+
+            // Draw label above the bar
+            let text_y = bar_y - 14.0; // Position text above the bar
+            self.draw_scale_text.draw_abs(cx, dvec2(bar_x, text_y), &label);
+        }
+
+        // Draw attribution overlay if enabled, or if the tile provider requires it
+        self.attribution_rect = None;
+        if let Some((attribution_text, _)) = self.shown_attribution() {
+            let margin = 10.0;
+            let padding = 4.0;
+
+            let text_size = measure_text(cx, &self.draw_attribution_text, &attribution_text);
+
+            // Position: bottom-right with margin
+            let bg_width = text_size.x + padding * 2.0;
+            let bg_height = text_size.y + padding * 2.0;
+            let bg_x = rect.pos.x + rect.size.x - margin - bg_width;
+            let bg_y = rect.pos.y + rect.size.y - margin - bg_height;
+
+            // Draw semi-transparent white background behind text
+            let bg_rect = Rect {
+                pos: dvec2(bg_x, bg_y),
+                size: dvec2(bg_width, bg_height),
+            };
+            self.draw_attribution_bg.draw_abs(cx, bg_rect);
+            self.attribution_rect = Some(bg_rect);
+
+            // Draw small gray text (positioned inside the background with padding)
+            let text_x = bg_x + padding;
+            let text_y = bg_y + padding;
+            self.draw_attribution_text.draw_abs(cx, dvec2(text_x, text_y), &attribution_text);
+        }
+
+        if self.show_tile_stats {
+            self.draw_tile_stats(cx, rect);
+        }
+
+        if self.show_layer_control {
+            self.draw_layer_control(cx, rect);
+        } else {
+            self.layer_control_rect = None;
+        }
+
+        self.context_menu_rect = None;
+        self.context_menu_rows.clear();
+        if let Some((pos, _, _)) = self.context_menu {
+            self.draw_context_menu(cx, rect, pos);
+        }
+
+        self.compass_rect = None;
+        if self.show_compass && self.bearing.abs() > 0.01 {
+            self.draw_compass(cx, rect);
+        }
+
+        if let Some(hint) = self.gesture_hint {
+            self.draw_gesture_hint(cx, rect, hint);
+        }
+
+        if swipe_clip {
+            cx.end_turtle();
+        }
+        if self.show_swipe_divider {
+            let x = rect.pos.x + rect.size.x * self.swipe_position.clamp(0.0, 1.0);
+            self.draw_swipe_divider.draw_abs(cx, Rect {
+                pos: dvec2(x - SWIPE_DIVIDER_WIDTH / 2.0, rect.pos.y),
+                size: dvec2(SWIPE_DIVIDER_WIDTH, rect.size.y),
+            });
+            self.draw_swipe_divider.draw_abs(cx, Rect {
+                pos: dvec2(x - SWIPE_HANDLE_WIDTH / 2.0, rect.pos.y + (rect.size.y - SWIPE_HANDLE_HEIGHT) / 2.0),
+                size: dvec2(SWIPE_HANDLE_WIDTH, SWIPE_HANDLE_HEIGHT),
+            });
+        }
+
+        // End turtle and set area for hit detection
+        cx.end_turtle_with_area(&mut self.draw_tile.draw_super.draw_vars.area);
+
+        DrawStep::done()
+    }
+}
+
+impl GeoMapView {
+    /// The projection of the tile pyramid
+    fn projection(&self) -> &dyn Projection {
+        self.projection.as_deref().unwrap_or(&WebMercator)
+    }
+
+    /// Camera snapshot handed to overlay layers
+    fn map_viewport(&self, rect: Rect) -> MapViewport {
+        MapViewport {
+            rect,
+            center_lng: self.center_lng,
+            center_lat: self.center_lat,
+            zoom: self.zoom,
+            bearing: self.bearing,
+            pitch: self.pitch,
+            world_repeat: self.repeats_world(),
+            center_offset: self.center_offset(),
+            projection: self.projection.clone().unwrap_or_else(|| Rc::new(WebMercator)),
+        }
+    }
+
+    /// Camera with the viewport at the origin, for positions relative to the viewport top-left
+    fn local_viewport(&self) -> MapViewport {
+        self.map_viewport(Rect { pos: DVec2::default(), size: self.viewport_size })
+    }
+
+    /// Offset of the logical center (the center of the padded area) from the viewport center
+    fn center_offset(&self) -> DVec2 {
+        dvec2(
+            (self.padding.left - self.padding.right) / 2.0,
+            (self.padding.top - self.padding.bottom) / 2.0,
+        )
+    }
+
+    /// Screen position of the logical center, relative to viewport top-left
+    fn screen_center(&self) -> DVec2 {
+        self.viewport_size / 2.0 + self.center_offset()
+    }
+
+    /// Size of the viewport area not covered by padding
+    fn padded_size(&self) -> DVec2 {
+        dvec2(
+            (self.viewport_size.x - self.padding.left - self.padding.right).max(1.0),
+            (self.viewport_size.y - self.padding.top - self.padding.bottom).max(1.0),
+        )
+    }
+
+    /// Check if an absolute position is on the swipe divider's drag zone
+    fn hits_swipe_divider(&self, abs: DVec2) -> bool {
+        if !self.show_swipe_divider {
+            return false;
+        }
+        let x = self.viewport_pos.x + self.viewport_size.x * self.swipe_position.clamp(0.0, 1.0);
+        (abs.x - x).abs() <= SWIPE_HANDLE_WIDTH
+    }
+
+    /// Whether world copies are shown side by side
+    fn repeats_world(&self) -> bool {
+        self.world_repeat && self.projection().wraps_longitude()
+    }
+
+    /// Clamp a latitude to the range the projection can center on
+    fn clamp_lat(&self, lat: f64) -> f64 {
+        let (min_lat, max_lat) = self.projection().lat_range();
+        lat.clamp(min_lat, max_lat)
+    }
+
+    /// Clamp latitude and wrap longitude to valid ranges
+    fn normalize_coordinates(&mut self) {
+        self.center_lat = self.clamp_lat(self.center_lat);
+        while self.center_lng > 180.0 { self.center_lng -= 360.0; }
+        while self.center_lng < -180.0 { self.center_lng += 360.0; }
+    }
+
+    /// Minimum zoom including the fit-to-viewport constraint: the world must fill
+    /// the viewport height (and width too when the world doesn't repeat)
+    fn effective_min_zoom(&self) -> f64 {
+        if !self.fit_world_zoom || self.viewport_size.y <= 0.0 {
+            return self.min_zoom;
+        }
+        let extent = if self.repeats_world() {
+            self.viewport_size.y
+        } else {
+            self.viewport_size.x.max(self.viewport_size.y)
+        };
+        let fit_zoom = (extent / TILE_SIZE).log2();
+        self.min_zoom.max(fit_zoom).min(self.max_zoom)
+    }
+
+    /// Move the center by a north-up pixel offset at the current zoom, starting from `start`
+    fn pan_from(&mut self, start: (f64, f64), offset: DVec2) {
+        let start_world = self.projection().geo_to_world(start.0, start.1, self.zoom);
+        let (lng, lat) = self.projection().world_to_geo(start_world - offset, self.zoom);
+        self.center_lng = lng;
+        self.center_lat = lat;
+        self.normalize_coordinates();
+    }
+
+    /// Convert screen coordinates (relative to viewport top-left) to geographic coordinates
+    fn screen_to_geo(&self, screen_pos: DVec2) -> (f64, f64) {
+        self.local_viewport().screen_to_geo(screen_pos)
+    }
+
+    /// Convert geographic coordinates to screen coordinates (relative to viewport top-left),
+    /// using the copy of the longitude nearest the map center so positions across the
+    /// antimeridian land on the visible side
+    fn geo_to_screen(&self, lng: f64, lat: f64) -> DVec2 {
+        self.geo_to_screen_unwrapped(self.unwrap_lng(lng), lat)
+    }
+
+    /// Convert geographic coordinates to screen coordinates without wrapping the longitude
+    fn geo_to_screen_unwrapped(&self, lng: f64, lat: f64) -> DVec2 {
+        self.local_viewport().geo_to_screen_unwrapped(lng, lat)
+    }
+
+    /// Turn a north-up offset from the center into a screen offset from the
+    /// center for the current bearing and pitch
+    fn rotate_to_screen(&self, v: DVec2) -> DVec2 {
+        tilt_vec(rotate_vec(v, -self.bearing.to_radians()), self.pitch.to_radians(), camera_distance(self.viewport_size.y))
+    }
+
+    /// Turn a screen offset from the center back into a north-up offset from
+    /// the center for the current bearing and pitch
+    fn rotate_from_screen(&self, v: DVec2) -> DVec2 {
+        rotate_vec(untilt_vec(v, self.pitch.to_radians(), camera_distance(self.viewport_size.y)), self.bearing.to_radians())
+    }
+
+    /// North-up offset between the map points under two screen positions
+    /// (relative to the viewport), e.g. for a drag
+    fn screen_delta_to_world(&self, from: DVec2, to: DVec2) -> DVec2 {
+        let center = self.screen_center();
+        self.rotate_from_screen(to - center) - self.rotate_from_screen(from - center)
+    }
+
+    /// All on-screen positions (within `margin`) of a point and its copies one or
+    /// more world widths away, for viewports wider than the world
+    fn world_copies(&self, screen_pos: DVec2, margin: f64) -> Vec<DVec2> {
+        let world_width = world_size(self.zoom);
+        let copies = if self.repeats_world() {
+            (self.viewport_size.length() / world_width).ceil() as i32
+        } else {
+            0
+        };
+        let offset = self.rotate_from_screen(screen_pos - self.screen_center());
+        (-copies..=copies)
+            .map(|k| self.screen_center() + self.rotate_to_screen(offset + dvec2(k as f64 * world_width, 0.0)))
+            .filter(|pos| {
+                pos.x >= -margin && pos.x <= self.viewport_size.x + margin
+                    && pos.y >= -margin && pos.y <= self.viewport_size.y + margin
+            })
+            .collect()
+    }
+
+    /// Move the center so that the given geographic point appears at `screen_pos`
+    /// (relative to viewport top-left) at the current zoom
+    fn center_on_anchor(&mut self, lng: f64, lat: f64, screen_pos: DVec2) {
+        let anchor_world = self.projection().geo_to_world(lng, lat, self.zoom);
+        let center_world = anchor_world - self.rotate_from_screen(screen_pos - self.screen_center());
+        let (center_lng, center_lat) = self.projection().world_to_geo(center_world, self.zoom);
+        self.center_lng = center_lng;
+        self.center_lat = center_lat;
+        self.normalize_coordinates();
+    }
+
+    /// Clamp a zoom requested by user input to the zoom range, reporting
+    /// `MinZoomReached`/`MaxZoomReached` when it pushes past a limit: once per
+    /// push, again after the zoom came off the limit or a new gesture started
+    fn clamp_user_zoom(&mut self, cx: &mut Cx, uid: WidgetUid, path: &HeapLiveIdPath, zoom: f64) -> f64 {
+        let (min_zoom, max_zoom) = (self.effective_min_zoom(), self.max_zoom);
+        let action = if zoom > max_zoom && self.zoom >= max_zoom - 1e-9 {
+            Some(GeoMapViewAction::MaxZoomReached)
+        } else if zoom < min_zoom && self.zoom <= min_zoom + 1e-9 {
+            Some(GeoMapViewAction::MinZoomReached)
+        } else {
+            None
+        };
+        match action {
+            Some(action) if !self.zoom_limit_reported => {
+                self.zoom_limit_reported = true;
+                cx.widget_action(uid, path, action);
+            }
+            Some(_) => {}
+            None => self.zoom_limit_reported = false,
+        }
+        zoom.clamp(min_zoom, max_zoom)
+    }
+
+    /// `zoom` on the `zoom_snap` grid: the nearest step, or the next step up
+    /// or down when `direction` is positive or negative
+    fn snap_zoom(&self, zoom: f64, direction: f64) -> f64 {
+        if self.zoom_snap <= 0.0 {
+            return zoom;
+        }
+        let steps = zoom / self.zoom_snap;
+        let steps = if direction > 0.0 {
+            (steps - 1e-9).ceil()
+        } else if direction < 0.0 {
+            (steps + 1e-9).floor()
+        } else {
+            steps.round()
+        };
+        steps * self.zoom_snap
+    }
+
+    /// Start an animated zoom to `target_zoom` anchored at `screen_pos` (relative to viewport top-left)
+    fn start_zoom_animation(&mut self, cx: &mut Cx, target_zoom: f64, screen_pos: DVec2) {
+        if (target_zoom - self.zoom).abs() < f64::EPSILON {
+            return;
+        }
+        let (anchor_lng, anchor_lat) = self.screen_to_geo(screen_pos);
+        let kind = CameraAnimationKind::AnchoredZoom {
+            from_zoom: self.zoom,
+            to_zoom: target_zoom,
+            anchor_lng,
+            anchor_lat,
+            anchor_offset: screen_pos - self.screen_center(),
+        };
+        self.start_camera_animation(cx, CameraAnimation::new(kind, ZOOM_ANIMATION_DURATION));
+    }
+
+    fn start_camera_animation(&mut self, cx: &mut Cx, animation: CameraAnimation) {
+        self.is_flicking = false;
+        self.camera_controller.start(animation);
+        self.request_frame(cx);
+    }
+
+    /// Advance the camera animation, keeping its anchor point at the animated screen offset
+    fn apply_camera_animation(&mut self, cx: &mut Cx, time: f64, uid: WidgetUid, path: &HeapLiveIdPath) {
+        let open_popup = self.camera_controller.open_popup();
+        let Some(frame) = self.camera_controller.frame(time, self.projection.as_deref().unwrap_or(&WebMercator)) else {
+            return;
+        };
+
+        self.zoom = frame.zoom.clamp(self.effective_min_zoom(), self.max_zoom);
+        if let Some(bearing) = frame.bearing {
+            self.bearing = normalize_bearing(bearing);
+        }
+        if let Some(pitch) = frame.pitch {
+            self.pitch = pitch.clamp(0.0, MAX_PITCH);
+        }
+        self.center_on_anchor(frame.lng, frame.lat, self.screen_center() + frame.offset);
+        let unit = self.projection().project(self.center_lng, self.center_lat);
+        self.camera_controller.record(time, unit, self.zoom, self.bearing, self.pitch);
+        self.draw_tile.redraw(cx);
+
+        if frame.finished {
+            self.emit_region_changed(cx, uid, path);
+            if frame.bearing.is_some() {
+                cx.widget_action(uid, path, GeoMapViewAction::BearingChanged { bearing: self.bearing });
+            }
+            if let Some(id) = open_popup {
+                self.open_popup = Some(id);
+                cx.widget_action(uid, path, GeoMapViewAction::PopupOpened { id });
+            }
+        } else {
+            self.request_frame(cx);
+        }
+    }
+
+    /// Move the navigation position and, while following, ease the camera into
+    /// the navigation zoom, pitch and heading with the position at the anchor
+    fn apply_navigation(&mut self, cx: &mut Cx, time: f64) {
+        let projection = self.projection.clone();
+        let Some(navigation) = self.navigation.as_mut() else {
+            return;
+        };
+        let Some(frame) = navigation.frame(time, projection.as_deref().unwrap_or(&WebMercator)) else {
+            return;
+        };
+        let (options, following) = (navigation.options, navigation.following);
+        self.draw_tile.redraw(cx);
+
+        let mut settling = false;
+        if following && !self.is_animating() {
+            let zoom = options.zoom.clamp(self.effective_min_zoom(), self.max_zoom);
+            let pitch = options.pitch.clamp(0.0, MAX_PITCH);
+            self.zoom += (zoom - self.zoom) * frame.settle;
+            self.pitch += (pitch - self.pitch) * frame.settle;
+            let mut turn = 0.0;
+            if options.follow_heading {
+                turn = (frame.position.heading - self.bearing + 180.0).rem_euclid(360.0) - 180.0;
+                self.bearing = normalize_bearing(self.bearing + turn * frame.settle);
+            }
+            let padded = self.padded_size();
+            let anchor = dvec2(
+                self.padding.left + padded.x / 2.0,
+                self.padding.top + padded.y * options.anchor.clamp(0.0, 1.0),
+            );
+            self.center_on_anchor(frame.position.lng, frame.position.lat, anchor);
+            settling = (zoom - self.zoom).abs() > 0.001 || (pitch - self.pitch).abs() > 0.01 || turn.abs() > 0.1;
+        }
+        if frame.moving || settling {
+            self.request_frame(cx);
+        }
+    }
+
+    /// Position puck with an arrow pointing in the direction of travel
+    fn draw_navigation_position(&mut self, cx: &mut Cx2d, rect: Rect, position: NavigationFix) {
+        let pos = rect.pos + self.geo_to_screen(position.lng, position.lat);
+        if !rect.contains(pos) {
+            return;
+        }
+        self.draw_navigation_puck.draw_circle(cx, pos, NAVIGATION_PUCK_RADIUS);
+        let angle = (position.heading - self.bearing).to_radians();
+        let ahead = dvec2(angle.sin(), -angle.cos());
+        let side = dvec2(-ahead.y, ahead.x);
+        let size = NAVIGATION_PUCK_RADIUS * 0.55;
+        self.draw_navigation_arrow.draw_triangle(
+            cx,
+            pos + ahead * size,
+            pos - ahead * size * 0.7 + side * size * 0.8,
+            pos - ahead * size * 0.7 - side * size * 0.8,
+        );
+    }
+
+    fn apply_timeline(&mut self, cx: &mut Cx, time: f64, uid: WidgetUid, path: &HeapLiveIdPath) {
+        let Some(timeline) = self.timeline.as_mut() else {
+            return;
+        };
+        if timeline.advance(time) {
+            let time = timeline.time();
+            self.seek(cx, time);
+            cx.widget_action(uid, path, GeoMapViewAction::TimeChanged { time });
+        }
+        if self.timeline.as_ref().is_some_and(|timeline| timeline.is_playing()) {
+            self.request_frame(cx);
+        }
+    }
+
+    fn apply_marker_animations(&mut self, cx: &mut Cx, time: f64, uid: WidgetUid, path: &HeapLiveIdPath) {
+        let projection = self.projection.clone();
+        let mut animations = std::mem::take(&mut self.marker_animations);
+        let mut finished = false;
+        animations.retain_mut(|animation| {
+            let frame = animation.frame(time, projection.as_deref().unwrap_or(&WebMercator));
+            let Some(marker) = self.markers.iter_mut().find(|m| m.id == animation.id) else {
+                return false;
+            };
+            marker.lng = frame.lng;
+            marker.lat = frame.lat;
+            marker.rotation_deg = frame.heading;
+            cx.widget_action(uid, path, GeoMapViewAction::MarkerAnimationProgress {
+                id: animation.id,
+                lng: frame.lng,
+                lat: frame.lat,
+                heading: frame.heading,
+                progress: frame.progress,
+            });
+            if frame.finished {
+                cx.widget_action(uid, path, GeoMapViewAction::MarkerAnimationFinished { id: animation.id });
+                finished = true;
+            }
+            !frame.finished
+        });
+        // Moving markers are tested directly (see `with_animated_markers`),
+        // the index only needs them where they came to rest
+        if finished {
+            self.marker_index = None;
+        }
+        self.marker_animations = animations;
+        self.draw_tile.redraw(cx);
+        if !self.marker_animations.is_empty() {
+            self.request_frame(cx);
+        }
+    }
+
+    /// Find the marker at a screen position (if any), checking in reverse order (topmost first)
+    /// screen_pos should be in absolute window coordinates (as received from events)
+    /// The topmost overlay at an absolute position: a shape of a visible
+    /// overlay layer, else a polyline within reach of its stroke or inside its
+    /// closed outline
+    fn overlay_at(&self, abs_pos: DVec2) -> Option<OverlayHit> {
+        let viewport = self.map_viewport(Rect { pos: self.viewport_pos, size: self.viewport_size });
+        // Overlay layers are drawn above the polylines
+        let layer_hit = self.layers.iter().rev()
+            .filter(|(id, layer)| layer.is_visible() && self.is_layer_shown(*id))
+            .find_map(|(_, layer)| layer.overlay_at(&viewport, abs_pos));
+        if layer_hit.is_some() || !self.is_layer_shown(POLYLINES_LAYER) {
+            return layer_hit;
+        }
+
+        let rel_pos = abs_pos - self.viewport_pos;
+        let p = (rel_pos.x, rel_pos.y);
+        self.polylines.iter().rev()
+            .filter(|line| line.is_shown_at(self.zoom))
+            .find(|line| {
+                let path: Vec<(f64, f64)> = self.polyline_screen_path(&line.outline(), line.geodesic)
+                    .into_iter()
+                    .map(|pos| (pos.x, pos.y))
+                    .collect();
+                let reach = line.width / 2.0 + OVERLAY_HIT_SLOP;
+                path.windows(2).any(|segment| segment_distance(p, segment[0], segment[1]) <= reach)
+                    || (line.closed && point_in_polygon(p, std::slice::from_ref(&path)))
+            })
+            .map(|line| OverlayHit { id: line.id, tooltip: line.tooltip.clone() })
+    }
+
+    /// Track the overlay under the pointer, reporting it with `OverlayHovered`
+    /// when it changes. Markers sit above overlays, so they hide them.
+    fn update_hovered_overlay(&mut self, cx: &mut Cx, uid: WidgetUid, path: &HeapLiveIdPath, abs_pos: DVec2) {
+        let hit = if self.find_marker_at_screen_pos(abs_pos).is_some() {
+            None
+        } else {
+            self.overlay_at(abs_pos)
+        };
+        let previous = self.hovered_overlay.take();
+        if let Some(hit) = &hit {
+            if previous.as_ref().is_none_or(|(previous, _)| previous.id != hit.id) {
+                cx.widget_action(uid, path, GeoMapViewAction::OverlayHovered { id: hit.id });
+            }
+        }
+        // Tooltips follow the pointer, so any shown tooltip needs a redraw
+        let has_tooltip = |overlay: &Option<(OverlayHit, DVec2)>| overlay.as_ref().is_some_and(|(hit, _)| !hit.tooltip.is_empty());
+        self.hovered_overlay = hit.map(|hit| (hit, abs_pos - self.viewport_pos));
+        if has_tooltip(&previous) || has_tooltip(&self.hovered_overlay) {
+            self.draw_tile.redraw(cx);
+        }
+    }
+
+    /// Hide the overlay tooltip, e.g. when the pointer leaves the map
+    fn clear_hovered_overlay(&mut self, cx: &mut Cx) {
+        if let Some((hit, _)) = self.hovered_overlay.take() {
+            if !hit.tooltip.is_empty() {
+                self.draw_tile.redraw(cx);
+            }
+        }
+    }
+
+    fn find_marker_at_screen_pos(&self, abs_pos: DVec2) -> Option<LiveId> {
+        // Convert absolute position to relative viewport position
+        let rel_pos = abs_pos - self.viewport_pos;
+
+        // Hit radius covers the marker shape - use full marker size for easier tapping
+        let hit_radius = self.marker_size * 0.6;
+
+        if !self.is_layer_shown(MARKERS_LAYER) {
+            return None;
+        }
+
+        // Only markers near the tap, from the index of the last draw
+        let candidates = match &self.marker_index {
+            Some(index) => {
+                let reach = dvec2(self.marker_size, self.marker_size);
+                let viewport = self.map_viewport(Rect { pos: self.viewport_pos, size: self.viewport_size });
+                let (min, max) = viewport.unit_bounds(Rect { pos: abs_pos - reach, size: reach * 2.0 });
+                index.query_wrapped(min, max, self.projection().wraps_longitude())
+            }
+            None => (0..self.markers.len()).collect(),
+        };
+        let candidates = self.with_animated_markers(candidates);
+
+        // Check markers in reverse order (last drawn = topmost = checked first)
+        for marker in candidates.into_iter().rev().map(|i| &self.markers[i]).filter(|marker| marker.is_shown_at(self.zoom)) {
+            let base_screen = self.geo_to_screen(marker.lng, marker.lat);
+            for marker_screen in self.world_copies(base_screen, self.marker_size) {
+                // A pin is drawn with its point at marker_screen, but the visible
+                // head is above that point. Check against the center of the visible marker.
+                let marker_center_y = marker_screen.y - self.marker_size * marker.shape.center_height();
+
+                let dx = rel_pos.x - marker_screen.x;
+                let dy = rel_pos.y - marker_center_y;
+                let distance = (dx * dx + dy * dy).sqrt();
+
+                if distance <= hit_radius {
+                    return Some(marker.id);
+                }
+            }
+        }
+        None
+    }
+
+    /// Shift a longitude by whole turns so it is within 180° of the map center
+    fn unwrap_lng(&self, lng: f64) -> f64 {
+        self.center_lng + (lng - self.center_lng + 180.0).rem_euclid(360.0) - 180.0
+    }
+
+    /// Screen positions (relative to the viewport) of a polyline's vertices, with
+    /// geodesic lines densified along great circles. Longitudes continue across
+    /// the antimeridian instead of jumping back, starting from the copy nearest the center.
+    fn polyline_screen_path(&self, points: &[(f64, f64)], geodesic: bool) -> Vec<DVec2> {
+        if points.len() < 2 {
+            return Vec::new();
+        }
+        let mut geo = vec![points[0]];
+        for pair in points.windows(2) {
+            let ((lng1, lat1), (lng2, lat2)) = (pair[0], pair[1]);
+            if geodesic {
+                geo.extend(great_circle_path(lng1, lat1, lng2, lat2, GEODESIC_STEP_DEG).into_iter().skip(1));
+            } else {
+                geo.push((lng2, lat2));
+            }
+        }
+
+        let mut prev_lng = self.unwrap_lng(geo[0].0);
+        let mut path = Vec::with_capacity(geo.len());
+        path.push(self.geo_to_screen_unwrapped(prev_lng, geo[0].1));
+        for &(lng, lat) in &geo[1..] {
+            prev_lng += (lng - prev_lng + 180.0).rem_euclid(360.0) - 180.0;
+            path.push(self.geo_to_screen_unwrapped(prev_lng, lat));
+        }
+        path
+    }
+
+    /// Add a measurement point at a viewport position, closing the polygon when
+    /// the first point is tapped. A closed measurement is replaced by a new one.
+    fn add_measure_point(&mut self, screen_pos: DVec2) {
+        if self.measurement.closed {
+            self.measurement = Measurement::default();
+        }
+        if self.measurement.points.len() >= 3 {
+            let (lng, lat) = self.measurement.points[0];
+            if (self.geo_to_screen(lng, lat) - screen_pos).length() <= MEASURE_CLOSE_DISTANCE {
+                self.measurement.closed = true;
+                return;
+            }
+        }
+        let (lng, lat) = self.screen_to_geo(screen_pos);
+        self.measurement.points.push((wrap_lng(lng), lat));
+    }
+
+    /// Draw the measurement path, its points and the distance/area label
+    fn draw_measurement(&mut self, cx: &mut Cx2d, rect: Rect) {
+        let mut points = self.measurement.points.clone();
+        if self.measurement.closed {
+            points.push(points[0]);
+        }
+        let path = self.polyline_screen_path(&points, true);
+        for segment in path.windows(2) {
+            if self.segment_visible(segment[0], segment[1], self.draw_measure_line.line_width as f64) {
+                self.draw_measure_line.draw_line(cx, rect.pos + segment[0], rect.pos + segment[1]);
+            }
+        }
+        let vertices: Vec<DVec2> = self.measurement.points.iter()
+            .map(|&(lng, lat)| self.geo_to_screen(lng, lat))
+            .collect();
+        for &pos in &vertices {
+            self.draw_measure_point.draw_circle(cx, rect.pos + pos, MEASURE_POINT_RADIUS);
+        }
+
+        // Label at the last point, or the middle of a closed polygon
+        let label = match self.measurement.area() {
+            Some(area) => format!("{} ({})", self.locale.format_area(area), self.locale.format_distance(self.measurement.distance())),
+            None if vertices.len() >= 2 => self.locale.format_distance(self.measurement.distance()),
+            None => return,
+        };
+        let anchor = if self.measurement.closed {
+            vertices.iter().fold(DVec2::default(), |sum, &pos| sum + pos) / vertices.len() as f64
+        } else {
+            vertices[vertices.len() - 1] + dvec2(0.0, 12.0)
+        };
+        let text_size = measure_text(cx, &self.draw_measure_label, &label);
+        let padding = 3.0;
+        let text_pos = rect.pos + anchor - dvec2(text_size.x / 2.0, 0.0);
+        self.draw_marker_label_bg.draw_abs(cx, Rect {
+            pos: text_pos - dvec2(padding, padding),
+            size: text_size + dvec2(padding * 2.0, padding * 2.0),
+        });
+        self.draw_measure_label.draw_abs(cx, text_pos, &label);
+    }
+
+    /// Add a polygon vertex at a viewport position. Returns true if the tap hit
+    /// the first vertex and finished the polygon.
+    fn add_sketch_point(&mut self, screen_pos: DVec2) -> bool {
+        if self.sketch.len() >= 3 {
+            let (lng, lat) = self.sketch[0];
+            if (self.geo_to_screen(lng, lat) - screen_pos).length() <= MEASURE_CLOSE_DISTANCE {
+                return true;
+            }
+        }
+        let (lng, lat) = self.screen_to_geo(screen_pos);
+        self.sketch.push((wrap_lng(lng), lat));
+        false
+    }
+
+    /// Vertex and midpoint handles of the editable polylines on screen
+    fn edit_handles(&self) -> Vec<EditHandle> {
+        let mut handles = Vec::new();
+        if !self.is_layer_shown(POLYLINES_LAYER) {
+            return handles;
+        }
+        let lines = self.polylines.iter().enumerate().filter(|(_, line)| line.editable && line.is_shown_at(self.zoom));
+        for (line_index, line) in lines {
+            let outline = line.outline();
+            for (index, pair) in outline.windows(2).enumerate() {
+                let ((lng1, lat1), (lng2, lat2)) = (pair[0], pair[1]);
+                let (lng, lat) = if line.geodesic {
+                    midpoint(lng1, lat1, lng2, lat2)
+                } else {
+                    let mid = (self.geo_to_screen(lng1, lat1) + self.geo_to_screen(lng2, lat2)) / 2.0;
+                    let (lng, lat) = self.screen_to_geo(mid);
+                    (wrap_lng(lng), lat)
+                };
+                handles.push(EditHandle { line: line_index, index: index + 1, midpoint: true, pos: self.geo_to_screen(lng, lat), lng, lat });
+            }
+            for (index, &(lng, lat)) in line.points.iter().enumerate() {
+                handles.push(EditHandle { line: line_index, index, midpoint: false, pos: self.geo_to_screen(lng, lat), lng, lat });
+            }
+        }
+        handles.retain(|handle| self.segment_visible(handle.pos, handle.pos, EDIT_HANDLE_HIT_DISTANCE));
+        handles
+    }
+
+    /// The editing handle nearest a viewport position, vertices taking precedence over midpoints
+    fn edit_handle_at(&self, pos: DVec2) -> Option<EditHandle> {
+        if !self.polylines.iter().any(|line| line.editable) {
+            return None;
+        }
+        self.edit_handles()
+            .into_iter()
+            .map(|handle| (handle, (handle.pos - pos).length()))
+            .filter(|(_, distance)| *distance <= EDIT_HANDLE_HIT_DISTANCE)
+            .min_by(|(a, da), (b, db)| a.midpoint.cmp(&b.midpoint).then(da.total_cmp(db)))
+            .map(|(handle, _)| handle)
+    }
+
+    /// Geographic bounds of a rectangle on screen (viewport positions). The
+    /// corners are unwrapped around the center, so a rotated or wide selection
+    /// gets the longitudes it actually covers.
+    fn screen_rect_bounds(&self, a: DVec2, b: DVec2) -> GeoBounds {
+        let corners = [a, dvec2(a.x, b.y), b, dvec2(b.x, a.y)].map(|pos| self.screen_to_geo(pos));
+        let (mut west, mut east) = (f64::INFINITY, f64::NEG_INFINITY);
+        let (mut south, mut north) = (f64::INFINITY, f64::NEG_INFINITY);
+        for (lng, lat) in corners {
+            west = west.min(lng);
+            east = east.max(lng);
+            south = south.min(lat);
+            north = north.max(lat);
+        }
+        if east - west >= 360.0 {
+            (west, east) = (-180.0, 180.0);
+        } else {
+            (west, east) = (wrap_lng(west), wrap_lng(east));
+        }
+        GeoBounds { west, south, east, north }
+    }
+
+    /// Emit the action for a finished select/draw drag, adding drawn shapes as closed polylines
+    fn finish_drag_shape(&mut self, cx: &mut Cx, uid: WidgetUid, path: &HeapLiveIdPath, start: DVec2, end: DVec2) {
+        let action = match self.interaction_mode {
+            InteractionMode::DrawRectangle => {
+                let id = LiveId::unique();
+                self.add_drawn_shape(cx, id, self.drag_shape(start, end));
+                GeoMapViewAction::RectangleDrawn { id, bounds: self.screen_rect_bounds(start, end) }
+            }
+            InteractionMode::DrawCircle => {
+                let id = LiveId::unique();
+                self.add_drawn_shape(cx, id, self.drag_shape(start, end));
+                let (lng, lat) = self.screen_to_geo(start);
+                let (edge_lng, edge_lat) = self.screen_to_geo(end);
+                let radius = haversine_distance(lng, lat, edge_lng, edge_lat);
+                GeoMapViewAction::CircleDrawn { id, lng: wrap_lng(lng), lat, radius }
+            }
+            _ => GeoMapViewAction::AreaSelected { bounds: self.screen_rect_bounds(start, end) },
+        };
+        cx.widget_action(uid, path, action);
+    }
+
+    fn add_drawn_shape(&mut self, cx: &mut Cx, id: LiveId, points: Vec<(f64, f64)>) {
+        let color = self.draw_sketch_line.color;
+        let width = self.draw_sketch_line.line_width as f64;
+        let line = self.add_polyline(cx, id, points);
+        line.closed = true;
+        line.color = color;
+        line.width = width;
+    }
+
+    /// Outline of the rectangle or circle being dragged in the drawing modes:
+    /// the geographic bounds of the dragged rectangle, or a geodesic circle
+    /// around the start position
+    fn drag_shape(&self, start: DVec2, end: DVec2) -> Vec<(f64, f64)> {
+        if self.interaction_mode == InteractionMode::DrawCircle {
+            let (lng, lat) = self.screen_to_geo(start);
+            let (edge_lng, edge_lat) = self.screen_to_geo(end);
+            let radius = haversine_distance(lng, lat, edge_lng, edge_lat);
+            return circle_polygon(wrap_lng(lng), lat, radius, CIRCLE_SEGMENTS);
+        }
+        let GeoBounds { west, south, east, north } = self.screen_rect_bounds(start, end);
+        vec![(west, south), (east, south), (east, north), (west, north)]
+    }
+
+    /// Draw the polygon being drawn and the selection rectangle
+    fn draw_sketch(&mut self, cx: &mut Cx2d, rect: Rect) {
+        let path = self.polyline_screen_path(&self.sketch, false);
+        for segment in path.windows(2) {
+            if self.segment_visible(segment[0], segment[1], self.draw_sketch_line.line_width as f64) {
+                self.draw_sketch_line.draw_line(cx, rect.pos + segment[0], rect.pos + segment[1]);
+            }
+        }
+        for &(lng, lat) in &self.sketch {
+            let pos = self.geo_to_screen(lng, lat);
+            self.draw_sketch_point.draw_circle(cx, rect.pos + pos, MEASURE_POINT_RADIUS);
+        }
+
+        if let Some((a, b)) = self.selection.filter(|_| self.interaction_mode != InteractionMode::Select) {
+            let mut outline = self.drag_shape(a, b);
+            outline.push(outline[0]);
+            let path = self.polyline_screen_path(&outline, false);
+            for segment in path.windows(2) {
+                self.draw_sketch_line.draw_line(cx, rect.pos + segment[0], rect.pos + segment[1]);
+            }
+        } else if let Some((a, b)) = self.selection {
+            let min = rect.pos + dvec2(a.x.min(b.x), a.y.min(b.y));
+            let max = rect.pos + dvec2(a.x.max(b.x), a.y.max(b.y));
+            self.draw_selection.draw_abs(cx, Rect { pos: min, size: max - min });
+            let corners = [min, dvec2(max.x, min.y), max, dvec2(min.x, max.y), min];
+            for edge in corners.windows(2) {
+                self.draw_sketch_line.draw_line(cx, edge[0], edge[1]);
+            }
+        }
+    }
+
+    /// Draw the tile cache statistics overlay in the top-left corner
+    fn draw_tile_stats(&mut self, cx: &mut Cx2d, rect: Rect) {
+        let stats = self.tile_cache.stats();
+        let mb = |bytes: f64| bytes / (1024.0 * 1024.0);
+        let lines = [
+            format!("tiles  {} loaded, {} in flight", stats.loaded_tiles, stats.in_flight),
+            format!("hits   {} mem, {} disk, {} net", stats.memory_hits, stats.disk_hits, stats.misses),
+            format!("net    {:.1} MB, {} dup, {} 4xx", mb(stats.bytes_downloaded as f64), stats.requests.duplicate_requests, stats.requests.client_errors),
+            format!("decode {:.1} ms avg, {:.1} ms max", stats.average_decode_time().unwrap_or(0.0) * 1000.0, stats.max_decode_time * 1000.0),
+            format!("gpu    {:.1} / {:.0} MB", mb(stats.memory_bytes as f64), mb(stats.memory_budget as f64)),
+            format!("disk   {:.1} MB", mb(stats.disk_bytes as f64)),
+        ];
+
+        let margin = 10.0;
+        let padding = 4.0;
+        let font_size = self.draw_stats_text.text_style.font_size as f64;
+        let line_height = font_size * 1.4;
+        let text_width = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0) as f64 * font_size * 0.6;
+        let origin = rect.pos + dvec2(margin, margin);
+        self.draw_attribution_bg.draw_abs(cx, Rect {
+            pos: origin,
+            size: dvec2(text_width + padding * 2.0, line_height * lines.len() as f64 + padding * 2.0),
+        });
+        for (i, line) in lines.iter().enumerate() {
+            let pos = origin + dvec2(padding, padding + line_height * i as f64);
+            self.draw_stats_text.draw_abs(cx, pos, line);
+        }
+    }
+
+    /// Draw the tiles of the visible grid, falling back to scaled parent tiles
+    /// Draw the tiles into the tile draw list, or keep the list from the last
+    /// draw if nothing it shows has changed since. Redraws for marker
+    /// animations, hover effects or overlays then cost no tile draw calls.
+    fn draw_tiles(&mut self, cx: &mut Cx2d, viewport: &MapViewport, grid: &TileGrid, opacity: f64) {
+        let key = TilePassKey {
+            rect: viewport.rect,
+            center: (viewport.center_lng, viewport.center_lat),
+            zoom: viewport.zoom,
+            bearing: viewport.bearing,
+            pitch: viewport.pitch,
+            center_offset: viewport.center_offset,
+            world_repeat: viewport.world_repeat,
+            opacity,
+            swipe_position: self.swipe_position,
+            filter: self.tile_filter,
+            content_generation: self.tile_cache.content_generation(),
+        };
+        let draw_list = self.tile_draw_list.get_or_insert_with(|| DrawList2d::new(cx.cx.cx));
+        if self.tile_pass.as_ref() == Some(&key) {
+            // Drawn again only if something redrew the list itself
+            if draw_list.begin(cx, Walk::fill()).is_not_redrawing() {
+                return;
+            }
+        } else {
+            draw_list.begin_always(cx);
+        }
+        self.tile_pass = Some(key);
+        self.draw_tile.opacity = opacity as f32;
+        grid.draw(cx, &mut self.draw_tile, &self.tile_cache, viewport.rect, true);
+        draw_list.end(cx);
+    }
+
+    /// Draw the polylines below the markers
+    fn draw_polylines(&mut self, cx: &mut Cx2d, rect: Rect, opacity: f64) {
+        self.update_polyline_simplification();
+        let polyline_paths: Vec<_> = self.polylines.iter()
+            .filter(|line| line.is_shown_at(self.zoom))
+            .map(|line| (self.polyline_screen_path(&self.polyline_outline(line), line.geodesic), line.color, line.width))
+            .collect();
+        for (path, color, width) in polyline_paths {
+            self.draw_polyline.color = vec4(color.x, color.y, color.z, color.w * opacity as f32);
+            self.draw_polyline.line_width = width as f32;
+            for segment in path.windows(2) {
+                if self.segment_visible(segment[0], segment[1], width) {
+                    self.draw_polyline.draw_line(cx, rect.pos + segment[0], rect.pos + segment[1]);
+                }
+            }
+        }
+    }
+
+    /// Whole zoom level polylines are simplified for. Rounded up, so the
+    /// simplified line is never more than `polyline_tolerance` pixels off.
+    fn simplification_level(&self) -> i32 {
+        self.zoom.ceil() as i32
+    }
+
+    /// Simplify the shown polylines for the current zoom level, once per line and level
+    fn update_polyline_simplification(&mut self) {
+        if self.polyline_tolerance <= 0.0 {
+            return;
+        }
+        let level = self.simplification_level();
+        // Tolerance in unit square positions, where the thresholds are computed
+        let tolerance = self.polyline_tolerance / world_size(level as f64);
+        let projection = self.projection.clone().unwrap_or_else(|| Rc::new(WebMercator));
+        for line in self.polylines.iter().filter(|line| line.is_shown_at(self.zoom) && !line.editable) {
+            let outline = line.outline();
+            let simplified = self.simplified_polylines.entry(line.id).or_insert_with(|| {
+                // Continuous longitudes, so lines across the antimeridian aren't cut short
+                let mut prev_lng = outline.first().map_or(0.0, |p| p.0);
+                let units: Vec<(f64, f64)> = outline.iter().map(|&(lng, lat)| {
+                    prev_lng += (lng - prev_lng + 180.0).rem_euclid(360.0) - 180.0;
+                    let unit = projection.project(prev_lng, lat);
+                    (unit.x, unit.y)
+                }).collect();
+                SimplifiedPolyline { thresholds: simplification_thresholds(&units), levels: HashMap::new() }
+            });
+            simplified.levels.entry(level)
+                .or_insert_with(|| simplify_line(&outline, &simplified.thresholds, tolerance));
+        }
+    }
+
+    /// Outline of a polyline to draw, simplified for the current zoom level
+    /// when `update_polyline_simplification` has done so
+    fn polyline_outline<'a>(&'a self, line: &'a MapPolyline) -> Cow<'a, [(f64, f64)]> {
+        if line.editable || self.polyline_tolerance <= 0.0 {
+            return Cow::Owned(line.outline());
+        }
+        match self.simplified_polylines.get(&line.id).and_then(|s| s.levels.get(&self.simplification_level())) {
+            Some(points) => Cow::Borrowed(points),
+            None => Cow::Owned(line.outline()),
+        }
+    }
+
+    /// Index candidates plus the markers moving along a path. The index keeps
+    /// a moving marker where it was when the index was built, rebuilding it
+    /// for every animation frame would cost more than testing the few moving
+    /// markers directly. Sorted by marker index, i.e. in drawing order.
+    fn with_animated_markers(&self, mut candidates: Vec<usize>) -> Vec<usize> {
+        if self.marker_animations.is_empty() {
+            return candidates;
+        }
+        let animated: HashSet<LiveId> = self.marker_animations.iter().map(|animation| animation.id).collect();
+        candidates.extend(self.markers.iter().enumerate()
+            .filter(|(_, marker)| animated.contains(&marker.id))
+            .map(|(index, _)| index));
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates
+    }
+
+    /// Draw the markers with their labels
+    fn draw_markers(&mut self, cx: &mut Cx2d, rect: Rect, quality: QualityLevel, opacity: f64) {
+        let (label_color, label_bg_color) = (self.draw_marker_label.color, self.draw_marker_label_bg.color);
+        self.draw_marker_label.color.w *= opacity as f32;
+        self.draw_marker_label_bg.color.w *= opacity as f32;
+        self.draw_marker.opacity = opacity as f32;
+
+        if self.marker_index.is_none() {
+            let projection = self.projection();
+            self.marker_index = Some(SpatialIndex::from_boxes(self.markers.iter().map(|m| {
+                let unit = projection.project(m.lng, m.lat);
+                (unit, unit)
+            })));
+        }
+        let candidates = self.marker_index.as_ref()
+            .map(|index| index.query_viewport(&self.map_viewport(rect), self.marker_size))
+            .unwrap_or_default();
+        let candidates = self.with_animated_markers(candidates);
+
+        // Collect the on-screen positions first to avoid borrow issues.
+        // Markers repeat with the world when the viewport is wider than one world copy.
+        let margin = self.marker_size;
+        let on_screen = |pos: &DVec2| pos.x >= -margin && pos.x <= self.viewport_size.x + margin
+            && pos.y >= -margin && pos.y <= self.viewport_size.y + margin;
+        let mut visible: Vec<(usize, DVec2, f64)> = candidates.into_iter()
+            .map(|index| (index, &self.markers[index]))
+            .filter(|(_, m)| m.is_shown_at(self.zoom))
+            .flat_map(|(index, m)| {
+                let positions = self.world_copies(self.geo_to_screen(m.lng, m.lat), self.marker_size);
+                let rotation = if positions.iter().any(on_screen) { self.marker_screen_rotation(m) } else { 0.0 };
+                positions.into_iter().filter(on_screen).map(move |pos| (index, pos, rotation))
+            })
+            .collect();
+        // The selected marker is drawn last so it sits on top of its neighbours
+        if let Some(selected) = self.selected_marker {
+            visible.sort_by_key(|&(index, _, _)| self.markers[index].id == selected);
+        }
+
+        // All markers go into one instanced draw call; labels follow in two more
+        // (backgrounds, then text) so they don't split the batch
+        self.draw_marker.begin_many_instances(cx);
+        for &(index, screen_pos, rotation) in &visible {
+            let marker = &self.markers[index];
+            let selected = self.selected_marker == Some(marker.id);
+            let size = if selected { self.marker_size * self.selected_marker_scale } else { self.marker_size };
+            // Position marker so its anchor is at the geo location
+            // The shader anchors the pin at pos (0.5, 0.7), so we offset accordingly
+            let marker_rect = Rect {
+                pos: rect.pos + dvec2(
+                    screen_pos.x - size / 2.0,
+                    screen_pos.y - size * marker.shape.extent().0,
+                ),
+                size: dvec2(size, size),
+            };
+            self.draw_marker.marker_color = marker.color;
+            self.draw_marker.shape = marker.shape.shader_id();
+            self.draw_marker.rotation = rotation as f32;
+            self.draw_marker.selected = if selected { 1.0 } else { 0.0 };
+            self.draw_marker.draw_abs(cx, marker_rect);
+        }
+        self.draw_marker.end_many_instances(cx);
+
+        // Only labels of on-screen markers are laid out
+        if quality < QualityLevel::Minimal {
+            let padding = 3.0;
+            let labels: Vec<(usize, DVec2, DVec2)> = visible.iter()
+                .filter(|(index, _, _)| !self.markers[*index].label.is_empty())
+                .map(|&(index, screen_pos, _)| {
+                    let marker = &self.markers[index];
+                    let text_size = measure_text(cx, &self.draw_marker_label, &marker.label);
+                    let text_pos = rect.pos + marker.label_offset
+                        + self.marker_label_pos(screen_pos, text_size, marker.shape, marker.label_placement, &marker.label);
+                    (index, text_pos, text_size)
+                })
+                .collect();
+            self.draw_marker_label_bg.begin_many_instances(cx);
+            for &(_, text_pos, text_size) in &labels {
+                self.draw_marker_label_bg.draw_abs(cx, Rect {
+                    pos: text_pos - dvec2(padding, padding),
+                    size: text_size + dvec2(padding * 2.0, padding * 2.0),
+                });
+            }
+            self.draw_marker_label_bg.end_many_instances(cx);
+            for (index, text_pos, _) in labels {
+                self.draw_marker_label.draw_abs(cx, text_pos, &self.markers[index].label);
+            }
+        }
+        self.draw_marker_label.color = label_color;
+        self.draw_marker_label_bg.color = label_bg_color;
+    }
+
+    /// Clockwise screen angle in radians of the marker's `rotation_deg`, found
+    /// by projecting a short step in that direction so the map's bearing and
+    /// pitch are accounted for
+    fn marker_screen_rotation(&self, marker: &MapMarker) -> f64 {
+        if marker.shape == MarkerShape::Pin {
+            return 0.0;
+        }
+        if self.pitch == 0.0 {
+            return (marker.rotation_deg - self.bearing).to_radians();
+        }
+        let (lng, lat) = destination(marker.lng, marker.lat, marker.rotation_deg, 1.0);
+        let step = self.geo_to_screen(lng, lat) - self.geo_to_screen(marker.lng, marker.lat);
+        step.x.atan2(-step.y)
+    }
+
+    /// Top left of a marker label of `text_size` for the pin whose point is at `screen_pos`
+    fn marker_label_pos(&self, screen_pos: DVec2, text_size: DVec2, shape: MarkerShape, placement: LabelPlacement, label: &str) -> DVec2 {
+        let head_y = screen_pos.y - self.marker_size * shape.center_height();
+        let (above, below) = shape.extent();
+        let half_width = self.marker_size / 2.0;
+        let gap = 8.0;
+        let aligned_x = || match self.marker_label_align {
+            LabelAlign::Start if is_right_to_left(label) => screen_pos.x - text_size.x,
+            LabelAlign::Start | LabelAlign::Left => screen_pos.x,
+            LabelAlign::Right => screen_pos.x - text_size.x,
+            LabelAlign::Center => screen_pos.x - text_size.x / 2.0,
+        };
+        match placement {
+            LabelPlacement::Below => dvec2(aligned_x(), screen_pos.y + self.marker_size * below + gap),
+            LabelPlacement::Above => dvec2(aligned_x(), screen_pos.y - self.marker_size * above - gap - text_size.y),
+            LabelPlacement::Left => dvec2(screen_pos.x - half_width - gap - text_size.x, head_y - text_size.y / 2.0),
+            LabelPlacement::Right => dvec2(screen_pos.x + half_width + gap, head_y - text_size.y / 2.0),
+            LabelPlacement::Center => dvec2(screen_pos.x - text_size.x / 2.0, head_y - text_size.y / 2.0),
+        }
+    }
+
+    /// Draw the layer control: base maps with radio buttons, then overlay layers with checkboxes
+    fn draw_layer_control(&mut self, cx: &mut Cx2d, rect: Rect) {
+        let mut entries: Vec<(LayerControlEntry, String, bool)> = self.base_layers.iter()
+            .map(|base| (LayerControlEntry::Base(base.id), base.title.clone(), self.active_base_layer == Some(base.id)))
+            .collect();
+        let base_count = entries.len();
+        for (id, layer) in &self.layers {
+            if layer.in_layer_control() {
+                let title = self.layer_titles.get(id).cloned().unwrap_or_else(|| id.to_string());
+                entries.push((LayerControlEntry::Overlay(*id), title, layer.is_visible()));
+            }
+        }
+        self.layer_control_rows.clear();
+        self.layer_control_rect = None;
+        if entries.is_empty() {
+            return;
+        }
+
+        let margin = 10.0;
+        let padding = 8.0;
+        let font_size = self.draw_layer_control_text.text_style.font_size as f64;
+        // Space between the base maps and the overlays
+        let gap = if base_count > 0 && base_count < entries.len() { padding } else { 0.0 };
+        let text_width = entries.iter()
+            .map(|(_, title, _)| measure_text(cx, &self.draw_layer_control_text, title).x)
+            .fold(0.0, f64::max);
+        let size = dvec2(
+            padding * 3.0 + LAYER_CONTROL_BOX_SIZE + text_width,
+            padding * 2.0 + gap + LAYER_CONTROL_ROW_HEIGHT * entries.len() as f64,
+        );
+        let pos = self.layer_control_corner.place(rect, size, margin);
+        self.draw_layer_control_bg.draw_abs(cx, Rect { pos, size });
+
+        let mut y = pos.y + padding;
+        for (i, (entry, title, checked)) in entries.into_iter().enumerate() {
+            if i == base_count {
+                y += gap;
+            }
+            let center = dvec2(pos.x + padding + LAYER_CONTROL_BOX_SIZE / 2.0, y + LAYER_CONTROL_ROW_HEIGHT / 2.0);
+            match entry {
+                LayerControlEntry::Base(_) => {
+                    self.draw_layer_control_radio.draw_circle(cx, center, LAYER_CONTROL_BOX_SIZE / 2.0);
+                    if checked {
+                        self.draw_layer_control_dot.draw_circle(cx, center, LAYER_CONTROL_BOX_SIZE / 4.0);
+                    }
+                }
+                LayerControlEntry::Overlay(_) => {
+                    let half = LAYER_CONTROL_BOX_SIZE / 2.0;
+                    self.draw_layer_control_box.draw_abs(cx, Rect {
+                        pos: center - dvec2(half, half),
+                        size: dvec2(LAYER_CONTROL_BOX_SIZE, LAYER_CONTROL_BOX_SIZE),
+                    });
+                    if checked {
+                        self.draw_layer_control_check.draw_abs(cx, Rect {
+                            pos: center - dvec2(half - 3.0, half - 3.0),
+                            size: dvec2(LAYER_CONTROL_BOX_SIZE - 6.0, LAYER_CONTROL_BOX_SIZE - 6.0),
+                        });
+                    }
+                }
+            }
+            let text_pos = dvec2(pos.x + padding * 2.0 + LAYER_CONTROL_BOX_SIZE, y + (LAYER_CONTROL_ROW_HEIGHT - font_size * 1.2) / 2.0);
+            self.draw_layer_control_text.draw_abs(cx, text_pos, &title);
+            self.layer_control_rows.push((Rect {
+                pos: dvec2(pos.x, y),
+                size: dvec2(size.x, LAYER_CONTROL_ROW_HEIGHT),
+            }, entry));
+            y += LAYER_CONTROL_ROW_HEIGHT;
+        }
+        self.layer_control_rect = Some(Rect { pos, size });
+    }
+
+    /// Compass needle pointing north: red half towards north, grey half towards south
+    fn draw_compass(&mut self, cx: &mut Cx2d, rect: Rect) {
+        let size = dvec2(COMPASS_RADIUS * 2.0, COMPASS_RADIUS * 2.0);
+        let pos = self.compass_corner.place(rect, size, 10.0);
+        let center = pos + size / 2.0;
+        self.compass_rect = Some(Rect { pos, size });
+        self.draw_compass_bg.draw_circle(cx, center, COMPASS_RADIUS);
+
+        let angle = -self.bearing.to_radians();
+        let north = dvec2(angle.sin(), -angle.cos());
+        let side = dvec2(-north.y, north.x) * COMPASS_RADIUS * 0.25;
+        let length = COMPASS_RADIUS * 0.7;
+        self.draw_compass_north.draw_triangle(cx, center + north * length, center + side, center - side);
+        self.draw_compass_south.draw_triangle(cx, center - north * length, center - side, center + side);
+    }
+
+    fn hits_compass(&self, abs: DVec2) -> bool {
+        self.compass_rect.is_some_and(|rect| (abs - (rect.pos + rect.size / 2.0)).length() <= COMPASS_RADIUS)
+    }
+
+    fn hits_layer_control(&self, abs: DVec2) -> bool {
+        self.show_layer_control && self.layer_control_rect.is_some_and(|rect| rect.contains(abs))
+    }
+
+    /// The on-map control at an absolute position, topmost first
+    fn control_at(&self, abs: DVec2) -> Option<MapControl> {
+        if self.context_menu_rect.is_some_and(|rect| rect.contains(abs)) {
+            // The menu is drawn above the other controls
+            let item = self.context_menu_rows.iter().find(|(rect, _)| rect.contains(abs)).map(|(_, id)| *id);
+            Some(MapControl::ContextMenu(item))
+        } else if self.hits_compass(abs) {
+            Some(MapControl::Compass)
+        } else if self.hits_layer_control(abs) {
+            Some(MapControl::LayerControl(self.layer_control_entry_at(abs)))
+        } else if self.attribution_rect.is_some_and(|rect| rect.contains(abs)) {
+            Some(MapControl::Attribution)
+        } else if self.show_scale_bar && self.scale_bar_rect.is_some_and(|rect| rect.contains(abs)) {
+            Some(MapControl::ScaleBar)
+        } else {
+            None
+        }
+    }
+
+    /// Act on a tap on an on-map control
+    fn activate_control(&mut self, cx: &mut Cx, uid: WidgetUid, path: &HeapLiveIdPath, control: MapControl) {
+        match control {
+            MapControl::Compass => self.reset_north(cx),
+            MapControl::LayerControl(Some(entry)) => self.toggle_layer_control_entry(cx, uid, path, entry),
+            MapControl::LayerControl(None) => {}
+            MapControl::Attribution => {
+                if let Some((_, url)) = self.shown_attribution() {
+                    cx.widget_action(uid, path, GeoMapViewAction::AttributionTapped { url });
+                }
+            }
+            MapControl::ScaleBar => cx.widget_action(uid, path, GeoMapViewAction::ScaleBarTapped),
+            MapControl::ContextMenu(Some(id)) => {
+                if let Some((_, lng, lat)) = self.context_menu {
+                    cx.widget_action(uid, path, GeoMapViewAction::ContextMenuItemSelected { id, lng, lat });
+                }
+                self.close_context_menu(cx);
+            }
+            MapControl::ContextMenu(None) => {}
+        }
+    }
+
+    /// Draw the context menu items below and right of `pos` (absolute),
+    /// flipped to the other side where the menu would leave the map
+    fn draw_context_menu(&mut self, cx: &mut Cx2d, rect: Rect, pos: DVec2) {
+        let padding = 4.0;
+        let item_padding = 12.0;
+        let font_size = self.draw_context_menu_text.text_style.font_size as f64;
+        let text_width = self.context_menu_items.iter()
+            .map(|(_, title)| measure_text(cx, &self.draw_context_menu_text, title).x)
+            .fold(0.0, f64::max);
+        let size = dvec2(
+            item_padding * 2.0 + text_width,
+            padding * 2.0 + CONTEXT_MENU_ROW_HEIGHT * self.context_menu_items.len() as f64,
+        );
+        let mut menu_pos = pos;
+        if menu_pos.x + size.x > rect.pos.x + rect.size.x {
+            menu_pos.x = pos.x - size.x;
+        }
+        if menu_pos.y + size.y > rect.pos.y + rect.size.y {
+            menu_pos.y = pos.y - size.y;
+        }
+        self.draw_context_menu_bg.draw_abs(cx, Rect { pos: menu_pos, size });
+
+        let mut y = menu_pos.y + padding;
+        let items = std::mem::take(&mut self.context_menu_items);
+        for (id, title) in &items {
+            let text_pos = dvec2(menu_pos.x + item_padding, y + (CONTEXT_MENU_ROW_HEIGHT - font_size * 1.2) / 2.0);
+            self.draw_context_menu_text.draw_abs(cx, text_pos, title);
+            self.context_menu_rows.push((Rect {
+                pos: dvec2(menu_pos.x, y),
+                size: dvec2(size.x, CONTEXT_MENU_ROW_HEIGHT),
+            }, *id));
+            y += CONTEXT_MENU_ROW_HEIGHT;
+        }
+        self.context_menu_items = items;
+        self.context_menu_rect = Some(Rect { pos: menu_pos, size });
+    }
+
+    fn layer_control_entry_at(&self, abs: DVec2) -> Option<LayerControlEntry> {
+        self.layer_control_rows.iter().find(|(rect, _)| rect.contains(abs)).map(|(_, entry)| *entry)
+    }
+
+    /// Toggle an overlay or switch to a base map from the layer control
+    fn toggle_layer_control_entry(&mut self, cx: &mut Cx, uid: WidgetUid, path: &HeapLiveIdPath, entry: LayerControlEntry) {
+        match entry {
+            LayerControlEntry::Overlay(id) => {
+                if let Some((_, layer)) = self.layers.iter_mut().find(|(layer_id, _)| *layer_id == id) {
+                    let visible = !layer.is_visible();
+                    layer.set_visible(visible);
+                    cx.widget_action(uid, path, GeoMapViewAction::LayerToggled { id, visible });
+                }
+            }
+            LayerControlEntry::Base(id) => {
+                let previous = self.active_base_layer;
+                if previous != Some(id) && self.select_base_layer(cx, id) {
+                    if let Some(previous) = previous {
+                        cx.widget_action(uid, path, GeoMapViewAction::LayerToggled { id: previous, visible: false });
+                    }
+                    cx.widget_action(uid, path, GeoMapViewAction::LayerToggled { id, visible: true });
+                }
+            }
+        }
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Check if a segment (viewport coordinates) may cross the viewport
+    fn segment_visible(&self, from: DVec2, to: DVec2, margin: f64) -> bool {
+        !(from.x.max(to.x) < -margin
+            || from.y.max(to.y) < -margin
+            || from.x.min(to.x) > self.viewport_size.x + margin
+            || from.y.min(to.y) > self.viewport_size.y + margin)
+    }
+
+    /// Draw the UTM grid: zone and latitude band boundaries with zone labels,
+    /// plus the kilometer grid of the center's zone when zoomed in
+    fn draw_utm_grid(&mut self, cx: &mut Cx2d, rect: Rect) {
+        let size = self.viewport_size;
+        let (west, _) = self.screen_to_geo(dvec2(0.0, 0.0));
+        let (east, _) = self.screen_to_geo(dvec2(size.x, 0.0));
+        let top = self.geo_to_screen(self.center_lng, 84.0).y.max(0.0);
+        let bottom = self.geo_to_screen(self.center_lng, -80.0).y.min(size.y);
+        if bottom <= top {
+            return;
+        }
+
+        // Zone boundaries every 6° of longitude
+        let zone_line_width = self.draw_grid_line.line_width;
+        self.draw_grid_line.line_width = zone_line_width * 2.0;
+        let first_zone = (west / 6.0).ceil() as i64;
+        let last_zone = (east / 6.0).floor() as i64;
+        for k in first_zone..=last_zone {
+            let x = self.geo_to_screen_unwrapped(k as f64 * 6.0, 0.0).x;
+            self.draw_grid_line.draw_line(cx, rect.pos + dvec2(x, top), rect.pos + dvec2(x, bottom));
+        }
+
+        // Latitude band boundaries every 8° (the X band is 12° tall)
+        let mut band_lats: Vec<f64> = (0..=19).map(|i| -80.0 + i as f64 * 8.0).collect();
+        band_lats.push(84.0);
+        for lat in band_lats {
+            let y = self.geo_to_screen(self.center_lng, lat).y;
+            if y >= 0.0 && y <= size.y {
+                self.draw_grid_line.draw_line(cx, rect.pos + dvec2(0.0, y), rect.pos + dvec2(size.x, y));
+            }
+        }
+        self.draw_grid_line.line_width = zone_line_width;
+
+        // Zone labels when zoomed out far enough that cells are small
+        let zone_width_px = 6.0 * world_size(self.zoom) / 360.0;
+        if self.zoom < UTM_GRID_MIN_ZOOM && zone_width_px > 30.0 {
+            for k in (first_zone - 1)..=last_zone {
+                let zone_lng = k as f64 * 6.0 + 3.0;
+                for i in 0..20 {
+                    let band_lat = -80.0 + i as f64 * 8.0 + if i == 19 { 6.0 } else { 4.0 };
+                    let pos = self.geo_to_screen_unwrapped(zone_lng, band_lat);
+                    if pos.x < 0.0 || pos.x > size.x || pos.y < 0.0 || pos.y > size.y {
+                        continue;
+                    }
+                    let zone = utm::utm_zone(zone_lng, band_lat);
+                    if let Some(band) = utm::utm_band(band_lat) {
+                        let label = format!("{}{}", zone, band);
+                        self.draw_grid_label.draw_abs(cx, rect.pos + pos, &label);
+                    }
+                }
+            }
+            return;
+        }
+
+        if self.zoom < UTM_GRID_MIN_ZOOM {
+            return;
+        }
+
+        // Grid of the center's zone, in a zone-consistent hemisphere
+        let zone = utm::utm_zone(self.center_lng, self.center_lat);
+        let north = self.center_lat >= 0.0;
+        let spacing = if self.zoom < 10.0 {
+            100000.0
+        } else if self.zoom < 13.0 {
+            10000.0
+        } else if self.zoom < 16.0 {
+            1000.0
+        } else {
+            100.0
+        };
+
+        // UTM extent of the viewport (sampled on its edges)
+        let mut e_min = f64::MAX;
+        let mut e_max = f64::MIN;
+        let mut n_min = f64::MAX;
+        let mut n_max = f64::MIN;
+        for i in 0..=4 {
+            let f = i as f64 / 4.0;
+            for screen_pos in [
+                dvec2(size.x * f, 0.0),
+                dvec2(size.x * f, size.y),
+                dvec2(0.0, size.y * f),
+                dvec2(size.x, size.y * f),
+            ] {
+                let (lng, lat) = self.screen_to_geo(screen_pos);
+                let Some(p) = utm::to_utm_in_zone(lng, lat.clamp(-80.0, 84.0), zone) else {
+                    continue;
+                };
+                let northing = match (p.north, north) {
+                    (true, false) => p.northing + 10000000.0,
+                    (false, true) => p.northing - 10000000.0,
+                    _ => p.northing,
+                };
+                e_min = e_min.min(p.easting);
+                e_max = e_max.max(p.easting);
+                n_min = n_min.min(northing);
+                n_max = n_max.max(northing);
+            }
+        }
+        if e_min > e_max || n_min > n_max {
+            return;
+        }
+
+        let first_e = (e_min / spacing).ceil() as i64;
+        let last_e = (e_max / spacing).floor() as i64;
+        let first_n = (n_min / spacing).ceil() as i64;
+        let last_n = (n_max / spacing).floor() as i64;
+        if last_e - first_e > 100 || last_n - first_n > 100 {
+            return;
+        }
+
+        let format_km = |meters: f64| {
+            if spacing >= 1000.0 {
+                format!("{}", (meters / 1000.0).round() as i64)
+            } else {
+                format!("{:.1}", meters / 1000.0)
+            }
+        };
+
+        // Easting lines (roughly north-south), labeled at the top edge
+        for e in first_e..=last_e {
+            let easting = e as f64 * spacing;
+            let points: Vec<DVec2> = (0..=UTM_GRID_SEGMENTS).map(|i| {
+                let northing = n_max - (n_max - n_min) * i as f64 / UTM_GRID_SEGMENTS as f64;
+                let (lng, lat) = utm::from_utm(zone, north, easting, northing);
+                self.geo_to_screen(lng, lat)
+            }).collect();
+            for segment in points.windows(2) {
+                self.draw_grid_line.draw_line(cx, rect.pos + segment[0], rect.pos + segment[1]);
+            }
+            let label = format!("{}km E", format_km(easting));
+            let label_x = points[0].x + 3.0;
+            if label_x > 0.0 && label_x < size.x - 20.0 {
+                self.draw_grid_label.draw_abs(cx, rect.pos + dvec2(label_x, 4.0), &label);
+            }
+        }
+
+        // Northing lines (roughly east-west), labeled at the left edge
+        for n in first_n..=last_n {
+            let northing = n as f64 * spacing;
+            let points: Vec<DVec2> = (0..=UTM_GRID_SEGMENTS).map(|i| {
+                let easting = e_min + (e_max - e_min) * i as f64 / UTM_GRID_SEGMENTS as f64;
+                let (lng, lat) = utm::from_utm(zone, north, easting, northing);
+                self.geo_to_screen(lng, lat)
+            }).collect();
+            for segment in points.windows(2) {
+                self.draw_grid_line.draw_line(cx, rect.pos + segment[0], rect.pos + segment[1]);
+            }
+            let display_northing = if north { northing } else { northing.rem_euclid(10000000.0) };
+            let label = format!("{}km N", format_km(display_northing));
+            let label_y = points[0].y - 14.0;
+            if label_y > 0.0 && label_y < size.y - 14.0 {
+                self.draw_grid_label.draw_abs(cx, rect.pos + dvec2(4.0, label_y), &label);
+            }
+        }
+
+        // Zone designation in the top-left corner
+        if let Some(band) = utm::utm_band(self.center_lat) {
+            let label = format!("UTM {}{}", zone, band);
+            self.draw_grid_label.draw_abs(cx, rect.pos + dvec2(4.0, 20.0), &label);
+        }
+    }
+
+    /// Draw a popup bubble centered above a marker whose pin tip is at `anchor` (absolute)
+    fn draw_marker_popup(&mut self, cx: &mut Cx2d, anchor: DVec2, text: &str) {
+        let text_size = measure_text(cx, &self.draw_popup_text, text);
+        let padding = 8.0;
+
+        let bg_size = text_size + dvec2(padding * 2.0, padding * 2.0);
+        let bg_pos = dvec2(
+            anchor.x - bg_size.x / 2.0,
+            anchor.y - self.marker_size - bg_size.y - 4.0,
+        );
+        self.draw_popup_bg.draw_abs(cx, Rect { pos: bg_pos, size: bg_size });
+        self.draw_popup_text.draw_abs(cx, bg_pos + dvec2(padding, padding), text);
+    }
+
+    /// Draw a plus at the camera center (shifted by the map's padding)
+    fn draw_crosshair(&mut self, cx: &mut Cx2d, rect: Rect) {
+        let center = rect.pos + self.screen_center();
+        let (arm, gap) = (10.0, 3.0);
+        for direction in [dvec2(1.0, 0.0), dvec2(-1.0, 0.0), dvec2(0.0, 1.0), dvec2(0.0, -1.0)] {
+            self.draw_crosshair.draw_line(cx, center + direction * gap, center + direction * arm);
+        }
+    }
+
+    /// Position the readout shows: under the mouse, else at the crosshair
+    fn readout_position(&self) -> Option<(f64, f64)> {
+        let pos = self.cursor_pos.or_else(|| self.show_crosshair.then(|| self.screen_center()))?;
+        let (lng, lat) = self.screen_to_geo(pos);
+        Some((wrap_lng(lng), lat))
+    }
+
+    /// Draw the readout position in a corner, with the coordinate provider's
+    /// readout (e.g. MGRS) on a second line
+    fn draw_cursor_readout(&mut self, cx: &mut Cx2d, rect: Rect) {
+        let Some((lng, lat)) = self.readout_position() else {
+            return;
+        };
+        let position = self.format_position(lng, lat);
+        let provider_readout = self.coordinate_readout(lng, lat).filter(|readout| *readout != position);
+        let lines: Vec<String> = std::iter::once(position).chain(provider_readout).collect();
+
+        let margin = 10.0;
+        let padding = 4.0;
+        let font_size = self.draw_stats_text.text_style.font_size as f64;
+        let line_height = font_size * 1.4;
+        let text_width = lines.iter()
+            .map(|line| measure_text(cx, &self.draw_stats_text, line).x)
+            .fold(0.0, f64::max);
+        let size = dvec2(text_width + padding * 2.0, line_height * lines.len() as f64 + padding * 2.0);
+        let mut pos = self.cursor_readout_corner.place(rect, size, margin);
+        // Stay clear of the scale bar in its corner
+        if self.show_scale_bar && self.cursor_readout_corner == MapCorner::BottomLeft {
+            pos.y -= 24.0;
+        }
+        self.draw_attribution_bg.draw_abs(cx, Rect { pos, size });
+        for (i, line) in lines.iter().enumerate() {
+            self.draw_stats_text.draw_abs(cx, pos + dvec2(padding, padding + line_height * i as f64), line);
+        }
+    }
+
+    /// Follow the mouse for the readout and `CursorMoved`. Reports are
+    /// throttled to one per `cursor_moved_interval`: the first goes out at
+    /// once, later moves within the interval are reported when it ends.
+    fn update_cursor(&mut self, cx: &mut Cx, uid: WidgetUid, path: &HeapLiveIdPath, abs_pos: DVec2) {
+        self.cursor_pos = Some(abs_pos - self.viewport_pos);
+        if self.show_cursor_readout {
+            self.draw_tile.redraw(cx);
+        }
+        if !self.track_cursor {
+            return;
+        }
+        if self.cursor_timer.is_empty() {
+            self.emit_cursor_moved(cx, uid, path);
+            self.cursor_timer = cx.start_timeout(self.cursor_moved_interval.max(0.0));
+        } else {
+            self.cursor_moved_pending = true;
+        }
+    }
+
+    fn emit_cursor_moved(&self, cx: &mut Cx, uid: WidgetUid, path: &HeapLiveIdPath) {
+        if let Some(pos) = self.cursor_pos {
+            let (lng, lat) = self.screen_to_geo(pos);
+            cx.widget_action(uid, path, GeoMapViewAction::CursorMoved { lng: wrap_lng(lng), lat });
+        }
+    }
+
+    /// Draw a tooltip below and right of the pointer at `pos` (absolute),
+    /// flipped to the other side where it would leave the map
+    fn draw_overlay_tooltip(&mut self, cx: &mut Cx2d, rect: Rect, pos: DVec2, text: &str) {
+        let text_size = measure_text(cx, &self.draw_tooltip_text, text);
+        let padding = 5.0;
+        let offset = 14.0;
+
+        let bg_size = text_size + dvec2(padding * 2.0, padding * 2.0);
+        let mut bg_pos = pos + dvec2(offset, offset);
+        if bg_pos.x + bg_size.x > rect.pos.x + rect.size.x {
+            bg_pos.x = pos.x - offset - bg_size.x;
+        }
+        if bg_pos.y + bg_size.y > rect.pos.y + rect.size.y {
+            bg_pos.y = pos.y - offset - bg_size.y;
+        }
+        self.draw_tooltip_bg.draw_abs(cx, Rect { pos: bg_pos, size: bg_size });
+        self.draw_tooltip_text.draw_abs(cx, bg_pos + dvec2(padding, padding), text);
+    }
+
+    /// Calculate meters per pixel at the current zoom level and map center
+    fn meters_per_pixel(&self) -> f64 {
+        self.projection().meters_per_unit(self.center_lng, self.center_lat) / world_size(self.zoom)
+    }
+
+    /// Ground meters per pixel across a horizontal line of `width` pixels
+    /// through the map center, measured along the great circle. Unlike
+    /// `meters_per_pixel` it follows rotation, tilt and the projection's
+    /// distortion away from the center; falls back to it where the line
+    /// leaves the map (e.g. the edge of a polar projection).
+    fn ground_meters_per_pixel(&self, width: f64) -> f64 {
+        let center = self.geo_to_screen(self.center_lng, self.center_lat);
+        let (lng1, lat1) = self.screen_to_geo(center - dvec2(width / 2.0, 0.0));
+        let (lng2, lat2) = self.screen_to_geo(center + dvec2(width / 2.0, 0.0));
+        let meters = haversine_distance(lng1, lat1, lng2, lat2);
+        if meters.is_finite() && meters > 0.0 {
+            meters / width
+        } else {
+            self.meters_per_pixel()
+        }
+    }
+
+    /// Calculate the scale bar width and label for a given maximum width
+    fn calculate_scale_bar(&self, max_width: f64) -> (f64, String) {
+        let mpp = self.ground_meters_per_pixel(max_width);
+        let max_meters = max_width * mpp;
+
+        // Find largest step that fits within max_width
+        let mut steps = self.locale.scale_steps().into_iter();
+        let mut selected = steps.next().unwrap_or_default();
+        for step in steps {
+            if step.0 <= max_meters {
+                selected = step;
+            } else {
+                break;
+            }
+        }
+
+        let (meters, label) = selected;
+        (meters / mpp, label)
+    }
+
+    /// Calculate flick velocity (pixels per second) from the samples within
+    /// VELOCITY_WINDOW before release, independent of the input event rate
+    fn calculate_flick_velocity(&self, release_pos: DVec2, release_time: f64) -> DVec2 {
+        let Some(&(start_pos, start_time)) = self.velocity_samples
+            .iter()
+            .find(|(_, time)| release_time - time <= VELOCITY_WINDOW)
+        else {
+            // The finger rested before release
+            return DVec2::default();
+        };
+
+        let dt = release_time - start_time;
+        if dt > 0.0001 {
+            (release_pos - start_pos) / dt
+        } else {
+            DVec2::default()
+        }
+    }
+
+    /// Rings of tiles loaded beyond the viewport: `overscan_tiles`, or none
+    /// in low power mode to save bandwidth and memory
+    fn effective_overscan_tiles(&self) -> usize {
+        if self.low_power {
+            0
+        } else {
+            self.overscan_tiles.min(MAX_OVERSCAN_TILES)
+        }
+    }
+
+    /// Ask for an animation frame: the next one, or in low power mode one
+    /// after `LOW_POWER_FRAME_TIME` so the display isn't kept busy
+    fn request_frame(&mut self, cx: &mut Cx) {
+        if !self.low_power {
+            self.next_frame = cx.new_next_frame();
+        } else if self.frame_timer.is_empty() {
+            self.frame_timer = cx.start_timeout(LOW_POWER_FRAME_TIME);
+        }
+    }
+
+    /// Redraw after a gesture moved the camera, in low power mode at most
+    /// every `LOW_POWER_FRAME_TIME` (a timer catches the last movement)
+    fn redraw_gesture(&mut self, cx: &mut Cx, time: f64) {
+        if self.low_power {
+            let elapsed = time - self.last_gesture_redraw;
+            if (0.0..LOW_POWER_FRAME_TIME).contains(&elapsed) {
+                if self.gesture_redraw_timer.is_empty() {
+                    self.gesture_redraw_timer = cx.start_timeout(LOW_POWER_FRAME_TIME - elapsed);
+                }
+                return;
+            }
+            self.last_gesture_redraw = time;
+        }
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Apply momentum decay and update map position based on the real elapsed
+    /// time, so the glide is the same at 60Hz and 120Hz
+    fn apply_momentum(&mut self, cx: &mut Cx, time: f64, uid: WidgetUid, path: &HeapLiveIdPath) {
+        // Elapsed seconds since the previous momentum frame (one reference frame at the start)
+        let dt = match self.last_momentum_time.replace(time) {
+            Some(last_time) => (time - last_time).clamp(0.0, 0.1),
+            None => 1.0 / REFERENCE_FRAME_RATE,
+        };
+
+        // momentum_decay is the fraction kept per reference frame
+        self.flick_velocity *= self.momentum_decay.powf(dt * REFERENCE_FRAME_RATE);
+
+        let speed = self.flick_velocity.x.hypot(self.flick_velocity.y) / REFERENCE_FRAME_RATE;
+        if speed < self.momentum_threshold * 0.01 {
+            self.is_flicking = false;
+            self.emit_region_changed(cx, uid, path);
+            return;
+        }
+
+        let movement = self.screen_delta_to_world(self.screen_center(), self.screen_center() + self.flick_velocity * dt);
+        self.pan_from((self.center_lng, self.center_lat), movement);
+
+        self.draw_tile.redraw(cx);
+        self.request_frame(cx);
+    }
+
+    /// Advance a press-and-hold zoom around the center, ending it at the zoom limits
+    fn apply_continuous_zoom(&mut self, cx: &mut Cx, time: f64, uid: WidgetUid, path: &HeapLiveIdPath) {
+        let dt = match self.last_zoom_time.replace(time) {
+            Some(last_time) => (time - last_time).clamp(0.0, 0.1),
+            None => 1.0 / REFERENCE_FRAME_RATE,
+        };
+        let zoom = self.clamp_user_zoom(cx, uid, path, self.zoom + self.zoom_direction * self.zoom_rate * dt);
+        if zoom == self.zoom {
+            self.zoom_direction = 0.0;
+            self.emit_region_changed(cx, uid, path);
+            return;
+        }
+        self.zoom = zoom;
+        self.draw_tile.redraw(cx);
+        self.request_frame(cx);
+    }
+
+    fn emit_region_changed(&self, cx: &mut Cx, uid: WidgetUid, path: &HeapLiveIdPath) {
+        cx.widget_action(
+            uid,
+            path,
+            GeoMapViewAction::RegionChanged {
+                center_lng: self.center_lng,
+                center_lat: self.center_lat,
+                zoom: self.zoom,
+            },
+        );
+    }
+
+    fn emit_coordinate_lookup(cx: &mut Cx, uid: WidgetUid, path: &HeapLiveIdPath, lookup: CoordinateLookup) {
+        cx.widget_action(
+            uid,
+            path,
+            GeoMapViewAction::CoordinateLookedUp {
+                lng: lookup.lng,
+                lat: lookup.lat,
+                text: lookup.text,
+            },
+        );
+    }
+
+    /// Report a finished geocoding request, flying to the first result of a `search_and_fly_to`
+    fn handle_geocode_response(&mut self, cx: &mut Cx, uid: WidgetUid, path: &HeapLiveIdPath, response: GeocodeResponse) {
+        if self.long_press_lookup == Some(response.request_id) {
+            self.long_press_lookup = None;
+            if let GeocodeQuery::Reverse { lng, lat } = response.query {
+                let address = match response.result {
+                    Ok(results) => results.into_iter().next().map(|result| result.address),
+                    Err(error) => {
+                        log!("Reverse geocoding {}, {} failed: {}", lng, lat, error);
+                        None
+                    }
+                };
+                cx.widget_action(uid, path, GeoMapViewAction::LongPressedWithAddress { lng, lat, address });
+            }
+            return;
+        }
+        let results = match response.result {
+            Ok(results) => results,
+            Err(error) => {
+                if self.fly_to_search == Some(response.request_id) {
+                    self.fly_to_search = None;
+                }
+                cx.widget_action(uid, path, GeoMapViewAction::GeocodeFailed { query: response.query, error });
+                return;
+            }
+        };
+        if self.fly_to_search == Some(response.request_id) {
+            self.fly_to_search = None;
+            if let Some(first) = results.first() {
+                self.fly_to_result(cx, first);
+            }
+        }
+        let action = match response.query {
+            GeocodeQuery::Search(query) => GeoMapViewAction::GeocodeResults { query, results },
+            GeocodeQuery::Reverse { lng, lat } => GeoMapViewAction::ReverseGeocoded {
+                lng,
+                lat,
+                result: results.into_iter().next(),
+            },
+        };
+        cx.widget_action(uid, path, action);
+    }
+
+    /// Show the route of the latest `request_route` and report it; answers to
+    /// replaced requests are dropped
+    fn handle_route_response(&mut self, cx: &mut Cx, uid: WidgetUid, path: &HeapLiveIdPath, response: RouteResponse) {
+        if self.route_request != Some(response.request_id) {
+            return;
+        }
+        self.route_request = None;
+        let route = match response.result {
+            Ok(route) => route,
+            Err(error) => {
+                cx.widget_action(uid, path, GeoMapViewAction::RouteFailed { error });
+                return;
+            }
+        };
+        self.clear_route(cx);
+        let style = self.route_style;
+        let line = self.add_polyline(cx, ROUTE_POLYLINE, route.points.clone());
+        line.color = style.color;
+        line.width = style.width;
+        if style.show_endpoints {
+            if let (Some(&(start_lng, start_lat)), Some(&(end_lng, end_lat))) = (response.waypoints.first(), response.waypoints.last()) {
+                self.add_marker(cx, ROUTE_START_MARKER, start_lng, start_lat).color = style.start_color;
+                self.add_marker(cx, ROUTE_END_MARKER, end_lng, end_lat).color = style.end_color;
+            }
+        }
+        let (distance, duration) = (route.distance, route.duration);
+        self.route = Some(route);
+        cx.widget_action(uid, path, GeoMapViewAction::RouteReady { distance, duration });
+    }
+
+    /// Zoom at which bounds fill the padded viewport, with a small margin
+    fn camera_to_fit(&self, bounds: &GeoBounds) -> (f64, f64, f64) {
+        let size = if self.viewport_size.x > 0.0 && self.viewport_size.y > 0.0 {
+            self.padded_size()
+        } else {
+            DVec2::default()
+        };
+        camera_for_bounds(self.projection(), bounds, size, self.effective_min_zoom(), self.max_zoom).unwrap_or_else(|| {
+            let (lng, lat) = bounds.center();
+            (lng, lat, SEARCH_RESULT_ZOOM.min(self.max_zoom))
+        })
+    }
+
+    fn show_gesture_hint(&mut self, cx: &mut Cx, hint: GestureHint) {
+        self.gesture_hint = Some(hint);
+        self.gesture_hint_timer = cx.start_timeout(GESTURE_HINT_DURATION);
+        self.draw_tile.redraw(cx);
+    }
+
+    fn hide_gesture_hint(&mut self, cx: &mut Cx) {
+        if self.gesture_hint.take().is_some() {
+            cx.stop_timer(self.gesture_hint_timer);
+            self.gesture_hint_timer = Timer::empty();
+            self.draw_tile.redraw(cx);
+        }
+    }
+
+    /// Dim the map and center the cooperative gesture hint on it
+    fn draw_gesture_hint(&mut self, cx: &mut Cx2d, rect: Rect, hint: GestureHint) {
+        let text = match hint {
+            GestureHint::TwoFingers => self.cooperative_touch_hint.clone(),
+            GestureHint::ModifierScroll => self.cooperative_wheel_hint.clone(),
+        };
+        self.draw_hint_bg.draw_abs(cx, rect);
+        let text_size = measure_text(cx, &self.draw_hint_text, &text);
+        let pos = rect.pos + (rect.size - text_size) / 2.0;
+        self.draw_hint_text.draw_abs(cx, pos, &text);
+    }
+
+    /// Request the visible tiles nearest the center first, so a limited request
+    /// budget is spent where the user is looking; the rest fall back to parents.
+    /// Only done when the camera moved onto other tiles or tiles left the
+    /// cache, not on every redraw (hover, animations of markers and overlays).
+    fn request_tiles(&mut self, cx: &mut Cx, grid: &TileGrid) {
+        let coords: Vec<TileCoord> = grid.tiles().into_iter().map(|(coord, _)| coord).collect();
+        let generation = self.tile_cache.request_generation();
+        if self.requested_tiles.as_ref().is_some_and(|(requested, requested_generation)| {
+            *requested == coords && *requested_generation == generation
+        }) {
+            return;
+        }
+        self.tile_cache.adapt_request_budget(coords.len(), self.tile_request_budget, self.max_pending_tiles);
+        self.tile_cache.set_request_guard(self.max_tile_repeats_per_minute, self.max_tile_client_errors_per_minute);
+        self.tile_cache.begin_frame();
+        for &coord in &coords {
+            self.tile_cache.request_tile(cx, coord);
+        }
+        if let Some(max_age) = self.refresh_age() {
+            self.tile_cache.refresh_tiles(cx, &coords, max_age);
+        }
+        if self.tile_cache.has_deferred_requests() {
+            // Tiles over the per-frame budget are requested on the next frame,
+            // those over the limit in flight once a response frees a slot
+            self.requested_tiles = None;
+            if self.tile_cache.needs_next_frame() {
+                self.request_frame(cx);
+            }
+        } else {
+            self.requested_tiles = Some((coords, self.tile_cache.request_generation()));
+        }
+    }
+
+    /// Age after which visible tiles are refetched: the active base layer's
+    /// refresh interval, else `tile_refresh_interval` (None never)
+    fn refresh_age(&self) -> Option<Duration> {
+        let interval = self.active_base_layer
+            .and_then(|id| self.base_layers.iter().find(|base| base.id == id))
+            .map_or(self.tile_refresh_interval, |base| base.refresh_interval);
+        (interval.is_finite() && interval > 0.0).then(|| Duration::from_secs_f64(interval))
+    }
+
+    /// Wake up for the next refresh once a visible tile gets too old. Tiles
+    /// still loading start the timer on a later draw.
+    fn schedule_tile_refresh(&mut self, cx: &mut Cx) {
+        let Some(max_age) = self.refresh_age() else {
+            return;
+        };
+        if !self.tile_refresh_timer.is_empty() {
+            return;
+        }
+        let Some((coords, _)) = &self.requested_tiles else {
+            return;
+        };
+        if let Some(secs) = self.tile_cache.next_refresh(coords, max_age) {
+            // Not below a second, refreshes waiting for the request limits retry then
+            self.tile_refresh_timer = cx.start_timeout(secs.max(1.0));
+        }
+    }
+
+    /// Refetch all visible tiles now, keeping the cached ones on screen until
+    /// the new ones arrive (unlike clearing the cache)
+    pub fn refresh_tiles(&mut self, cx: &mut Cx) {
+        if let Some((coords, _)) = self.requested_tiles.clone() {
+            self.tile_cache.refresh_tiles(cx, &coords, Duration::ZERO);
+        }
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Report guardrail backoffs and provider throttles, and schedule the
+    /// redraw that resumes requests once they end
+    fn emit_tile_request_warnings(&mut self, cx: &mut Cx, uid: WidgetUid, path: &HeapLiveIdPath) {
+        if let Some(remaining) = self.tile_cache.backoff_remaining() {
+            if self.backoff_timer.is_empty() {
+                self.backoff_timer = cx.start_timeout(remaining);
+            }
+        }
+        if let Some(remaining) = self.tile_cache.next_timeout() {
+            if self.tile_timeout_timer.is_empty() {
+                self.tile_timeout_timer = cx.start_timeout(remaining);
+            }
+        }
+        for (warning, duration) in self.tile_cache.take_warnings() {
+            cx.widget_action(uid, path, GeoMapViewAction::TileRequestsBackedOff { warning, duration });
+        }
+        for throttle in self.tile_cache.take_throttles() {
+            cx.widget_action(uid, path, GeoMapViewAction::ProviderThrottled {
+                host: throttle.host,
+                status: throttle.status,
+                duration: throttle.duration,
+            });
+        }
+    }
+
+    fn emit_tile_loading(&mut self, cx: &mut Cx, uid: WidgetUid, path: &HeapLiveIdPath) {
+        let pending = self.tile_cache.loading_count();
+        let finished = self.tile_cache.finished_load_count();
+        if pending == 0 && !self.tile_cache.has_deferred_requests() {
+            if self.tile_loading.take().is_some() || !self.tiles_idle_reported {
+                self.tiles_idle_reported = true;
+                cx.widget_action(uid, path, GeoMapViewAction::TilesIdle);
+            }
+            return;
+        }
+        let (started_at, last_pending, last_total) = self.tile_loading.unwrap_or((finished, 0, 0));
+        let total = pending + (finished - started_at) as usize;
+        if (pending, total) != (last_pending, last_total) {
+            cx.widget_action(uid, path, GeoMapViewAction::TilesLoading { pending, total });
+        }
+        self.tile_loading = Some((started_at, pending, total));
+    }
+
+    fn emit_download_progress(&mut self, cx: &mut Cx, uid: WidgetUid, path: &HeapLiveIdPath) {
+        let progress = self.tile_cache.download_progress();
+        if progress != self.last_download_progress {
+            self.last_download_progress = progress;
+            cx.widget_action(uid, path, GeoMapViewAction::DownloadProgressChanged { progress });
+        }
+    }
+
+    /// Set the projection of the tile pyramid (None = Web Mercator). The tile
+    /// server must serve tiles in the same projection.
+    pub fn set_projection(&mut self, cx: &mut Cx, projection: Option<Box<dyn Projection>>) {
+        self.stop_animations(cx);
+        self.projection = projection.map(Rc::from);
+        self.marker_index = None;
+        self.simplified_polylines.clear();
+        self.requested_tiles = None;
+        self.tile_pass = None;
+        let tile_projection = self.projection.clone().unwrap_or_else(|| Rc::new(WebMercator));
+        self.tile_cache.set_projection(tile_projection);
+        self.normalize_coordinates();
+        self.draw_tile.redraw(cx);
+    }
+
+    /// CRS identifier of the current projection (e.g. "EPSG:3857")
+    pub fn projection_code(&self) -> &str {
+        self.projection().code()
+    }
+
+    /// Get an overlay layer declared in live_design by its id and type
+    pub fn layer_mut<T: MapLayer>(&mut self, id: LiveId) -> Option<&mut T> {
+        self.layers.iter_mut().find(|(layer_id, _)| *layer_id == id).and_then(|(_, layer)| {
+            let layer: &mut dyn std::any::Any = layer.as_mut();
+            layer.downcast_mut::<T>()
+        })
+    }
+
+    /// Attribution the current tile source's usage policy requires: that of a
+    /// known provider (see `TileSource::required_attribution`), else the one
+    /// set with `set_required_attribution`
+    pub fn required_attribution(&self) -> Option<ProviderAttribution> {
+        self.tile_cache.source().required_attribution().or_else(|| self.custom_attribution.clone())
+    }
+
+    /// Declare the attribution a custom tile source's terms require, so it
+    /// is always shown like that of known providers. It can't replace a known
+    /// provider's attribution.
+    pub fn set_required_attribution(&mut self, cx: &mut Cx, attribution: Option<ProviderAttribution>) {
+        self.custom_attribution = attribution;
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Attribution text and url shown on the map. A required attribution is
+    /// shown even with `show_attribution` off, and put in front of
+    /// `attribution_text` unless that already contains it.
+    fn shown_attribution(&self) -> Option<(String, String)> {
+        match self.required_attribution() {
+            Some(required) if self.show_attribution && self.attribution_text.contains(&required.text) => {
+                Some((self.attribution_text.clone(), self.attribution_url.clone()))
+            }
+            Some(required) if self.show_attribution && !self.attribution_text.is_empty() => {
+                Some((format!("{} {}", required.text, self.attribution_text), required.url))
+            }
+            Some(required) => Some((required.text, required.url)),
+            None => self.show_attribution.then(|| (self.attribution_text.clone(), self.attribution_url.clone())),
+        }
+    }
+
+    /// Set where map tiles are loaded from (XYZ server or WMS layer)
+    pub fn set_tile_source(&mut self, cx: &mut Cx, source: TileSource) {
+        self.tile_cache.set_source(source);
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Fetch tiles through a transport instead of plain HTTP requests, e.g.
+    /// to add auth headers or serve them from an asset pack (None goes back
+    /// to plain HTTP)
+    pub fn set_tile_transport(&mut self, cx: &mut Cx, transport: Option<Box<dyn TileTransport>>) {
+        self.tile_cache.set_transport(transport);
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Hand over the response to a tile request a `TileTransport` fetched on
+    /// its own, by the `request_id` it was given
+    pub fn deliver_tile_response(&mut self, cx: &mut Cx, request_id: LiveId, response: &HttpResponse) {
+        if self.tile_cache.handle_response(cx, request_id, response) {
+            self.draw_tile.redraw(cx);
+        }
+    }
+
+    /// Set the base maps offered by the layer control. Keeps the active one
+    /// if it's still offered, otherwise switches to the first.
+    pub fn set_base_layers(&mut self, cx: &mut Cx, layers: Vec<BaseLayer>) {
+        self.base_layers = layers;
+        let active = self.active_base_layer
+            .filter(|id| self.base_layers.iter().any(|base| base.id == *id))
+            .or_else(|| self.base_layers.first().map(|base| base.id));
+        self.active_base_layer = None;
+        if let Some(id) = active {
+            self.select_base_layer(cx, id);
+        }
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Switch to one of the base maps set with `set_base_layers`.
+    /// Returns false if there is no base layer with this id.
+    pub fn select_base_layer(&mut self, cx: &mut Cx, id: LiveId) -> bool {
+        let Some(base) = self.base_layers.iter().find(|base| base.id == id) else {
+            return false;
+        };
+        if self.active_base_layer != Some(id) {
+            self.active_base_layer = Some(id);
+            let source = base.source.clone();
+            self.set_tile_source(cx, source);
+        }
+        true
+    }
+
+    /// The base map currently shown, if base layers were set
+    pub fn active_base_layer(&self) -> Option<LiveId> {
+        self.active_base_layer
+    }
+
+    /// Name of an overlay layer in the layer control (defaults to its live id)
+    pub fn set_layer_title(&mut self, cx: &mut Cx, id: LiveId, title: &str) {
+        self.layer_titles.insert(id, title.to_string());
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Add an overlay layer from Rust, directly below the markers. A layer
+    /// with the same id is replaced, keeping its place in the draw order.
+    pub fn add_layer(&mut self, cx: &mut Cx, id: LayerId, layer: Box<dyn MapLayer>) {
+        if let Some((_, existing)) = self.layers.iter_mut().find(|(layer_id, _)| *layer_id == id) {
+            *existing = layer;
+        } else {
+            self.insert_layer(id, layer);
+        }
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Remove an overlay layer. Built-in layers can't be removed, only hidden.
+    pub fn remove_layer(&mut self, cx: &mut Cx, id: LayerId) -> Option<Box<dyn MapLayer>> {
+        let index = self.layers.iter().position(|(layer_id, _)| *layer_id == id)?;
+        self.layer_order.retain(|layer_id| *layer_id != id);
+        self.layer_opacity.remove(&id);
+        self.draw_tile.redraw(cx);
+        Some(self.layers.remove(index).1)
+    }
+
+    /// Draw order of all layers, bottom first
+    pub fn layer_order(&self) -> Vec<LayerId> {
+        if !self.layer_order.is_empty() {
+            return self.layer_order.clone();
+        }
+        let mut order = DEFAULT_LAYER_ORDER.to_vec();
+        let markers = order.len() - 1;
+        order.splice(markers..markers, self.layers.iter().map(|(id, _)| *id));
+        order
+    }
+
+    /// Move a layer so it's drawn directly above another one, e.g. a heatmap
+    /// above the markers. Returns false if either layer doesn't exist.
+    pub fn move_layer_above(&mut self, cx: &mut Cx, id: LayerId, other: LayerId) -> bool {
+        let mut order = self.layer_order();
+        if id == other || !order.contains(&id) || !order.contains(&other) {
+            return false;
+        }
+        order.retain(|layer_id| *layer_id != id);
+        let index = order.iter().position(|layer_id| *layer_id == other).unwrap_or(order.len() - 1);
+        order.insert(index + 1, id);
+        self.layer_order = order;
+        self.draw_tile.redraw(cx);
+        true
+    }
+
+    /// Set the opacity of a layer (0..1). Tiles, polylines, markers and the
+    /// UTM grid fade as a whole; overlay layers get it through `MapLayer::set_opacity`.
+    pub fn set_layer_opacity(&mut self, cx: &mut Cx, id: LayerId, opacity: f64) {
+        let opacity = opacity.clamp(0.0, 1.0);
+        self.layer_opacity.insert(id, opacity);
+        if let Some((_, layer)) = self.layers.iter_mut().find(|(layer_id, _)| *layer_id == id) {
+            layer.set_opacity(opacity);
+        }
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Opacity of a layer (1 unless changed)
+    pub fn layer_opacity(&self, id: LayerId) -> f64 {
+        self.layer_opacity.get(&id).copied().unwrap_or(1.0)
+    }
+
+    /// Show or hide a built-in or overlay layer
+    pub fn set_layer_visible(&mut self, cx: &mut Cx, id: LayerId, visible: bool) {
+        if let Some((_, layer)) = self.layers.iter_mut().find(|(layer_id, _)| *layer_id == id) {
+            layer.set_visible(visible);
+        } else if visible {
+            self.hidden_layers.remove(&id);
+        } else {
+            self.hidden_layers.insert(id);
+        }
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Whether a layer is drawn (false for unknown ids)
+    pub fn is_layer_visible(&self, id: LayerId) -> bool {
+        match self.layers.iter().find(|(layer_id, _)| *layer_id == id) {
+            Some((_, layer)) => layer.is_visible(),
+            None => DEFAULT_LAYER_ORDER.contains(&id) && !self.hidden_layers.contains(&id),
+        }
+    }
+
+    /// Only show a layer within a zoom range, e.g. building footprints from
+    /// zoom 15. `set_layer_visible` still hides it at any zoom.
+    pub fn set_layer_zoom_range(&mut self, cx: &mut Cx, id: LayerId, min_zoom: f64, max_zoom: f64) {
+        self.layer_zoom_ranges.insert(id, (min_zoom, max_zoom));
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Whether a layer is visible and within its zoom range
+    fn is_layer_shown(&self, id: LayerId) -> bool {
+        let in_range = self.layer_zoom_ranges.get(&id).is_none_or(|(min, max)| self.zoom >= *min && self.zoom <= *max);
+        in_range && self.is_layer_visible(id)
+    }
+
+    /// Add an overlay layer to the draw order, directly below the markers
+    fn insert_layer(&mut self, id: LayerId, layer: Box<dyn MapLayer>) {
+        if !self.layer_order.is_empty() {
+            let index = self.layer_order.iter().position(|layer_id| *layer_id == MARKERS_LAYER).unwrap_or(self.layer_order.len());
+            self.layer_order.insert(index, id);
+        }
+        self.layers.push((id, layer));
+    }
+
+    /// Session-long tile request counters (requests, duplicates, client errors, backoffs)
+    pub fn tile_request_stats(&self) -> TileRequestStats {
+        self.tile_cache.request_stats()
+    }
+
+    /// Tile cache hits/misses, download volume, decode times and memory/disk usage
+    pub fn tile_cache_stats(&self) -> TileCacheStats {
+        self.tile_cache.stats()
+    }
+
+    /// Set the disk cache location and size limit, or disable disk caching.
+    /// Overrides the `disk_cache_*` properties until they're applied again.
+    pub fn set_disk_cache_config(&mut self, config: DiskCacheConfig) {
+        self.tile_cache.set_disk_cache_config(config);
+        self.requested_tiles = None;
+    }
+
+    /// Download the tiles within `corridor_meters` of a route of (lng, lat)
+    /// points into the disk cache, for each zoom level in `zoom_range`, so the
+    /// route can be followed offline. Progress is reported with
+    /// `DownloadProgressChanged` actions. Returns the number of tiles queued.
+    pub fn prefetch_route(
+        &mut self,
+        cx: &mut Cx,
+        points: &[(f64, f64)],
+        zoom_range: RangeInclusive<u8>,
+        corridor_meters: f64,
+    ) -> usize {
+        self.tile_cache.prefetch_route(cx, points, zoom_range, corridor_meters)
+    }
+
+    /// Progress of the queued tile downloads
+    pub fn download_progress(&self) -> DownloadProgress {
+        self.tile_cache.download_progress()
+    }
+
+    /// Drop the queued tile downloads
+    pub fn cancel_downloads(&mut self) {
+        self.tile_cache.cancel_downloads();
+    }
+
+    /// Rendering quality in effect (see `adaptive_quality`)
+    pub fn quality_level(&self) -> QualityLevel {
+        if self.adaptive_quality {
+            self.quality.level()
+        } else {
+            self.quality.override_level().unwrap_or_default()
+        }
+    }
+
+    /// Force a rendering quality, overriding the adaptive governor (None hands control back)
+    pub fn set_quality_override(&mut self, cx: &mut Cx, level: Option<QualityLevel>) {
+        self.quality.set_override(level);
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Turn power saving on or off: no momentum after pans, and animation
+    /// frames and gesture redraws at most 30 times a second. Turning it on
+    /// stops a glide in progress.
+    pub fn set_low_power(&mut self, cx: &mut Cx, low_power: bool) {
+        self.low_power = low_power;
+        if low_power && self.is_flicking {
+            self.is_flicking = false;
+            self.flick_velocity = DVec2::default();
+        }
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Set (or remove) the coordinate provider used for readouts and tap lookups
+    pub fn set_coordinate_provider(&mut self, provider: Option<Box<dyn CoordinateProvider>>) {
+        self.coordinate_provider = provider;
+    }
+
+    /// Format a position with the coordinate provider (if one is set)
+    pub fn coordinate_readout(&self, lng: f64, lat: f64) -> Option<String> {
+        self.coordinate_provider.as_ref()?.readout(lng, lat)
+    }
+
+    /// Write a position in the map's `coordinate_format`, e.g. for a long press
+    pub fn format_position(&self, lng: f64, lat: f64) -> String {
+        self.coordinate_format.format(lng, lat, &self.locale)
+    }
+
+    /// Change the format of the cursor readout and `format_position`
+    pub fn set_coordinate_format(&mut self, cx: &mut Cx, format: CoordinateFormat) {
+        self.coordinate_format = format;
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Use another geocoder (e.g. Photon or a self-hosted Nominatim) for searches
+    /// and reverse lookups. Requests of the previous one are dropped.
+    pub fn set_geocoder(&mut self, geocoder: Geocoder) {
+        self.geocoder = Some(geocoder);
+        self.fly_to_search = None;
+        self.long_press_lookup = None;
+    }
+
+    fn geocoder(&mut self) -> &mut Geocoder {
+        self.geocoder.get_or_insert_with(Geocoder::nominatim)
+    }
+
+    /// Search for places, reported by a `GeocodeResults` action. Returns the request's id.
+    pub fn geocode(&mut self, cx: &mut Cx, query: &str) -> LiveId {
+        self.geocoder().search(cx, query)
+    }
+
+    /// Look up the address at a position, reported by a `ReverseGeocoded` action
+    pub fn reverse_geocode(&mut self, cx: &mut Cx, lng: f64, lat: f64) -> LiveId {
+        self.geocoder().reverse(cx, lng, lat)
+    }
+
+    /// Use another routing server or profile for `request_route`
+    pub fn set_router(&mut self, router: Router) {
+        self.router = Some(router);
+        self.route_request = None;
+    }
+
+    /// Request a route through (lng, lat) waypoints. When it arrives it
+    /// replaces the shown route as the `ROUTE_POLYLINE` with start and end
+    /// markers, styled by `set_route_style`, and `RouteReady` is reported.
+    pub fn request_route(&mut self, cx: &mut Cx, waypoints: &[(f64, f64)]) -> LiveId {
+        let request_id = self.router.get_or_insert_with(Router::default).route(cx, waypoints);
+        self.route_request = Some(request_id);
+        request_id
+    }
+
+    /// Line and marker style of routes shown from now on
+    pub fn set_route_style(&mut self, style: RouteStyle) {
+        self.route_style = style;
+    }
+
+    /// The route shown, if any
+    pub fn route(&self) -> Option<&Route> {
+        self.route.as_ref()
+    }
+
+    /// Remove the shown route with its markers
+    pub fn clear_route(&mut self, cx: &mut Cx) {
+        self.route = None;
+        self.remove_polyline(cx, ROUTE_POLYLINE);
+        self.remove_marker(cx, ROUTE_START_MARKER);
+        self.remove_marker(cx, ROUTE_END_MARKER);
+    }
+
+    /// Fly to a geocoded place, framing its extent if it has one
+    pub fn fly_to_result(&mut self, cx: &mut Cx, result: &GeocodeResult) {
+        let zoom = result.bounds.map_or(SEARCH_RESULT_ZOOM, |bounds| self.camera_to_fit(&bounds).2);
+        self.fly_to(cx, result.lng, result.lat, zoom);
+    }
+
+    /// Search for a place and fly to the best match when the results arrive,
+    /// framing its extent if it has one. The results are also reported by a
+    /// `GeocodeResults` action.
+    pub fn search_and_fly_to(&mut self, cx: &mut Cx, query: &str) -> LiveId {
+        let request_id = self.geocode(cx, query);
+        self.fly_to_search = Some(request_id);
+        request_id
+    }
+
+    /// Set the map center programmatically
+    pub fn set_center(&mut self, cx: &mut Cx, lng: f64, lat: f64) {
+        self.stop_animations(cx);
+        self.center_lng = lng;
+        self.center_lat = self.clamp_lat(lat);
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Set the zoom level programmatically
+    pub fn set_zoom(&mut self, cx: &mut Cx, zoom: f64) {
+        self.stop_animations(cx);
+        self.zoom = zoom.clamp(self.effective_min_zoom(), self.max_zoom);
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Set the number format and units of the scale bar and measurement labels
+    pub fn set_locale(&mut self, cx: &mut Cx, locale: MapLocale) {
+        self.locale = locale;
+        self.draw_tile.redraw(cx);
+    }
+
+    pub fn locale(&self) -> &MapLocale {
+        &self.locale
+    }
+
+    /// Current camera, e.g. to persist with `CameraState::to_json`
+    pub fn save_camera(&self) -> CameraState {
+        self.camera()
+    }
+
+    /// Jump to a saved camera, clamped to the map's zoom and pitch limits
+    pub fn restore_camera(&mut self, cx: &mut Cx, camera: &CameraState) {
+        self.apply_camera(cx, camera);
+    }
+
+    /// Current center, zoom, bearing and pitch
+    pub fn camera(&self) -> MapCamera {
+        MapCamera {
+            center: (self.center_lng, self.center_lat),
+            zoom: self.zoom,
+            bearing: self.bearing,
+            pitch: self.pitch,
+        }
+    }
+
+    /// Move the camera in one step, clamped to the map's zoom and pitch limits.
+    /// Unlike separate `set_center` and `set_zoom` calls there are no
+    /// intermediate states: the map redraws once and reports one `RegionChanged`.
+    pub fn set_camera(&mut self, cx: &mut Cx, camera: MapCamera) {
+        self.apply_camera(cx, &camera);
+        self.region_change_pending = true;
+    }
+
+    fn apply_camera(&mut self, cx: &mut Cx, camera: &MapCamera) {
+        self.stop_animations(cx);
+        let (lng, lat) = camera.center;
+        self.center_lng = lng;
+        self.center_lat = self.clamp_lat(lat);
+        self.normalize_coordinates();
+        self.zoom = camera.zoom.clamp(self.effective_min_zoom(), self.max_zoom);
+        self.bearing = normalize_bearing(camera.bearing);
+        self.pitch = camera.pitch.clamp(0.0, MAX_PITCH);
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Jump to the view of a map link (see `parse_map_link` for the formats).
+    /// Returns false, leaving the camera alone, if the link isn't understood.
+    pub fn open_map_link(&mut self, cx: &mut Cx, link: &str) -> bool {
+        let Some(camera) = parse_map_link(link) else {
+            return false;
+        };
+        self.restore_camera(cx, &camera);
+        true
+    }
+
+    /// Inset the map by UI covering its edges (e.g. a bottom sheet), in pixels.
+    /// The center, zooming, `fit_bounds` and the reported `RegionChanged` then
+    /// refer to the area left uncovered. The map under the padding stays visible,
+    /// and the center moves to the middle of the uncovered area.
+    pub fn set_padding(&mut self, cx: &mut Cx, left: f64, top: f64, right: f64, bottom: f64) {
+        self.padding = Padding { left, top, right, bottom };
+        self.draw_tile.redraw(cx);
+    }
+
+    pub fn padding(&self) -> Padding {
+        self.padding
+    }
+
+    /// Fly to bounds so they fill the padded viewport
+    pub fn fit_bounds(&mut self, cx: &mut Cx, bounds: &GeoBounds) {
+        let (lng, lat, zoom) = self.camera_to_fit(bounds);
+        self.fly_to(cx, lng, lat, zoom);
+    }
+
+    /// Set the map bearing (degrees clockwise from north)
+    pub fn set_bearing(&mut self, cx: &mut Cx, bearing: f64) {
+        self.bearing = normalize_bearing(bearing);
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Turn the map back to north with a short animation (as tapping the
+    /// compass does), reporting `BearingChanged` when done
+    pub fn reset_north(&mut self, cx: &mut Cx) {
+        if self.bearing == 0.0 {
+            return;
+        }
+        let kind = CameraAnimationKind::Rotate {
+            from_bearing: self.bearing,
+            to_bearing: 0.0,
+            lng: self.center_lng,
+            lat: self.center_lat,
+            zoom: self.zoom,
+        };
+        self.start_camera_animation(cx, CameraAnimation::new(kind, RESET_NORTH_DURATION));
+    }
+
+    /// Tilt the map plane back by `pitch` degrees (0 to `MAX_PITCH`), for a
+    /// perspective view ahead of the center as in navigation apps
+    pub fn set_pitch(&mut self, cx: &mut Cx, pitch: f64) {
+        self.pitch = pitch.clamp(0.0, MAX_PITCH);
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Start the navigation camera: from the first `update_navigation` on, the
+    /// camera follows the position at the options' zoom and pitch, turned to the
+    /// heading, with the position pinned at the anchor. Panning pauses following
+    /// (reported by `NavigationPaused`) until `resume_navigation`.
+    pub fn start_navigation(&mut self, cx: &mut Cx, options: NavigationOptions) {
+        self.stop_animations(cx);
+        self.navigation = Some(NavigationCamera::new(options));
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Report a new position (e.g. a GPS fix) to the navigation camera, with
+    /// the heading in degrees clockwise from north if the device knows it (taken
+    /// from the movement otherwise). The camera moves smoothly to it over the
+    /// time since the previous fix.
+    pub fn update_navigation(&mut self, cx: &mut Cx, lng: f64, lat: f64, heading: Option<f64>) {
+        let Some(navigation) = self.navigation.as_mut() else {
+            return;
+        };
+        navigation.push_fix(lng, lat, heading);
+        self.request_frame(cx);
+    }
+
+    /// Follow the position again after the user panned away
+    pub fn resume_navigation(&mut self, cx: &mut Cx) {
+        if let Some(navigation) = self.navigation.as_mut() {
+            navigation.following = true;
+            navigation.reset_clock();
+            self.stop_animations(cx);
+            self.request_frame(cx);
+        }
+    }
+
+    /// Leave navigation mode, keeping the camera where it is
+    pub fn stop_navigation(&mut self, cx: &mut Cx) {
+        if self.navigation.take().is_some() {
+            self.draw_tile.redraw(cx);
+        }
+    }
+
+    /// Whether the navigation camera is following the position
+    pub fn is_navigating(&self) -> bool {
+        self.navigation.as_ref().is_some_and(|navigation| navigation.following)
+    }
+
+    /// Smoothed position and heading shown by the navigation camera
+    pub fn navigation_position(&self) -> Option<NavigationFix> {
+        self.navigation.as_ref()?.position()
+    }
+
+    /// Switch what taps do. Leaving `Measure` clears the measurement.
+    pub fn set_interaction_mode(&mut self, cx: &mut Cx, mode: InteractionMode) {
+        if mode != InteractionMode::Measure {
+            self.measurement = Measurement::default();
+        }
+        if mode != InteractionMode::DrawPolygon {
+            self.sketch.clear();
+        }
+        self.selection = None;
+        self.interaction_mode = mode;
+        self.draw_tile.redraw(cx);
+    }
+
+    /// The current measurement (empty outside `InteractionMode::Measure`)
+    pub fn measurement(&self) -> &Measurement {
+        &self.measurement
+    }
+
+    /// Remove all measurement points
+    pub fn clear_measurement(&mut self, cx: &mut Cx) {
+        self.measurement = Measurement::default();
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Vertices of the polygon being drawn in `InteractionMode::DrawPolygon`
+    pub fn sketch(&self) -> &[(f64, f64)] {
+        &self.sketch
+    }
+
+    /// Remove the last vertex of the polygon being drawn
+    pub fn undo_sketch_point(&mut self, cx: &mut Cx) {
+        if self.sketch.pop().is_some() {
+            self.draw_tile.redraw(cx);
+        }
+    }
+
+    /// Discard the polygon being drawn
+    pub fn cancel_sketch(&mut self, cx: &mut Cx) {
+        self.sketch.clear();
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Sample tiles nearest for crisp labels at fractional zoom, or linear for smooth scaling
+    pub fn set_tile_filter(&mut self, cx: &mut Cx, filter: TileFilter) {
+        self.tile_filter = filter;
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Zoom step that pinch, scroll and double-tap zoom settle on, 0 to turn snapping off
+    pub fn set_zoom_snap(&mut self, zoom_snap: f64) {
+        self.zoom_snap = zoom_snap.max(0.0);
+    }
+
+    /// Set which fraction of the width is drawn (1.0 = all), for swipe comparisons
+    pub fn set_swipe_position(&mut self, cx: &mut Cx, position: f64) {
+        self.swipe_position = position.clamp(0.0, 1.0);
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Immediately halt momentum scrolling and camera animations (fly_to, animated zoom),
+    /// leaving the camera where it is. Returns true if anything was animating.
+    pub fn stop_animations(&mut self, cx: &mut Cx) -> bool {
+        let was_animating = self.is_animating();
+        self.is_flicking = false;
+        self.flick_velocity = DVec2::default();
+        self.camera_controller.cancel();
+        self.zoom_direction = 0.0;
+        if was_animating {
+            self.draw_tile.redraw(cx);
+        }
+        was_animating
+    }
+
+    /// Set (or remove) the camera tour followed by `seek`
+    pub fn set_tour(&mut self, cx: &mut Cx, tour: Option<CameraTour>) {
+        self.tour = tour;
+        if let Some(time) = self.timeline_time {
+            self.seek(cx, time);
+        }
+    }
+
+    /// Show the map at a time of an external timeline (seconds): the camera
+    /// follows the tour (if set) and every layer is seeked to the same time.
+    /// Call it for each frame of the external clock, e.g. a playing video.
+    pub fn seek(&mut self, cx: &mut Cx, time: f64) {
+        self.timeline_time = Some(time);
+        if let Some(timeline) = &mut self.timeline {
+            timeline.seek(time);
+        }
+        let camera = self.tour.as_ref().and_then(|tour| tour.camera_at(time, self.projection()));
+        if let Some(camera) = camera {
+            self.stop_animations(cx);
+            self.center_lng = camera.lng;
+            self.center_lat = camera.lat;
+            self.zoom = camera.zoom.clamp(self.effective_min_zoom(), self.max_zoom);
+            self.bearing = normalize_bearing(camera.bearing);
+            self.normalize_coordinates();
+        }
+        for (_, layer) in &mut self.layers {
+            layer.seek(time);
+        }
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Time of the last `seek`, if the map is driven by a timeline
+    pub fn timeline_time(&self) -> Option<f64> {
+        self.timeline_time
+    }
+
+    /// Set (or remove) the playback clock. The map is seeked to its time, and
+    /// while it plays, to every new time, reporting it with `TimeChanged`.
+    pub fn set_timeline(&mut self, cx: &mut Cx, timeline: Option<Timeline>) {
+        self.timeline = timeline;
+        let Some(timeline) = &self.timeline else {
+            return;
+        };
+        let (time, playing) = (timeline.time(), timeline.is_playing());
+        self.seek(cx, time);
+        if playing {
+            self.request_frame(cx);
+        }
+    }
+
+    pub fn timeline(&self) -> Option<&Timeline> {
+        self.timeline.as_ref()
+    }
+
+    /// Play the timeline (see `set_timeline`)
+    pub fn play_timeline(&mut self, cx: &mut Cx) {
+        if let Some(timeline) = &mut self.timeline {
+            timeline.play();
+            self.request_frame(cx);
+        }
+    }
+
+    pub fn pause_timeline(&mut self) {
+        if let Some(timeline) = &mut self.timeline {
+            timeline.pause();
+        }
+    }
+
+    /// Timeline seconds per real second while playing
+    pub fn set_timeline_speed(&mut self, speed: f64) {
+        if let Some(timeline) = &mut self.timeline {
+            timeline.set_speed(speed);
+        }
+    }
+
+    /// Check if the camera is currently moving on its own (momentum or animation)
+    pub fn is_animating(&self) -> bool {
+        self.is_flicking || self.camera_controller.is_active() || self.zoom_direction != 0.0
+    }
+
+    /// Animate the camera to a new center and zoom. A flight already under
+    /// way is retargeted, continuing from its current speed and direction.
+    pub fn fly_to(&mut self, cx: &mut Cx, lng: f64, lat: f64, zoom: f64) {
+        let kind = CameraAnimationKind::FlyTo {
+            from_lng: self.center_lng,
+            from_lat: self.center_lat,
+            from_zoom: self.zoom,
+            to_lng: lng,
+            to_lat: self.clamp_lat(lat),
+            to_zoom: zoom.clamp(self.effective_min_zoom(), self.max_zoom),
+            target_offset: DVec2::default(),
+            bearing: None,
+            pitch: None,
+        };
+        self.start_camera_animation(cx, CameraAnimation::new(kind, FLY_TO_DURATION));
+    }
+
+    /// Zoom in continuously at `zoom_rate` until `stop_zoom`, e.g. while a
+    /// zoom button is held. Interrupted like other camera animations.
+    pub fn start_zoom_in(&mut self, cx: &mut Cx) {
+        self.start_continuous_zoom(cx, 1.0);
+    }
+
+    /// Zoom out continuously at `zoom_rate` until `stop_zoom`
+    pub fn start_zoom_out(&mut self, cx: &mut Cx) {
+        self.start_continuous_zoom(cx, -1.0);
+    }
+
+    fn start_continuous_zoom(&mut self, cx: &mut Cx, direction: f64) {
+        self.stop_animations(cx);
+        self.zoom_limit_reported = false;
+        self.zoom_direction = direction;
+        self.last_zoom_time = None;
+        self.zoom_hold_start = self.zoom;
+        self.request_frame(cx);
+    }
+
+    /// End a press-and-hold zoom, settling on the next `zoom_snap` step in
+    /// its direction. A quick tap, released before the zoom moved half a
+    /// level, zooms a whole level like a plain zoom button. Reports
+    /// `RegionChanged` once the zoom comes to rest.
+    pub fn stop_zoom(&mut self, cx: &mut Cx) {
+        let direction = std::mem::take(&mut self.zoom_direction);
+        if direction == 0.0 {
+            return;
+        }
+        let zoom = if (self.zoom - self.zoom_hold_start).abs() < 0.5 {
+            self.zoom_hold_start + direction
+        } else {
+            self.zoom
+        };
+        let target_zoom = self.snap_zoom(zoom, direction).clamp(self.effective_min_zoom(), self.max_zoom);
+        self.start_zoom_animation(cx, target_zoom, self.screen_center());
+        if !self.camera_controller.is_active() {
+            self.region_change_pending = true;
+            self.draw_tile.redraw(cx);
+        }
+    }
+
+    /// Animate the center, zoom, bearing and pitch to `camera` over `duration`
+    /// seconds, clamped to the map's limits. Like `fly_to`, a transition
+    /// under way is retargeted smoothly, and user gestures interrupt it.
+    pub fn ease_to(&mut self, cx: &mut Cx, camera: MapCamera, duration: f64) {
+        let (lng, lat) = camera.center;
+        let bearing = normalize_bearing(camera.bearing);
+        let pitch = camera.pitch.clamp(0.0, MAX_PITCH);
+        let kind = CameraAnimationKind::FlyTo {
+            from_lng: self.center_lng,
+            from_lat: self.center_lat,
+            from_zoom: self.zoom,
+            to_lng: lng,
+            to_lat: self.clamp_lat(lat),
+            to_zoom: camera.zoom.clamp(self.effective_min_zoom(), self.max_zoom),
+            target_offset: DVec2::default(),
+            bearing: (bearing != self.bearing).then_some((self.bearing, bearing)),
+            pitch: (pitch != self.pitch).then_some((self.pitch, pitch)),
+        };
+        self.start_camera_animation(cx, CameraAnimation::new(kind, duration));
+    }
+
+    /// Move the camera to `camera` at once, cancelling any transition (the
+    /// instant counterpart of `ease_to`, see `set_camera`)
+    pub fn jump_to(&mut self, cx: &mut Cx, camera: MapCamera) {
+        self.set_camera(cx, camera);
+    }
+
+    /// Fly to a marker, centering it in the padded viewport and optionally
+    /// opening its popup on arrival. Returns false if the marker doesn't exist.
+    /// The options' padding is added to the map's own padding.
+    pub fn focus_marker(&mut self, cx: &mut Cx, id: LiveId, options: FocusOptions) -> bool {
+        let Some(marker) = self.get_marker(id) else {
+            return false;
+        };
+        let (to_lng, to_lat) = (marker.lng, marker.lat);
+
+        // Center of the unpadded area, relative to the logical center
+        let padding = options.padding;
+        let target_offset = dvec2(
+            (padding.left - padding.right) / 2.0,
+            (padding.top - padding.bottom) / 2.0,
+        );
+
+        if self.open_popup.take().is_some() {
+            self.draw_tile.redraw(cx);
+        }
+
+        let kind = CameraAnimationKind::FlyTo {
+            from_lng: self.center_lng,
+            from_lat: self.center_lat,
+            from_zoom: self.zoom,
+            to_lng,
+            to_lat,
+            to_zoom: options.zoom.unwrap_or(self.zoom).clamp(self.effective_min_zoom(), self.max_zoom),
+            target_offset,
+            bearing: None,
+            pitch: None,
+        };
+        let mut animation = CameraAnimation::new(kind, options.duration);
+        if options.open_popup {
+            animation.open_popup = Some(id);
+        }
+        self.start_camera_animation(cx, animation);
+        true
+    }
+
+    /// Open the popup (info window) of a marker
+    pub fn open_popup(&mut self, cx: &mut Cx, id: LiveId) {
+        if self.get_marker(id).is_some() {
+            self.open_popup = Some(id);
+            self.draw_tile.redraw(cx);
+        }
+    }
+
+    /// Close the open marker popup
+    pub fn close_popup(&mut self, cx: &mut Cx) {
+        if self.open_popup.take().is_some() {
+            self.draw_tile.redraw(cx);
+        }
+    }
+
+    /// Select a marker, drawing it enlarged (`selected_marker_scale`) with an
+    /// outline. Returns false if there is no marker with this ID. Selecting
+    /// from code emits no actions, so a list can mirror its selection onto the
+    /// map without being told about it again.
+    pub fn select_marker(&mut self, cx: &mut Cx, id: LiveId) -> bool {
+        if !self.markers.iter().any(|m| m.id == id) {
+            return false;
+        }
+        if self.selected_marker != Some(id) {
+            self.selected_marker = Some(id);
+            self.draw_tile.redraw(cx);
+        }
+        true
+    }
+
+    /// Clear the marker selection
+    pub fn deselect_marker(&mut self, cx: &mut Cx) {
+        if self.selected_marker.take().is_some() {
+            self.draw_tile.redraw(cx);
+        }
+    }
+
+    /// Get the ID of the selected marker
+    pub fn selected_marker(&self) -> Option<LiveId> {
+        self.selected_marker
+    }
+
+    /// Get the ID of the polyline or layer shape under the pointer (or last
+    /// tapped on touch screens)
+    pub fn hovered_overlay(&self) -> Option<LiveId> {
+        self.hovered_overlay.as_ref().map(|(hit, _)| hit.id)
+    }
+
+    /// Set the items of the built-in menu shown on right-click, as (ID, title)
+    /// pairs in menu order; picking one emits `ContextMenuItemSelected`. With
+    /// no items (the default) right-clicks only emit `ContextRequested`.
+    pub fn set_context_menu(&mut self, cx: &mut Cx, items: Vec<(LiveId, String)>) {
+        self.context_menu_items = items;
+        if self.context_menu_items.is_empty() {
+            self.close_context_menu(cx);
+        } else if self.context_menu.is_some() {
+            self.draw_tile.redraw(cx);
+        }
+    }
+
+    /// Close the built-in context menu
+    pub fn close_context_menu(&mut self, cx: &mut Cx) {
+        if self.context_menu.take().is_some() {
+            self.draw_tile.redraw(cx);
+        }
+    }
+
+    /// Get the ID of the marker whose popup is open
+    pub fn popup_marker(&self) -> Option<LiveId> {
+        self.open_popup
+    }
+
+    /// Add a marker at the specified geographic coordinates
+    /// Returns a mutable reference to the marker for further customization
+    pub fn add_marker(&mut self, cx: &mut Cx, id: LiveId, lng: f64, lat: f64) -> &mut MapMarker {
+        // Default red color for markers
+        let marker = MapMarker {
+            id,
+            lng,
+            lat,
+            label: String::new(),
+            popup: String::new(),
+            color: vec4(0.9, 0.2, 0.2, 1.0), // Default red
+            min_zoom: 0.0,
+            max_zoom: f64::INFINITY,
+            label_placement: LabelPlacement::Below,
+            label_offset: DVec2::default(),
+            shape: MarkerShape::Pin,
+            rotation_deg: 0.0,
+            user_data: None,
+        };
+        self.markers.push(marker);
+        self.marker_index = None;
+        self.draw_tile.redraw(cx);
+        self.markers.last_mut().unwrap()
+    }
+
+    /// Remove a marker by ID
+    pub fn remove_marker(&mut self, cx: &mut Cx, id: LiveId) {
+        self.markers.retain(|m| m.id != id);
+        self.marker_index = None;
+        self.marker_animations.retain(|animation| animation.id != id);
+        if self.open_popup == Some(id) {
+            self.open_popup = None;
+        }
+        if self.selected_marker == Some(id) {
+            self.selected_marker = None;
+        }
+        self.draw_tile.redraw(cx);
+    }
+
+    /// All markers in draw order
+    pub fn markers(&self) -> &[MapMarker] {
+        &self.markers
+    }
+
+    /// Add a complete marker, e.g. one restored from saved state, replacing
+    /// any marker with the same ID
+    pub fn insert_marker(&mut self, cx: &mut Cx, marker: MapMarker) {
+        match self.markers.iter_mut().find(|m| m.id == marker.id) {
+            Some(existing) => *existing = marker,
+            None => self.markers.push(marker),
+        }
+        self.marker_index = None;
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Get a reference to a marker by ID
+    pub fn get_marker(&self, id: LiveId) -> Option<&MapMarker> {
+        self.markers.iter().find(|m| m.id == id)
+    }
+
+    /// Get a mutable reference to a marker by ID
+    pub fn get_marker_mut(&mut self, id: LiveId) -> Option<&mut MapMarker> {
+        // The marker may be moved
+        self.marker_index = None;
+        self.markers.iter_mut().find(|m| m.id == id)
+    }
+
+    /// Move a marker to new coordinates. Returns false if there is no such marker.
+    pub fn move_marker(&mut self, cx: &mut Cx, id: LiveId, lng: f64, lat: f64) -> bool {
+        let Some(marker) = self.get_marker_mut(id) else {
+            return false;
+        };
+        marker.lng = lng;
+        marker.lat = lat;
+        self.draw_tile.redraw(cx);
+        true
+    }
+
+    /// Point a marker in a direction, degrees clockwise from north (see
+    /// `MapMarker::rotation_deg`). Returns false if there is no such marker.
+    pub fn rotate_marker(&mut self, cx: &mut Cx, id: LiveId, rotation_deg: f64) -> bool {
+        let Some(marker) = self.get_marker_mut(id) else {
+            return false;
+        };
+        marker.rotation_deg = rotation_deg.rem_euclid(360.0);
+        self.draw_tile.redraw(cx);
+        true
+    }
+
+    /// Remove all markers
+    pub fn clear_markers(&mut self, cx: &mut Cx) {
+        self.markers.clear();
+        self.marker_index = None;
+        self.marker_animations.clear();
+        self.open_popup = None;
+        self.selected_marker = None;
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Get the number of markers
+    pub fn marker_count(&self) -> usize {
+        self.markers.len()
+    }
+
+    /// Move a marker along a path of (lng, lat) positions at constant ground
+    /// speed, straight in the map projection like a polyline. Each frame
+    /// reports the position and the heading of travel with
+    /// `MarkerAnimationProgress`, and the end with `MarkerAnimationFinished`.
+    /// Replaces the marker's running animation. Returns false if there is no
+    /// marker with this id or the path is empty.
+    pub fn animate_marker_along_path(&mut self, cx: &mut Cx, id: LiveId, points: Vec<(f64, f64)>, timing: PathTiming) -> bool {
+        let Some(marker) = self.markers.iter_mut().find(|m| m.id == id) else {
+            return false;
+        };
+        let Some(&(lng, lat)) = points.first() else {
+            return false;
+        };
+        marker.lng = lng;
+        marker.lat = lat;
+        self.marker_index = None;
+        let mut animation = MarkerPathAnimation::new(id, points, 0.0);
+        animation.duration = match timing {
+            PathTiming::Duration(seconds) => seconds.max(0.0),
+            PathTiming::Speed(speed) if speed > 0.0 => animation.length() / speed,
+            PathTiming::Speed(_) => 0.0,
+        };
+        self.marker_animations.retain(|animation| animation.id != id);
+        self.marker_animations.push(animation);
+        self.request_frame(cx);
+        self.draw_tile.redraw(cx);
+        true
+    }
+
+    /// Stop a marker where it is. Returns false if it wasn't animating.
+    pub fn stop_marker_animation(&mut self, id: LiveId) -> bool {
+        let before = self.marker_animations.len();
+        self.marker_animations.retain(|animation| animation.id != id);
+        if self.marker_animations.len() == before {
+            return false;
+        }
+        // Index the marker where it stopped
+        self.marker_index = None;
+        true
+    }
+
+    /// Whether a marker is moving along a path
+    pub fn is_marker_animating(&self, id: LiveId) -> bool {
+        self.marker_animations.iter().any(|animation| animation.id == id)
+    }
+
+    /// Add a polyline through (lng, lat) positions, returns it for customization
+    pub fn add_polyline(&mut self, cx: &mut Cx, id: LiveId, points: Vec<(f64, f64)>) -> &mut MapPolyline {
+        self.simplified_polylines.remove(&id);
+        self.polylines.push(MapPolyline {
+            id,
+            points,
+            color: vec4(0.12, 0.53, 0.9, 1.0),
+            width: 3.0,
+            geodesic: false,
+            closed: false,
+            editable: false,
+            min_zoom: 0.0,
+            max_zoom: f64::INFINITY,
+            tooltip: String::new(),
+        });
+        self.draw_tile.redraw(cx);
+        self.polylines.last_mut().unwrap()
+    }
+
+    /// Remove a polyline by ID
+    pub fn remove_polyline(&mut self, cx: &mut Cx, id: LiveId) {
+        self.polylines.retain(|line| line.id != id);
+        self.simplified_polylines.remove(&id);
+        if self.hovered_overlay.as_ref().is_some_and(|(hit, _)| hit.id == id) {
+            self.hovered_overlay = None;
+        }
+        self.draw_tile.redraw(cx);
+    }
+
+    /// All polylines and polygons in draw order
+    pub fn polylines(&self) -> &[MapPolyline] {
+        &self.polylines
+    }
+
+    /// Add a complete polyline, e.g. one restored from saved state, replacing
+    /// any polyline with the same ID
+    pub fn insert_polyline(&mut self, cx: &mut Cx, polyline: MapPolyline) {
+        self.simplified_polylines.remove(&polyline.id);
+        match self.polylines.iter_mut().find(|line| line.id == polyline.id) {
+            Some(existing) => *existing = polyline,
+            None => self.polylines.push(polyline),
+        }
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Get a mutable reference to a polyline by ID (redraw the map after changing it)
+    pub fn get_polyline_mut(&mut self, id: LiveId) -> Option<&mut MapPolyline> {
+        // The points may change
+        self.simplified_polylines.remove(&id);
+        self.polylines.iter_mut().find(|line| line.id == id)
+    }
+
+    /// Remove all polylines
+    pub fn clear_polylines(&mut self, cx: &mut Cx) {
+        self.polylines.clear();
+        self.simplified_polylines.clear();
+        self.hovered_overlay = None;
+        self.draw_tile.redraw(cx);
+    }
+
+    /// Place the temporary long-press pin, replacing any previously dropped pin
+    fn drop_pin(&mut self, cx: &mut Cx, lng: f64, lat: f64) -> LiveId {
+        if let Some(old_id) = self.dropped_pin.take() {
+            self.markers.retain(|m| m.id != old_id);
+            self.marker_index = None;
+        }
+        let id = LiveId::unique();
+        self.add_marker(cx, id, lng, lat);
+        self.dropped_pin = Some(id);
+        id
+    }
+
+    /// Get the ID of the temporary pin dropped by long press (if any)
+    pub fn dropped_pin(&self) -> Option<LiveId> {
+        self.dropped_pin
+    }
+
+    /// Remove the temporary pin dropped by long press
+    pub fn clear_dropped_pin(&mut self, cx: &mut Cx) {
+        if let Some(id) = self.dropped_pin.take() {
+            self.remove_marker(cx, id);
+        }
+    }
+}
+
+/// Size of `text` drawn with `draw_text`, from the shaped text layout, so
+/// labels in any script (CJK, Arabic, combining accents) get the right width
+pub(crate) fn measure_text(cx: &mut Cx, draw_text: &DrawText, text: &str) -> DVec2 {
+    let laidout = draw_text.layout(cx, 0.0, 0.0, None, false, Align::default(), text);
+    dvec2(laidout.size_in_lpxs.width as f64, laidout.size_in_lpxs.height as f64)
+}
+
+/// Whether text reads right to left: its first letter is Hebrew, Arabic,
+/// Syriac, Thaana or N'Ko
+pub(crate) fn is_right_to_left(text: &str) -> bool {
+    text.chars().find(|c| c.is_alphabetic()).is_some_and(|c| matches!(c as u32,
+        0x0590..=0x08FF | 0xFB1D..=0xFDFF | 0xFE70..=0xFEFF | 0x10800..=0x10FFF | 0x1E800..=0x1EFFF))
+}
+
+/// Wrap a longitude into [-180, 180)
+fn wrap_lng(lng: f64) -> f64 {
+    (lng + 180.0).rem_euclid(360.0) - 180.0
+}
+
+/// Normalize a bearing to [0, 360)
+fn normalize_bearing(bearing: f64) -> f64 {
+    bearing.rem_euclid(360.0)
+}
+
+impl GeoMapViewRef {
+    pub fn set_center(&self, cx: &mut Cx, lng: f64, lat: f64) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_center(cx, lng, lat);
+        }
+    }
+
+    pub fn set_zoom(&self, cx: &mut Cx, zoom: f64) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_zoom(cx, zoom);
+        }
+    }
+
+    /// Set the number format and units of the scale bar and measurement labels
+    pub fn set_locale(&self, cx: &mut Cx, locale: MapLocale) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_locale(cx, locale);
+        }
+    }
+
+    /// Current camera, e.g. to persist the last viewed region across sessions
+    pub fn save_camera(&self) -> Option<CameraState> {
+        self.borrow().map(|inner| inner.save_camera())
+    }
+
+    /// Jump to a saved camera
+    pub fn restore_camera(&self, cx: &mut Cx, camera: &CameraState) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.restore_camera(cx, camera);
+        }
+    }
+
+    pub fn camera(&self) -> Option<MapCamera> {
+        self.borrow().map(|inner| inner.camera())
+    }
+
+    /// Move the camera in one step, with one redraw and one `RegionChanged`
+    pub fn set_camera(&self, cx: &mut Cx, camera: MapCamera) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_camera(cx, camera);
+        }
+    }
+
+    /// Jump to the view of a map link, returning false if it isn't understood
+    pub fn open_map_link(&self, cx: &mut Cx, link: &str) -> bool {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.open_map_link(cx, link)
+        } else {
+            false
+        }
+    }
+
+    /// Inset the map by UI covering its edges, see `GeoMapView::set_padding`
+    pub fn set_padding(&self, cx: &mut Cx, left: f64, top: f64, right: f64, bottom: f64) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_padding(cx, left, top, right, bottom);
+        }
+    }
+
+    pub fn padding(&self) -> Padding {
+        self.borrow().map(|inner| inner.padding()).unwrap_or_default()
+    }
+
+    /// Fly to bounds so they fill the padded viewport
+    pub fn fit_bounds(&self, cx: &mut Cx, bounds: &GeoBounds) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.fit_bounds(cx, bounds);
+        }
+    }
+
+    /// Set the map bearing (degrees clockwise from north)
+    pub fn set_bearing(&self, cx: &mut Cx, bearing: f64) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_bearing(cx, bearing);
+        }
+    }
+
+    /// Tilt the map plane in perspective (degrees, 0 to `MAX_PITCH`)
+    pub fn set_pitch(&self, cx: &mut Cx, pitch: f64) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_pitch(cx, pitch);
+        }
+    }
+
+    /// Turn the map back to north with a short animation
+    pub fn reset_north(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.reset_north(cx);
+        }
+    }
+
+    /// Start the navigation camera, following positions from `update_navigation`
+    pub fn start_navigation(&self, cx: &mut Cx, options: NavigationOptions) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.start_navigation(cx, options);
+        }
+    }
+
+    /// Report a new position (and heading, if known) to the navigation camera
+    pub fn update_navigation(&self, cx: &mut Cx, lng: f64, lat: f64, heading: Option<f64>) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.update_navigation(cx, lng, lat, heading);
+        }
+    }
+
+    /// Follow the position again after the user panned away
+    pub fn resume_navigation(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.resume_navigation(cx);
+        }
+    }
+
+    /// Leave navigation mode, keeping the camera where it is
+    pub fn stop_navigation(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.stop_navigation(cx);
+        }
+    }
+
+    /// Whether the navigation camera is following the position
+    pub fn is_navigating(&self) -> bool {
+        self.borrow().is_some_and(|inner| inner.is_navigating())
+    }
+
+    /// Check if the user panned away from the navigation camera
+    pub fn navigation_paused(&self, actions: &Actions) -> bool {
+        self.map_actions(actions).into_iter().any(|action| matches!(action, GeoMapViewAction::NavigationPaused))
+    }
+
+    /// Check if the tile source changed to a provider requiring attribution
+    pub fn attribution_required(&self, actions: &Actions) -> Option<ProviderAttribution> {
+        self.map_actions(actions).into_iter().find_map(|action| match action {
+            GeoMapViewAction::AttributionRequired { attribution } => Some(attribution),
+            _ => None,
+        })
+    }
+
+    /// Check if the attribution was tapped, returning the url to open
+    pub fn attribution_tapped(&self, actions: &Actions) -> Option<String> {
+        self.map_actions(actions).into_iter().find_map(|action| match action {
+            GeoMapViewAction::AttributionTapped { url } => Some(url),
+            _ => None,
+        })
+    }
+
+    /// Check if the scale bar was tapped
+    pub fn scale_bar_tapped(&self, actions: &Actions) -> bool {
+        self.map_actions(actions).into_iter().any(|action| matches!(action, GeoMapViewAction::ScaleBarTapped))
+    }
+
+    /// Check if the map was right-clicked (returns the position and the
+    /// absolute pointer position)
+    pub fn context_requested(&self, actions: &Actions) -> Option<(f64, f64, DVec2)> {
+        self.map_actions(actions).into_iter().find_map(|action| match action {
+            GeoMapViewAction::ContextRequested { lng, lat, abs } => Some((lng, lat, abs)),
+            _ => None,
+        })
+    }
+
+    /// Check if the mouse moved over the map (returns the latest position)
+    pub fn cursor_moved(&self, actions: &Actions) -> Option<(f64, f64)> {
+        self.map_actions(actions).into_iter().rev().find_map(|action| match action {
+            GeoMapViewAction::CursorMoved { lng, lat } => Some((lng, lat)),
+            _ => None,
+        })
+    }
+
+    /// Check if an item of the built-in context menu was picked (returns its
+    /// ID and the position the menu was opened for)
+    pub fn context_menu_item_selected(&self, actions: &Actions) -> Option<(LiveId, f64, f64)> {
+        self.map_actions(actions).into_iter().find_map(|action| match action {
+            GeoMapViewAction::ContextMenuItemSelected { id, lng, lat } => Some((id, lng, lat)),
+            _ => None,
+        })
+    }
 
-                        self.center_lng = start_lng - delta.x * deg_per_px_x;
-                        self.center_lat = start_lat + delta.y * deg_per_px_y;
-                        self.normalize_coordinates();
+    /// Set the items of the built-in right-click menu (see `GeoMapView::set_context_menu`)
+    pub fn set_context_menu(&self, cx: &mut Cx, items: Vec<(LiveId, String)>) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_context_menu(cx, items);
+        }
+    }
 
-                        self.last_abs = fe.abs;
-                        self.draw_tile.redraw(cx);
+    /// Close the built-in context menu
+    pub fn close_context_menu(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.close_context_menu(cx);
+        }
+    }
 
-                        // Add velocity sample (keep last 4)
-                        self.velocity_samples.push((fe.abs, fe.time));
-                        if self.velocity_samples.len() > 4 {
-                            self.velocity_samples.remove(0);
-                        }
-                    }
-                }
-            }
-            Hit::FingerUp(fe) if fe.is_primary_hit() => {
-                let was_pinching = self.initial_pinch_distance.is_some();
-                self.initial_pinch_distance = None;
-                self.pinch_zoom_start = None;
+    /// Check if user input tried to zoom out past the minimum zoom
+    pub fn min_zoom_reached(&self, actions: &Actions) -> bool {
+        self.map_actions(actions).into_iter().any(|action| matches!(action, GeoMapViewAction::MinZoomReached))
+    }
 
-                // Check if this was a tap (minimal movement from start)
-                let is_tap = if let Some(start) = self.drag_start {
-                    let dist = (fe.abs - start).length();
-                    dist < 10.0  // Less than 10px movement = tap
-                } else {
-                    false
-                };
+    /// Check if user input tried to zoom in past `max_zoom`
+    pub fn max_zoom_reached(&self, actions: &Actions) -> bool {
+        self.map_actions(actions).into_iter().any(|action| matches!(action, GeoMapViewAction::MaxZoomReached))
+    }
 
-                if fe.is_over && is_tap {
-                    // Check if a marker was tapped
-                    if let Some(marker_id) = self.find_marker_at_screen_pos(fe.abs) {
-                        cx.widget_action(uid, &scope.path, GeoMapViewAction::MarkerTapped { id: marker_id });
-                    } else {
-                        let (lng, lat) = self.screen_to_geo(fe.abs);
-                        cx.widget_action(uid, &scope.path, GeoMapViewAction::Tapped { lng, lat });
-                    }
-                } else if fe.is_over && fe.tap_count == 2 {
-                    self.zoom = (self.zoom + 1.0).min(self.max_zoom);
-                    self.draw_tile.redraw(cx);
-                }
+    /// Switch what taps do (`InteractionMode::Measure` builds a measurement)
+    pub fn set_interaction_mode(&self, cx: &mut Cx, mode: InteractionMode) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_interaction_mode(cx, mode);
+        }
+    }
 
-                // Start momentum scrolling if above threshold (only for drags, not taps)
-                if !is_tap && !was_pinching {
-                    let velocity = self.calculate_flick_velocity();
-                    if velocity.x.hypot(velocity.y) > self.momentum_threshold {
-                        self.flick_velocity = velocity;
-                        self.is_flicking = true;
-                        self.next_frame = cx.new_next_frame();
-                    }
-                }
+    /// Remove all measurement points
+    pub fn clear_measurement(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.clear_measurement(cx);
+        }
+    }
 
-                self.drag_start = None;
-                self.drag_start_center = None;
-                self.velocity_samples.clear();
-                if !is_tap {
-                    self.emit_region_changed(cx, uid, &scope.path);
-                }
-            }
-            Hit::FingerScroll(fe) => {
-                // Handle scroll wheel zoom (desktop)
-                let zoom_delta = if fe.scroll.y > 0.0 { 0.5 } else { -0.5 };
-                let new_zoom = (self.zoom + zoom_delta).clamp(self.min_zoom, self.max_zoom);
+    /// Remove the last vertex of the polygon being drawn
+    pub fn undo_sketch_point(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.undo_sketch_point(cx);
+        }
+    }
 
-                if new_zoom != self.zoom {
-                    self.zoom = new_zoom;
-                    self.draw_tile.redraw(cx);
-                    self.emit_region_changed(cx, uid, &scope.path);
-                }
-            }
-            Hit::FingerLongPress(fe) => {
-                let (lng, lat) = self.screen_to_geo(fe.abs);
-                cx.widget_action(uid, &scope.path, GeoMapViewAction::LongPressed { lng, lat });
-            }
-            _ => {}
+    /// Discard the polygon being drawn
+    pub fn cancel_sketch(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.cancel_sketch(cx);
         }
     }
 
-    fn draw_walk(&mut self, cx: &mut Cx2d, _scope: &mut Scope, walk: Walk) -> DrawStep {
-        // Begin drawing and get the rect
-        cx.begin_turtle(walk, Layout::default());
-        let rect = cx.turtle().rect();
-        self.viewport_size = rect.size;
-        self.viewport_pos = rect.pos;
+    /// Check if an editable polyline was changed (returns its ID and new points)
+    pub fn overlay_edited(&self, actions: &Actions) -> Option<(LiveId, Vec<(f64, f64)>)> {
+        self.map_actions(actions).into_iter().rev().find_map(|action| match action {
+            GeoMapViewAction::OverlayEdited { id, points } => Some((id, points)),
+            _ => None,
+        })
+    }
 
-        // Calculate tile zoom level (integer zoom for tiles)
-        let tile_zoom = self.zoom.floor() as u8;
-        let tile_zoom = tile_zoom.clamp(0, 19);
+    /// Check if the pointer moved onto a polyline or layer shape (returns its ID)
+    pub fn overlay_hovered(&self, actions: &Actions) -> Option<LiveId> {
+        self.map_actions(actions).into_iter().rev().find_map(|action| match action {
+            GeoMapViewAction::OverlayHovered { id } => Some(id),
+            _ => None,
+        })
+    }
 
-        // Calculate the fractional zoom for scaling tiles
-        let zoom_scale = 2.0_f64.powf(self.zoom - tile_zoom as f64);
+    /// Get the ID of the polyline or layer shape under the pointer
+    pub fn hovered_overlay(&self) -> Option<LiveId> {
+        self.borrow().and_then(|inner| inner.hovered_overlay())
+    }
 
-        // Calculate world coordinates of the center
-        let world_size = TILE_SIZE * 2.0_f64.powf(tile_zoom as f64);
-        let center_world_x = (self.center_lng + 180.0) / 360.0 * world_size;
-        let lat_rad = self.center_lat.to_radians();
-        let center_world_y = (1.0 - lat_rad.tan().asinh() / std::f64::consts::PI) / 2.0 * world_size;
+    /// Check if an area was selected in `InteractionMode::Select`
+    pub fn area_selected(&self, actions: &Actions) -> Option<GeoBounds> {
+        self.map_actions(actions).into_iter().rev().find_map(|action| match action {
+            GeoMapViewAction::AreaSelected { bounds } => Some(bounds),
+            _ => None,
+        })
+    }
 
-        // Calculate which tiles are visible
-        let scaled_tile_size = TILE_SIZE * zoom_scale;
-        let tiles_x = (self.viewport_size.x / scaled_tile_size / 2.0).ceil() as i32 + 1;
-        let tiles_y = (self.viewport_size.y / scaled_tile_size / 2.0).ceil() as i32 + 1;
+    /// Check if a rectangle was drawn (returns its polyline ID and bounds)
+    pub fn rectangle_drawn(&self, actions: &Actions) -> Option<(LiveId, GeoBounds)> {
+        self.map_actions(actions).into_iter().rev().find_map(|action| match action {
+            GeoMapViewAction::RectangleDrawn { id, bounds } => Some((id, bounds)),
+            _ => None,
+        })
+    }
 
-        let center_tile_x = (center_world_x / TILE_SIZE).floor() as i32;
-        let center_tile_y = (center_world_y / TILE_SIZE).floor() as i32;
+    /// Check if a circle was drawn (returns its polyline ID, center and radius in meters)
+    pub fn circle_drawn(&self, actions: &Actions) -> Option<(LiveId, f64, f64, f64)> {
+        self.map_actions(actions).into_iter().rev().find_map(|action| match action {
+            GeoMapViewAction::CircleDrawn { id, lng, lat, radius } => Some((id, lng, lat, radius)),
+            _ => None,
+        })
+    }
 
-        let max_tile = 2_i32.pow(tile_zoom as u32);
+    /// Check if a polygon was finished in `InteractionMode::DrawPolygon` (returns its vertices)
+    pub fn polygon_drawn(&self, actions: &Actions) -> Option<Vec<(f64, f64)>> {
+        self.map_actions(actions).into_iter().rev().find_map(|action| match action {
+            GeoMapViewAction::PolygonDrawn { points } => Some(points),
+            _ => None,
+        })
+    }
 
-        // Calculate the offset of the center tile from the viewport center
-        let center_tile_world_x = center_tile_x as f64 * TILE_SIZE;
-        let center_tile_world_y = center_tile_y as f64 * TILE_SIZE;
-        let offset_x = (center_world_x - center_tile_world_x) * zoom_scale;
-        let offset_y = (center_world_y - center_tile_world_y) * zoom_scale;
+    /// Check if the measurement changed (returns distance in meters and area in square meters)
+    pub fn measurement_changed(&self, actions: &Actions) -> Option<(f64, Option<f64>)> {
+        self.map_actions(actions).into_iter().rev().find_map(|action| match action {
+            GeoMapViewAction::MeasurementChanged { distance, area } => Some((distance, area)),
+            _ => None,
+        })
+    }
 
-        // Draw tiles
-        for dy in -tiles_y..=tiles_y {
-            for dx in -tiles_x..=tiles_x {
-                let tile_x = (center_tile_x + dx).rem_euclid(max_tile);
-                let tile_y = center_tile_y + dy;
+    pub fn set_tile_filter(&self, cx: &mut Cx, filter: TileFilter) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_tile_filter(cx, filter);
+        }
+    }
 
-                // Skip tiles outside valid y range
-                if tile_y < 0 || tile_y >= max_tile {
-                    continue;
-                }
+    pub fn set_zoom_snap(&self, zoom_snap: f64) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_zoom_snap(zoom_snap);
+        }
+    }
 
-                let coord = TileCoord {
-                    x: tile_x as u32,
-                    y: tile_y as u32,
-                    z: tile_zoom,
-                };
+    /// Set which fraction of the width is drawn (1.0 = all), for swipe comparisons
+    pub fn set_swipe_position(&self, cx: &mut Cx, position: f64) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_swipe_position(cx, position);
+        }
+    }
 
-                // Request tile
-                self.tile_cache.request_tile(cx.cx.cx, coord);
-
-                // Calculate tile position on screen
-                let tile_screen_x = self.viewport_size.x / 2.0
-                    + (dx as f64 * scaled_tile_size)
-                    - offset_x;
-                let tile_screen_y = self.viewport_size.y / 2.0
-                    + (dy as f64 * scaled_tile_size)
-                    - offset_y;
-
-                // Set up texture - try current tile, then fall back to parent tiles
-                if let Some(texture) = self.tile_cache.get_tile(&coord) {
-                    // Use the exact tile
-                    self.draw_tile.draw_vars.set_texture(0, texture);
-                    self.draw_tile.has_texture = 1.0;
-                    self.draw_tile.uv_offset = Vec2 { x: 0.0, y: 0.0 };
-                    self.draw_tile.uv_scale = Vec2 { x: 1.0, y: 1.0 };
-                } else if let Some((parent_coord, uv_offset, uv_scale)) = self.find_parent_tile_coord(&coord) {
-                    // Use scaled parent tile as fallback
-                    if let Some(parent_texture) = self.tile_cache.get_tile(&parent_coord) {
-                        self.draw_tile.draw_vars.set_texture(0, parent_texture);
-                        self.draw_tile.has_texture = 1.0;
-                        self.draw_tile.uv_offset = uv_offset;
-                        self.draw_tile.uv_scale = uv_scale;
-                    } else {
-                        self.draw_tile.has_texture = 0.0;
-                    }
-                } else {
-                    // No tile available, show placeholder
-                    self.draw_tile.has_texture = 0.0;
-                    self.draw_tile.uv_offset = Vec2 { x: 0.0, y: 0.0 };
-                    self.draw_tile.uv_scale = Vec2 { x: 1.0, y: 1.0 };
-                }
+    /// Check if the swipe divider was dragged (returns the new position)
+    pub fn swipe_moved(&self, actions: &Actions) -> Option<f64> {
+        self.map_actions(actions).into_iter().rev().find_map(|action| match action {
+            GeoMapViewAction::SwipeMoved { position } => Some(position),
+            _ => None,
+        })
+    }
 
-                // Draw the tile
-                let tile_rect = Rect {
-                    pos: rect.pos + dvec2(tile_screen_x, tile_screen_y),
-                    size: dvec2(scaled_tile_size, scaled_tile_size),
-                };
-                self.draw_tile.draw_abs(cx, tile_rect);
-            }
+    /// Check if the map bearing changed (returns the new bearing in degrees)
+    pub fn bearing_changed(&self, actions: &Actions) -> Option<f64> {
+        self.map_actions(actions).into_iter().rev().find_map(|action| match action {
+            GeoMapViewAction::BearingChanged { bearing } => Some(bearing),
+            _ => None,
+        })
+    }
+
+    /// Immediately halt momentum scrolling and camera animations, so the app can take over the camera
+    pub fn stop_animations(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.stop_animations(cx);
         }
+    }
 
-        // Draw markers - collect data first to avoid borrow issues
-        let marker_data: Vec<_> = self.markers.iter().map(|m| {
-            (self.geo_to_screen(m.lng, m.lat), m.color, m.label.clone())
-        }).collect();
+    /// Check if the camera is currently moving on its own (momentum or animation)
+    pub fn is_animating(&self) -> bool {
+        self.borrow().map(|inner| inner.is_animating()).unwrap_or(false)
+    }
 
-        for (screen_pos, color, label) in marker_data {
-            // Skip if marker is off-screen (with some margin for the marker size)
-            let margin = self.marker_size;
-            if screen_pos.x < -margin || screen_pos.x > self.viewport_size.x + margin
-                || screen_pos.y < -margin || screen_pos.y > self.viewport_size.y + margin
-            {
-                continue;
-            }
+    /// Animate the camera to a new center and zoom
+    pub fn fly_to(&self, cx: &mut Cx, lng: f64, lat: f64, zoom: f64) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.fly_to(cx, lng, lat, zoom);
+        }
+    }
 
-            // Position marker so the point (bottom of pin) is at the geo location
-            // The shader anchors at pos (0.5, 0.7), so we offset accordingly
-            let marker_rect = Rect {
-                pos: rect.pos + dvec2(
-                    screen_pos.x - self.marker_size / 2.0,
-                    screen_pos.y - self.marker_size * 0.7,
-                ),
-                size: dvec2(self.marker_size, self.marker_size),
-            };
+    /// Zoom in continuously until `stop_zoom`, for press-and-hold zoom buttons
+    pub fn start_zoom_in(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.start_zoom_in(cx);
+        }
+    }
 
-            self.draw_marker.marker_color = color;
-            self.draw_marker.draw_abs(cx, marker_rect);
+    /// Zoom out continuously until `stop_zoom`
+    pub fn start_zoom_out(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.start_zoom_out(cx);
+        }
+    }
+
+    /// End a press-and-hold zoom
+    pub fn stop_zoom(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.stop_zoom(cx);
+        }
+    }
 
-            // Draw label below the marker if it has one
-            if !label.is_empty() {
-                let text_pos = rect.pos + dvec2(screen_pos.x, screen_pos.y + 8.0);
+    /// Animate the center, zoom, bearing and pitch over `duration` seconds
+    pub fn ease_to(&self, cx: &mut Cx, camera: MapCamera, duration: f64) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.ease_to(cx, camera, duration);
+        }
+    }
 
-                // Estimate text size for background
-                let font_size = self.draw_marker_label.text_style.font_size as f64;
-                let text_width = label.len() as f64 * font_size * 0.6;
-                let text_height = font_size * 1.3;
-                let padding = 3.0;
+    /// Move the camera at once, cancelling any transition
+    pub fn jump_to(&self, cx: &mut Cx, camera: MapCamera) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.jump_to(cx, camera);
+        }
+    }
 
-                // Draw background centered under marker
-                let bg_rect = Rect {
-                    pos: dvec2(text_pos.x - text_width / 2.0 - padding, text_pos.y - padding),
-                    size: dvec2(text_width + padding * 2.0, text_height + padding * 2.0),
-                };
-                self.draw_marker_label_bg.draw_abs(cx, bg_rect);
+    /// Fly to a marker, respecting viewport padding, and optionally open its popup on arrival
+    pub fn focus_marker(&self, cx: &mut Cx, id: LiveId, options: FocusOptions) -> bool {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.focus_marker(cx, id, options)
+        } else {
+            false
+        }
+    }
 
-                // Draw text centered
-                self.draw_marker_label.draw_abs(cx, dvec2(text_pos.x - text_width / 2.0, text_pos.y), &label);
-            }
+    /// Open the popup (info window) of a marker
+    pub fn open_popup(&self, cx: &mut Cx, id: LiveId) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.open_popup(cx, id);
         }
+    }
 
-        // Draw scale bar if enabled
-        if self.show_scale_bar {
-            let (bar_width, label) = self.calculate_scale_bar(100.0);
-            let margin = 10.0;
-            let bar_height = 4.0;
-            let bar_y = rect.pos.y + rect.size.y - margin - bar_height;
-            let bar_x = rect.pos.x + margin;
+    /// Close the open marker popup
+    pub fn close_popup(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.close_popup(cx);
+        }
+    }
 
-            // Draw the scale bar background (dark line)
-            self.draw_scale_bg.draw_abs(cx, Rect {
-                pos: dvec2(bar_x, bar_y),
-                size: dvec2(bar_width, bar_height),
-            });
+    /// Check if a marker popup was opened (returns marker ID)
+    pub fn popup_opened(&self, actions: &Actions) -> Option<LiveId> {
+        self.map_actions(actions).into_iter().find_map(|action| match action {
+            GeoMapViewAction::PopupOpened { id } => Some(id),
+            _ => None,
+        })
+    }
 
-            // Draw label above the bar
-            let text_y = bar_y - 14.0; // Position text above the bar
-            self.draw_scale_text.draw_abs(cx, dvec2(bar_x, text_y), &label);
+    /// Select a marker, drawing it enlarged with an outline (see `GeoMapView::select_marker`)
+    pub fn select_marker(&self, cx: &mut Cx, id: LiveId) -> bool {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.select_marker(cx, id)
+        } else {
+            false
         }
+    }
 
-        // Draw attribution overlay if enabled
-        if self.show_attribution {
-            let attribution_text = "\u{00A9} OpenStreetMap \u{00A9} CARTO";
-            let margin = 10.0;
-            let padding = 4.0;
+    /// Clear the marker selection
+    pub fn deselect_marker(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.deselect_marker(cx);
+        }
+    }
 
-            // Estimate text dimensions based on font size and character count
-            // Using approximate character width of 0.5 * font_size for small text
-            let font_size = self.draw_attribution_text.text_style.font_size as f64;
-            let char_count = attribution_text.chars().count() as f64;
-            let text_width = char_count * font_size * 0.5;
-            let text_height = font_size * 1.2; // Line height
+    /// Get the ID of the selected marker
+    pub fn selected_marker(&self) -> Option<LiveId> {
+        self.borrow().and_then(|inner| inner.selected_marker())
+    }
 
-            // Position: bottom-right with margin
-            let bg_width = text_width + padding * 2.0;
-            let bg_height = text_height + padding * 2.0;
-            let bg_x = rect.pos.x + rect.size.x - margin - bg_width;
-            let bg_y = rect.pos.y + rect.size.y - margin - bg_height;
+    /// Check if a marker was selected by a tap (returns marker ID)
+    pub fn marker_selected(&self, actions: &Actions) -> Option<LiveId> {
+        self.map_actions(actions).into_iter().find_map(|action| match action {
+            GeoMapViewAction::MarkerSelected { id } => Some(id),
+            _ => None,
+        })
+    }
 
-            // Draw semi-transparent white background behind text
-            self.draw_attribution_bg.draw_abs(cx, Rect {
-                pos: dvec2(bg_x, bg_y),
-                size: dvec2(bg_width, bg_height),
-            });
+    /// Check if the selected marker was deselected by a tap (returns marker ID)
+    pub fn marker_deselected(&self, actions: &Actions) -> Option<LiveId> {
+        self.map_actions(actions).into_iter().find_map(|action| match action {
+            GeoMapViewAction::MarkerDeselected { id } => Some(id),
+            _ => None,
+        })
+    }
 
-            // Draw small gray text (positioned inside the background with padding)
-            let text_x = bg_x + padding;
-            let text_y = bg_y + padding;
-            self.draw_attribution_text.draw_abs(cx, dvec2(text_x, text_y), attribution_text);
+    /// Add a marker at the specified geographic coordinates
+    pub fn add_marker(&self, cx: &mut Cx, id: LiveId, lng: f64, lat: f64) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.add_marker(cx, id, lng, lat);
+        }
+    }
+
+    /// Copies of all markers, e.g. to save them
+    pub fn markers(&self) -> Vec<MapMarker> {
+        self.borrow().map(|inner| inner.markers().to_vec()).unwrap_or_default()
+    }
+
+    /// Add a complete marker, replacing any marker with the same ID
+    pub fn insert_marker(&self, cx: &mut Cx, marker: MapMarker) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.insert_marker(cx, marker);
+        }
+    }
+
+    /// Add a marker with a custom color
+    pub fn add_marker_with_color(&self, cx: &mut Cx, id: LiveId, lng: f64, lat: f64, color: Vec4) {
+        if let Some(mut inner) = self.borrow_mut() {
+            let marker = inner.add_marker(cx, id, lng, lat);
+            marker.color = color;
+        }
+    }
+
+    /// Add a marker with label and color
+    pub fn add_marker_with_label(&self, cx: &mut Cx, id: LiveId, lng: f64, lat: f64, label: &str, color: Vec4) {
+        if let Some(mut inner) = self.borrow_mut() {
+            let marker = inner.add_marker(cx, id, lng, lat);
+            marker.label = label.to_string();
+            marker.color = color;
+        }
+    }
+
+    /// Add a polyline with a color, straight in the map projection or along great circles
+    pub fn add_polyline(&self, cx: &mut Cx, id: LiveId, points: &[(f64, f64)], color: Vec4, geodesic: bool) {
+        if let Some(mut inner) = self.borrow_mut() {
+            let line = inner.add_polyline(cx, id, points.to_vec());
+            line.color = color;
+            line.geodesic = geodesic;
+        }
+    }
+
+    /// Remove a polyline by ID
+    pub fn remove_polyline(&self, cx: &mut Cx, id: LiveId) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.remove_polyline(cx, id);
+        }
+    }
+
+    /// Remove all polylines
+    pub fn clear_polylines(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.clear_polylines(cx);
+        }
+    }
+
+    /// Copies of all polylines and polygons, e.g. to save them
+    pub fn polylines(&self) -> Vec<MapPolyline> {
+        self.borrow().map(|inner| inner.polylines().to_vec()).unwrap_or_default()
+    }
+
+    /// Add a complete polyline, replacing any polyline with the same ID
+    pub fn insert_polyline(&self, cx: &mut Cx, polyline: MapPolyline) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.insert_polyline(cx, polyline);
+        }
+    }
+
+    /// Move a marker, e.g. to follow the point hovered on an elevation profile
+    pub fn move_marker(&self, cx: &mut Cx, id: LiveId, lng: f64, lat: f64) -> bool {
+        self.borrow_mut().is_some_and(|mut inner| inner.move_marker(cx, id, lng, lat))
+    }
+
+    /// Point a marker in a direction, e.g. a vehicle's heading from GPS
+    pub fn rotate_marker(&self, cx: &mut Cx, id: LiveId, rotation_deg: f64) -> bool {
+        self.borrow_mut().is_some_and(|mut inner| inner.rotate_marker(cx, id, rotation_deg))
+    }
+
+    /// Remove a marker by ID
+    pub fn remove_marker(&self, cx: &mut Cx, id: LiveId) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.remove_marker(cx, id);
+        }
+    }
+
+    /// Move a marker along a path over a duration or at a speed, reporting its
+    /// heading and progress each frame (see `GeoMapView::animate_marker_along_path`)
+    pub fn animate_marker_along_path(&self, cx: &mut Cx, id: LiveId, points: &[(f64, f64)], timing: PathTiming) -> bool {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.animate_marker_along_path(cx, id, points.to_vec(), timing)
+        } else {
+            false
+        }
+    }
+
+    /// Stop a marker's path animation where it is
+    pub fn stop_marker_animation(&self, id: LiveId) -> bool {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.stop_marker_animation(id)
+        } else {
+            false
+        }
+    }
+
+    /// Remove all markers
+    pub fn clear_markers(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.clear_markers(cx);
         }
+    }
 
-        // End turtle and set area for hit detection
-        cx.end_turtle_with_area(&mut self.draw_tile.draw_super.draw_vars.area);
+    /// Get the number of markers
+    pub fn marker_count(&self) -> usize {
+        if let Some(inner) = self.borrow() {
+            inner.marker_count()
+        } else {
+            0
+        }
+    }
 
-        DrawStep::done()
+    /// Get all map actions emitted by this widget, in the order they were emitted.
+    /// Several actions can fire in the same event frame (e.g. a tap and a region change).
+    pub fn map_actions(&self, actions: &Actions) -> Vec<GeoMapViewAction> {
+        actions
+            .filter_widget_actions(self.widget_uid())
+            .map(|action| action.cast::<GeoMapViewAction>())
+            .collect()
     }
-}
 
-impl GeoMapView {
-    /// Clamp latitude and wrap longitude to valid ranges
-    fn normalize_coordinates(&mut self) {
-        self.center_lat = self.center_lat.clamp(-85.0, 85.0);
-        while self.center_lng > 180.0 { self.center_lng -= 360.0; }
-        while self.center_lng < -180.0 { self.center_lng += 360.0; }
+    /// Check if the map was tapped (returns coordinates if tapped)
+    pub fn tapped(&self, actions: &Actions) -> Option<(f64, f64)> {
+        self.map_actions(actions).into_iter().find_map(|action| match action {
+            GeoMapViewAction::Tapped { lng, lat } => Some((lng, lat)),
+            _ => None,
+        })
     }
 
-    /// Get degrees per pixel at current zoom and latitude
-    fn degrees_per_pixel(&self) -> (f64, f64) {
-        let world_size = TILE_SIZE * 2.0_f64.powf(self.zoom);
-        let deg_per_px_x = 360.0 / world_size;
-        let deg_per_px_y = deg_per_px_x / self.center_lat.to_radians().cos();
-        (deg_per_px_x, deg_per_px_y)
+    /// Check if the map was long pressed (returns coordinates if long pressed)
+    pub fn long_pressed(&self, actions: &Actions) -> Option<(f64, f64)> {
+        self.map_actions(actions).into_iter().find_map(|action| match action {
+            GeoMapViewAction::LongPressed { lng, lat } => Some((lng, lat)),
+            _ => None,
+        })
     }
 
-    /// Convert screen coordinates to geographic coordinates
-    fn screen_to_geo(&self, screen_pos: DVec2) -> (f64, f64) {
-        let tile_zoom = self.zoom.floor() as u8;
-        let zoom_scale = 2.0_f64.powf(self.zoom - tile_zoom as f64);
-        let world_size = TILE_SIZE * 2.0_f64.powf(tile_zoom as f64);
+    /// Check for a long press, returning its position written in the map's
+    /// `coordinate_format` (e.g. an MGRS grid reference)
+    pub fn long_pressed_position(&self, actions: &Actions) -> Option<String> {
+        let (lng, lat) = self.long_pressed(actions)?;
+        Some(self.format_position(lng, lat))
+    }
 
-        let center_world_x = (self.center_lng + 180.0) / 360.0 * world_size;
-        let lat_rad = self.center_lat.to_radians();
-        let center_world_y = (1.0 - lat_rad.tan().asinh() / std::f64::consts::PI) / 2.0 * world_size;
+    /// Set (or remove) the coordinate provider used for readouts and tap lookups
+    pub fn set_coordinate_provider(&self, provider: Option<Box<dyn CoordinateProvider>>) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_coordinate_provider(provider);
+        }
+    }
 
-        let screen_offset = screen_pos - self.viewport_size / 2.0;
-        let world_x = center_world_x + screen_offset.x / zoom_scale;
-        let world_y = center_world_y + screen_offset.y / zoom_scale;
+    /// Format a position with the coordinate provider (if one is set)
+    pub fn coordinate_readout(&self, lng: f64, lat: f64) -> Option<String> {
+        self.borrow()?.coordinate_readout(lng, lat)
+    }
 
-        let lng = world_x / world_size * 360.0 - 180.0;
-        let lat_rad = (std::f64::consts::PI * (1.0 - 2.0 * world_y / world_size)).sinh().atan();
-        (lng, lat_rad.to_degrees())
+    /// Write a position in the map's `coordinate_format`
+    pub fn format_position(&self, lng: f64, lat: f64) -> String {
+        match self.borrow() {
+            Some(inner) => inner.format_position(lng, lat),
+            None => CoordinateFormat::Decimal.format(lng, lat, &MapLocale::default()),
+        }
     }
 
-    /// Convert geographic coordinates to screen coordinates (relative to viewport top-left)
-    fn geo_to_screen(&self, lng: f64, lat: f64) -> DVec2 {
-        let tile_zoom = self.zoom.floor() as u8;
-        let zoom_scale = 2.0_f64.powf(self.zoom - tile_zoom as f64);
-        let world_size = TILE_SIZE * 2.0_f64.powf(tile_zoom as f64);
+    /// Change the format of the cursor readout and `format_position`
+    pub fn set_coordinate_format(&self, cx: &mut Cx, format: CoordinateFormat) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_coordinate_format(cx, format);
+        }
+    }
 
-        // Convert center to world coords
-        let center_world_x = (self.center_lng + 180.0) / 360.0 * world_size;
-        let center_lat_rad = self.center_lat.to_radians();
-        let center_world_y = (1.0 - center_lat_rad.tan().asinh() / std::f64::consts::PI) / 2.0 * world_size;
+    /// Use another geocoder for searches and reverse lookups
+    pub fn set_geocoder(&self, geocoder: Geocoder) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_geocoder(geocoder);
+        }
+    }
 
-        // Convert target to world coords
-        let target_world_x = (lng + 180.0) / 360.0 * world_size;
-        let target_lat_rad = lat.to_radians();
-        let target_world_y = (1.0 - target_lat_rad.tan().asinh() / std::f64::consts::PI) / 2.0 * world_size;
+    /// Search for places, reported by a `GeocodeResults` action
+    pub fn geocode(&self, cx: &mut Cx, query: &str) -> Option<LiveId> {
+        Some(self.borrow_mut()?.geocode(cx, query))
+    }
 
-        // Calculate screen offset from center
-        let offset_x = (target_world_x - center_world_x) * zoom_scale;
-        let offset_y = (target_world_y - center_world_y) * zoom_scale;
+    /// Look up the address at a position, reported by a `ReverseGeocoded` action
+    pub fn reverse_geocode(&self, cx: &mut Cx, lng: f64, lat: f64) -> Option<LiveId> {
+        Some(self.borrow_mut()?.reverse_geocode(cx, lng, lat))
+    }
 
-        // Return position relative to viewport top-left
-        dvec2(
-            self.viewport_size.x / 2.0 + offset_x,
-            self.viewport_size.y / 2.0 + offset_y,
-        )
+    /// Use another routing server or profile for `request_route`
+    pub fn set_router(&self, router: Router) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_router(router);
+        }
     }
 
-    /// Find the marker at a screen position (if any), checking in reverse order (topmost first)
-    /// screen_pos should be in absolute window coordinates (as received from events)
-    fn find_marker_at_screen_pos(&self, abs_pos: DVec2) -> Option<LiveId> {
-        // Convert absolute position to relative viewport position
-        let rel_pos = abs_pos - self.viewport_pos;
+    /// Line and marker style of routes shown from now on
+    pub fn set_route_style(&self, style: RouteStyle) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_route_style(style);
+        }
+    }
 
-        // Hit radius covers the marker shape - use full marker size for easier tapping
-        let hit_radius = self.marker_size * 0.6;
+    /// Request a route through (lng, lat) waypoints, shown when it arrives
+    pub fn request_route(&self, cx: &mut Cx, waypoints: &[(f64, f64)]) -> Option<LiveId> {
+        Some(self.borrow_mut()?.request_route(cx, waypoints))
+    }
 
-        // Check markers in reverse order (last drawn = topmost = checked first)
-        for marker in self.markers.iter().rev() {
-            let marker_screen = self.geo_to_screen(marker.lng, marker.lat);
+    /// Remove the shown route with its markers
+    pub fn clear_route(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.clear_route(cx);
+        }
+    }
 
-            // The marker is drawn with the pin point at marker_screen, but the visible
-            // head is above that point. Check against the center of the visible marker.
-            let marker_center_y = marker_screen.y - self.marker_size * 0.35;
+    /// Check if a requested route is shown (returns distance in meters and duration in seconds)
+    pub fn route_ready(&self, actions: &Actions) -> Option<(f64, f64)> {
+        self.map_actions(actions).into_iter().find_map(|action| match action {
+            GeoMapViewAction::RouteReady { distance, duration } => Some((distance, duration)),
+            _ => None,
+        })
+    }
 
-            let dx = rel_pos.x - marker_screen.x;
-            let dy = rel_pos.y - marker_center_y;
-            let distance = (dx * dx + dy * dy).sqrt();
+    /// Check if a route request failed
+    pub fn route_failed(&self, actions: &Actions) -> Option<RouteError> {
+        self.map_actions(actions).into_iter().find_map(|action| match action {
+            GeoMapViewAction::RouteFailed { error } => Some(error),
+            _ => None,
+        })
+    }
 
-            if distance <= hit_radius {
-                return Some(marker.id);
-            }
+    /// Fly to a geocoded place, framing its extent if it has one
+    pub fn fly_to_result(&self, cx: &mut Cx, result: &GeocodeResult) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.fly_to_result(cx, result);
         }
-        None
     }
 
-    /// Find a parent tile that can be used as fallback, returns (parent_coord, uv_offset, uv_scale)
-    fn find_parent_tile_coord(&self, coord: &TileCoord) -> Option<(TileCoord, Vec2, Vec2)> {
-        // Try parent tiles up to 4 zoom levels back
-        let mut x = coord.x;
-        let mut y = coord.y;
-        let mut z = coord.z;
-
-        for _ in 0..4 {
-            if z == 0 {
-                break;
-            }
+    /// Search for a place and fly to the best match when the results arrive
+    pub fn search_and_fly_to(&self, cx: &mut Cx, query: &str) -> Option<LiveId> {
+        Some(self.borrow_mut()?.search_and_fly_to(cx, query))
+    }
 
-            // Move to parent coordinates
-            x /= 2;
-            y /= 2;
-            z -= 1;
+    /// Check if a geocoding search finished (returns the query and its results)
+    pub fn geocode_results(&self, actions: &Actions) -> Option<(String, Vec<GeocodeResult>)> {
+        self.map_actions(actions).into_iter().find_map(|action| match action {
+            GeoMapViewAction::GeocodeResults { query, results } => Some((query, results)),
+            _ => None,
+        })
+    }
 
-            let parent_coord = TileCoord { x, y, z };
+    /// Check if a reverse lookup finished (returns the position and the place there, if any)
+    pub fn reverse_geocoded(&self, actions: &Actions) -> Option<(f64, f64, Option<GeocodeResult>)> {
+        self.map_actions(actions).into_iter().find_map(|action| match action {
+            GeoMapViewAction::ReverseGeocoded { lng, lat, result } => Some((lng, lat, result)),
+            _ => None,
+        })
+    }
 
-            if self.tile_cache.get_tile(&parent_coord).is_some() {
-                // Calculate UV offset and scale for the portion we need
-                let zoom_diff = coord.z - z;
-                let scale = 1.0 / (1 << zoom_diff) as f32;
+    /// Check if the address at a long-pressed position was looked up
+    /// (returns the position and its address, if one was found)
+    pub fn long_pressed_with_address(&self, actions: &Actions) -> Option<(f64, f64, Option<String>)> {
+        self.map_actions(actions).into_iter().find_map(|action| match action {
+            GeoMapViewAction::LongPressedWithAddress { lng, lat, address } => Some((lng, lat, address)),
+            _ => None,
+        })
+    }
 
-                // Calculate which portion of the parent tile our tile occupies
-                let offset_x = ((coord.x % (1 << zoom_diff)) as f32) * scale;
-                let offset_y = ((coord.y % (1 << zoom_diff)) as f32) * scale;
+    /// Check if a geocoding request failed
+    pub fn geocode_failed(&self, actions: &Actions) -> Option<(GeocodeQuery, GeocodeError)> {
+        self.map_actions(actions).into_iter().find_map(|action| match action {
+            GeoMapViewAction::GeocodeFailed { query, error } => Some((query, error)),
+            _ => None,
+        })
+    }
 
-                return Some((
-                    parent_coord,
-                    Vec2 { x: offset_x, y: offset_y },
-                    Vec2 { x: scale, y: scale },
-                ));
-            }
+    /// Set the projection of the tile pyramid (None = Web Mercator)
+    pub fn set_projection(&self, cx: &mut Cx, projection: Option<Box<dyn Projection>>) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_projection(cx, projection);
         }
-        None
     }
 
-    /// Calculate meters per pixel at the current zoom level and latitude
-    fn meters_per_pixel(&self) -> f64 {
-        // Earth circumference at equator = 40075016.686 meters
-        // World width in pixels = 256 * 2^zoom
-        // Adjust for latitude: multiply by cos(latitude)
-        let world_size_meters = 40075016.686;
-        let world_size_pixels = 256.0 * 2.0_f64.powf(self.zoom);
-        let meters_per_pixel_at_equator = world_size_meters / world_size_pixels;
-        meters_per_pixel_at_equator * self.center_lat.to_radians().cos()
+    /// Set (or remove) the camera tour followed by `seek`
+    pub fn set_tour(&self, cx: &mut Cx, tour: Option<CameraTour>) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_tour(cx, tour);
+        }
     }
 
-    /// Calculate the scale bar width and label for a given maximum width
-    fn calculate_scale_bar(&self, max_width: f64) -> (f64, String) {
-        let mpp = self.meters_per_pixel();
-        let max_meters = max_width * mpp;
-
-        // Find largest step that fits within max_width
-        let mut selected_meters = SCALE_STEPS[0];
-        for &step in SCALE_STEPS {
-            if step <= max_meters {
-                selected_meters = step;
-            } else {
-                break;
-            }
+    /// Show the map at a time of an external timeline (seconds)
+    pub fn seek(&self, cx: &mut Cx, time: f64) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.seek(cx, time);
         }
+    }
 
-        let bar_width = selected_meters / mpp;
-        let label = if selected_meters >= 1000.0 {
-            format!("{} km", (selected_meters / 1000.0) as i32)
-        } else {
-            format!("{} m", selected_meters as i32)
-        };
+    /// Set (or remove) the playback clock driving `seek`
+    pub fn set_timeline(&self, cx: &mut Cx, timeline: Option<Timeline>) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_timeline(cx, timeline);
+        }
+    }
 
-        (bar_width, label)
+    /// Current state of the playback clock
+    pub fn timeline(&self) -> Option<Timeline> {
+        self.borrow().and_then(|inner| inner.timeline().cloned())
     }
 
-    /// Calculate flick velocity from position/time samples
-    fn calculate_flick_velocity(&self) -> DVec2 {
-        if self.velocity_samples.len() < 2 {
-            return DVec2::default();
+    pub fn play_timeline(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.play_timeline(cx);
         }
+    }
 
-        let mut total = DVec2::default();
-        let mut count = 0;
-
-        for window in self.velocity_samples.windows(2) {
-            let (pos_prev, time_prev) = window[0];
-            let (pos_curr, time_curr) = window[1];
-            let dt = time_curr - time_prev;
-            if dt > 0.0001 {
-                total += (pos_curr - pos_prev) / dt;
-                count += 1;
-            }
+    pub fn pause_timeline(&self) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.pause_timeline();
         }
+    }
 
-        if count > 0 {
-            // Scale from pixels/second to per-frame velocity (~60fps)
-            total * (0.016 / count as f64)
-        } else {
-            DVec2::default()
+    /// Timeline seconds per real second while playing
+    pub fn set_timeline_speed(&self, speed: f64) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_timeline_speed(speed);
         }
     }
 
-    /// Apply momentum decay and update map position
-    fn apply_momentum(&mut self, cx: &mut Cx, uid: WidgetUid, path: &HeapLiveIdPath) {
-        self.flick_velocity *= self.momentum_decay;
+    /// Check if the playing timeline advanced (returns the time, e.g. for a slider)
+    pub fn time_changed(&self, actions: &Actions) -> Option<f64> {
+        self.map_actions(actions).into_iter().rev().find_map(|action| match action {
+            GeoMapViewAction::TimeChanged { time } => Some(time),
+            _ => None,
+        })
+    }
 
-        let speed = self.flick_velocity.x.hypot(self.flick_velocity.y);
-        if speed < self.momentum_threshold * 0.01 {
-            self.is_flicking = false;
-            self.emit_region_changed(cx, uid, path);
-            return;
+    /// Modify an overlay layer declared in live_design and redraw the map.
+    /// Returns None if there is no layer with this id and type.
+    pub fn with_layer<T: MapLayer, R>(&self, cx: &mut Cx, id: LiveId, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        let mut inner = self.borrow_mut()?;
+        let result = f(inner.layer_mut::<T>(id)?);
+        inner.draw_tile.redraw(cx);
+        Some(result)
+    }
+
+    /// Declare the attribution a custom tile source's terms require
+    pub fn set_required_attribution(&self, cx: &mut Cx, attribution: Option<ProviderAttribution>) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_required_attribution(cx, attribution);
         }
+    }
 
-        let (deg_per_px_x, deg_per_px_y) = self.degrees_per_pixel();
-        self.center_lng -= self.flick_velocity.x * deg_per_px_x;
-        self.center_lat += self.flick_velocity.y * deg_per_px_y;
-        self.normalize_coordinates();
+    /// Set where map tiles are loaded from (XYZ server or WMS layer)
+    pub fn set_tile_source(&self, cx: &mut Cx, source: TileSource) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_tile_source(cx, source);
+        }
+    }
 
-        self.draw_tile.redraw(cx);
-        self.next_frame = cx.new_next_frame();
+    /// Fetch tiles through a transport instead of plain HTTP requests
+    pub fn set_tile_transport(&self, cx: &mut Cx, transport: Option<Box<dyn TileTransport>>) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_tile_transport(cx, transport);
+        }
     }
 
-    fn emit_region_changed(&self, cx: &mut Cx, uid: WidgetUid, path: &HeapLiveIdPath) {
-        cx.widget_action(
-            uid,
-            path,
-            GeoMapViewAction::RegionChanged {
-                center_lng: self.center_lng,
-                center_lat: self.center_lat,
-                zoom: self.zoom,
-            },
-        );
+    /// Hand over the response to a tile request a `TileTransport` fetched on its own
+    pub fn deliver_tile_response(&self, cx: &mut Cx, request_id: LiveId, response: &HttpResponse) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.deliver_tile_response(cx, request_id, response);
+        }
     }
 
-    /// Set the map center programmatically
-    pub fn set_center(&mut self, cx: &mut Cx, lng: f64, lat: f64) {
-        self.center_lng = lng;
-        self.center_lat = lat.clamp(-85.0, 85.0);
-        self.draw_tile.redraw(cx);
+    /// Refetch all visible tiles now, keeping the cached ones on screen meanwhile
+    pub fn refresh_tiles(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.refresh_tiles(cx);
+        }
     }
 
-    /// Set the zoom level programmatically
-    pub fn set_zoom(&mut self, cx: &mut Cx, zoom: f64) {
-        self.zoom = zoom.clamp(self.min_zoom, self.max_zoom);
-        self.draw_tile.redraw(cx);
+    /// Set the base maps offered by the layer control
+    pub fn set_base_layers(&self, cx: &mut Cx, layers: Vec<BaseLayer>) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_base_layers(cx, layers);
+        }
     }
 
-    /// Add a marker at the specified geographic coordinates
-    /// Returns a mutable reference to the marker for further customization
-    pub fn add_marker(&mut self, cx: &mut Cx, id: LiveId, lng: f64, lat: f64) -> &mut MapMarker {
-        // Default red color for markers
-        let marker = MapMarker {
-            id,
-            lng,
-            lat,
-            label: String::new(),
-            color: vec4(0.9, 0.2, 0.2, 1.0), // Default red
-        };
-        self.markers.push(marker);
-        self.draw_tile.redraw(cx);
-        self.markers.last_mut().unwrap()
+    /// Switch to one of the base maps set with `set_base_layers`
+    pub fn select_base_layer(&self, cx: &mut Cx, id: LiveId) -> bool {
+        self.borrow_mut().is_some_and(|mut inner| inner.select_base_layer(cx, id))
     }
 
-    /// Remove a marker by ID
-    pub fn remove_marker(&mut self, cx: &mut Cx, id: LiveId) {
-        self.markers.retain(|m| m.id != id);
-        self.draw_tile.redraw(cx);
+    /// Name of an overlay layer in the layer control
+    pub fn set_layer_title(&self, cx: &mut Cx, id: LiveId, title: &str) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_layer_title(cx, id, title);
+        }
     }
 
-    /// Get a reference to a marker by ID
-    pub fn get_marker(&self, id: LiveId) -> Option<&MapMarker> {
-        self.markers.iter().find(|m| m.id == id)
+    /// Add an overlay layer from Rust, directly below the markers
+    pub fn add_layer(&self, cx: &mut Cx, id: LayerId, layer: Box<dyn MapLayer>) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.add_layer(cx, id, layer);
+        }
     }
 
-    /// Get a mutable reference to a marker by ID
-    pub fn get_marker_mut(&mut self, id: LiveId) -> Option<&mut MapMarker> {
-        self.markers.iter_mut().find(|m| m.id == id)
+    /// Remove an overlay layer
+    pub fn remove_layer(&self, cx: &mut Cx, id: LayerId) -> Option<Box<dyn MapLayer>> {
+        self.borrow_mut()?.remove_layer(cx, id)
     }
 
-    /// Remove all markers
-    pub fn clear_markers(&mut self, cx: &mut Cx) {
-        self.markers.clear();
-        self.draw_tile.redraw(cx);
+    /// Draw order of all layers, bottom first
+    pub fn layer_order(&self) -> Vec<LayerId> {
+        self.borrow().map(|inner| inner.layer_order()).unwrap_or_default()
     }
 
-    /// Get the number of markers
-    pub fn marker_count(&self) -> usize {
-        self.markers.len()
+    /// Move a layer so it's drawn directly above another one
+    pub fn move_layer_above(&self, cx: &mut Cx, id: LayerId, other: LayerId) -> bool {
+        self.borrow_mut().is_some_and(|mut inner| inner.move_layer_above(cx, id, other))
     }
-}
 
-impl GeoMapViewRef {
-    pub fn set_center(&self, cx: &mut Cx, lng: f64, lat: f64) {
+    /// Set the opacity of a layer (0..1)
+    pub fn set_layer_opacity(&self, cx: &mut Cx, id: LayerId, opacity: f64) {
         if let Some(mut inner) = self.borrow_mut() {
-            inner.set_center(cx, lng, lat);
+            inner.set_layer_opacity(cx, id, opacity);
         }
     }
 
-    pub fn set_zoom(&self, cx: &mut Cx, zoom: f64) {
+    /// Show or hide a built-in or overlay layer
+    pub fn set_layer_visible(&self, cx: &mut Cx, id: LayerId, visible: bool) {
         if let Some(mut inner) = self.borrow_mut() {
-            inner.set_zoom(cx, zoom);
+            inner.set_layer_visible(cx, id, visible);
         }
     }
 
-    /// Add a marker at the specified geographic coordinates
-    pub fn add_marker(&self, cx: &mut Cx, id: LiveId, lng: f64, lat: f64) {
+    /// Only show a layer within a zoom range
+    pub fn set_layer_zoom_range(&self, cx: &mut Cx, id: LayerId, min_zoom: f64, max_zoom: f64) {
         if let Some(mut inner) = self.borrow_mut() {
-            inner.add_marker(cx, id, lng, lat);
+            inner.set_layer_zoom_range(cx, id, min_zoom, max_zoom);
         }
     }
 
-    /// Add a marker with a custom color
-    pub fn add_marker_with_color(&self, cx: &mut Cx, id: LiveId, lng: f64, lat: f64, color: Vec4) {
+    /// Check if a layer was toggled from the layer control (returns id and visibility)
+    pub fn layer_toggled(&self, actions: &Actions) -> Option<(LiveId, bool)> {
+        self.map_actions(actions).into_iter().rev().find_map(|action| match action {
+            GeoMapViewAction::LayerToggled { id, visible } => Some((id, visible)),
+            _ => None,
+        })
+    }
+
+    /// Check if a layer loaded data with repaired polygons (returns the layer and the repairs)
+    pub fn geometry_repaired(&self, actions: &Actions) -> Option<(LiveId, Vec<(usize, GeometryWarning)>)> {
+        self.map_actions(actions).into_iter().rev().find_map(|action| match action {
+            GeoMapViewAction::GeometryRepaired { layer, warnings } => Some((layer, warnings)),
+            _ => None,
+        })
+    }
+
+    /// Check if a GeoJSON layer failed to load (returns the layer, url and error)
+    pub fn geojson_failed(&self, actions: &Actions) -> Option<(LiveId, String, GeoJsonError)> {
+        self.map_actions(actions).into_iter().rev().find_map(|action| match action {
+            GeoMapViewAction::GeoJsonFailed { layer, url, error } => Some((layer, url, error)),
+            _ => None,
+        })
+    }
+
+    /// Check if an animated marker moved (returns id, heading and progress)
+    pub fn marker_animation_progress(&self, actions: &Actions) -> Option<(LiveId, f64, f64)> {
+        self.map_actions(actions).into_iter().rev().find_map(|action| match action {
+            GeoMapViewAction::MarkerAnimationProgress { id, heading, progress, .. } => Some((id, heading, progress)),
+            _ => None,
+        })
+    }
+
+    /// Check if an animated marker reached the end of its path
+    pub fn marker_animation_finished(&self, actions: &Actions) -> Option<LiveId> {
+        self.map_actions(actions).into_iter().rev().find_map(|action| match action {
+            GeoMapViewAction::MarkerAnimationFinished { id } => Some(id),
+            _ => None,
+        })
+    }
+
+    /// Session-long tile request counters
+    pub fn tile_request_stats(&self) -> TileRequestStats {
+        self.borrow().map(|inner| inner.tile_request_stats()).unwrap_or_default()
+    }
+
+    /// Tile cache hits/misses, download volume, decode times and memory/disk usage
+    pub fn tile_cache_stats(&self) -> TileCacheStats {
+        self.borrow().map(|inner| inner.tile_cache_stats()).unwrap_or_default()
+    }
+
+    /// Set the disk cache location and size limit, or disable disk caching
+    pub fn set_disk_cache_config(&self, config: DiskCacheConfig) {
         if let Some(mut inner) = self.borrow_mut() {
-            let marker = inner.add_marker(cx, id, lng, lat);
-            marker.color = color;
+            inner.set_disk_cache_config(config);
         }
     }
 
-    /// Add a marker with label and color
-    pub fn add_marker_with_label(&self, cx: &mut Cx, id: LiveId, lng: f64, lat: f64, label: &str, color: Vec4) {
+    /// Download the tiles along a route for offline use (see `GeoMapView::prefetch_route`)
+    pub fn prefetch_route(
+        &self,
+        cx: &mut Cx,
+        points: &[(f64, f64)],
+        zoom_range: RangeInclusive<u8>,
+        corridor_meters: f64,
+    ) -> usize {
+        self.borrow_mut()
+            .map(|mut inner| inner.prefetch_route(cx, points, zoom_range, corridor_meters))
+            .unwrap_or(0)
+    }
+
+    /// Progress of the queued tile downloads
+    pub fn download_progress(&self) -> DownloadProgress {
+        self.borrow().map(|inner| inner.download_progress()).unwrap_or_default()
+    }
+
+    /// Drop the queued tile downloads
+    pub fn cancel_downloads(&self) {
         if let Some(mut inner) = self.borrow_mut() {
-            let marker = inner.add_marker(cx, id, lng, lat);
-            marker.label = label.to_string();
-            marker.color = color;
+            inner.cancel_downloads();
         }
     }
 
-    /// Remove a marker by ID
-    pub fn remove_marker(&self, cx: &mut Cx, id: LiveId) {
+    /// Check if tiles for the current view are loading (returns pending and total tiles)
+    pub fn tiles_loading(&self, actions: &Actions) -> Option<(usize, usize)> {
+        self.map_actions(actions).into_iter().rev().find_map(|action| match action {
+            GeoMapViewAction::TilesLoading { pending, total } => Some((pending, total)),
+            _ => None,
+        })
+    }
+
+    /// Check if all tiles for the current view finished loading
+    pub fn tiles_idle(&self, actions: &Actions) -> bool {
+        self.map_actions(actions).into_iter().any(|action| matches!(action, GeoMapViewAction::TilesIdle))
+    }
+
+    /// Check if queued tile downloads progressed
+    pub fn download_progress_changed(&self, actions: &Actions) -> Option<DownloadProgress> {
+        self.map_actions(actions).into_iter().rev().find_map(|action| match action {
+            GeoMapViewAction::DownloadProgressChanged { progress } => Some(progress),
+            _ => None,
+        })
+    }
+
+    /// Rendering quality in effect (see `adaptive_quality`)
+    pub fn quality_level(&self) -> QualityLevel {
+        self.borrow().map(|inner| inner.quality_level()).unwrap_or_default()
+    }
+
+    /// Force a rendering quality, overriding the adaptive governor (None hands control back)
+    pub fn set_quality_override(&self, cx: &mut Cx, level: Option<QualityLevel>) {
         if let Some(mut inner) = self.borrow_mut() {
-            inner.remove_marker(cx, id);
+            inner.set_quality_override(cx, level);
         }
     }
 
-    /// Remove all markers
-    pub fn clear_markers(&self, cx: &mut Cx) {
+    /// Turn power saving on or off (see `GeoMapView::set_low_power`)
+    pub fn set_low_power(&self, cx: &mut Cx, low_power: bool) {
         if let Some(mut inner) = self.borrow_mut() {
-            inner.clear_markers(cx);
+            inner.set_low_power(cx, low_power);
         }
     }
 
-    /// Get the number of markers
-    pub fn marker_count(&self) -> usize {
-        if let Some(inner) = self.borrow() {
-            inner.marker_count()
-        } else {
-            0
-        }
+    /// Check if the adaptive quality governor changed the rendering quality
+    pub fn quality_changed(&self, actions: &Actions) -> Option<QualityLevel> {
+        self.map_actions(actions).into_iter().rev().find_map(|action| match action {
+            GeoMapViewAction::QualityChanged { level } => Some(level),
+            _ => None,
+        })
     }
 
-    /// Check if the map was tapped (returns coordinates if tapped)
-    pub fn tapped(&self, actions: &Actions) -> Option<(f64, f64)> {
-        if let GeoMapViewAction::Tapped { lng, lat } = actions.find_widget_action(self.widget_uid()).cast() {
-            Some((lng, lat))
-        } else {
-            None
+    /// Check if tile requests were paused by the guardrails (returns the warning and backoff in seconds)
+    pub fn tile_requests_backed_off(&self, actions: &Actions) -> Option<(TileRequestWarning, f64)> {
+        self.map_actions(actions).into_iter().find_map(|action| match action {
+            GeoMapViewAction::TileRequestsBackedOff { warning, duration } => Some((warning, duration)),
+            _ => None,
+        })
+    }
+
+    /// Check if a tile request timed out and is being retried (returns the tile and the timeout in seconds)
+    pub fn tile_timeout(&self, actions: &Actions) -> Option<(TileCoord, f64)> {
+        self.map_actions(actions).into_iter().find_map(|action| match action {
+            GeoMapViewAction::TileTimeout { coord, timeout } => Some((coord, timeout)),
+            _ => None,
+        })
+    }
+
+    /// Check if the tile server throttled requests (returns host, HTTP status and cooldown in seconds)
+    pub fn provider_throttled(&self, actions: &Actions) -> Option<(String, u16, f64)> {
+        self.map_actions(actions).into_iter().find_map(|action| match action {
+            GeoMapViewAction::ProviderThrottled { host, status, duration } => Some((host, status, duration)),
+            _ => None,
+        })
+    }
+
+    /// Check if a tapped position was looked up (returns coordinates and provider text)
+    pub fn coordinate_looked_up(&self, actions: &Actions) -> Option<(f64, f64, String)> {
+        self.map_actions(actions).into_iter().find_map(|action| match action {
+            GeoMapViewAction::CoordinateLookedUp { lng, lat, text } => Some((lng, lat, text)),
+            _ => None,
+        })
+    }
+
+    /// Check if a temporary pin was dropped by long press (returns pin ID and coordinates)
+    pub fn pin_dropped(&self, actions: &Actions) -> Option<(LiveId, f64, f64)> {
+        self.map_actions(actions).into_iter().find_map(|action| match action {
+            GeoMapViewAction::PinDropped { id, lng, lat } => Some((id, lng, lat)),
+            _ => None,
+        })
+    }
+
+    /// Remove the temporary pin dropped by long press
+    pub fn clear_dropped_pin(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.clear_dropped_pin(cx);
         }
     }
 
     /// Check if a marker was tapped (returns marker ID if tapped)
     pub fn marker_tapped(&self, actions: &Actions) -> Option<LiveId> {
-        if let GeoMapViewAction::MarkerTapped { id } = actions.find_widget_action(self.widget_uid()).cast() {
-            Some(id)
-        } else {
-            None
-        }
+        self.map_actions(actions).into_iter().find_map(|action| match action {
+            GeoMapViewAction::MarkerTapped { id } => Some(id),
+            _ => None,
+        })
+    }
+
+    /// Check if a marker was tapped, returning the object attached to it with
+    /// `MapMarker::set_data` (None for markers without a `T` attached)
+    pub fn marker_tapped_data<T: Any + Send + Sync>(&self, actions: &Actions) -> Option<Arc<T>> {
+        self.marker_data(self.marker_tapped(actions)?)
+    }
+
+    /// The object attached to a marker with `MapMarker::set_data`, if it's a `T`
+    pub fn marker_data<T: Any + Send + Sync>(&self, id: LiveId) -> Option<Arc<T>> {
+        self.borrow()?.get_marker(id)?.data()
     }
 
-    /// Check if the map region changed (returns new center and zoom)
+    /// Check if the map region changed (returns the latest center and zoom)
     pub fn region_changed(&self, actions: &Actions) -> Option<(f64, f64, f64)> {
-        if let GeoMapViewAction::RegionChanged { center_lng, center_lat, zoom } = actions.find_widget_action(self.widget_uid()).cast() {
-            Some((center_lng, center_lat, zoom))
-        } else {
-            None
-        }
+        self.map_actions(actions).into_iter().rev().find_map(|action| match action {
+            GeoMapViewAction::RegionChanged { center_lng, center_lat, zoom } => Some((center_lng, center_lat, zoom)),
+            _ => None,
+        })
     }
 }
@@ -0,0 +1,157 @@
+use crate::geo::{bearing, haversine_distance};
+use crate::projection::Projection;
+
+/// Movement below this many meters between fixes doesn't change the heading
+/// derived from the track (GPS noise while standing still)
+const MIN_HEADING_DISTANCE: f64 = 2.0;
+
+/// Time constant in seconds of the camera easing into the navigation zoom and pitch
+const SETTLE_TIME: f64 = 0.4;
+
+/// Settings of the navigation camera, see `GeoMapView::start_navigation`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NavigationOptions {
+    /// Zoom level while navigating
+    pub zoom: f64,
+    /// Tilt of the map in degrees, showing more of the road ahead
+    pub pitch: f64,
+    /// Turn the map so the direction of travel points up
+    pub follow_heading: bool,
+    /// Where the position is pinned, as a fraction of the padded viewport height from
+    /// the top (2/3 shows the road ahead in the upper two thirds)
+    pub anchor: f64,
+    /// Longest time in seconds a move between two fixes is spread over. Fixes
+    /// arriving further apart (a GPS dropout) move the position this fast.
+    pub max_interpolation: f64,
+}
+
+impl Default for NavigationOptions {
+    fn default() -> Self {
+        Self {
+            zoom: 17.0,
+            pitch: 45.0,
+            follow_heading: true,
+            anchor: 2.0 / 3.0,
+            max_interpolation: 2.0,
+        }
+    }
+}
+
+/// Position and heading (degrees clockwise from north) of the navigated object
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NavigationFix {
+    pub lng: f64,
+    pub lat: f64,
+    pub heading: f64,
+}
+
+/// Smooths the jumps between position fixes (e.g. 1 Hz GPS) into continuous
+/// motion: each new fix starts a move from the shown position that lasts as
+/// long as the time since the previous fix, so the position keeps moving at
+/// the reported speed and arrives as the next fix comes in.
+pub(crate) struct NavigationCamera {
+    pub options: NavigationOptions,
+    /// Whether the camera follows the position (false after the user panned)
+    pub following: bool,
+    from: NavigationFix,
+    to: Option<NavigationFix>,
+    // Fix reported since the last frame, started on the next frame to get its time
+    pending: Option<NavigationFix>,
+    // Position shown by the last frame
+    shown: Option<NavigationFix>,
+    move_start: f64,
+    move_duration: f64,
+    last_fix_time: Option<f64>,
+    last_frame_time: Option<f64>,
+}
+
+/// Camera for one navigation frame
+pub(crate) struct NavigationFrame {
+    pub position: NavigationFix,
+    /// Fraction (0..1) to move the zoom, pitch and bearing towards their targets
+    pub settle: f64,
+    /// Whether more frames are needed
+    pub moving: bool,
+}
+
+impl NavigationCamera {
+    pub fn new(options: NavigationOptions) -> Self {
+        Self {
+            options,
+            following: true,
+            from: NavigationFix::default(),
+            to: None,
+            pending: None,
+            shown: None,
+            move_start: 0.0,
+            move_duration: 0.0,
+            last_fix_time: None,
+            last_frame_time: None,
+        }
+    }
+
+    /// Report a new fix. Without a heading it is taken from the movement since
+    /// the last fix.
+    pub fn push_fix(&mut self, lng: f64, lat: f64, heading: Option<f64>) {
+        let previous = self.pending.or(self.to);
+        let heading = heading.unwrap_or_else(|| match previous {
+            Some(prev) if haversine_distance(prev.lng, prev.lat, lng, lat) >= MIN_HEADING_DISTANCE => {
+                bearing(prev.lng, prev.lat, lng, lat)
+            }
+            Some(prev) => prev.heading,
+            None => 0.0,
+        });
+        self.pending = Some(NavigationFix { lng, lat, heading });
+    }
+
+    /// Shown position, None before the first fix
+    pub fn position(&self) -> Option<NavigationFix> {
+        self.shown
+    }
+
+    /// Advance to `time` (seconds)
+    pub fn frame(&mut self, time: f64, projection: &dyn Projection) -> Option<NavigationFrame> {
+        if let Some(fix) = self.pending.take() {
+            let current = self.current(time, projection);
+            // The first fix is shown right away, later ones take as long as the fixes are apart
+            self.move_duration = match (current, self.last_fix_time) {
+                (Some(_), Some(last)) => (time - last).clamp(0.0, self.options.max_interpolation),
+                _ => 0.0,
+            };
+            self.from = current.unwrap_or(fix);
+            self.to = Some(fix);
+            self.move_start = time;
+            self.last_fix_time = Some(time);
+        }
+        let position = self.current(time, projection)?;
+        self.shown = Some(position);
+
+        let dt = self.last_frame_time.map_or(0.0, |last| (time - last).max(0.0));
+        self.last_frame_time = Some(time);
+        let settle = 1.0 - (-dt / SETTLE_TIME).exp();
+        let moving = time < self.move_start + self.move_duration;
+        Some(NavigationFrame { position, settle, moving })
+    }
+
+    /// Interpolated position at `time`
+    fn current(&self, time: f64, projection: &dyn Projection) -> Option<NavigationFix> {
+        let to = self.to?;
+        let t = if self.move_duration > 0.0 {
+            ((time - self.move_start) / self.move_duration).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        // Linear in projected space, the short way around the antimeridian
+        let to_lng = self.from.lng + (to.lng - self.from.lng + 180.0).rem_euclid(360.0) - 180.0;
+        let from_unit = projection.project(self.from.lng, self.from.lat);
+        let to_unit = projection.project(to_lng, to.lat);
+        let (lng, lat) = projection.unproject(from_unit + (to_unit - from_unit) * t);
+        let turn = (to.heading - self.from.heading + 180.0).rem_euclid(360.0) - 180.0;
+        Some(NavigationFix { lng, lat, heading: (self.from.heading + turn * t).rem_euclid(360.0) })
+    }
+
+    /// Forget the frame clock, e.g. when following resumes after a pause
+    pub fn reset_clock(&mut self) {
+        self.last_frame_time = None;
+    }
+}
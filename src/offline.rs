@@ -0,0 +1,99 @@
+use makepad_widgets::*;
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+
+use crate::projection::{world_size, Projection, TILE_SIZE};
+use crate::tiles::TileCoord;
+
+/// Progress of the tiles queued for download into the disk cache
+/// (see `TileCache::queue_downloads`)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DownloadProgress {
+    /// Tiles queued since the queue was last empty
+    pub total: usize,
+    /// Tiles stored (or found already cached)
+    pub completed: usize,
+    /// Tiles that failed to download or were dropped
+    pub failed: usize,
+}
+
+impl DownloadProgress {
+    /// Tiles not finished yet
+    pub fn remaining(&self) -> usize {
+        self.total.saturating_sub(self.completed + self.failed)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// Finished fraction (0..1), 1 for an empty queue
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            (self.completed + self.failed) as f64 / self.total as f64
+        }
+    }
+}
+
+/// Tiles within `corridor_meters` of a route given as (lng, lat) points, for
+/// every zoom level in `zoom_range`. Segments crossing the antimeridian take
+/// the short way around.
+pub fn route_corridor_tiles(
+    projection: &dyn Projection,
+    points: &[(f64, f64)],
+    zoom_range: RangeInclusive<u8>,
+    corridor_meters: f64,
+) -> Vec<TileCoord> {
+    // Continuous longitudes, so each segment is a short straight line in the projection
+    let mut unwrapped: Vec<(f64, f64)> = Vec::with_capacity(points.len());
+    for &(lng, lat) in points {
+        let lng = match unwrapped.last() {
+            Some(&(prev, _)) if projection.wraps_longitude() => prev + (lng - prev + 180.0).rem_euclid(360.0) - 180.0,
+            _ => lng,
+        };
+        unwrapped.push((lng, lat));
+    }
+    let Some(&first) = unwrapped.first() else {
+        return Vec::new();
+    };
+    let segments: Vec<((f64, f64), (f64, f64))> = if unwrapped.len() == 1 {
+        vec![(first, first)]
+    } else {
+        unwrapped.windows(2).map(|pair| (pair[0], pair[1])).collect()
+    };
+
+    let mut seen = HashSet::new();
+    let mut tiles = Vec::new();
+    for zoom in *zoom_range.start()..=(*zoom_range.end()).min(19) {
+        let size = world_size(zoom as f64);
+        let max_tile = 1i64 << zoom;
+        for &((lng1, lat1), (lng2, lat2)) in &segments {
+            let from = projection.project(lng1, lat1) * size;
+            let to = projection.project(lng2, lat2) * size;
+            let meters_per_pixel = projection.meters_per_unit(lng1, lat1) / size;
+            let radius = corridor_meters.max(0.0) / meters_per_pixel.max(1e-9);
+
+            // Sample at most half a tile apart, covering a square of the corridor radius around each sample
+            let steps = ((to - from).length() / (TILE_SIZE / 2.0)).ceil().max(1.0) as usize;
+            for i in 0..=steps {
+                let pos = from + (to - from) * (i as f64 / steps as f64);
+                let min = ((pos - dvec2(radius, radius)) / TILE_SIZE).floor();
+                let max = ((pos + dvec2(radius, radius)) / TILE_SIZE).floor();
+                for y in (min.y as i64).max(0)..=(max.y as i64).min(max_tile - 1) {
+                    for x in min.x as i64..=max.x as i64 {
+                        if !projection.wraps_longitude() && (x < 0 || x >= max_tile) {
+                            continue;
+                        }
+                        let coord = TileCoord { x: x.rem_euclid(max_tile) as u32, y: y as u32, z: zoom };
+                        if seen.insert(coord) {
+                            tiles.push(coord);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    tiles
+}
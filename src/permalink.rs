@@ -0,0 +1,208 @@
+use crate::camera::MapCamera;
+
+/// Zoom of links that only carry a position (geo: URIs without `z`, place links)
+pub const DEFAULT_LINK_ZOOM: f64 = 15.0;
+
+/// Read a camera from a map link or permalink. Understood formats:
+///
+/// - OpenStreetMap style hashes `#map=14/37.77/-122.41`, optionally followed
+///   by `/bearing/pitch`, alone or at the end of a URL
+/// - MapLibre style hashes `#14/37.77/-122.41/bearing/pitch`
+/// - geo: URIs (RFC 5870) like `geo:37.77,-122.41?z=14`, including Android's
+///   `geo:0,0?q=37.77,-122.41(Label)`
+/// - Google Maps links with `@37.77,-122.41,14z` in the path, or `q=`, `ll=`,
+///   `query=` or `center=` parameters
+///
+/// Returns None if the link isn't one of these or its position is out of range.
+pub fn parse_map_link(link: &str) -> Option<MapCamera> {
+    let link = link.trim();
+    let camera = if let Some(uri) = strip_prefix_ignore_case(link, "geo:") {
+        parse_geo_uri(uri)
+    } else if link.contains("google.") {
+        parse_google_maps(link)
+    } else if let Some(hash) = link.find('#').map(|i| &link[i + 1..]).filter(|hash| !hash.is_empty()) {
+        parse_hash(hash)
+    } else {
+        parse_hash(link)
+    }?;
+    let valid = (-90.0..=90.0).contains(&camera.center.1) && (-180.0..=180.0).contains(&camera.center.0)
+        && camera.zoom.is_finite() && camera.bearing.is_finite() && camera.pitch.is_finite();
+    valid.then_some(camera)
+}
+
+/// URL hash sharing a view, e.g. `#map=14/37.7749/-122.4194`, with the
+/// bearing and pitch appended when the map is turned or tilted. Append it
+/// to the URL of a web map or the app's own link.
+pub fn map_hash(camera: &MapCamera) -> String {
+    let mut hash = format!(
+        "#map={}/{}/{}",
+        trim_number(camera.zoom, 2),
+        trim_number(camera.center.1, coordinate_decimals(camera.zoom)),
+        trim_number(camera.center.0, coordinate_decimals(camera.zoom)),
+    );
+    if camera.bearing != 0.0 || camera.pitch != 0.0 {
+        hash.push_str(&format!("/{}/{}", trim_number(camera.bearing, 1), trim_number(camera.pitch, 1)));
+    }
+    hash
+}
+
+/// geo: URI of the camera's center, e.g. `geo:37.7749,-122.4194?z=14`, which
+/// mobile platforms open in the user's map app
+pub fn geo_uri(camera: &MapCamera) -> String {
+    let decimals = coordinate_decimals(camera.zoom);
+    format!(
+        "geo:{},{}?z={}",
+        trim_number(camera.center.1, decimals),
+        trim_number(camera.center.0, decimals),
+        // Android only takes whole zoom levels from 1 to 21
+        camera.zoom.round().clamp(1.0, 21.0),
+    )
+}
+
+/// Google Maps link to the camera's view, e.g.
+/// `https://www.google.com/maps/@37.7749,-122.4194,14z`
+pub fn google_maps_url(camera: &MapCamera) -> String {
+    let decimals = coordinate_decimals(camera.zoom);
+    format!(
+        "https://www.google.com/maps/@{},{},{}z",
+        trim_number(camera.center.1, decimals),
+        trim_number(camera.center.0, decimals),
+        trim_number(camera.zoom, 2),
+    )
+}
+
+/// `map=zoom/lat/lng[/bearing/pitch]` or `zoom/lat/lng[/bearing/pitch]`,
+/// possibly among other `&`-separated hash parameters
+fn parse_hash(hash: &str) -> Option<MapCamera> {
+    let value = hash.split('&')
+        .find_map(|param| param.strip_prefix("map="))
+        .unwrap_or(hash);
+    let parts: Vec<f64> = value.split('/').map(|part| part.trim().parse().ok()).collect::<Option<_>>()?;
+    if !(3..=5).contains(&parts.len()) {
+        return None;
+    }
+    Some(MapCamera {
+        center: (parts[2], parts[1]),
+        zoom: parts[0],
+        bearing: parts.get(3).copied().unwrap_or_default(),
+        pitch: parts.get(4).copied().unwrap_or_default(),
+    })
+}
+
+/// `lat,lng[,alt][;params][?query]` after the `geo:` scheme
+fn parse_geo_uri(uri: &str) -> Option<MapCamera> {
+    let (path, query) = uri.split_once('?').unwrap_or((uri, ""));
+    let coordinates = path.split(';').next().unwrap_or_default();
+    let zoom = query_param(query, "z").and_then(|z| z.parse().ok()).unwrap_or(DEFAULT_LINK_ZOOM);
+    let (mut lat, mut lng) = parse_lat_lng(coordinates)?;
+    // Android puts the actual position in q= and 0,0 in the path
+    if let Some(q) = query_param(query, "q") {
+        let q = q.split('(').next().unwrap_or_default();
+        if let Some(position) = parse_lat_lng(q) {
+            (lat, lng) = position;
+        }
+    }
+    Some(MapCamera { center: (lng, lat), zoom, ..Default::default() })
+}
+
+fn parse_google_maps(url: &str) -> Option<MapCamera> {
+    let url = url.split('#').next().unwrap_or_default();
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+
+    // .../maps/@37.77,-122.41,14z or .../maps/place/Name/@37.77,-122.41,17z/data=...
+    if let Some(at) = path.rfind('@') {
+        let view = path[at + 1..].split('/').next().unwrap_or_default();
+        let mut parts = view.split(',');
+        let lat = parts.next().and_then(|lat| lat.parse().ok());
+        let lng = parts.next().and_then(|lng| lng.parse().ok());
+        if let (Some(lat), Some(lng)) = (lat, lng) {
+            let mut camera = MapCamera { center: (lng, lat), zoom: DEFAULT_LINK_ZOOM, ..Default::default() };
+            // Remaining parts are numbers with a unit: zoom, heading and tilt
+            for part in parts {
+                let Some(unit) = part.chars().last() else {
+                    continue;
+                };
+                let Ok(value) = part[..part.len() - unit.len_utf8()].parse::<f64>() else {
+                    continue;
+                };
+                match unit {
+                    'z' => camera.zoom = value,
+                    'h' => camera.bearing = value,
+                    't' => camera.pitch = value,
+                    _ => {}
+                }
+            }
+            return Some(camera);
+        }
+    }
+
+    let zoom = query_param(query, "z")
+        .or_else(|| query_param(query, "zoom"))
+        .and_then(|z| z.parse().ok())
+        .unwrap_or(DEFAULT_LINK_ZOOM);
+    let (lat, lng) = ["q", "ll", "query", "center"].iter()
+        .find_map(|key| query_param(query, key).and_then(|value| parse_lat_lng(&value)))?;
+    Some(MapCamera { center: (lng, lat), zoom, ..Default::default() })
+}
+
+/// Percent-decoded value of a query parameter
+fn query_param(query: &str, key: &str) -> Option<String> {
+    query.split('&')
+        .filter_map(|param| param.split_once('='))
+        .find(|(k, _)| *k == key)
+        .map(|(_, value)| percent_decode(value))
+}
+
+/// "lat,lng" (spaces allowed)
+fn parse_lat_lng(text: &str) -> Option<(f64, f64)> {
+    let (lat, lng) = text.split_once(',')?;
+    let lng = lng.split(',').next().unwrap_or_default();
+    Some((lat.trim().parse().ok()?, lng.trim().parse().ok()?))
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => match (hex_digit(bytes[i + 1]), hex_digit(bytes[i + 2])) {
+                (Some(high), Some(low)) => {
+                    out.push(high << 4 | low);
+                    i += 2;
+                }
+                _ => out.push(b'%'),
+            },
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn hex_digit(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|digit| digit as u8)
+}
+
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = text.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix).then(|| &text[prefix.len()..])
+}
+
+/// Decimals of latitude and longitude that resolve about a pixel at `zoom`
+fn coordinate_decimals(zoom: f64) -> usize {
+    // 256 px tiles: a degree is 2^zoom * 256 / 360 pixels at the equator
+    let pixels_per_degree = 2f64.powf(zoom.max(0.0)) * 256.0 / 360.0;
+    (pixels_per_degree.log10().ceil().max(0.0) as usize).min(7)
+}
+
+/// `value` with at most `decimals` decimals, without trailing zeros
+fn trim_number(value: f64, decimals: usize) -> String {
+    let text = format!("{:.*}", decimals, value);
+    let text = if text.contains('.') { text.trim_end_matches('0').trim_end_matches('.') } else { &text };
+    match text {
+        "-0" => "0".to_string(),
+        text => text.to_string(),
+    }
+}
@@ -0,0 +1,251 @@
+use makepad_widgets::*;
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+use crate::geo::haversine_distance;
+use crate::tiles::TileCoord;
+
+/// Tile size in pixels (standard OSM tile size)
+pub const TILE_SIZE: f64 = 256.0;
+
+/// WGS84 semi-major axis in meters
+const WGS84_A: f64 = 6378137.0;
+/// WGS84 first eccentricity
+const WGS84_E: f64 = 0.0818191908426215;
+
+/// Size of the world in pixels at a (fractional) zoom level
+pub fn world_size(zoom: f64) -> f64 {
+    TILE_SIZE * 2.0_f64.powf(zoom)
+}
+
+/// A map projection between geographic coordinates and the tiled world.
+///
+/// The world is the unit square with y pointing down; zoom level `z` covers it
+/// with `2^z x 2^z` tiles. Implement this trait to use tile pyramids in other
+/// projections (e.g. a polar stereographic arctic basemap).
+pub trait Projection {
+    /// CRS identifier, e.g. "EPSG:3857"
+    fn code(&self) -> &str;
+
+    /// Project geographic coordinates (degrees) into the unit square
+    fn project(&self, lng: f64, lat: f64) -> DVec2;
+
+    /// Inverse of `project`, returns (lng, lat). Longitudes outside the unit
+    /// square are not wrapped, so positions east of the antimeridian stay east.
+    fn unproject(&self, unit: DVec2) -> (f64, f64);
+
+    /// Convert a unit square position into native CRS units (meters or degrees)
+    fn to_crs(&self, unit: DVec2) -> DVec2;
+
+    /// Latitude range the map center is kept in
+    fn lat_range(&self) -> (f64, f64) {
+        (-85.0, 85.0)
+    }
+
+    /// Whether x is linear in longitude, so the world repeats horizontally
+    /// (world copies, antimeridian handling, UTM grid)
+    fn wraps_longitude(&self) -> bool {
+        true
+    }
+
+    /// Bounding box of a tile in CRS units as (min_x, min_y, max_x, max_y)
+    fn tile_bbox(&self, coord: &TileCoord) -> (f64, f64, f64, f64) {
+        let tiles = 2.0_f64.powi(coord.z as i32);
+        let top_left = self.to_crs(dvec2(coord.x as f64, coord.y as f64) / tiles);
+        let bottom_right = self.to_crs(dvec2(coord.x as f64 + 1.0, coord.y as f64 + 1.0) / tiles);
+        (
+            top_left.x.min(bottom_right.x),
+            top_left.y.min(bottom_right.y),
+            top_left.x.max(bottom_right.x),
+            top_left.y.max(bottom_right.y),
+        )
+    }
+
+    /// Ground meters per unit square unit at a position (east-west direction)
+    fn meters_per_unit(&self, lng: f64, lat: f64) -> f64 {
+        const STEP: f64 = 1e-6;
+        let unit = self.project(lng, lat);
+        let (lng2, lat2) = self.unproject(unit + dvec2(STEP, 0.0));
+        haversine_distance(lng, lat, lng2, lat2) / STEP
+    }
+
+    /// World pixel coordinates at a (fractional) zoom level
+    fn geo_to_world(&self, lng: f64, lat: f64, zoom: f64) -> DVec2 {
+        self.project(lng, lat) * world_size(zoom)
+    }
+
+    /// Geographic coordinates of world pixel coordinates at a (fractional) zoom level
+    fn world_to_geo(&self, world: DVec2, zoom: f64) -> (f64, f64) {
+        self.unproject(world / world_size(zoom))
+    }
+}
+
+/// Spherical Web Mercator (EPSG:3857), used by OSM and nearly all XYZ tile servers
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WebMercator;
+
+impl Projection for WebMercator {
+    fn code(&self) -> &str {
+        "EPSG:3857"
+    }
+
+    fn project(&self, lng: f64, lat: f64) -> DVec2 {
+        let x = (lng + 180.0) / 360.0;
+        let y = (1.0 - lat.to_radians().tan().asinh() / PI) / 2.0;
+        dvec2(x, y)
+    }
+
+    fn unproject(&self, unit: DVec2) -> (f64, f64) {
+        let lng = unit.x * 360.0 - 180.0;
+        let lat_rad = (PI * (1.0 - 2.0 * unit.y)).sinh().atan();
+        (lng, lat_rad.to_degrees())
+    }
+
+    fn to_crs(&self, unit: DVec2) -> DVec2 {
+        let half = PI * WGS84_A;
+        dvec2(unit.x * 2.0 * half - half, half - unit.y * 2.0 * half)
+    }
+
+    fn meters_per_unit(&self, _lng: f64, lat: f64) -> f64 {
+        2.0 * PI * WGS84_A * lat.to_radians().cos()
+    }
+}
+
+/// Plate carrée / equirectangular (EPSG:4326). The 360° x 180° world sits in
+/// the middle of the unit square so degrees stay square on screen.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PlateCarree;
+
+impl Projection for PlateCarree {
+    fn code(&self) -> &str {
+        "EPSG:4326"
+    }
+
+    fn project(&self, lng: f64, lat: f64) -> DVec2 {
+        dvec2((lng + 180.0) / 360.0, 0.25 + (90.0 - lat) / 360.0)
+    }
+
+    fn unproject(&self, unit: DVec2) -> (f64, f64) {
+        (unit.x * 360.0 - 180.0, 90.0 - (unit.y - 0.25) * 360.0)
+    }
+
+    fn to_crs(&self, unit: DVec2) -> DVec2 {
+        let (lng, lat) = self.unproject(unit);
+        dvec2(lng, lat)
+    }
+
+    fn lat_range(&self) -> (f64, f64) {
+        (-90.0, 90.0)
+    }
+}
+
+/// Ellipsoidal polar stereographic projection with a standard parallel
+/// (variant B), e.g. EPSG:3413 for the Arctic or EPSG:3031 for the Antarctic
+#[derive(Clone, Debug)]
+pub struct PolarStereographic {
+    pub code: String,
+    /// True for the north polar aspect
+    pub north: bool,
+    /// Latitude of true scale in degrees
+    pub standard_parallel: f64,
+    /// Longitude pointing straight down from the pole in degrees
+    pub central_meridian: f64,
+    /// Half the width of the tiled square in meters, centered on the pole
+    pub extent: f64,
+}
+
+impl PolarStereographic {
+    /// NSIDC Sea Ice Polar Stereographic North (EPSG:3413)
+    pub fn arctic() -> Self {
+        Self {
+            code: "EPSG:3413".to_string(),
+            north: true,
+            standard_parallel: 70.0,
+            central_meridian: -45.0,
+            extent: 4194304.0,
+        }
+    }
+
+    /// Antarctic Polar Stereographic (EPSG:3031)
+    pub fn antarctic() -> Self {
+        Self {
+            code: "EPSG:3031".to_string(),
+            north: false,
+            standard_parallel: -71.0,
+            central_meridian: 0.0,
+            extent: 4194304.0,
+        }
+    }
+
+    /// Snyder's t function (15-9)
+    fn t(phi: f64) -> f64 {
+        let e_sin = WGS84_E * phi.sin();
+        (FRAC_PI_4 - phi / 2.0).tan() / ((1.0 - e_sin) / (1.0 + e_sin)).powf(WGS84_E / 2.0)
+    }
+
+    /// Radius per unit t at the standard parallel (a * mc / tc)
+    fn rho_scale(&self) -> f64 {
+        let phi_c = self.standard_parallel.abs().to_radians();
+        let e_sin = WGS84_E * phi_c.sin();
+        let mc = phi_c.cos() / (1.0 - e_sin * e_sin).sqrt();
+        WGS84_A * mc / Self::t(phi_c)
+    }
+
+    /// Projected meters, with the sign flips of the south polar aspect
+    fn to_meters(&self, lng: f64, lat: f64) -> DVec2 {
+        let sign = if self.north { 1.0 } else { -1.0 };
+        let phi = (sign * lat).to_radians();
+        let lambda = (sign * (lng - self.central_meridian)).to_radians();
+        let rho = self.rho_scale() * Self::t(phi);
+        dvec2(sign * rho * lambda.sin(), -sign * rho * lambda.cos())
+    }
+
+    fn from_meters(&self, meters: DVec2) -> (f64, f64) {
+        let sign = if self.north { 1.0 } else { -1.0 };
+        let (x, y) = (sign * meters.x, sign * meters.y);
+        let t = x.hypot(y) / self.rho_scale();
+
+        // Iterate for the latitude (Snyder 7-9)
+        let mut phi = FRAC_PI_2 - 2.0 * t.atan();
+        for _ in 0..8 {
+            let e_sin = WGS84_E * phi.sin();
+            phi = FRAC_PI_2 - 2.0 * (t * ((1.0 - e_sin) / (1.0 + e_sin)).powf(WGS84_E / 2.0)).atan();
+        }
+
+        let lambda = x.atan2(-y);
+        let lng = self.central_meridian + sign * lambda.to_degrees();
+        ((lng + 180.0).rem_euclid(360.0) - 180.0, sign * phi.to_degrees())
+    }
+}
+
+impl Projection for PolarStereographic {
+    fn code(&self) -> &str {
+        &self.code
+    }
+
+    fn project(&self, lng: f64, lat: f64) -> DVec2 {
+        let meters = self.to_meters(lng, lat);
+        dvec2(
+            (meters.x + self.extent) / (2.0 * self.extent),
+            (self.extent - meters.y) / (2.0 * self.extent),
+        )
+    }
+
+    fn unproject(&self, unit: DVec2) -> (f64, f64) {
+        self.from_meters(self.to_crs(unit))
+    }
+
+    fn to_crs(&self, unit: DVec2) -> DVec2 {
+        dvec2(
+            unit.x * 2.0 * self.extent - self.extent,
+            self.extent - unit.y * 2.0 * self.extent,
+        )
+    }
+
+    fn lat_range(&self) -> (f64, f64) {
+        if self.north { (30.0, 90.0) } else { (-90.0, -30.0) }
+    }
+
+    fn wraps_longitude(&self) -> bool {
+        false
+    }
+}
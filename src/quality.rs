@@ -0,0 +1,161 @@
+use std::collections::VecDeque;
+
+/// Rendering quality steps of the adaptive quality governor, from full
+/// quality to the cheapest rendering. Each step keeps the savings of the
+/// steps before it. There are no steps for tile crossfades or label halos,
+/// the map draws neither.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum QualityLevel {
+    #[default]
+    Full,
+    /// High-resolution (@2x) tiles are halved before upload
+    ReducedTiles,
+    /// Overlays aggregate at half resolution (coarser heatmaps, bins and clusters)
+    CoarseOverlays,
+    /// Marker labels are not drawn
+    Minimal,
+}
+
+impl QualityLevel {
+    fn lower(self) -> Self {
+        match self {
+            Self::Full => Self::ReducedTiles,
+            Self::ReducedTiles => Self::CoarseOverlays,
+            Self::CoarseOverlays | Self::Minimal => Self::Minimal,
+        }
+    }
+
+    fn higher(self) -> Self {
+        match self {
+            Self::Full | Self::ReducedTiles => Self::Full,
+            Self::CoarseOverlays => Self::ReducedTiles,
+            Self::Minimal => Self::CoarseOverlays,
+        }
+    }
+}
+
+/// Number of frame intervals averaged before deciding
+const FRAME_WINDOW: usize = 30;
+
+/// Longer intervals between frames are idle time, not slow frames (seconds)
+const MAX_FRAME_GAP: f64 = 0.25;
+
+/// Average frame time above target * this lowers the quality
+const DEGRADE_FACTOR: f64 = 1.25;
+
+/// Average frame time below target * this counts towards raising the quality
+const UPGRADE_FACTOR: f64 = 0.75;
+
+/// Minimum time between two quality drops (seconds)
+const DEGRADE_COOLDOWN: f64 = 1.0;
+
+/// Animated time with fast frames needed before the quality is raised again (seconds)
+const UPGRADE_DELAY: f64 = 5.0;
+
+/// Watches frame times while the map animates and steps the rendering
+/// quality down when frames are slower than the target, and back up after
+/// a sustained period of fast frames.
+#[derive(Clone, Debug)]
+pub struct QualityGovernor {
+    target_frame_time: f64,
+    intervals: VecDeque<f64>,
+    last_frame: Option<f64>,
+    level: QualityLevel,
+    override_level: Option<QualityLevel>,
+    last_drop: Option<f64>,
+    fast_time: f64,
+}
+
+impl Default for QualityGovernor {
+    fn default() -> Self {
+        Self::new(60.0)
+    }
+}
+
+impl QualityGovernor {
+    pub fn new(target_fps: f64) -> Self {
+        Self {
+            target_frame_time: 1.0 / target_fps.max(1.0),
+            intervals: VecDeque::with_capacity(FRAME_WINDOW + 1),
+            last_frame: None,
+            level: QualityLevel::Full,
+            override_level: None,
+            last_drop: None,
+            fast_time: 0.0,
+        }
+    }
+
+    pub fn set_target_fps(&mut self, target_fps: f64) {
+        self.target_frame_time = 1.0 / target_fps.max(1.0);
+    }
+
+    /// Quality to render with: the override if set, otherwise the governor's choice
+    pub fn level(&self) -> QualityLevel {
+        self.override_level.unwrap_or(self.level)
+    }
+
+    /// Force a quality level (None hands control back to the governor)
+    pub fn set_override(&mut self, level: Option<QualityLevel>) {
+        self.override_level = level;
+    }
+
+    pub fn override_level(&self) -> Option<QualityLevel> {
+        self.override_level
+    }
+
+    /// Average of the recent frame intervals in seconds
+    pub fn average_frame_time(&self) -> Option<f64> {
+        if self.intervals.is_empty() {
+            return None;
+        }
+        Some(self.intervals.iter().sum::<f64>() / self.intervals.len() as f64)
+    }
+
+    /// Go back to full quality and forget the measurements
+    pub fn reset(&mut self) {
+        self.intervals.clear();
+        self.last_frame = None;
+        self.level = QualityLevel::Full;
+        self.last_drop = None;
+        self.fast_time = 0.0;
+    }
+
+    /// Record the time (seconds) of an animation frame.
+    /// Returns the new level if the governor changed it.
+    pub fn record_frame(&mut self, time: f64) -> Option<QualityLevel> {
+        let last = self.last_frame.replace(time)?;
+        let interval = time - last;
+        if interval <= 0.0 || interval > MAX_FRAME_GAP {
+            self.intervals.clear();
+            return None;
+        }
+        self.intervals.push_back(interval);
+        if self.intervals.len() > FRAME_WINDOW {
+            self.intervals.pop_front();
+        }
+        if self.intervals.len() < FRAME_WINDOW || self.override_level.is_some() {
+            return None;
+        }
+
+        let average = self.average_frame_time()?;
+        if average > self.target_frame_time * DEGRADE_FACTOR {
+            self.fast_time = 0.0;
+            let cooled_down = self.last_drop.is_none_or(|drop| time - drop >= DEGRADE_COOLDOWN);
+            if self.level != QualityLevel::Minimal && cooled_down {
+                self.level = self.level.lower();
+                self.last_drop = Some(time);
+                self.intervals.clear();
+                return Some(self.level);
+            }
+        } else if average < self.target_frame_time * UPGRADE_FACTOR && self.level != QualityLevel::Full {
+            self.fast_time += interval;
+            if self.fast_time >= UPGRADE_DELAY {
+                self.level = self.level.higher();
+                self.fast_time = 0.0;
+                self.intervals.clear();
+                return Some(self.level);
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,223 @@
+use makepad_widgets::*;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::json::{JsonError, JsonValue};
+
+/// Public OSRM demo server. Car routing only and no guarantees, fine for
+/// development; host your own or use a commercial OSRM-compatible service in
+/// production.
+pub const OSRM_DEMO_SERVER: &str = "https://router.project-osrm.org";
+
+/// Polyline of the route shown by `GeoMapView::request_route`
+pub const ROUTE_POLYLINE: LiveId = LiveId::from_str("route");
+/// Marker at the first waypoint of the shown route
+pub const ROUTE_START_MARKER: LiveId = LiveId::from_str("route_start");
+/// Marker at the last waypoint of the shown route
+pub const ROUTE_END_MARKER: LiveId = LiveId::from_str("route_end");
+
+/// A route found by a [`Router`]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Route {
+    /// Path along the roads as (lng, lat)
+    pub points: Vec<(f64, f64)>,
+    /// Length in meters
+    pub distance: f64,
+    /// Expected travel time in seconds
+    pub duration: f64,
+}
+
+/// Why a route request failed
+#[derive(Clone, Debug, PartialEq)]
+pub enum RouteError {
+    /// The response isn't valid JSON
+    Json(JsonError),
+    /// Valid JSON, but not an OSRM route response
+    Invalid(String),
+    /// The router answered but found no route, with its code (e.g. "NoRoute")
+    NoRoute(String),
+    /// The server answered with a non-200 status
+    Status(u16),
+    /// The request failed (offline, DNS, TLS, ...)
+    Network(String),
+}
+
+impl fmt::Display for RouteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RouteError::Json(error) => write!(f, "invalid JSON: {}", error),
+            RouteError::Invalid(message) => write!(f, "invalid route response: {}", message),
+            RouteError::NoRoute(code) => write!(f, "no route found ({})", code),
+            RouteError::Status(status) => write!(f, "HTTP status {}", status),
+            RouteError::Network(message) => write!(f, "request failed: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for RouteError {}
+
+/// A finished route request
+#[derive(Clone, Debug)]
+pub struct RouteResponse {
+    pub request_id: LiveId,
+    /// Waypoints the route was requested for, as (lng, lat)
+    pub waypoints: Vec<(f64, f64)>,
+    pub result: Result<Route, RouteError>,
+}
+
+/// How `GeoMapView` shows a route
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RouteStyle {
+    pub color: Vec4,
+    /// Line width in pixels
+    pub width: f64,
+    /// Add markers at the first and last waypoint
+    pub show_endpoints: bool,
+    pub start_color: Vec4,
+    pub end_color: Vec4,
+}
+
+impl Default for RouteStyle {
+    fn default() -> Self {
+        Self {
+            color: vec4(0.2, 0.4, 0.84, 0.9),
+            width: 5.0,
+            show_endpoints: true,
+            start_color: vec4(0.26, 0.63, 0.28, 1.0),
+            end_color: vec4(0.9, 0.2, 0.2, 1.0),
+        }
+    }
+}
+
+/// Minimal client for OSRM-compatible routing servers.
+///
+/// Like a [`Geocoder`](crate::geocoding::Geocoder), requests go out through
+/// `cx.http_request` and the owner routes network responses back through
+/// `handle_response` and `handle_error`. `GeoMapView` does this for its own
+/// router.
+#[derive(Clone, Debug)]
+pub struct Router {
+    url: String,
+    /// Routing profile in the request path (e.g. "driving", "cycling", "foot"),
+    /// which profiles exist depends on the server
+    pub profile: String,
+    pending: HashMap<LiveId, Vec<(f64, f64)>>,
+}
+
+impl Default for Router {
+    fn default() -> Self {
+        Self::new(OSRM_DEMO_SERVER)
+    }
+}
+
+impl Router {
+    /// Client for an OSRM-compatible server at `url` (without a trailing slash)
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            profile: "driving".to_string(),
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Whether any request is waiting for its response
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Request a route through (lng, lat) waypoints in order, returning the
+    /// request's id. At least two waypoints are needed.
+    pub fn route(&mut self, cx: &mut Cx, waypoints: &[(f64, f64)]) -> LiveId {
+        let coordinates: Vec<String> = waypoints.iter().map(|(lng, lat)| format!("{:.6},{:.6}", lng, lat)).collect();
+        let url = format!(
+            "{}/route/v1/{}/{}?overview=full&geometries=polyline6",
+            self.url,
+            self.profile,
+            coordinates.join(";"),
+        );
+        let request_id = LiveId::unique();
+        let mut request = HttpRequest::new(url, HttpMethod::GET);
+        request.set_header("User-Agent".to_string(), "MakepadMap/0.1".to_string());
+        cx.http_request(request_id, request);
+        self.pending.insert(request_id, waypoints.to_vec());
+        request_id
+    }
+
+    /// Handle an HTTP response, returning the finished request if it was one of this router's
+    pub fn handle_response(&mut self, request_id: LiveId, response: &HttpResponse) -> Option<RouteResponse> {
+        let waypoints = self.pending.remove(&request_id)?;
+        let body = response.body.as_deref().unwrap_or_default();
+        let result = match parse_route(&String::from_utf8_lossy(body)) {
+            // OSRM reports "NoRoute" and friends with a 400 and a code
+            Err(RouteError::NoRoute(code)) => Err(RouteError::NoRoute(code)),
+            _ if response.status_code != 200 => Err(RouteError::Status(response.status_code)),
+            result => result,
+        };
+        Some(RouteResponse { request_id, waypoints, result })
+    }
+
+    /// Handle a failed HTTP request, returning the failed request if it was one of this router's
+    pub fn handle_error(&mut self, request_id: LiveId, error: &HttpError) -> Option<RouteResponse> {
+        let waypoints = self.pending.remove(&request_id)?;
+        let result = Err(RouteError::Network(format!("{:?}", error)));
+        Some(RouteResponse { request_id, waypoints, result })
+    }
+}
+
+/// Parse the first route of an OSRM route response with polyline6 geometry
+pub fn parse_route(text: &str) -> Result<Route, RouteError> {
+    let root = JsonValue::parse(text).map_err(RouteError::Json)?;
+    let code = root.get("code").and_then(JsonValue::as_str).unwrap_or_default();
+    if code != "Ok" {
+        return Err(RouteError::NoRoute(code.to_string()));
+    }
+    let route = root.get("routes")
+        .and_then(JsonValue::as_array)
+        .and_then(|routes| routes.first())
+        .ok_or_else(|| RouteError::NoRoute("NoRoute".to_string()))?;
+    let geometry = route.get("geometry")
+        .and_then(JsonValue::as_str)
+        .ok_or_else(|| RouteError::Invalid("route without polyline geometry".to_string()))?;
+    Ok(Route {
+        points: decode_polyline(geometry, 6),
+        distance: route.get("distance").and_then(JsonValue::as_f64).unwrap_or_default(),
+        duration: route.get("duration").and_then(JsonValue::as_f64).unwrap_or_default(),
+    })
+}
+
+/// Decode an encoded polyline (Google's format, used by OSRM and Valhalla)
+/// into (lng, lat) positions. `precision` is the number of decimals: 5 for
+/// the classic format, 6 for OSRM's polyline6 and Valhalla. Decoding stops at
+/// the first malformed value.
+pub fn decode_polyline(encoded: &str, precision: u32) -> Vec<(f64, f64)> {
+    let factor = 10f64.powi(precision as i32);
+    let mut bytes = encoded.bytes();
+    let mut next_value = || -> Option<i64> {
+        let mut result = 0i64;
+        let mut shift = 0;
+        loop {
+            let byte = bytes.next()? as i64 - 63;
+            if !(0..64).contains(&byte) || shift > 60 {
+                return None;
+            }
+            result |= (byte & 0x1f) << shift;
+            shift += 5;
+            if byte < 0x20 {
+                break;
+            }
+        }
+        Some(if result & 1 != 0 { !(result >> 1) } else { result >> 1 })
+    };
+
+    let mut points = Vec::new();
+    let (mut lat, mut lng) = (0i64, 0i64);
+    while let Some(dlat) = next_value() {
+        let Some(dlng) = next_value() else {
+            break;
+        };
+        lat += dlat;
+        lng += dlng;
+        points.push((lng as f64 / factor, lat as f64 / factor));
+    }
+    points
+}
@@ -0,0 +1,385 @@
+use makepad_widgets::*;
+
+use crate::geocoding::{GeocodeResponse, GeocodeResult, Geocoder};
+use crate::map_view::GeoMapViewRef;
+
+live_design! {
+    link widgets;
+    use link::theme::*;
+    use link::shaders::*;
+    use link::widgets::*;
+
+    pub MapSearchBoxBase = {{MapSearchBox}} {
+        draw_list_bg: {
+            color: #ffffff
+        }
+        draw_item_hover: {
+            color: #e3f2fd
+        }
+        draw_item_name: {
+            color: #212121
+            text_style: <THEME_FONT_BOLD> {
+                font_size: 10.0
+            }
+        }
+        draw_item_address: {
+            color: #757575
+            text_style: {
+                font_size: 9.0
+            }
+        }
+        marker_color: #1e88e5
+
+        input = <TextInput> {
+            width: Fill,
+            height: Fit,
+            empty_text: "Search places"
+        }
+    }
+
+    // Search field with a dropdown of geocoding results, e.g. overlaid on a
+    // corner of a GeoMapView
+    pub MapSearchBox = <MapSearchBoxBase> {
+        width: 320,
+        height: Fit,
+        flow: Down,
+    }
+}
+
+/// Marker the search box drops on the selected place
+pub const SEARCH_RESULT_MARKER: LiveId = LiveId::from_str("search_result");
+
+/// Height of a result row in the dropdown
+const RESULT_ROW_HEIGHT: f64 = 40.0;
+/// Space between the left edge of the dropdown and its text
+const RESULT_PADDING: f64 = 8.0;
+
+#[derive(Clone, Debug, DefaultNone)]
+pub enum MapSearchBoxAction {
+    /// A place was picked from the dropdown
+    ResultSelected { result: GeocodeResult },
+    None,
+}
+
+/// What the dropdown below the field shows
+#[derive(Clone, Debug, Default, PartialEq)]
+enum Dropdown {
+    #[default]
+    Closed,
+    Searching,
+    Results,
+    Message(String),
+}
+
+/// Place search for a `GeoMapView`: a text field wired to a `Geocoder`, with
+/// the matches in a dropdown below it.
+///
+/// Searches start on Return, or while typing after `search_delay` seconds if
+/// it is set (only with a geocoder that allows autocomplete, like Photon).
+/// Picking a result reports `ResultSelected`; with a map linked by `set_map`
+/// it also drops the `SEARCH_RESULT_MARKER` on the place and flies there.
+#[derive(Live, LiveHook, Widget)]
+pub struct MapSearchBox {
+    #[deref] view: View,
+    #[live] draw_list_bg: DrawColor,
+    #[live] draw_item_hover: DrawColor,
+    #[live] draw_item_name: DrawText,
+    #[live] draw_item_address: DrawText,
+    /// Color of the marker dropped on the selected place
+    #[live] pub marker_color: Vec4,
+    /// Drop a marker on the selected place (with a linked map)
+    #[live(true)] pub drop_marker: bool,
+    /// Seconds after the last keystroke to search, 0 searches only on Return
+    #[live(0.0)] pub search_delay: f64,
+    /// Shortest text searched while typing
+    #[live(3)] pub min_query_length: usize,
+    #[rust] geocoder: Geocoder,
+    #[rust] map: Option<GeoMapViewRef>,
+    #[rust] request: Option<LiveId>,
+    #[rust] results: Vec<GeocodeResult>,
+    #[rust] dropdown: Dropdown,
+    #[rust] hovered: Option<usize>,
+    // Row under the finger when it went down, picked if it comes up there
+    #[rust] pressed: Option<usize>,
+    // Absolute rect of the dropdown from the last draw
+    #[rust] list_rect: Rect,
+    #[rust] search_timer: Timer,
+}
+
+impl MapSearchBox {
+    /// Use another geocoder, e.g. `Geocoder::photon()` for search-as-you-type
+    pub fn set_geocoder(&mut self, geocoder: Geocoder) {
+        self.geocoder = geocoder;
+        self.request = None;
+    }
+
+    /// Map to drop the result marker on and fly to when a place is picked
+    pub fn set_map(&mut self, map: GeoMapViewRef) {
+        self.map = Some(map);
+    }
+
+    /// Search for places and show the matches in the dropdown
+    pub fn search(&mut self, cx: &mut Cx, query: &str) {
+        cx.stop_timer(self.search_timer);
+        let query = query.trim();
+        if query.is_empty() {
+            self.close(cx);
+            return;
+        }
+        self.request = Some(self.geocoder.search(cx, query));
+        self.dropdown = Dropdown::Searching;
+        self.view.redraw(cx);
+    }
+
+    /// Results of the last search
+    pub fn results(&self) -> &[GeocodeResult] {
+        &self.results
+    }
+
+    /// Hide the dropdown
+    pub fn close(&mut self, cx: &mut Cx) {
+        self.request = None;
+        self.dropdown = Dropdown::Closed;
+        self.hovered = None;
+        self.pressed = None;
+        self.view.redraw(cx);
+    }
+
+    /// Empty the field, close the dropdown and remove the result marker
+    pub fn clear(&mut self, cx: &mut Cx) {
+        self.view.text_input(ids!(input)).set_text(cx, "");
+        self.results.clear();
+        self.close(cx);
+        if let Some(map) = &self.map {
+            map.remove_marker(cx, SEARCH_RESULT_MARKER);
+        }
+    }
+
+    fn handle_geocode_response(&mut self, cx: &mut Cx, response: GeocodeResponse) {
+        // Answers to searches replaced by a newer one are dropped
+        if self.request != Some(response.request_id) {
+            return;
+        }
+        self.request = None;
+        self.hovered = None;
+        match response.result {
+            Ok(results) if results.is_empty() => {
+                self.results.clear();
+                self.dropdown = Dropdown::Message("No places found".to_string());
+            }
+            Ok(results) => {
+                self.results = results;
+                self.dropdown = Dropdown::Results;
+            }
+            Err(error) => {
+                log!("Place search failed: {}", error);
+                self.results.clear();
+                self.dropdown = Dropdown::Message("Search failed".to_string());
+            }
+        }
+        self.view.redraw(cx);
+    }
+
+    fn select(&mut self, cx: &mut Cx, uid: WidgetUid, path: &HeapLiveIdPath, index: usize) {
+        let Some(result) = self.results.get(index).cloned() else {
+            return;
+        };
+        self.view.text_input(ids!(input)).set_text(cx, &result.name);
+        self.close(cx);
+        if let Some(map) = &self.map {
+            if let Some(mut inner) = map.borrow_mut() {
+                if self.drop_marker {
+                    inner.remove_marker(cx, SEARCH_RESULT_MARKER);
+                    let marker = inner.add_marker(cx, SEARCH_RESULT_MARKER, result.lng, result.lat);
+                    marker.label = result.name.clone();
+                    marker.popup = result.address.clone();
+                    marker.color = self.marker_color;
+                }
+                inner.fly_to_result(cx, &result);
+            }
+        }
+        cx.widget_action(uid, path, MapSearchBoxAction::ResultSelected { result });
+    }
+
+    /// Dropdown row under an absolute position
+    fn row_at(&self, abs: DVec2) -> Option<usize> {
+        let index = ((abs.y - self.list_rect.pos.y) / RESULT_ROW_HEIGHT).floor();
+        (index >= 0.0 && (index as usize) < self.results.len()).then_some(index as usize)
+    }
+
+    fn set_hovered(&mut self, cx: &mut Cx, hovered: Option<usize>) {
+        if self.hovered != hovered {
+            self.hovered = hovered;
+            self.view.redraw(cx);
+        }
+    }
+
+    fn draw_dropdown(&mut self, cx: &mut Cx2d) {
+        let rows = match &self.dropdown {
+            Dropdown::Closed => return,
+            Dropdown::Results => self.results.len(),
+            Dropdown::Searching | Dropdown::Message(_) => 1,
+        };
+        let rect = cx.walk_turtle(Walk::new(Size::Fill, Size::Fixed(rows as f64 * RESULT_ROW_HEIGHT)));
+        self.list_rect = rect;
+        self.draw_list_bg.draw_abs(cx, rect);
+
+        let name_size = self.draw_item_name.text_style.font_size as f64;
+        let address_size = self.draw_item_address.text_style.font_size as f64;
+        let max_chars = |font_size: f64| ((rect.size.x - 2.0 * RESULT_PADDING) / (font_size * 0.5)).max(1.0) as usize;
+        let text_x = rect.pos.x + RESULT_PADDING;
+        match &self.dropdown {
+            Dropdown::Searching | Dropdown::Message(_) => {
+                let text = match &self.dropdown {
+                    Dropdown::Message(message) => message.as_str(),
+                    _ => "Searching...",
+                };
+                let y = rect.pos.y + (RESULT_ROW_HEIGHT - address_size * 1.3) / 2.0;
+                self.draw_item_address.draw_abs(cx, dvec2(text_x, y), text);
+            }
+            _ => {
+                for (index, result) in self.results.iter().enumerate() {
+                    let row_y = rect.pos.y + index as f64 * RESULT_ROW_HEIGHT;
+                    if self.hovered == Some(index) {
+                        self.draw_item_hover.draw_abs(cx, Rect {
+                            pos: dvec2(rect.pos.x, row_y),
+                            size: dvec2(rect.size.x, RESULT_ROW_HEIGHT),
+                        });
+                    }
+                    let name = ellipsize(&result.name, max_chars(name_size));
+                    self.draw_item_name.draw_abs(cx, dvec2(text_x, row_y + 5.0), &name);
+                    let address = ellipsize(&result.address, max_chars(address_size));
+                    self.draw_item_address.draw_abs(cx, dvec2(text_x, row_y + 8.0 + name_size * 1.3), &address);
+                }
+            }
+        }
+    }
+}
+
+impl Widget for MapSearchBox {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        let uid = self.widget_uid();
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+
+        if let Event::NetworkResponses(responses) = event {
+            for response in responses {
+                let geocoded = match &response.response {
+                    NetworkResponse::HttpResponse(http_response) => {
+                        self.geocoder.handle_response(response.request_id, http_response)
+                    }
+                    NetworkResponse::HttpRequestError(error) => self.geocoder.handle_error(response.request_id, error),
+                    _ => None,
+                };
+                if let Some(geocoded) = geocoded {
+                    self.handle_geocode_response(cx, geocoded);
+                }
+            }
+        }
+
+        // Typing paused long enough
+        if self.search_timer.is_event(event).is_some() {
+            self.search_timer = Timer::empty();
+            let query = self.view.text_input(ids!(input)).text();
+            self.search(cx, &query);
+        }
+
+        if self.dropdown != Dropdown::Results {
+            return;
+        }
+        match event.hits(cx, self.draw_list_bg.area()) {
+            Hit::FingerHoverIn(fe) | Hit::FingerHoverOver(fe) => {
+                cx.set_cursor(MouseCursor::Hand);
+                let row = self.row_at(fe.abs);
+                self.set_hovered(cx, row);
+            }
+            Hit::FingerHoverOut(_) => self.set_hovered(cx, None),
+            Hit::FingerDown(fe) if fe.is_primary_hit() => {
+                self.pressed = self.row_at(fe.abs);
+            }
+            Hit::FingerUp(fe) => {
+                if let Some(index) = self.pressed.take() {
+                    if fe.is_over && self.row_at(fe.abs) == Some(index) {
+                        self.select(cx, uid, &scope.path, index);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        cx.begin_turtle(walk, Layout::flow_down());
+        let _ = self.view.draw_walk(cx, scope, Walk::fill_fit());
+        self.draw_dropdown(cx);
+        cx.end_turtle();
+        DrawStep::done()
+    }
+}
+
+impl WidgetMatchEvent for MapSearchBox {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, _scope: &mut Scope) {
+        let input = self.view.text_input(ids!(input));
+        if let Some((query, _)) = input.returned(actions) {
+            self.search(cx, &query);
+        }
+        if let Some(query) = input.changed(actions) {
+            cx.stop_timer(self.search_timer);
+            self.search_timer = Timer::empty();
+            if query.trim().is_empty() {
+                self.close(cx);
+            } else if self.search_delay > 0.0 && query.trim().chars().count() >= self.min_query_length {
+                self.search_timer = cx.start_timeout(self.search_delay);
+            }
+        }
+    }
+}
+
+/// Shorten text to at most `max_chars` characters, ending with "..." if it was cut
+fn ellipsize(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let kept: String = text.chars().take(max_chars.saturating_sub(3)).collect();
+    format!("{}...", kept.trim_end())
+}
+
+impl MapSearchBoxRef {
+    /// Use another geocoder for the searches
+    pub fn set_geocoder(&self, geocoder: Geocoder) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_geocoder(geocoder);
+        }
+    }
+
+    /// Map to drop the result marker on and fly to when a place is picked
+    pub fn set_map(&self, map: GeoMapViewRef) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_map(map);
+        }
+    }
+
+    /// Search for places and show the matches in the dropdown
+    pub fn search(&self, cx: &mut Cx, query: &str) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.search(cx, query);
+        }
+    }
+
+    /// Empty the field, close the dropdown and remove the result marker
+    pub fn clear(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.clear(cx);
+        }
+    }
+
+    /// Check if a place was picked from the dropdown
+    pub fn result_selected(&self, actions: &Actions) -> Option<GeocodeResult> {
+        actions.filter_widget_actions(self.widget_uid())
+            .map(|action| action.cast::<MapSearchBoxAction>())
+            .find_map(|action| match action {
+                MapSearchBoxAction::ResultSelected { result } => Some(result),
+                MapSearchBoxAction::None => None,
+            })
+    }
+}
@@ -0,0 +1,88 @@
+//! Field adapters for the `serde` feature, for makepad types without serde
+//! support and values JSON can't hold
+
+use makepad_widgets::*;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// `LiveId` as its 64-bit value
+pub(crate) mod live_id {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(id: &LiveId, serializer: S) -> Result<S::Ok, S::Error> {
+        id.0.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<LiveId, D::Error> {
+        u64::deserialize(deserializer).map(LiveId)
+    }
+}
+
+/// `Vec4` color as `[r, g, b, a]`
+pub(crate) mod color {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(color: &Vec4, serializer: S) -> Result<S::Ok, S::Error> {
+        [color.x, color.y, color.z, color.w].serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec4, D::Error> {
+        let [r, g, b, a] = <[f32; 4]>::deserialize(deserializer)?;
+        Ok(vec4(r, g, b, a))
+    }
+}
+
+/// `DVec2` as `[x, y]`
+pub(crate) mod dvec2 {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(v: &DVec2, serializer: S) -> Result<S::Ok, S::Error> {
+        [v.x, v.y].serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DVec2, D::Error> {
+        let [x, y] = <[f64; 2]>::deserialize(deserializer)?;
+        Ok(dvec2(x, y))
+    }
+}
+
+/// Zoom limit where infinity (no limit) is written as null
+pub(crate) mod max_zoom {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(zoom: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        zoom.is_finite().then_some(*zoom).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        Ok(Option::<f64>::deserialize(deserializer)?.unwrap_or(f64::INFINITY))
+    }
+
+    pub fn default() -> f64 {
+        f64::INFINITY
+    }
+}
+
+/// `MapCamera` with the fields of `MapCamera::to_json`: `lng` and `lat`
+/// instead of a `center` pair, `bearing` and `pitch` optional
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SavedCamera {
+    lng: f64,
+    lat: f64,
+    zoom: f64,
+    #[serde(default)]
+    bearing: f64,
+    #[serde(default)]
+    pitch: f64,
+}
+
+impl From<SavedCamera> for crate::camera::MapCamera {
+    fn from(saved: SavedCamera) -> Self {
+        Self { center: (saved.lng, saved.lat), zoom: saved.zoom, bearing: saved.bearing, pitch: saved.pitch }
+    }
+}
+
+impl From<crate::camera::MapCamera> for SavedCamera {
+    fn from(camera: crate::camera::MapCamera) -> Self {
+        Self { lng: camera.center.0, lat: camera.center.1, zoom: camera.zoom, bearing: camera.bearing, pitch: camera.pitch }
+    }
+}
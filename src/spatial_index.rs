@@ -0,0 +1,166 @@
+use makepad_widgets::*;
+use std::collections::HashMap;
+
+use crate::layers::MapViewport;
+
+/// Finest grid level, cells of 1/65536 of the world (about 600 m at the equator)
+const MAX_LEVEL: usize = 16;
+
+/// Items by bounding box in unit square positions (see `Projection::project`),
+/// to find the features in view without visiting all of them.
+///
+/// A hierarchy of grids, each level with twice the cells per side of the one
+/// above. Every item goes into the finest level whose cells are at least as
+/// large as its box, so it touches at most 2x2 cells there, and a query looks
+/// at a handful of cells per level. Items are numbered by the caller,
+/// typically their position in the layer's list of features.
+#[derive(Clone, Debug, Default)]
+pub struct SpatialIndex {
+    entries: Vec<(usize, DVec2, DVec2)>,
+    /// Entries by cell, one map per level
+    levels: Vec<HashMap<(i64, i64), Vec<usize>>>,
+    /// Horizontal extent of all entries
+    min_x: f64,
+    max_x: f64,
+}
+
+impl SpatialIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index of boxes `(min, max)` numbered by their position in the iterator
+    pub fn from_boxes(boxes: impl IntoIterator<Item = (DVec2, DVec2)>) -> Self {
+        let mut index = Self::new();
+        for (item, (min, max)) in boxes.into_iter().enumerate() {
+            index.insert(item, min, max);
+        }
+        index
+    }
+
+    /// Add an item covering the box from `min` to `max`. Boxes with
+    /// non-finite positions are left out, they can't be on screen.
+    pub fn insert(&mut self, item: usize, min: DVec2, max: DVec2) {
+        if !(min.x.is_finite() && min.y.is_finite() && max.x.is_finite() && max.y.is_finite()) {
+            return;
+        }
+        let size = (max.x - min.x).max(max.y - min.y);
+        let level = if size > 0.0 {
+            ((-size.log2()).floor().max(0.0) as usize).min(MAX_LEVEL)
+        } else {
+            MAX_LEVEL
+        };
+        if self.levels.len() <= level {
+            self.levels.resize_with(level + 1, HashMap::new);
+        }
+        if self.entries.is_empty() {
+            (self.min_x, self.max_x) = (min.x, max.x);
+        } else {
+            self.min_x = self.min_x.min(min.x);
+            self.max_x = self.max_x.max(max.x);
+        }
+        let entry = self.entries.len();
+        self.entries.push((item, min, max));
+        let (from, to) = (cell_of(min, level), cell_of(max, level));
+        for x in from.0..=to.0 {
+            for y in from.1..=to.1 {
+                self.levels[level].entry((x, y)).or_default().push(entry);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.levels.clear();
+    }
+
+    /// Items whose box intersects the box from `min` to `max`, in ascending order
+    pub fn query(&self, min: DVec2, max: DVec2) -> Vec<usize> {
+        let mut items = Vec::new();
+        self.collect(min, max, &mut items);
+        items.sort_unstable();
+        items.dedup();
+        items
+    }
+
+    /// Items that may be on screen, within `margin` pixels of the viewport,
+    /// in ascending order. With a projection that wraps around the
+    /// antimeridian, items on every visible world copy are included.
+    pub fn query_viewport(&self, viewport: &MapViewport, margin: f64) -> Vec<usize> {
+        let rect = Rect {
+            pos: viewport.rect.pos - dvec2(margin, margin),
+            size: viewport.rect.size + dvec2(margin, margin) * 2.0,
+        };
+        let (min, max) = viewport.unit_bounds(rect);
+        self.query_wrapped(min, max, viewport.projection.wraps_longitude())
+    }
+
+    /// `query` that, when `wraps`, also finds items whole world widths left or
+    /// right of the box
+    pub fn query_wrapped(&self, min: DVec2, max: DVec2, wraps: bool) -> Vec<usize> {
+        if !wraps || self.is_empty() {
+            return self.query(min, max);
+        }
+        let mut items = Vec::new();
+        if max.x - min.x >= 1.0 {
+            // Every longitude is in view
+            self.collect(dvec2(self.min_x, min.y), dvec2(self.max_x, max.y), &mut items);
+        } else {
+            let first = (min.x - self.max_x).floor() as i64;
+            let last = (max.x - self.min_x).ceil() as i64;
+            for shift in first..=last {
+                let shift = dvec2(shift as f64, 0.0);
+                self.collect(min - shift, max - shift, &mut items);
+            }
+        }
+        items.sort_unstable();
+        items.dedup();
+        items
+    }
+
+    fn collect(&self, min: DVec2, max: DVec2, items: &mut Vec<usize>) {
+        let mut visit = |entry: usize| {
+            let (item, entry_min, entry_max) = self.entries[entry];
+            if entry_min.x <= max.x && entry_max.x >= min.x && entry_min.y <= max.y && entry_max.y >= min.y {
+                items.push(item);
+            }
+        };
+        for (level, cells) in self.levels.iter().enumerate() {
+            if cells.is_empty() {
+                continue;
+            }
+            let (from, to) = (cell_of(min, level), cell_of(max, level));
+            let count = (to.0 - from.0 + 1).saturating_mul(to.1 - from.1 + 1);
+            // A large box at a fine level covers more cells than are occupied
+            if count as usize > cells.len() {
+                for (&(x, y), entries) in cells {
+                    if (from.0..=to.0).contains(&x) && (from.1..=to.1).contains(&y) {
+                        entries.iter().for_each(|&entry| visit(entry));
+                    }
+                }
+            } else {
+                for x in from.0..=to.0 {
+                    for y in from.1..=to.1 {
+                        if let Some(entries) = cells.get(&(x, y)) {
+                            entries.iter().for_each(|&entry| visit(entry));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Cell at a level containing a unit position
+fn cell_of(pos: DVec2, level: usize) -> (i64, i64) {
+    let cells = (1u64 << level) as f64;
+    ((pos.x * cells).floor() as i64, (pos.y * cells).floor() as i64)
+}
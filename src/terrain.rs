@@ -0,0 +1,291 @@
+use makepad_widgets::*;
+use makepad_widgets::image_cache::ImageBuffer;
+use std::collections::HashMap;
+
+use crate::layers::{MapLayer, MapViewport};
+use crate::camera_math::TileGrid;
+use crate::map_view::{DrawMapTile, TileBlendMode};
+use crate::projection::{Projection, WebMercator, TILE_SIZE};
+use crate::tiles::{decode_tile, TileCache, TileCoord};
+
+/// Terrarium elevation tiles of the AWS open data terrain tiles
+pub const TERRARIUM_TILE_SERVER: &str = "https://s3.amazonaws.com/elevation-tiles-prod/terrarium/{z}/{x}/{y}.png";
+
+live_design! {
+    link widgets;
+    use link::theme::*;
+    use link::shaders::*;
+    use link::widgets::*;
+
+    // Relief shading from elevation tiles, darkening the base map on shaded slopes
+    pub HillshadeLayer = {{HillshadeLayer}} {
+        url: "https://s3.amazonaws.com/elevation-tiles-prod/terrarium/{z}/{x}/{y}.png"
+        blend_mode: Multiply
+    }
+}
+
+/// How the tiles of a `HillshadeLayer` encode the terrain
+#[derive(Copy, Clone, Debug, Live, LiveHook, PartialEq)]
+#[live_ignore]
+pub enum TerrainEncoding {
+    /// Terrarium (Mapzen) elevation tiles: `R * 256 + G + B / 256 - 32768` meters
+    #[pick] Terrarium,
+    /// Mapbox Terrain-RGB elevation tiles: `(R * 65536 + G * 256 + B) / 10 - 10000` meters
+    TerrainRgb,
+    /// Tiles that are already shaded relief, composited as they are
+    Shaded,
+}
+
+impl TerrainEncoding {
+    /// Elevation in meters of a pixel packed as 0xAARRGGBB, None for shaded tiles
+    pub fn decode(self, pixel: u32) -> Option<f64> {
+        let r = ((pixel >> 16) & 0xff) as f64;
+        let g = ((pixel >> 8) & 0xff) as f64;
+        let b = (pixel & 0xff) as f64;
+        match self {
+            TerrainEncoding::Terrarium => Some(r * 256.0 + g + b / 256.0 - 32768.0),
+            TerrainEncoding::TerrainRgb => Some((r * 65536.0 + g * 256.0 + b) / 10.0 - 10000.0),
+            TerrainEncoding::Shaded => None,
+        }
+    }
+}
+
+/// Where elevations come from, e.g. for an `ElevationProfileView`.
+///
+/// Like a `CoordinateProvider`, a source may answer right away or issue its
+/// own `cx.http_request` calls; the widget using it routes network responses
+/// back to it.
+pub trait ElevationSource {
+    /// Elevation in meters above sea level, None while it isn't loaded or unknown
+    fn elevation(&self, lng: f64, lat: f64) -> Option<f64>;
+
+    /// Start loading what is needed for these positions as (lng, lat)
+    fn request(&mut self, _cx: &mut Cx, _points: &[(f64, f64)]) {}
+
+    /// Handle an HTTP response, returning true if it belonged to one of this
+    /// source's requests and new elevations may be available
+    fn handle_response(&mut self, _cx: &mut Cx, _request_id: LiveId, _response: &HttpResponse) -> bool {
+        false
+    }
+
+    /// Handle a failed HTTP request, returning true if it was one of this source's
+    fn handle_error(&mut self, _request_id: LiveId) -> bool {
+        false
+    }
+}
+
+/// Elevations sampled from elevation tiles at one zoom level. Tiles are
+/// downloaded when positions are requested and kept in memory.
+pub struct TerrainElevation {
+    url: String,
+    encoding: TerrainEncoding,
+    zoom: u8,
+    // Decoded tiles, None for tiles that failed to load
+    tiles: HashMap<TileCoord, Option<ImageBuffer>>,
+    pending: HashMap<LiveId, TileCoord>,
+}
+
+impl Default for TerrainElevation {
+    /// Terrarium tiles at zoom 12, about 30 m per pixel at mid latitudes
+    fn default() -> Self {
+        Self::new(TERRARIUM_TILE_SERVER, TerrainEncoding::Terrarium, 12)
+    }
+}
+
+impl TerrainElevation {
+    pub fn new(url: &str, encoding: TerrainEncoding, zoom: u8) -> Self {
+        Self {
+            url: url.to_string(),
+            encoding,
+            zoom,
+            tiles: HashMap::new(),
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Tile containing a position and the position in the tile's pixels
+    fn tile_position(&self, lng: f64, lat: f64) -> (TileCoord, DVec2) {
+        let world = WebMercator.geo_to_world(lng, lat, self.zoom as f64) / TILE_SIZE;
+        let max_tile = 1u32 << self.zoom;
+        let coord = TileCoord {
+            x: (world.x.floor() as i64).rem_euclid(max_tile as i64) as u32,
+            y: (world.y.floor().max(0.0) as u32).min(max_tile - 1),
+            z: self.zoom,
+        };
+        (coord, dvec2(world.x.rem_euclid(1.0), world.y - coord.y as f64))
+    }
+}
+
+impl ElevationSource for TerrainElevation {
+    fn elevation(&self, lng: f64, lat: f64) -> Option<f64> {
+        let (coord, pos) = self.tile_position(lng, lat);
+        let image = self.tiles.get(&coord)?.as_ref()?;
+        // Bilinear between the decoded elevations of the four nearest pixels
+        let x = (pos.x * image.width as f64 - 0.5).clamp(0.0, (image.width - 1) as f64);
+        let y = (pos.y * image.height as f64 - 0.5).clamp(0.0, (image.height - 1) as f64);
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(image.width - 1), (y0 + 1).min(image.height - 1));
+        let (fx, fy) = (x - x0 as f64, y - y0 as f64);
+        let at = |x: usize, y: usize| self.encoding.decode(image.data[y * image.width + x]);
+        let top = at(x0, y0)? * (1.0 - fx) + at(x1, y0)? * fx;
+        let bottom = at(x0, y1)? * (1.0 - fx) + at(x1, y1)? * fx;
+        Some(top * (1.0 - fy) + bottom * fy)
+    }
+
+    fn request(&mut self, cx: &mut Cx, points: &[(f64, f64)]) {
+        for &(lng, lat) in points {
+            let (coord, _) = self.tile_position(lng, lat);
+            if self.tiles.contains_key(&coord) || self.pending.values().any(|pending| *pending == coord) {
+                continue;
+            }
+            let request_id = LiveId::unique();
+            let mut request = HttpRequest::new(coord.tile_url(&self.url), HttpMethod::GET);
+            request.set_header("User-Agent".to_string(), "MakepadMap/0.1".to_string());
+            cx.http_request(request_id, request);
+            self.pending.insert(request_id, coord);
+        }
+    }
+
+    fn handle_response(&mut self, _cx: &mut Cx, request_id: LiveId, response: &HttpResponse) -> bool {
+        let Some(coord) = self.pending.remove(&request_id) else {
+            return false;
+        };
+        let image = match (&response.body, response.status_code) {
+            (Some(body), 200) => decode_tile(body).ok(),
+            _ => None,
+        };
+        self.tiles.insert(coord, image);
+        true
+    }
+
+    fn handle_error(&mut self, request_id: LiveId) -> bool {
+        let Some(coord) = self.pending.remove(&request_id) else {
+            return false;
+        };
+        self.tiles.insert(coord, None);
+        true
+    }
+}
+
+/// Hillshading blended over the base map, for relief without an outdoor basemap.
+///
+/// Loads elevation tiles (Terrarium by default, e.g. the free AWS terrain
+/// tiles) and shades them on the GPU for a light from `sun_azimuth` and
+/// `sun_altitude`. With the default `Multiply` blend flat ground leaves the
+/// base map unchanged and slopes facing away from the light darken it;
+/// `Overlay` also lightens slopes facing the light, and `Screen` only lightens
+/// them. Only Web Mercator maps are shaded, as elevation tiles use its tiling.
+#[derive(Live, LiveHook, LiveRegister)]
+pub struct HillshadeLayer {
+    #[live] draw_tile: DrawMapTile,
+    /// XYZ URL template of the elevation tiles
+    #[live] pub url: String,
+    #[live] pub encoding: TerrainEncoding,
+    #[live] pub blend_mode: TileBlendMode,
+    /// Vertical exaggeration, above 1 for stronger relief in gentle terrain
+    #[live(1.0)] pub exaggeration: f64,
+    /// Direction the light comes from, in degrees clockwise from north
+    #[live(315.0)] pub sun_azimuth: f64,
+    /// Height of the light above the horizon in degrees
+    #[live(45.0)] pub sun_altitude: f64,
+    /// Deepest zoom of the elevation tiles, deeper zooms scale them up
+    #[live(15)] pub max_zoom: usize,
+    /// Width of the elevation tiles in pixels
+    #[live(256.0)] pub tile_pixels: f64,
+    #[live(true)] pub visible: bool,
+    #[rust] tile_cache: TileCache,
+    #[rust] loaded_url: String,
+    #[rust] next_frame: NextFrame,
+    #[rust(1.0)] opacity: f64,
+}
+
+impl HillshadeLayer {
+    /// Load elevation tiles from another XYZ URL template
+    pub fn set_url(&mut self, url: &str, encoding: TerrainEncoding) {
+        self.url = url.to_string();
+        self.encoding = encoding;
+    }
+}
+
+impl MapLayer for HillshadeLayer {
+    fn draw_layer(&mut self, cx: &mut Cx2d, viewport: &MapViewport) {
+        if viewport.projection.code() != "EPSG:3857" {
+            return;
+        }
+        if self.url != self.loaded_url {
+            self.tile_cache.set_tile_server(&self.url);
+            self.loaded_url = self.url.clone();
+        }
+
+        // One ring of tiles beyond the viewport, as the base map loads by default
+        let grid = TileGrid::new(viewport, self.max_zoom as u8, 1);
+        self.tile_cache.begin_frame();
+        for (coord, _) in grid.tiles() {
+            self.tile_cache.request_tile(cx.cx.cx, coord);
+        }
+        if self.tile_cache.needs_next_frame() {
+            self.next_frame = cx.new_next_frame();
+        }
+        self.tile_cache.trim_memory(cx.cx.cx, grid.zoom);
+
+        // Slopes are measured with the ground size at the center, close enough
+        // at the zooms where relief is visible
+        let tile_meters = viewport.meters_per_pixel() * grid.scaled_tile_size;
+        self.draw_tile.set_viewport(viewport);
+        self.draw_tile.set_blend_mode(self.blend_mode);
+        self.draw_tile.terrain = match self.encoding {
+            TerrainEncoding::Terrarium => 1.0,
+            TerrainEncoding::TerrainRgb => 2.0,
+            TerrainEncoding::Shaded => 0.0,
+        };
+        self.draw_tile.tile_texels = self.tile_pixels as f32;
+        self.draw_tile.meters_per_texel = (tile_meters / self.tile_pixels) as f32;
+        self.draw_tile.exaggeration = self.exaggeration as f32;
+        self.draw_tile.sun_azimuth = self.sun_azimuth.to_radians() as f32;
+        self.draw_tile.sun_altitude = self.sun_altitude.clamp(1.0, 90.0).to_radians() as f32;
+        self.draw_tile.opacity = self.opacity as f32;
+        grid.draw(cx, &mut self.draw_tile, &self.tile_cache, viewport.rect, false);
+    }
+
+    fn handle_layer_event(&mut self, cx: &mut Cx, event: &Event, _viewport: &MapViewport) -> bool {
+        let mut redraw = false;
+        match event {
+            Event::NetworkResponses(responses) => {
+                let pending = self.tile_cache.pending_request_count();
+                for response in responses {
+                    match &response.response {
+                        NetworkResponse::HttpResponse(http_response) => {
+                            redraw |= self.tile_cache.handle_response(cx, response.request_id, http_response);
+                        }
+                        NetworkResponse::HttpRequestError(error) => {
+                            self.tile_cache.handle_error(response.request_id, error);
+                        }
+                        _ => {}
+                    }
+                }
+                // A freed slot lets the tiles held back by the limit in flight go out
+                redraw |= self.tile_cache.has_deferred_requests() && self.tile_cache.pending_request_count() < pending;
+            }
+            // Tiles read from the disk cache on the I/O thread
+            Event::Signal => redraw |= self.tile_cache.handle_disk_results(cx),
+            _ => {}
+        }
+        // Spend the next frame's request budget on the tiles skipped last frame
+        if self.next_frame.is_event(event).is_some() {
+            redraw = true;
+        }
+        redraw
+    }
+
+    fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    fn set_opacity(&mut self, opacity: f64) {
+        self.opacity = opacity;
+    }
+}
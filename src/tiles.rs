@@ -1,8 +1,14 @@
 use makepad_widgets::*;
-use makepad_widgets::image_cache::ImageBuffer;
-use std::collections::HashMap;
+use makepad_widgets::image_cache::{ImageBuffer, ImageError};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-use crate::disk_cache;
+use crate::disk_cache::{self, DiskCacheConfig, DiskJob, DiskResult, DiskWorker, TileValidators};
+use crate::offline::{route_corridor_tiles, DownloadProgress};
+use crate::projection::{Projection, WebMercator};
 
 /// OpenStreetMap tile coordinates
 #[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
@@ -22,6 +28,181 @@ impl TileCoord {
     }
 }
 
+/// Default tile server: Carto Voyager - clean, modern style (free, no API key required)
+pub const DEFAULT_TILE_SERVER: &str = "https://a.basemaps.cartocdn.com/rastertiles/voyager/{z}/{x}/{y}@2x.png";
+
+/// An OGC Web Map Service layer, requested tile by tile with GetMap bounding boxes
+#[derive(Clone, Debug)]
+pub struct WmsSource {
+    /// Service endpoint, may already contain query parameters (e.g. an API key)
+    pub url: String,
+    /// Comma separated layer names
+    pub layers: String,
+    /// Comma separated style names (empty for the default styles)
+    pub styles: String,
+    /// Image format, e.g. "image/png" or "image/jpeg"
+    pub format: String,
+    /// WMS version, "1.3.0" or "1.1.1"
+    pub version: String,
+    pub transparent: bool,
+    /// Requested image size in pixels (512 for sharp tiles on high-dpi screens)
+    pub tile_size: u32,
+}
+
+impl WmsSource {
+    pub fn new(url: &str, layers: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            layers: layers.to_string(),
+            styles: String::new(),
+            format: "image/png".to_string(),
+            version: "1.3.0".to_string(),
+            transparent: true,
+            tile_size: 256,
+        }
+    }
+
+    /// GetMap URL for a tile, with the bounding box computed by the projection
+    pub fn get_map_url(&self, coord: &TileCoord, projection: &dyn Projection) -> String {
+        let (min_x, min_y, max_x, max_y) = projection.tile_bbox(coord);
+        let crs = projection.code();
+
+        // WMS 1.3.0 uses CRS and lat/lng axis order for geographic coordinates
+        let v130 = self.version == "1.3.0";
+        let bbox = if v130 && crs == "EPSG:4326" {
+            format!("{:.9},{:.9},{:.9},{:.9}", min_y, min_x, max_y, max_x)
+        } else {
+            format!("{:.9},{:.9},{:.9},{:.9}", min_x, min_y, max_x, max_y)
+        };
+
+        let separator = if !self.url.contains('?') {
+            "?"
+        } else if self.url.ends_with('?') || self.url.ends_with('&') {
+            ""
+        } else {
+            "&"
+        };
+
+        format!(
+            "{}{}SERVICE=WMS&REQUEST=GetMap&VERSION={}&LAYERS={}&STYLES={}&FORMAT={}&TRANSPARENT={}&{}={}&BBOX={}&WIDTH={}&HEIGHT={}",
+            self.url,
+            separator,
+            self.version,
+            self.layers.replace(' ', "%20"),
+            self.styles.replace(' ', "%20"),
+            self.format,
+            if self.transparent { "TRUE" } else { "FALSE" },
+            if v130 { "CRS" } else { "SRS" },
+            crs,
+            bbox,
+            self.tile_size,
+            self.tile_size,
+        )
+    }
+}
+
+/// Intercepts the tile cache's network requests, e.g. to add OAuth tokens,
+/// route through a corporate proxy, serve tiles from an app-bundled asset
+/// pack or mock the tile server in tests. Set with `GeoMapView::set_tile_transport`.
+pub trait TileTransport {
+    /// Fetch a tile. `request` is what the cache would send: the tile URL,
+    /// User-Agent and revalidation headers. Return the response directly if it
+    /// is available immediately. Otherwise return None and either send the
+    /// (possibly modified) request with `cx.http_request(request_id, ...)`, whose
+    /// response the map handles like its own, or fetch the tile some other way
+    /// and hand the result to `GeoMapView::deliver_tile_response`.
+    fn fetch(&mut self, cx: &mut Cx, request_id: LiveId, coord: TileCoord, request: HttpRequest) -> Option<HttpResponse>;
+}
+
+/// Credit a tile provider's usage policy requires on the map
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProviderAttribution {
+    /// Copyright line, e.g. "© OpenStreetMap contributors"
+    pub text: String,
+    /// Page reported when the attribution is tapped
+    pub url: String,
+}
+
+/// Providers whose usage policies require attribution on the map, by host
+/// (subdomains included): (host, copyright line, url)
+const ATTRIBUTION_REQUIRED: &[(&str, &str, &str)] = &[
+    ("tile.openstreetmap.org", "© OpenStreetMap contributors", "https://www.openstreetmap.org/copyright"),
+    ("basemaps.cartocdn.com", "© OpenStreetMap contributors © CARTO", "https://carto.com/attributions"),
+    ("tile.opentopomap.org", "© OpenStreetMap contributors, SRTM | © OpenTopoMap (CC-BY-SA)", "https://opentopomap.org/about"),
+    ("tiles.stadiamaps.com", "© Stadia Maps © OpenMapTiles © OpenStreetMap contributors", "https://stadiamaps.com/attribution"),
+    ("tile.thunderforest.com", "© Thunderforest © OpenStreetMap contributors", "https://www.thunderforest.com/terms/"),
+];
+
+/// Where tiles are loaded from
+#[derive(Clone, Debug)]
+pub enum TileSource {
+    /// XYZ tile server URL template with `{z}`, `{x}` and `{y}` placeholders
+    Xyz(String),
+    /// WMS layer, tiles are requested with GetMap bounding boxes
+    Wms(WmsSource),
+    /// Tiles in a folder, a path template with `{z}`, `{x}` and `{y}`
+    /// placeholders like `/opt/kiosk/tiles/{z}/{x}/{y}.png`. Read directly,
+    /// without the network or the disk cache.
+    Files(String),
+    /// Tiles bundled with the app's resources, a dependency path template like
+    /// `crate://self/resources/tiles/{z}/{x}/{y}.png` read with `cx.get_dependency`
+    Bundled(String),
+}
+
+impl Default for TileSource {
+    fn default() -> Self {
+        TileSource::Xyz(DEFAULT_TILE_SERVER.to_string())
+    }
+}
+
+impl TileSource {
+    /// Request URL for a tile (file or dependency path of local sources)
+    pub fn tile_url(&self, coord: &TileCoord, projection: &dyn Projection) -> String {
+        match self {
+            TileSource::Xyz(template) | TileSource::Files(template) | TileSource::Bundled(template) => coord.tile_url(template),
+            TileSource::Wms(wms) => wms.get_map_url(coord, projection),
+        }
+    }
+
+    /// Check if tiles are read from files or app resources rather than downloaded
+    pub fn is_local(&self) -> bool {
+        matches!(self, TileSource::Files(_) | TileSource::Bundled(_))
+    }
+
+    /// Disk cache subdirectory keeping this source's tiles apart from others.
+    /// The default server uses the top-level tile directory.
+    pub fn cache_namespace(&self) -> Option<String> {
+        let key = match self {
+            TileSource::Xyz(template) if template == DEFAULT_TILE_SERVER => return None,
+            TileSource::Xyz(template) | TileSource::Files(template) | TileSource::Bundled(template) => template.clone(),
+            TileSource::Wms(wms) => format!("{}|{}|{}|{}|{}", wms.url, wms.layers, wms.styles, wms.format, wms.tile_size),
+        };
+        Some(format!("src-{:016x}", LiveId::from_str(&key).0))
+    }
+
+    /// Attribution the provider's usage policy requires, for the servers of
+    /// OpenStreetMap, CARTO, OpenTopoMap, Stadia Maps and Thunderforest
+    pub fn required_attribution(&self) -> Option<ProviderAttribution> {
+        let host = self.host()?;
+        ATTRIBUTION_REQUIRED.iter()
+            .find(|(domain, _, _)| host == *domain || host.ends_with(&format!(".{}", domain)))
+            .map(|(_, text, url)| ProviderAttribution { text: text.to_string(), url: url.to_string() })
+    }
+
+    /// Host name of the tile server (lowercase, without scheme and port)
+    pub fn host(&self) -> Option<String> {
+        let url = match self {
+            TileSource::Xyz(template) => template,
+            TileSource::Wms(wms) => &wms.url,
+            TileSource::Files(_) | TileSource::Bundled(_) => return None,
+        };
+        let rest = url.split_once("://").map_or(url.as_str(), |(_, rest)| rest);
+        let authority = rest.split(['/', '?', '#']).next()?;
+        let host = authority.rsplit('@').next()?.split(':').next()?;
+        (!host.is_empty()).then(|| host.to_ascii_lowercase())
+    }
+}
+
 /// State of a tile being loaded
 #[derive(Clone)]
 pub enum TileState {
@@ -30,12 +211,241 @@ pub enum TileState {
     Error(String),
 }
 
+/// Resolution of uploaded tiles, lowered by the quality governor at `QualityLevel::ReducedTiles`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TileQuality {
+    /// Upload tiles at their native resolution
+    Full,
+    /// Halve high-resolution (@2x) tiles before upload, using a quarter of the GPU memory
+    Reduced,
+}
+
+/// Size of the downscaled variant kept for tiles far from the current zoom
+const DOWNSCALED_TILE_SIZE: usize = 128;
+
+/// Outcome of starting a disk-only download
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DiskFetch {
+    /// Already on disk, being written or downloaded (or disk caching is off)
+    Cached,
+    /// Asked the I/O thread whether it's on disk, it's requested if it isn't
+    Checking,
+    /// A request was sent
+    Requested,
+    /// Too many requests in flight or backing off, retry later
+    Busy,
+}
+
+/// Memory bookkeeping for a loaded tile
+#[derive(Clone, Copy, Debug)]
+struct TileUsage {
+    /// Frame in which the tile was last visible
+    last_used: u64,
+    /// Texture size in pixels
+    texels: usize,
+    downscaled: bool,
+    /// When the tile was last fetched, or a refresh of it was sent
+    fetched_at: Instant,
+}
+
+/// Frame time the adaptive request budget spreads a round trip over
+const BUDGET_FRAME_SECS: f64 = 1.0 / 60.0;
+/// Fewest requests in flight and per frame the adaptive budget goes down to
+const MIN_PENDING_REQUESTS: usize = 8;
+const MIN_FRAME_REQUESTS: usize = 2;
+
+/// Window over which request guardrails count repeats and errors
+const GUARD_WINDOW: Duration = Duration::from_secs(60);
+/// First backoff after a guardrail trips, doubled for each further trip
+const GUARD_BACKOFF_BASE_SECS: f64 = 5.0;
+/// Longest backoff
+const GUARD_BACKOFF_MAX_SECS: f64 = 300.0;
+
+/// Cooldown after a 429 or 5xx response without a usable `Retry-After`,
+/// doubled for each further one
+const THROTTLE_BASE_SECS: f64 = 10.0;
+/// Longest cooldown, also the cap for `Retry-After`
+const THROTTLE_MAX_SECS: f64 = 600.0;
+
+/// Hosts that rate-limited or failed, with the end of their cooldown and the
+/// number of consecutive throttles. Shared by all tile caches, so a throttled
+/// provider isn't hit by a second map meanwhile.
+static THROTTLED_HOSTS: Mutex<Option<HashMap<String, (Instant, u32)>>> = Mutex::new(None);
+
+fn with_throttled_hosts<R>(f: impl FnOnce(&mut HashMap<String, (Instant, u32)>) -> R) -> R {
+    let mut hosts = THROTTLED_HOSTS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    f(hosts.get_or_insert_with(HashMap::new))
+}
+
+/// A tile server asking to slow down, see [`TileCache::take_throttles`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProviderThrottle {
+    pub host: String,
+    /// HTTP status of the response (429 or 5xx)
+    pub status: u16,
+    /// Cooldown in seconds before requests to the host resume
+    pub duration: f64,
+}
+
+/// Pathological request pattern that made the tile cache back off
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TileRequestWarning {
+    /// The same tile was requested from the network too often within a minute
+    RepeatedRequests { coord: TileCoord, count: usize },
+    /// Too many client errors (HTTP 4xx) within a minute
+    ClientErrors { count: usize },
+}
+
+/// Session-long tile request counters
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TileRequestStats {
+    /// Network requests sent
+    pub requests: u64,
+    /// Network requests for tiles that had already been requested this session
+    pub duplicate_requests: u64,
+    /// Responses with an HTTP 4xx status
+    pub client_errors: u64,
+    /// Times the guardrails made the cache back off
+    pub backoffs: u64,
+    /// Responses with HTTP 429 or 5xx that paused requests to the host
+    pub throttles: u64,
+    /// Requests given up on after the request timeout without a response
+    pub timeouts: u64,
+}
+
+/// Cache effectiveness and resource usage, see [`TileCache::stats`]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TileCacheStats {
+    /// Lookups of tiles already in GPU memory (counted every frame a tile is needed)
+    pub memory_hits: u64,
+    /// Tiles loaded from the disk cache
+    pub disk_hits: u64,
+    /// Tiles that had to be downloaded
+    pub misses: u64,
+    /// Response body bytes received from the tile server
+    pub bytes_downloaded: u64,
+    /// Conditional requests sent for cached tiles older than the TTL
+    pub revalidations: u64,
+    /// Revalidations answered with 304 Not Modified (no download needed)
+    pub not_modified: u64,
+    /// Network requests in flight
+    pub in_flight: usize,
+    /// Tiles resident in GPU memory
+    pub loaded_tiles: usize,
+    /// Images decoded (disk and network)
+    pub decodes: u64,
+    /// Total time spent decoding images in seconds
+    pub decode_time: f64,
+    /// Slowest single decode in seconds
+    pub max_decode_time: f64,
+    /// Approximate GPU memory used by loaded tiles in bytes
+    pub memory_bytes: usize,
+    /// GPU memory budget in bytes
+    pub memory_budget: usize,
+    /// Size of the disk cache in bytes (measured after eviction, plus tiles written since)
+    pub disk_bytes: u64,
+    /// Disk reads and writes queued on the I/O thread
+    pub disk_queue: usize,
+    /// Network request guardrail counters
+    pub requests: TileRequestStats,
+}
+
+impl TileCacheStats {
+    /// Average decode time in seconds
+    pub fn average_decode_time(&self) -> Option<f64> {
+        (self.decodes > 0).then(|| self.decode_time / self.decodes as f64)
+    }
+
+    /// Fraction of tile loads served from memory or disk
+    pub fn hit_rate(&self) -> Option<f64> {
+        let total = self.memory_hits + self.disk_hits + self.misses;
+        (total > 0).then(|| (self.memory_hits + self.disk_hits) as f64 / total as f64)
+    }
+}
+
 /// Manages tile loading and caching
 pub struct TileCache {
     tiles: HashMap<TileCoord, TileState>,
     pending_requests: HashMap<LiveId, TileCoord>,
+    // Requests that only fill the disk cache (offline downloads, prefetching)
+    disk_only_requests: HashSet<LiveId>,
+    // Conditional requests for stale cached tiles and refreshes (see
+    // `refresh_tiles`), the tiles stay on screen meanwhile
+    revalidations: HashSet<LiveId>,
+    // Download queue for offline use (areas, route prefetching), fed
+    // into disk-only requests as the request limits allow
+    download_queue: VecDeque<TileCoord>,
+    download_progress: DownloadProgress,
     request_counter: u64,
-    tile_server: String,
+    source: TileSource,
+    cache_namespace: Option<String>,
+
+    // Disk cache, read and written on a background thread. Tiles waiting for
+    // a disk read are marked as loading; tiles found missing go to the network.
+    disk_config: DiskCacheConfig,
+    disk_worker: DiskWorker,
+    disk_reads: HashSet<TileCoord>,
+    // Queued downloads waiting for the I/O thread to tell whether they're on disk
+    disk_checks: HashSet<TileCoord>,
+    not_on_disk: HashSet<TileCoord>,
+    disk_writes: HashSet<TileCoord>,
+    disk_bytes: u64,
+    projection: Rc<dyn Projection>,
+    quality: TileQuality,
+
+    // GPU memory budget: tiles far from the current zoom are downscaled
+    // before anything gets evicted
+    usage: HashMap<TileCoord, TileUsage>,
+    max_memory_bytes: usize,
+    frame: u64,
+
+    // Request budget: limits new network requests per frame and in flight,
+    // so huge viewports don't request hundreds of tiles at once
+    request_budget: usize,
+    max_pending: usize,
+    requests_this_frame: usize,
+    has_deferred: bool,
+    // Whether the per-frame budget (not the limit in flight) held requests back
+    frame_budget_spent: bool,
+    // Smoothed seconds from request to response, see `adapt_request_budget`
+    latency: Option<f64>,
+    // Bumped when tiles leave the cache, see `request_generation`
+    generation: u64,
+    // Bumped whenever the textures shown for any tile change, see `content_generation`
+    content_generation: u64,
+
+    // Request guardrails: back off from the network when the same tile is
+    // requested over and over or the provider keeps rejecting requests
+    max_repeats_per_minute: usize,
+    max_client_errors_per_minute: usize,
+    request_history: HashMap<TileCoord, Vec<Instant>>,
+    client_errors: Vec<Instant>,
+    backoff_until: Option<Instant>,
+    backoff_strikes: u32,
+    stats: TileRequestStats,
+    warnings: Vec<(TileRequestWarning, f64)>,
+    // Host of the tile source and cooldowns it imposed, see THROTTLED_HOSTS
+    host: Option<String>,
+    throttles: Vec<ProviderThrottle>,
+    // Requests without a response after request_timeout are given up and retried
+    request_timeout: Option<Duration>,
+    request_sent: HashMap<LiveId, Instant>,
+    // Replaces cx.http_request when set; responses it gave right away wait
+    // in ready_responses for `handle_ready_responses`
+    transport: Option<Box<dyn TileTransport>>,
+    ready_responses: Vec<(LiveId, HttpResponse)>,
+
+    // Instrumentation, see `stats()`
+    memory_hits: u64,
+    disk_hits: u64,
+    misses: u64,
+    bytes_downloaded: u64,
+    revalidation_count: u64,
+    not_modified: u64,
+    decodes: u64,
+    decode_time: f64,
+    max_decode_time: f64,
+    finished_loads: u64,
 }
 
 impl Default for TileCache {
@@ -49,49 +459,791 @@ impl TileCache {
         Self {
             tiles: HashMap::new(),
             pending_requests: HashMap::new(),
+            disk_only_requests: HashSet::new(),
+            revalidations: HashSet::new(),
+            download_queue: VecDeque::new(),
+            download_progress: DownloadProgress::default(),
             request_counter: 0,
-            // Carto Voyager - clean, modern style (free, no API key required)
-            tile_server: "https://a.basemaps.cartocdn.com/rastertiles/voyager/{z}/{x}/{y}@2x.png".to_string(),
+            source: TileSource::default(),
+            cache_namespace: None,
+            disk_config: DiskCacheConfig::default(),
+            disk_worker: DiskWorker::default(),
+            disk_reads: HashSet::new(),
+            disk_checks: HashSet::new(),
+            not_on_disk: HashSet::new(),
+            disk_writes: HashSet::new(),
+            disk_bytes: 0,
+            projection: Rc::new(WebMercator),
+            quality: TileQuality::Full,
+            usage: HashMap::new(),
+            max_memory_bytes: 128 * 1024 * 1024,
+            frame: 0,
+            request_budget: 16,
+            max_pending: 32,
+            requests_this_frame: 0,
+            has_deferred: false,
+            frame_budget_spent: false,
+            latency: None,
+            max_repeats_per_minute: 5,
+            max_client_errors_per_minute: 20,
+            request_history: HashMap::new(),
+            client_errors: Vec::new(),
+            backoff_until: None,
+            backoff_strikes: 0,
+            stats: TileRequestStats::default(),
+            warnings: Vec::new(),
+            host: TileSource::default().host(),
+            throttles: Vec::new(),
+            request_timeout: Some(Duration::from_secs(30)),
+            request_sent: HashMap::new(),
+            transport: None,
+            ready_responses: Vec::new(),
+            memory_hits: 0,
+            disk_hits: 0,
+            misses: 0,
+            bytes_downloaded: 0,
+            revalidation_count: 0,
+            not_modified: 0,
+            decodes: 0,
+            decode_time: 0.0,
+            max_decode_time: 0.0,
+            finished_loads: 0,
+            generation: 0,
+            content_generation: 0,
         }
     }
 
     pub fn set_tile_server(&mut self, server: &str) {
-        self.tile_server = server.to_string();
+        self.set_source(TileSource::Xyz(server.to_string()));
+    }
+
+    /// Set where tiles are loaded from, dropping tiles loaded from the previous source
+    pub fn set_source(&mut self, source: TileSource) {
+        self.cache_namespace = source.cache_namespace();
+        self.host = source.host();
+        self.source = source;
+        self.tiles.clear();
+        self.generation += 1;
+        self.content_generation += 1;
+        self.usage.clear();
+        self.pending_requests.clear();
+        self.request_sent.clear();
+        self.ready_responses.clear();
+        self.disk_only_requests.clear();
+        self.revalidations.clear();
+        self.disk_reads.clear();
+        self.not_on_disk.clear();
+        self.cancel_downloads();
+    }
+
+    /// Current tile source
+    pub fn source(&self) -> &TileSource {
+        &self.source
+    }
+
+    /// Set the disk cache location and size limit, or disable disk caching
+    pub fn set_disk_cache_config(&mut self, config: DiskCacheConfig) {
+        if config != self.disk_config {
+            self.disk_worker.submit_if_running(DiskJob::Configure(config.clone()));
+            self.disk_config = config;
+            self.not_on_disk.clear();
+        }
+    }
+
+    pub fn disk_cache_config(&self) -> &DiskCacheConfig {
+        &self.disk_config
+    }
+
+    /// Set the projection used for WMS bounding boxes (must match the map's projection)
+    pub fn set_projection(&mut self, projection: Rc<dyn Projection>) {
+        self.projection = projection;
+    }
+
+    /// Set the quality used when uploading newly loaded tiles
+    pub fn set_quality(&mut self, quality: TileQuality) {
+        self.quality = quality;
+    }
+
+    /// Create a texture for a decoded tile, applying the quality setting, and store it
+    fn upload_tile(&mut self, cx: &mut Cx, coord: TileCoord, mut buffer: ImageBuffer) {
+        if self.quality == TileQuality::Reduced && buffer.width > 256 && buffer.height > 256 {
+            downsample_half(&mut buffer);
+        }
+        self.usage.insert(coord, TileUsage {
+            last_used: self.frame,
+            texels: buffer.width * buffer.height,
+            downscaled: false,
+            fetched_at: Instant::now(),
+        });
+        let texture: Texture = buffer.into_new_texture(cx);
+        self.tiles.insert(coord, TileState::Loaded(texture));
+        self.content_generation += 1;
+        self.finished_loads += 1;
+    }
+
+    fn fail_tile(&mut self, coord: TileCoord, message: String) {
+        self.tiles.insert(coord, TileState::Error(message));
+        self.finished_loads += 1;
+    }
+
+    /// Set the GPU memory budget for loaded tiles in bytes
+    pub fn set_memory_budget(&mut self, max_bytes: usize) {
+        self.max_memory_bytes = max_bytes;
+    }
+
+    /// Approximate GPU memory used by loaded tiles in bytes
+    pub fn memory_usage(&self) -> usize {
+        self.usage.values().map(|u| u.texels * 4).sum()
+    }
+
+    /// Bring tile memory under budget. Tiles not visible this frame are handled
+    /// furthest zoom first, then least recently used: tiles two or more zoom
+    /// levels away are replaced by a small downscaled variant (halved from the
+    /// texture's CPU copy) so zooming far out still shows imagery, the rest are evicted.
+    pub fn trim_memory(&mut self, cx: &mut Cx, current_zoom: u8) {
+        let mut used = self.memory_usage();
+        if used <= self.max_memory_bytes {
+            return;
+        }
+
+        let frame = self.frame;
+        let mut candidates: Vec<(TileCoord, TileUsage)> = self.usage
+            .iter()
+            .filter(|(_, u)| u.last_used < frame)
+            .map(|(c, u)| (*c, *u))
+            .collect();
+        candidates.sort_by_key(|(c, u)| (std::cmp::Reverse(c.z.abs_diff(current_zoom)), u.last_used));
+
+        for (coord, usage) in candidates {
+            if used <= self.max_memory_bytes {
+                break;
+            }
+
+            if coord.z.abs_diff(current_zoom) >= 2 && !usage.downscaled {
+                if let Some(texels) = self.downscale_tile(cx, coord) {
+                    used = used.saturating_sub((usage.texels - texels) * 4);
+                    continue;
+                }
+            }
+
+            self.tiles.remove(&coord);
+            self.usage.remove(&coord);
+            self.generation += 1;
+            self.content_generation += 1;
+            used = used.saturating_sub(usage.texels * 4);
+        }
+    }
+
+    /// Replace a loaded tile with its downscaled variant, halved from the
+    /// pixels the texture keeps on the CPU: no disk read or decode, and tiles
+    /// that never reached the disk cache get one too. Returns its texel count.
+    fn downscale_tile(&mut self, cx: &mut Cx, coord: TileCoord) -> Option<usize> {
+        let mut image = self.tile_image(cx, &coord)?;
+        while image.width > DOWNSCALED_TILE_SIZE && image.height > DOWNSCALED_TILE_SIZE {
+            downsample_half(&mut image);
+        }
+        let texels = image.width * image.height;
+        let usage = self.usage.get_mut(&coord)?;
+        usage.texels = texels;
+        usage.downscaled = true;
+        let texture: Texture = image.into_new_texture(cx);
+        self.tiles.insert(coord, TileState::Loaded(texture));
+        self.content_generation += 1;
+        Some(texels)
+    }
+
+    /// Pixels of a loaded tile, copied from the texture's CPU copy
+    pub fn tile_image(&self, cx: &mut Cx, coord: &TileCoord) -> Option<ImageBuffer> {
+        let Some(TileState::Loaded(texture)) = self.tiles.get(coord) else {
+            return None;
+        };
+        match texture.get_format(cx) {
+            TextureFormat::VecBGRAu8_32 { width, height, data: Some(data), .. } => {
+                let mut image = ImageBuffer::default();
+                image.width = *width;
+                image.height = *height;
+                image.data = data.clone();
+                Some(image)
+            }
+            _ => None,
+        }
+    }
+
+    /// Set the request budget: new network requests per frame and maximum in flight.
+    /// Lower it on slow or metered connections.
+    pub fn set_request_budget(&mut self, per_frame: usize, max_pending: usize) {
+        self.request_budget = per_frame.max(1);
+        self.max_pending = max_pending.max(1);
+    }
+
+    /// Fit the request budget to the viewport and the network instead of fixed
+    /// values: one request in flight per visible tile, and per frame the share
+    /// of those that keeps them in flight over the observed round trip (all of
+    /// them until a response came back or on a fast network, a few on a slow
+    /// one). `max_per_frame` and `max_pending` cap both.
+    pub fn adapt_request_budget(&mut self, visible_tiles: usize, max_per_frame: usize, max_pending: usize) {
+        let max_per_frame = max_per_frame.max(1);
+        let max_pending = max_pending.max(1);
+        self.max_pending = visible_tiles.clamp(MIN_PENDING_REQUESTS.min(max_pending), max_pending);
+        let round_trip_frames = self.latency.map_or(1.0, |latency| (latency / BUDGET_FRAME_SECS).max(1.0));
+        let per_frame = (self.max_pending as f64 / round_trip_frames).ceil() as usize;
+        self.request_budget = per_frame.clamp(MIN_FRAME_REQUESTS.min(max_per_frame), max_per_frame);
+    }
+
+    /// Smoothed seconds from a tile request to its response, None before the first one
+    pub fn response_latency(&self) -> Option<f64> {
+        self.latency
+    }
+
+    fn record_latency(&mut self, sent: Instant) {
+        let secs = sent.elapsed().as_secs_f64();
+        self.latency = Some(self.latency.map_or(secs, |latency| latency + (secs - latency) * 0.2));
+    }
+
+    /// Reset the per-frame request budget, call before requesting a frame's tiles
+    pub fn begin_frame(&mut self) {
+        self.frame += 1;
+        self.requests_this_frame = 0;
+        self.has_deferred = false;
+        self.frame_budget_spent = false;
+    }
+
+    /// Check if tiles were skipped this frame because the budget ran out
+    pub fn has_deferred_requests(&self) -> bool {
+        self.has_deferred
+    }
+
+    /// Whether tiles were skipped because this frame's budget ran out, so
+    /// requesting again on the next frame gets further. Tiles skipped only for
+    /// the limit in flight wait for a response (or error) to free a slot.
+    pub fn needs_next_frame(&self) -> bool {
+        self.frame_budget_spent
+    }
+
+    /// Counter that changes whenever tiles leave the cache (evicted, missing
+    /// from disk, throttled or a new source), so requested tiles may need
+    /// requesting again. Callers that only request tiles when the view
+    /// changes also request them when this changes.
+    pub fn request_generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Counter that changes whenever a tile is loaded, swapped for its
+    /// downscaled variant or leaves the cache, i.e. whenever drawing the same
+    /// tiles again could look different
+    pub fn content_generation(&self) -> u64 {
+        self.content_generation
+    }
+
+    /// Number of network requests in flight
+    pub fn pending_request_count(&self) -> usize {
+        self.pending_requests.len()
+    }
+
+    /// Number of tiles for display being read from disk or downloaded
+    pub fn loading_count(&self) -> usize {
+        self.tiles.values().filter(|state| matches!(state, TileState::Loading)).count()
+    }
+
+    /// Number of tile loads that finished (loaded or failed) since the cache was created
+    pub fn finished_load_count(&self) -> u64 {
+        self.finished_loads
+    }
+
+    /// Number of tiles waiting to be written to the disk cache
+    pub fn pending_disk_writes(&self) -> usize {
+        self.disk_writes.len()
+    }
+
+    /// Set the guardrail limits: network requests for the same tile and client
+    /// errors (4xx) allowed per minute before backing off
+    pub fn set_request_guard(&mut self, max_repeats_per_minute: usize, max_client_errors_per_minute: usize) {
+        self.max_repeats_per_minute = max_repeats_per_minute.max(1);
+        self.max_client_errors_per_minute = max_client_errors_per_minute.max(1);
+    }
+
+    /// Session-long request counters
+    pub fn request_stats(&self) -> TileRequestStats {
+        self.stats
+    }
+
+    /// Hits and misses, download volume, decode times and memory/disk usage
+    /// since the cache was created
+    pub fn stats(&self) -> TileCacheStats {
+        TileCacheStats {
+            memory_hits: self.memory_hits,
+            disk_hits: self.disk_hits,
+            misses: self.misses,
+            bytes_downloaded: self.bytes_downloaded,
+            revalidations: self.revalidation_count,
+            not_modified: self.not_modified,
+            in_flight: self.pending_requests.len(),
+            loaded_tiles: self.usage.len(),
+            decodes: self.decodes,
+            decode_time: self.decode_time,
+            max_decode_time: self.max_decode_time,
+            memory_bytes: self.memory_usage(),
+            memory_budget: self.max_memory_bytes,
+            disk_bytes: self.disk_bytes,
+            disk_queue: self.disk_reads.len() + self.disk_checks.len() + self.disk_writes.len(),
+            requests: self.stats,
+        }
+    }
+
+    /// Decode tile data, recording the decode time
+    fn decode_timed(&mut self, data: &[u8]) -> Result<ImageBuffer, ImageError> {
+        let start = Instant::now();
+        let result = decode_tile(data);
+        self.record_decode(start.elapsed().as_secs_f64());
+        result
+    }
+
+    fn record_decode(&mut self, elapsed: f64) {
+        self.decodes += 1;
+        self.decode_time += elapsed;
+        self.max_decode_time = self.max_decode_time.max(elapsed);
+    }
+
+    /// Seconds left until network requests resume, if backing off or the
+    /// tile server throttled requests
+    pub fn backoff_remaining(&self) -> Option<f64> {
+        let until = self.backoff_until.into_iter().chain(self.throttled_until()).max()?;
+        let remaining = until.saturating_duration_since(Instant::now()).as_secs_f64();
+        (remaining > 0.0).then_some(remaining)
+    }
+
+    /// End of the cooldown of the tile server's host, if throttled
+    fn throttled_until(&self) -> Option<Instant> {
+        let host = self.host.as_ref()?;
+        with_throttled_hosts(|hosts| hosts.get(host).map(|(until, _)| *until))
+            .filter(|until| *until > Instant::now())
+    }
+
+    /// Take the provider throttles (429/5xx cooldowns) since the last call
+    pub fn take_throttles(&mut self) -> Vec<ProviderThrottle> {
+        std::mem::take(&mut self.throttles)
+    }
+
+    /// Check for a rate limit (429) or server error (5xx) and pause requests to
+    /// the host for the `Retry-After` time, or an increasing cooldown
+    fn throttle_if_needed(&mut self, response: &HttpResponse) -> bool {
+        let status = response.status_code;
+        if status != 429 && !(500..600).contains(&status) {
+            return false;
+        }
+        let Some(host) = self.host.clone() else {
+            return true;
+        };
+        let retry_after = response.headers.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case("Retry-After"))
+            .and_then(|(_, values)| values.first())
+            .and_then(|value| value.trim().parse::<f64>().ok())
+            .filter(|secs| secs.is_finite() && *secs > 0.0);
+        let now = Instant::now();
+        let secs = with_throttled_hosts(|hosts| {
+            let (until, strikes) = hosts.entry(host.clone()).or_insert((now, 0));
+            // Responses to requests sent before the cooldown started don't extend it
+            if *until > now {
+                return None;
+            }
+            // Consecutive throttles reset after a quiet window
+            if now > *until + GUARD_WINDOW {
+                *strikes = 0;
+            }
+            let secs = retry_after
+                .unwrap_or(THROTTLE_BASE_SECS * 2_f64.powi(*strikes as i32))
+                .min(THROTTLE_MAX_SECS);
+            *strikes += 1;
+            *until = now + Duration::from_secs_f64(secs);
+            Some(secs)
+        });
+        if let Some(duration) = secs {
+            self.stats.throttles += 1;
+            self.throttles.push(ProviderThrottle { host, status, duration });
+        }
+        true
+    }
+
+    /// Take the guardrail warnings raised since the last call, with the backoff in seconds
+    pub fn take_warnings(&mut self) -> Vec<(TileRequestWarning, f64)> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /// Stop network requests for a while, backing off longer on each consecutive trip
+    fn back_off(&mut self, now: Instant, warning: TileRequestWarning) {
+        // Strikes reset once a full window passed since the last backoff ended
+        if self.backoff_until.is_some_and(|until| now > until + GUARD_WINDOW) {
+            self.backoff_strikes = 0;
+        }
+        let secs = (GUARD_BACKOFF_BASE_SECS * 2_f64.powi(self.backoff_strikes as i32)).min(GUARD_BACKOFF_MAX_SECS);
+        self.backoff_strikes += 1;
+        self.backoff_until = Some(now + Duration::from_secs_f64(secs));
+        self.stats.backoffs += 1;
+        self.warnings.push((warning, secs));
+    }
+
+    /// Record a network request for the guardrails, returns false if it must not be sent
+    fn guard_request(&mut self, coord: TileCoord) -> bool {
+        let now = Instant::now();
+        if self.is_backing_off(now) {
+            return false;
+        }
+
+        // Entries are kept (possibly empty) for the session, so any existing entry is a duplicate
+        let duplicate = self.request_history.contains_key(&coord);
+        let history = self.request_history.entry(coord).or_default();
+        history.retain(|t| now.duration_since(*t) < GUARD_WINDOW);
+        if history.len() >= self.max_repeats_per_minute {
+            let count = history.len() + 1;
+            self.back_off(now, TileRequestWarning::RepeatedRequests { coord, count });
+            return false;
+        }
+        history.push(now);
+        self.stats.requests += 1;
+        if duplicate {
+            self.stats.duplicate_requests += 1;
+        }
+        true
+    }
+
+    /// Record a refresh of a loaded tile (see `refresh_tiles`). Refreshes are
+    /// scheduled rather than repeats of a failing request, and at most one per
+    /// tile is in flight, so they don't count towards `max_repeats_per_minute`;
+    /// backoffs and provider cooldowns still hold them back.
+    fn guard_refresh(&mut self) -> bool {
+        if self.is_backing_off(Instant::now()) {
+            return false;
+        }
+        self.stats.requests += 1;
+        true
+    }
+
+    fn is_backing_off(&self, now: Instant) -> bool {
+        self.backoff_until.is_some_and(|until| now < until) || self.throttled_until().is_some()
+    }
+
+    /// Record a client error response for the guardrails
+    fn guard_client_error(&mut self) {
+        let now = Instant::now();
+        self.stats.client_errors += 1;
+        self.client_errors.retain(|t| now.duration_since(*t) < GUARD_WINDOW);
+        self.client_errors.push(now);
+        if self.client_errors.len() > self.max_client_errors_per_minute {
+            let count = self.client_errors.len();
+            self.client_errors.clear();
+            self.back_off(now, TileRequestWarning::ClientErrors { count });
+        }
     }
 
     /// Request a tile if not already cached or loading
     pub fn request_tile(&mut self, cx: &mut Cx, coord: TileCoord) {
         // Check if already loaded or loading in memory
         if self.tiles.contains_key(&coord) {
+            if let Some(usage) = self.usage.get_mut(&coord) {
+                usage.last_used = self.frame;
+                self.memory_hits += 1;
+            }
             return;
         }
 
-        // Check disk cache first
-        if let Some(data) = disk_cache::load_tile(&coord) {
-            // Try to decode from disk cache
-            match ImageBuffer::from_png(&data) {
-                Ok(buffer) => {
-                    let texture: Texture = buffer.into_new_texture(cx);
-                    self.tiles.insert(coord, TileState::Loaded(texture));
-                    return; // Successfully loaded from disk
-                }
-                Err(_) => {
-                    // Corrupted cache file, will re-download
-                }
+        // Local tiles are read within the per-frame budget, files on the I/O thread
+        if self.source.is_local() {
+            if self.requests_this_frame >= self.request_budget {
+                self.has_deferred = true;
+                self.frame_budget_spent = true;
+            } else {
+                self.requests_this_frame += 1;
+                self.load_local(cx, coord);
+            }
+            return;
+        }
+
+        // Check disk cache first, the tile is loading until the read comes back
+        if self.disk_config.enabled && !self.not_on_disk.contains(&coord) {
+            self.disk_worker.submit(cx, &self.disk_config, DiskJob::Load {
+                namespace: self.cache_namespace.clone(),
+                coord,
+            });
+            self.disk_reads.insert(coord);
+            self.tiles.insert(coord, TileState::Loading);
+            return;
+        }
+
+        // Not in disk cache, fetch from network if the budget allows.
+        // Deferred tiles are not marked as loading, so they're requested on a later frame.
+        if self.requests_this_frame >= self.request_budget || self.pending_requests.len() >= self.max_pending {
+            self.has_deferred = true;
+            self.frame_budget_spent |= self.requests_this_frame >= self.request_budget;
+            return;
+        }
+        // While backing off nothing is deferred, requests resume once the backoff ends
+        if !self.guard_request(coord) {
+            return;
+        }
+        self.requests_this_frame += 1;
+        self.misses += 1;
+
+        let request_id = self.send_request(cx, coord, None);
+        self.tiles.insert(coord, TileState::Loading);
+        self.pending_requests.insert(request_id, coord);
+    }
+
+    /// Load a tile of a `Files` or `Bundled` source. Files are read and
+    /// decoded on the I/O thread, the tile is loading until the result comes
+    /// back; bundled tiles are in memory already and uploaded right away.
+    /// Missing tiles fail, so beyond a small basemap's deepest zoom its tiles
+    /// are shown scaled up (up to four levels).
+    fn load_local(&mut self, cx: &mut Cx, coord: TileCoord) {
+        let data = match &self.source {
+            TileSource::Files(template) => {
+                let path = coord.tile_url(template);
+                self.disk_worker.submit(cx, &self.disk_config, DiskJob::ReadFile { path, coord });
+                self.disk_reads.insert(coord);
+                self.tiles.insert(coord, TileState::Loading);
+                return;
             }
+            TileSource::Bundled(template) => cx.get_dependency(&coord.tile_url(template)).map(|data| data.to_vec()),
+            TileSource::Xyz(_) | TileSource::Wms(_) => return,
+        };
+        let buffer = data.and_then(|data| {
+            self.decode_timed(&data).map_err(|e| format!("Image decode error: {:?}", e))
+        });
+        match buffer {
+            Ok(buffer) => self.upload_tile(cx, coord, buffer),
+            Err(message) => self.fail_tile(coord, message),
         }
+    }
+
+    /// Start downloading a tile into the disk cache without uploading it to
+    /// the GPU. Whether it's on disk already is checked on the I/O thread
+    /// first, the download goes on when the answer arrives.
+    fn start_disk_fetch(&mut self, cx: &mut Cx, coord: TileCoord) -> DiskFetch {
+        if !self.disk_config.enabled
+            || self.source.is_local()
+            || self.disk_writes.contains(&coord)
+            || self.pending_requests.values().any(|pending| *pending == coord)
+        {
+            return DiskFetch::Cached;
+        }
+        if !self.not_on_disk.contains(&coord) {
+            self.disk_worker.submit(cx, &self.disk_config, DiskJob::Exists {
+                namespace: self.cache_namespace.clone(),
+                coord,
+            });
+            self.disk_checks.insert(coord);
+            return DiskFetch::Checking;
+        }
+        if self.pending_requests.len() >= self.max_pending || !self.guard_request(coord) {
+            return DiskFetch::Busy;
+        }
+        let request_id = self.send_request(cx, coord, None);
+        self.pending_requests.insert(request_id, coord);
+        self.disk_only_requests.insert(request_id);
+        DiskFetch::Requested
+    }
+
+    /// Queue tiles for download into the disk cache. They're requested in
+    /// order as the in-flight limit allows; follow them with `download_progress`.
+    pub fn queue_downloads(&mut self, cx: &mut Cx, coords: impl IntoIterator<Item = TileCoord>) {
+        if self.download_progress.is_finished() {
+            self.download_progress = DownloadProgress::default();
+        }
+        let mut queued: HashSet<TileCoord> = self.download_queue.iter().copied().collect();
+        for coord in coords {
+            if queued.insert(coord) {
+                self.download_queue.push_back(coord);
+                self.download_progress.total += 1;
+            }
+        }
+        self.resume_downloads(cx);
+    }
+
+    /// Download the tiles within `corridor_meters` of a route for every zoom
+    /// level in `zoom_range`, so imagery along it stays available offline (e.g.
+    /// turn-by-turn navigation through signal dropouts). Returns the number of tiles.
+    pub fn prefetch_route(
+        &mut self,
+        cx: &mut Cx,
+        points: &[(f64, f64)],
+        zoom_range: RangeInclusive<u8>,
+        corridor_meters: f64,
+    ) -> usize {
+        let tiles = route_corridor_tiles(self.projection.as_ref(), points, zoom_range, corridor_meters);
+        let count = tiles.len();
+        self.queue_downloads(cx, tiles);
+        count
+    }
+
+    /// Progress of the queued downloads
+    pub fn download_progress(&self) -> DownloadProgress {
+        self.download_progress
+    }
+
+    /// Drop the queued downloads (requests in flight still complete), counting them as failed
+    pub fn cancel_downloads(&mut self) {
+        self.download_progress.failed += self.download_queue.len() + self.disk_checks.len();
+        self.download_queue.clear();
+        self.disk_checks.clear();
+    }
+
+    /// Request queued downloads until the in-flight limit is reached. Called
+    /// after each response; call it when a backoff ends to continue.
+    pub fn resume_downloads(&mut self, cx: &mut Cx) {
+        while let Some(coord) = self.download_queue.front().copied() {
+            match self.start_disk_fetch(cx, coord) {
+                DiskFetch::Cached => self.download_progress.completed += 1,
+                DiskFetch::Checking | DiskFetch::Requested => (),
+                DiskFetch::Busy => break,
+            }
+            self.download_queue.pop_front();
+        }
+    }
 
-        // Not in disk cache, fetch from network
+    fn send_request(&mut self, cx: &mut Cx, coord: TileCoord, validators: Option<&TileValidators>) -> LiveId {
         self.request_counter += 1;
-        let request_id = LiveId::from_num(0, self.request_counter);
+        // Unique across caches, as every cache sees all network responses
+        let request_id = LiveId::unique();
 
-        let url = coord.tile_url(&self.tile_server);
+        let url = self.source.tile_url(&coord, self.projection.as_ref());
         let mut request = HttpRequest::new(url, HttpMethod::GET);
         request.set_header("User-Agent".to_string(), "MakepadMap/0.1".to_string());
-        cx.http_request(request_id, request);
+        if let Some(validators) = validators {
+            validators.apply(&mut request);
+        }
+        let response = match &mut self.transport {
+            Some(transport) => transport.fetch(cx, request_id, coord, request),
+            None => {
+                cx.http_request(request_id, request);
+                None
+            }
+        };
+        match response {
+            Some(response) => self.ready_responses.push((request_id, response)),
+            None => {
+                self.request_sent.insert(request_id, Instant::now());
+            }
+        }
+        request_id
+    }
 
-        self.tiles.insert(coord, TileState::Loading);
+    /// Send requests through a transport instead of `cx.http_request` (None
+    /// goes back to plain HTTP). Requests in flight are still answered.
+    pub fn set_transport(&mut self, transport: Option<Box<dyn TileTransport>>) {
+        self.transport = transport;
+    }
+
+    /// Handle the responses a transport returned right away. Call before
+    /// drawing; returns true if tiles were loaded.
+    pub fn handle_ready_responses(&mut self, cx: &mut Cx) -> bool {
+        let mut loaded = false;
+        // Handling a response may start queued downloads, answered right away as well
+        while !self.ready_responses.is_empty() {
+            for (request_id, response) in std::mem::take(&mut self.ready_responses) {
+                loaded |= self.handle_response(cx, request_id, &response);
+            }
+        }
+        loaded
+    }
+
+    /// Give up on network requests after `timeout` seconds without a response,
+    /// e.g. when the network silently stalls (None or 0 waits forever)
+    pub fn set_request_timeout(&mut self, timeout: Option<f64>) {
+        self.request_timeout = timeout
+            .filter(|secs| secs.is_finite() && *secs > 0.0)
+            .map(Duration::from_secs_f64);
+    }
+
+    /// Seconds until the oldest request in flight times out
+    pub fn next_timeout(&self) -> Option<f64> {
+        let timeout = self.request_timeout?;
+        let oldest = self.request_sent.values().min()?;
+        Some((*oldest + timeout).saturating_duration_since(Instant::now()).as_secs_f64())
+    }
+
+    /// Give up on the requests that timed out, returning their tiles. Tiles
+    /// for display are requested again on the next draw, downloads are queued
+    /// again; a late response is ignored. The request guardrails still apply,
+    /// so a dead network ends in a backoff rather than a request storm.
+    pub fn expire_requests(&mut self) -> Vec<TileCoord> {
+        let Some(timeout) = self.request_timeout else {
+            return Vec::new();
+        };
+        let now = Instant::now();
+        let expired: Vec<LiveId> = self.request_sent.iter()
+            .filter(|(_, sent)| now.duration_since(**sent) >= timeout)
+            .map(|(request_id, _)| *request_id)
+            .collect();
+        let mut coords = Vec::new();
+        for request_id in expired {
+            self.request_sent.remove(&request_id);
+            let Some(coord) = self.pending_requests.remove(&request_id) else {
+                continue;
+            };
+            self.stats.timeouts += 1;
+            coords.push(coord);
+            if self.disk_only_requests.remove(&request_id) {
+                self.download_queue.push_front(coord);
+            } else if !self.revalidations.remove(&request_id) {
+                // Stale tiles being revalidated stay, others are requested again
+                self.tiles.remove(&coord);
+                self.generation += 1;
+                self.content_generation += 1;
+            }
+        }
+        coords
+    }
+
+    /// Ask the server whether a stale cached tile changed. Skipped when the
+    /// network is busy or backing off; the tile is revalidated on its next load.
+    fn revalidate(&mut self, cx: &mut Cx, coord: TileCoord, validators: &TileValidators) {
+        if self.pending_requests.len() >= self.max_pending || !self.guard_request(coord) {
+            return;
+        }
+        let request_id = self.send_request(cx, coord, Some(validators));
         self.pending_requests.insert(request_id, coord);
+        self.revalidations.insert(request_id);
+        self.revalidation_count += 1;
+    }
+
+    /// Fetch the loaded tiles among `coords` again once they're older than
+    /// `max_age`, for imagery that changes (e.g. weather radar). Unlike
+    /// `clear` the cache is kept: old textures stay on screen until the new
+    /// ones arrive, and are kept if a request fails. Returns the number of
+    /// tiles requested; the rest wait for the request limits.
+    pub fn refresh_tiles(&mut self, cx: &mut Cx, coords: &[TileCoord], max_age: Duration) -> usize {
+        if self.source.is_local() {
+            return 0;
+        }
+        let now = Instant::now();
+        let mut count = 0;
+        for coord in coords {
+            let stale = self.usage.get(coord).is_some_and(|usage| now.duration_since(usage.fetched_at) >= max_age);
+            if !stale
+                || !matches!(self.tiles.get(coord), Some(TileState::Loaded(_)))
+                || self.pending_requests.values().any(|pending| pending == coord)
+            {
+                continue;
+            }
+            if self.pending_requests.len() >= self.max_pending || !self.guard_refresh() {
+                break;
+            }
+            let request_id = self.send_request(cx, *coord, None);
+            self.pending_requests.insert(request_id, *coord);
+            self.revalidations.insert(request_id);
+            // A failed refresh is retried after another `max_age`, not right away
+            if let Some(usage) = self.usage.get_mut(coord) {
+                usage.fetched_at = now;
+            }
+            count += 1;
+        }
+        count
+    }
+
+    /// Seconds until the first loaded tile among `coords` gets older than
+    /// `max_age`, to schedule the next `refresh_tiles`
+    pub fn next_refresh(&self, coords: &[TileCoord], max_age: Duration) -> Option<f64> {
+        let oldest = coords.iter().filter_map(|coord| self.usage.get(coord)).map(|usage| usage.fetched_at).min()?;
+        Some((oldest + max_age).saturating_duration_since(Instant::now()).as_secs_f64())
     }
 
     /// Get a tile if it's already loaded
@@ -103,51 +1255,295 @@ impl TileCache {
         }
     }
 
+    /// Whether a tile finished loading, successfully or not
+    pub fn is_finished(&self, coord: &TileCoord) -> bool {
+        matches!(self.tiles.get(coord), Some(TileState::Loaded(_) | TileState::Error(_)))
+    }
+
+    /// Width in pixels of a loaded tile's texture (tiles are square)
+    pub fn texture_width(&self, coord: &TileCoord) -> Option<usize> {
+        self.usage.get(coord).map(|usage| (usage.texels as f64).sqrt().round() as usize)
+    }
+
+    /// A loaded parent tile that can be shown scaled up in place of a missing
+    /// tile, with the UV offset and scale of the part covering it
+    pub fn find_parent(&self, coord: &TileCoord) -> Option<(&Texture, Vec2, Vec2)> {
+        // Try parent tiles up to 4 zoom levels back
+        let (mut x, mut y, mut z) = (coord.x, coord.y, coord.z);
+        for _ in 0..4 {
+            if z == 0 {
+                break;
+            }
+
+            // Move to parent coordinates
+            x /= 2;
+            y /= 2;
+            z -= 1;
+
+            if let Some(texture) = self.get_tile(&TileCoord { x, y, z }) {
+                // Calculate which portion of the parent tile our tile occupies
+                let zoom_diff = coord.z - z;
+                let scale = 1.0 / (1 << zoom_diff) as f32;
+                let offset_x = ((coord.x % (1 << zoom_diff)) as f32) * scale;
+                let offset_y = ((coord.y % (1 << zoom_diff)) as f32) * scale;
+                return Some((texture, Vec2 { x: offset_x, y: offset_y }, Vec2 { x: scale, y: scale }));
+            }
+        }
+        None
+    }
+
     /// Handle HTTP response for tile loading
     pub fn handle_response(&mut self, cx: &mut Cx, request_id: LiveId, response: &HttpResponse) -> bool {
         if let Some(coord) = self.pending_requests.remove(&request_id) {
+            if let Some(sent) = self.request_sent.remove(&request_id) {
+                self.record_latency(sent);
+            }
+            if let Some(body) = &response.body {
+                self.bytes_downloaded += body.len() as u64;
+            }
+            let throttled = self.throttle_if_needed(response);
+            if self.disk_only_requests.remove(&request_id) {
+                // Downloads are retried once the cooldown ends
+                if throttled {
+                    self.download_queue.push_front(coord);
+                    return false;
+                }
+                let stored = self.handle_disk_only_response(cx, coord, response);
+                if stored {
+                    self.download_progress.completed += 1;
+                } else {
+                    self.download_progress.failed += 1;
+                }
+                self.resume_downloads(cx);
+                return stored;
+            }
+            // A failed revalidation keeps the cached tile
+            let revalidation = self.revalidations.remove(&request_id);
+            if revalidation && response.status_code != 200 {
+                if response.status_code == 304 {
+                    self.not_modified += 1;
+                    self.disk_worker.submit(cx, &self.disk_config, DiskJob::Refresh {
+                        namespace: self.cache_namespace.clone(),
+                        coord,
+                    });
+                } else if !throttled && (400..500).contains(&response.status_code) {
+                    self.guard_client_error();
+                }
+                return false;
+            }
             if response.status_code == 200 {
                 if let Some(body) = &response.body {
-                    // Try to decode the PNG first (validates it's a real PNG)
-                    match ImageBuffer::from_png(body) {
+                    // Try to decode the image first (validates it's a real PNG/JPEG)
+                    match self.decode_timed(body) {
                         Ok(buffer) => {
                             // Save to disk cache only after successful decode
-                            disk_cache::save_tile(&coord, body);
-
-                            // Periodically check cache size (every 100 tiles saved)
-                            if self.request_counter.is_multiple_of(100) {
-                                disk_cache::evict_if_needed();
-                            }
-
-                            let texture: Texture = buffer.into_new_texture(cx);
-                            self.tiles.insert(coord, TileState::Loaded(texture));
+                            self.save_to_disk(cx, coord, body, TileValidators::from_response(response));
+                            self.upload_tile(cx, coord, buffer);
                             return true;
                         }
-                        Err(e) => {
-                            self.tiles.insert(coord, TileState::Error(format!("PNG decode error: {:?}", e)));
+                        Err(e) if !revalidation => {
+                            self.fail_tile(coord, format!("Image decode error: {:?}", e));
                         }
+                        Err(_) => (),
                     }
                 } else {
-                    self.tiles.insert(coord, TileState::Error("Empty response body".to_string()));
+                    self.fail_tile(coord, "Empty response body".to_string());
                 }
+            } else if throttled {
+                // Requested again on a draw after the cooldown
+                self.tiles.remove(&coord);
+                self.generation += 1;
+                self.content_generation += 1;
             } else {
-                self.tiles.insert(coord, TileState::Error(format!("HTTP {}", response.status_code)));
+                if (400..500).contains(&response.status_code) {
+                    self.guard_client_error();
+                }
+                self.fail_tile(coord, format!("HTTP {}", response.status_code));
             }
         }
         false
     }
 
+    /// Save the response of a disk-only download, returns true if the tile is being stored
+    fn handle_disk_only_response(&mut self, cx: &mut Cx, coord: TileCoord, response: &HttpResponse) -> bool {
+        if response.status_code != 200 {
+            if (400..500).contains(&response.status_code) {
+                self.guard_client_error();
+            }
+            return false;
+        }
+        match &response.body {
+            Some(body) if self.decode_timed(body).is_ok() => {
+                self.save_to_disk(cx, coord, body, TileValidators::from_response(response));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Queue a downloaded tile for the disk cache, evicting old tiles every 100 requests
+    fn save_to_disk(&mut self, cx: &mut Cx, coord: TileCoord, data: &[u8], validators: TileValidators) {
+        if !self.disk_config.enabled {
+            return;
+        }
+        self.disk_worker.submit(cx, &self.disk_config, DiskJob::Save {
+            namespace: self.cache_namespace.clone(),
+            coord,
+            data: data.to_vec(),
+            validators,
+        });
+        self.disk_writes.insert(coord);
+        self.not_on_disk.remove(&coord);
+        if self.request_counter.is_multiple_of(100) {
+            self.disk_worker.submit(cx, &self.disk_config, DiskJob::Evict);
+        }
+    }
+
+    /// Pick up finished disk reads and writes (call on `Event::Signal`).
+    /// Returns true if tiles were loaded and the map needs to redraw.
+    pub fn handle_disk_results(&mut self, cx: &mut Cx) -> bool {
+        let mut redraw = false;
+        while let Some(result) = self.disk_worker.poll() {
+            match result {
+                DiskResult::Loaded { namespace, coord, image, decode_time, stale } => {
+                    // Reads for a previous source are stale
+                    if namespace != self.cache_namespace {
+                        continue;
+                    }
+                    if image.is_some() {
+                        self.record_decode(decode_time);
+                    }
+                    if !self.disk_reads.remove(&coord) {
+                        continue;
+                    }
+                    match image {
+                        Some(image) => {
+                            self.disk_hits += 1;
+                            self.upload_tile(cx, coord, image);
+                            if let Some(validators) = stale {
+                                self.revalidate(cx, coord, &validators);
+                            }
+                        }
+                        None => {
+                            // Missing or corrupted, request it from the network on the next draw
+                            self.tiles.remove(&coord);
+                            self.not_on_disk.insert(coord);
+                            self.generation += 1;
+                            self.content_generation += 1;
+                        }
+                    }
+                    redraw = true;
+                }
+                DiskResult::FileRead { path, coord, image, decode_time } => {
+                    // Reads for a previous source are stale
+                    let current = matches!(&self.source, TileSource::Files(template) if coord.tile_url(template) == path);
+                    if !current || !self.disk_reads.remove(&coord) {
+                        continue;
+                    }
+                    match image {
+                        Ok(image) => {
+                            self.record_decode(decode_time);
+                            self.upload_tile(cx, coord, image);
+                        }
+                        Err(message) => self.fail_tile(coord, message),
+                    }
+                    redraw = true;
+                }
+                DiskResult::Exists { namespace, coord, cached } => {
+                    // Checks for a previous source or cancelled downloads are stale
+                    if namespace != self.cache_namespace || !self.disk_checks.remove(&coord) {
+                        continue;
+                    }
+                    if cached {
+                        self.download_progress.completed += 1;
+                    } else {
+                        self.not_on_disk.insert(coord);
+                        self.download_queue.push_front(coord);
+                        self.resume_downloads(cx);
+                    }
+                }
+                DiskResult::Saved { coord, bytes } => {
+                    self.disk_writes.remove(&coord);
+                    self.disk_bytes += bytes.unwrap_or(0);
+                }
+                DiskResult::Size(bytes) => {
+                    self.disk_bytes = bytes;
+                }
+            }
+        }
+        redraw
+    }
+
     /// Handle HTTP error
     pub fn handle_error(&mut self, request_id: LiveId, error: &HttpError) {
         if let Some(coord) = self.pending_requests.remove(&request_id) {
-            self.tiles.insert(coord, TileState::Error(format!("{:?}", error)));
+            self.request_sent.remove(&request_id);
+            if self.disk_only_requests.remove(&request_id) {
+                self.download_progress.failed += 1;
+                return;
+            }
+            if self.revalidations.remove(&request_id) {
+                return;
+            }
+            self.fail_tile(coord, format!("{:?}", error));
         }
     }
 
     /// Clear all cached tiles (memory and disk)
     pub fn clear(&mut self) {
         self.tiles.clear();
+        self.generation += 1;
+        self.content_generation += 1;
+        self.usage.clear();
         self.pending_requests.clear();
-        disk_cache::clear_cache();
+        self.request_sent.clear();
+        self.ready_responses.clear();
+        self.disk_only_requests.clear();
+        self.revalidations.clear();
+        self.disk_reads.clear();
+        self.not_on_disk.clear();
+        self.disk_checks.clear();
+        self.download_queue.clear();
+        self.download_progress = DownloadProgress::default();
+        if !self.disk_worker.submit_if_running(DiskJob::Clear) {
+            disk_cache::clear_cache(&self.disk_config);
+            self.disk_bytes = 0;
+        }
+    }
+}
+
+/// Decode tile image data, JPEG (common for WMS and aerial imagery) or PNG
+pub(crate) fn decode_tile(data: &[u8]) -> Result<ImageBuffer, ImageError> {
+    if data.starts_with(&[0xff, 0xd8]) {
+        ImageBuffer::from_jpg(data)
+    } else {
+        ImageBuffer::from_png(data)
+    }
+}
+
+/// Halve an image in both dimensions by averaging 2x2 pixel blocks (packed 8-bit channels)
+fn downsample_half(buffer: &mut ImageBuffer) {
+    let width = buffer.width / 2;
+    let height = buffer.height / 2;
+    let mut data = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * 2 * buffer.width + x * 2;
+            let pixels = [
+                buffer.data[i],
+                buffer.data[i + 1],
+                buffer.data[i + buffer.width],
+                buffer.data[i + buffer.width + 1],
+            ];
+            let mut out = 0u32;
+            for shift in [0, 8, 16, 24] {
+                let sum: u32 = pixels.iter().map(|p| (p >> shift) & 0xff).sum();
+                out |= (sum / 4) << shift;
+            }
+            data.push(out);
+        }
     }
+    buffer.width = width;
+    buffer.height = height;
+    buffer.data = data;
 }
@@ -0,0 +1,268 @@
+use makepad_widgets::*;
+
+use crate::layers::{DrawMapCircle, MapLayer, MapViewport};
+use crate::map_view::DrawMapLine;
+use crate::track::TrackPoint;
+
+live_design! {
+    link widgets;
+    use link::theme::*;
+    use link::shaders::*;
+    use link::widgets::*;
+
+    // Time-stamped points and tracks, showing the current time window
+    pub TimelineLayer = {{TimelineLayer}} {
+        draw_line: {
+            line_width: 2.5
+        }
+        draw_point: {
+            border_color: #ffffff
+            border_width: 1.5
+        }
+    }
+}
+
+/// Playback clock over a time range in seconds, e.g. a day of fleet history.
+///
+/// `GeoMapView::set_timeline` plays it: every frame the map advances the time
+/// by the elapsed real time times `speed`, seeks the layers to it and reports
+/// it with `TimeChanged`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Timeline {
+    start: f64,
+    end: f64,
+    time: f64,
+    /// Timeline seconds per real second
+    speed: f64,
+    playing: bool,
+    looping: bool,
+    // Frame time of the last advance while playing
+    last_frame: Option<f64>,
+}
+
+impl Timeline {
+    /// A paused timeline at `start`
+    pub fn new(start: f64, end: f64) -> Self {
+        Self {
+            start: start.min(end),
+            end: end.max(start),
+            time: start.min(end),
+            speed: 1.0,
+            playing: false,
+            looping: false,
+            last_frame: None,
+        }
+    }
+
+    /// Start playing, from the start if the end was reached
+    pub fn play(&mut self) {
+        if self.time >= self.end && self.speed > 0.0 {
+            self.time = self.start;
+        }
+        self.playing = true;
+        self.last_frame = None;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Jump to a time, clamped to the range
+    pub fn seek(&mut self, time: f64) {
+        self.time = time.clamp(self.start, self.end);
+    }
+
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Playback speed in timeline seconds per real second, e.g. 60 to replay
+    /// an hour in a minute. Negative speeds play backwards.
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed;
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Start over instead of pausing at the end
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    /// The range as (start, end)
+    pub fn range(&self) -> (f64, f64) {
+        (self.start, self.end)
+    }
+
+    /// Fraction of the range played, for a slider
+    pub fn progress(&self) -> f64 {
+        if self.end > self.start {
+            (self.time - self.start) / (self.end - self.start)
+        } else {
+            1.0
+        }
+    }
+
+    /// Advance by the real time since the previous frame. Returns true if the time changed.
+    pub(crate) fn advance(&mut self, frame_time: f64) -> bool {
+        if !self.playing {
+            return false;
+        }
+        let Some(last_frame) = self.last_frame.replace(frame_time) else {
+            return false;
+        };
+        let before = self.time;
+        let time = self.time + (frame_time - last_frame) * self.speed;
+        let length = self.end - self.start;
+        if self.looping && length > 0.0 {
+            self.time = self.start + (time - self.start).rem_euclid(length);
+        } else {
+            self.time = time.clamp(self.start, self.end);
+            if self.time != time {
+                self.playing = false;
+            }
+        }
+        self.time != before
+    }
+}
+
+/// A time-stamped point (one position) or track (several positions) of a
+/// `TimelineLayer`, e.g. the recorded history of one vehicle
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimedFeature {
+    pub id: LiveId,
+    /// Positions sorted by time
+    pub points: Vec<TrackPoint>,
+    pub color: Vec4,
+}
+
+/// Shows time-stamped points and tracks as of the time the map was seeked
+/// to (see `GeoMapView::seek` and `GeoMapView::set_timeline`).
+///
+/// Only positions within the last `window` seconds are shown (0 shows all
+/// history up to the time). Tracks end at a dot at their position at the
+/// current time, interpolated between fixes so playback moves smoothly.
+/// Before the first seek everything is shown.
+#[derive(Live, LiveHook, LiveRegister)]
+pub struct TimelineLayer {
+    #[live] draw_line: DrawMapLine,
+    #[live] draw_point: DrawMapCircle,
+    /// Seconds of history shown before the current time, 0 for all
+    #[live(0.0)] pub window: f64,
+    #[live(5.0)] pub point_radius: f64,
+    #[live(true)] pub visible: bool,
+    #[rust] features: Vec<TimedFeature>,
+    #[rust] time: Option<f64>,
+    #[rust(1.0)] opacity: f64,
+}
+
+/// What of a feature is shown at the current time
+struct VisibleFeature {
+    /// Positions as (lng, lat), ending at the current position
+    path: Vec<(f64, f64)>,
+    color: Vec4,
+}
+
+impl TimelineLayer {
+    /// Replace the features; each feature's points are sorted by time
+    pub fn set_features(&mut self, mut features: Vec<TimedFeature>) {
+        for feature in &mut features {
+            feature.points.sort_by(|a, b| a.time.total_cmp(&b.time));
+        }
+        self.features = features;
+    }
+
+    pub fn features(&self) -> &[TimedFeature] {
+        &self.features
+    }
+
+    /// Earliest and latest time of all features, e.g. for `Timeline::new`
+    pub fn time_range(&self) -> Option<(f64, f64)> {
+        let times = self.features.iter().flat_map(|feature| feature.points.iter().map(|p| p.time));
+        times.fold(None, |range, time| match range {
+            None => Some((time, time)),
+            Some((start, end)) => Some((f64::min(start, time), f64::max(end, time))),
+        })
+    }
+
+    /// Ids of the features shown at the current time
+    pub fn visible_features(&self) -> Vec<LiveId> {
+        self.features.iter()
+            .filter(|feature| self.visible_part(feature).is_some())
+            .map(|feature| feature.id)
+            .collect()
+    }
+
+    /// Part of a feature within the time window
+    fn visible_part(&self, feature: &TimedFeature) -> Option<VisibleFeature> {
+        let Some(time) = self.time else {
+            let path: Vec<_> = feature.points.iter().map(|p| (p.lng, p.lat)).collect();
+            return (!path.is_empty()).then_some(VisibleFeature { path, color: feature.color });
+        };
+        let from = if self.window > 0.0 { time - self.window } else { f64::NEG_INFINITY };
+        let first = feature.points.partition_point(|p| p.time < from);
+        let last = feature.points.partition_point(|p| p.time <= time);
+        let mut path: Vec<(f64, f64)> = feature.points[first..last].iter().map(|p| (p.lng, p.lat)).collect();
+        // Current position between the last fix and the next one
+        if let (Some(before), Some(after)) = (last.checked_sub(1).map(|i| feature.points[i]), feature.points.get(last)) {
+            if after.time > before.time {
+                let t = (time - before.time) / (after.time - before.time);
+                let lng = before.lng + ((after.lng - before.lng + 180.0).rem_euclid(360.0) - 180.0) * t;
+                path.push((lng, before.lat + (after.lat - before.lat) * t));
+            }
+        }
+        (!path.is_empty()).then_some(VisibleFeature { path, color: feature.color })
+    }
+
+    fn faded(&self, mut color: Vec4) -> Vec4 {
+        color.w *= self.opacity as f32;
+        color
+    }
+}
+
+impl MapLayer for TimelineLayer {
+    fn draw_layer(&mut self, cx: &mut Cx2d, viewport: &MapViewport) {
+        let visible: Vec<VisibleFeature> = self.features.iter().filter_map(|feature| self.visible_part(feature)).collect();
+        let border_color = self.draw_point.border_color;
+        self.draw_point.border_color = self.faded(border_color);
+        for feature in visible {
+            let points: Vec<DVec2> = feature.path.iter().map(|&(lng, lat)| viewport.geo_to_screen(lng, lat)).collect();
+            self.draw_line.color = self.faded(feature.color);
+            for pair in points.windows(2) {
+                let (from, to) = (pair[0], pair[1]);
+                if from != to && viewport.is_visible((from + to) / 2.0, (to - from).length() / 2.0) {
+                    self.draw_line.draw_line(cx, from, to);
+                }
+            }
+            if let Some(&head) = points.last() {
+                if viewport.is_visible(head, self.point_radius) {
+                    self.draw_point.color = self.faded(feature.color);
+                    self.draw_point.draw_circle(cx, head, self.point_radius);
+                }
+            }
+        }
+        self.draw_point.border_color = border_color;
+    }
+
+    fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    fn set_opacity(&mut self, opacity: f64) {
+        self.opacity = opacity;
+    }
+
+    fn seek(&mut self, time: f64) {
+        self.time = Some(time);
+    }
+}
@@ -0,0 +1,79 @@
+use crate::projection::Projection;
+
+/// Camera position at a point of a tour
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TourKeyframe {
+    /// Timeline time in seconds
+    pub time: f64,
+    pub lng: f64,
+    pub lat: f64,
+    pub zoom: f64,
+    /// Degrees clockwise from north
+    pub bearing: f64,
+}
+
+/// A camera path over a timeline, sampled with `seek` semantics.
+///
+/// The camera at any time is a pure function of that time, so an app can
+/// drive it from an external clock (e.g. video playback or telemetry replay)
+/// and get the same frame for the same time, including when scrubbing backwards.
+#[derive(Clone, Debug, Default)]
+pub struct CameraTour {
+    keyframes: Vec<TourKeyframe>,
+}
+
+impl CameraTour {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a keyframe, keeping keyframes sorted by time
+    pub fn add_keyframe(&mut self, keyframe: TourKeyframe) {
+        let index = self.keyframes.partition_point(|k| k.time <= keyframe.time);
+        self.keyframes.insert(index, keyframe);
+    }
+
+    pub fn keyframes(&self) -> &[TourKeyframe] {
+        &self.keyframes
+    }
+
+    /// Time span covered by the keyframes as (start, end)
+    pub fn time_range(&self) -> Option<(f64, f64)> {
+        Some((self.keyframes.first()?.time, self.keyframes.last()?.time))
+    }
+
+    /// Camera at `time`, clamped to the first and last keyframe. Positions are
+    /// interpolated in projected space (the short way around the antimeridian),
+    /// zoom linearly and bearing along the shorter arc.
+    pub fn camera_at(&self, time: f64, projection: &dyn Projection) -> Option<TourKeyframe> {
+        let first = self.keyframes.first()?;
+        let last = self.keyframes.last()?;
+        if time <= first.time {
+            return Some(TourKeyframe { time, ..*first });
+        }
+        if time >= last.time {
+            return Some(TourKeyframe { time, ..*last });
+        }
+
+        let index = self.keyframes.partition_point(|k| k.time <= time);
+        let from = &self.keyframes[index - 1];
+        let to = &self.keyframes[index];
+        let span = to.time - from.time;
+        let t = if span > 0.0 { (time - from.time) / span } else { 1.0 };
+
+        let to_lng = from.lng + (to.lng - from.lng + 180.0).rem_euclid(360.0) - 180.0;
+        let from_unit = projection.project(from.lng, from.lat);
+        let to_unit = projection.project(to_lng, to.lat);
+        let (lng, lat) = projection.unproject(from_unit + (to_unit - from_unit) * t);
+
+        let bearing_delta = (to.bearing - from.bearing + 180.0).rem_euclid(360.0) - 180.0;
+
+        Some(TourKeyframe {
+            time,
+            lng,
+            lat,
+            zoom: from.zoom + (to.zoom - from.zoom) * t,
+            bearing: (from.bearing + bearing_delta * t).rem_euclid(360.0),
+        })
+    }
+}
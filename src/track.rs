@@ -0,0 +1,226 @@
+use makepad_widgets::*;
+use std::collections::VecDeque;
+
+use crate::layers::{DrawMapCircle, MapLayer, MapViewport};
+use crate::map_view::DrawMapLine;
+use crate::quality::QualityLevel;
+
+live_design! {
+    link widgets;
+    use link::theme::*;
+    use link::shaders::*;
+    use link::widgets::*;
+
+    // Breadcrumb trail of a moving object with a dot at its latest position
+    pub TrackLayer = {{TrackLayer}} {
+        draw_line: {
+            color: #e53935
+            line_width: 3.0
+        }
+        draw_head: {
+            color: #e53935
+            border_color: #ffffff
+            border_width: 2.0
+        }
+    }
+}
+
+/// A position of a track at a time in seconds (e.g. a GPS fix's timestamp)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TrackPoint {
+    pub lng: f64,
+    pub lat: f64,
+    pub time: f64,
+}
+
+/// Live track of a moving object, e.g. a vehicle reporting GPS fixes at 1 Hz.
+///
+/// Points are appended with `push_point` and projected once when first drawn,
+/// so long tracks don't cost more per new point. `max_points` and `max_age`
+/// trim the tail; with `follow` the map keeps the newest point centered until
+/// the user pans.
+#[derive(Live, LiveHook, LiveRegister)]
+pub struct TrackLayer {
+    #[live] draw_line: DrawMapLine,
+    #[live] draw_head: DrawMapCircle,
+    /// Oldest points are dropped beyond this many, 0 keeps all
+    #[live(0)] pub max_points: usize,
+    /// Points older than this many seconds (relative to the newest) are dropped, 0 keeps all
+    #[live(0.0)] pub max_age: f64,
+    /// Keep the newest point centered
+    #[live(false)] pub follow: bool,
+    #[live(6.0)] pub head_radius: f64,
+    #[live(true)] pub visible: bool,
+    #[rust] points: VecDeque<TrackPoint>,
+    // Unit positions of the first points, for the projection with this code
+    #[rust] projected: VecDeque<DVec2>,
+    #[rust] projection_code: String,
+    // Timeline time from `seek`: only points up to it are shown
+    #[rust] seek_time: Option<f64>,
+    #[rust] quality: QualityLevel,
+    #[rust(1.0)] opacity: f64,
+}
+
+impl TrackLayer {
+    /// Append a position. Times should increase; the track is trimmed to
+    /// `max_points` and `max_age` afterwards.
+    pub fn push_point(&mut self, lng: f64, lat: f64, time: f64) {
+        self.points.push_back(TrackPoint { lng, lat, time });
+        self.trim();
+    }
+
+    /// Append several positions at once (e.g. history loaded on startup)
+    pub fn extend_points(&mut self, points: impl IntoIterator<Item = TrackPoint>) {
+        self.points.extend(points);
+        self.trim();
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+        self.projected.clear();
+    }
+
+    pub fn points(&self) -> &VecDeque<TrackPoint> {
+        &self.points
+    }
+
+    /// Newest position shown (the latest point, or the latest up to the seeked time)
+    pub fn head(&self) -> Option<TrackPoint> {
+        self.points.get(self.shown_count().checked_sub(1)?).copied()
+    }
+
+    /// Start or stop keeping the newest point centered
+    pub fn set_follow(&mut self, follow: bool) {
+        self.follow = follow;
+    }
+
+    fn trim(&mut self) {
+        let mut excess = self.points.len().saturating_sub(if self.max_points > 0 { self.max_points } else { usize::MAX });
+        if self.max_age > 0.0 {
+            if let Some(newest) = self.points.back().map(|p| p.time) {
+                let old = self.points.iter().take_while(|p| newest - p.time > self.max_age).count();
+                excess = excess.max(old);
+            }
+        }
+        for _ in 0..excess {
+            self.points.pop_front();
+            self.projected.pop_front();
+        }
+    }
+
+    /// Number of points up to the seeked time (all without a seek)
+    fn shown_count(&self) -> usize {
+        match self.seek_time {
+            Some(time) => self.points.partition_point(|p| p.time <= time),
+            None => self.points.len(),
+        }
+    }
+
+    /// Project the points appended since the last draw
+    fn update_projection(&mut self, viewport: &MapViewport) {
+        let projection = viewport.projection.as_ref();
+        if self.projection_code != projection.code() {
+            self.projection_code = projection.code().to_string();
+            self.projected.clear();
+        }
+        for point in self.points.range(self.projected.len()..) {
+            self.projected.push_back(projection.project(point.lng, point.lat));
+        }
+    }
+
+    /// Screen positions of the shown points, each placed next to the previous
+    /// one so the trail stays in one piece across the antimeridian. Points
+    /// closer than `min_spacing` pixels to the last kept one are skipped,
+    /// except the head.
+    fn screen_points(&self, viewport: &MapViewport, min_spacing: f64) -> Vec<DVec2> {
+        let count = self.shown_count().min(self.projected.len());
+        let wraps = viewport.projection.wraps_longitude();
+        let mut out: Vec<DVec2> = Vec::new();
+        // First point and the previous point with continuous x
+        let mut previous: Option<(DVec2, DVec2)> = None;
+        for (i, &unit) in self.projected.range(..count).enumerate() {
+            let (first, unit) = match previous {
+                None => (unit, unit),
+                Some((first, prev_unit)) => {
+                    let mut delta = unit - prev_unit;
+                    if wraps {
+                        delta.x -= delta.x.round();
+                    }
+                    (first, prev_unit + delta)
+                }
+            };
+            previous = Some((first, unit));
+            let pos = viewport.unit_to_screen_near(unit, first);
+            let is_head = i + 1 == count;
+            if is_head || out.last().is_none_or(|last| (pos - *last).length() >= min_spacing) {
+                out.push(pos);
+            }
+        }
+        out
+    }
+
+    fn faded(&self, color: Vec4) -> Vec4 {
+        vec4(color.x, color.y, color.z, color.w * self.opacity as f32)
+    }
+}
+
+impl MapLayer for TrackLayer {
+    fn draw_layer(&mut self, cx: &mut Cx2d, viewport: &MapViewport) {
+        self.update_projection(viewport);
+        // Coarser trails when frames are slow
+        let spacing = if self.quality >= QualityLevel::CoarseOverlays { 4.0 } else { 1.0 };
+        let points = self.screen_points(viewport, spacing);
+        let colors = (self.draw_line.color, self.draw_head.color, self.draw_head.border_color);
+        self.draw_line.color = self.faded(colors.0);
+        self.draw_head.color = self.faded(colors.1);
+        self.draw_head.border_color = self.faded(colors.2);
+
+        for pair in points.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            let length = (to - from).length();
+            if viewport.is_visible((from + to) / 2.0, length / 2.0 + self.draw_line.line_width as f64) {
+                self.draw_line.draw_line(cx, from, to);
+            }
+        }
+        if let Some(&head) = points.last() {
+            if viewport.is_visible(head, self.head_radius) {
+                self.draw_head.draw_circle(cx, head, self.head_radius);
+            }
+        }
+
+        self.draw_line.color = colors.0;
+        self.draw_head.color = colors.1;
+        self.draw_head.border_color = colors.2;
+    }
+
+    fn follow_position(&self) -> Option<(f64, f64)> {
+        if !self.follow {
+            return None;
+        }
+        self.head().map(|head| (head.lng, head.lat))
+    }
+
+    fn stop_following(&mut self) {
+        self.follow = false;
+    }
+
+    fn set_quality(&mut self, level: QualityLevel) {
+        self.quality = level;
+    }
+
+    fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    fn set_opacity(&mut self, opacity: f64) {
+        self.opacity = opacity;
+    }
+
+    fn seek(&mut self, time: f64) {
+        self.seek_time = Some(time);
+    }
+}
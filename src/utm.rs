@@ -0,0 +1,272 @@
+//! UTM and MGRS coordinate conversions (WGS84)
+//!
+//! Based on the classic transverse Mercator series (Snyder, "Map Projections -
+//! A Working Manual"), accurate to well under a meter within a UTM zone.
+
+/// WGS84 semi-major axis in meters
+const WGS84_A: f64 = 6378137.0;
+/// WGS84 flattening
+const WGS84_F: f64 = 1.0 / 298.257223563;
+/// UTM scale factor on the central meridian
+const UTM_K0: f64 = 0.9996;
+/// False easting applied to all zones
+const FALSE_EASTING: f64 = 500000.0;
+/// False northing applied in the southern hemisphere
+const FALSE_NORTHING_SOUTH: f64 = 10000000.0;
+
+/// Latitude band letters from 80°S to 84°N (8° bands, X is 12°)
+const LAT_BANDS: &[u8] = b"CDEFGHJKLMNPQRSTUVWX";
+
+/// MGRS column letters, repeating every three zones
+const MGRS_COL_SETS: [&[u8]; 3] = [b"ABCDEFGH", b"JKLMNPQR", b"STUVWXYZ"];
+/// MGRS row letters, repeating every 2000km of northing
+const MGRS_ROW_LETTERS: &[u8] = b"ABCDEFGHJKLMNPQRSTUV";
+
+/// Lowest northing (in 100km) found in each latitude band, to place an MGRS
+/// row letter in the right 2000km cycle
+const BAND_MIN_NORTHING: [u8; 20] = [11, 20, 28, 37, 46, 55, 64, 73, 82, 91, 0, 8, 17, 26, 35, 44, 53, 62, 70, 79];
+
+/// A position in Universal Transverse Mercator coordinates
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UtmCoord {
+    pub zone: u8,
+    pub band: char,
+    pub easting: f64,
+    pub northing: f64,
+    pub north: bool,
+}
+
+impl std::fmt::Display for UtmCoord {
+    /// Zone, band, easting and northing in whole meters, e.g. "10S 551079 4181098"
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{} {:.0} {:.0}", self.zone, self.band, self.easting.floor(), self.northing.floor())
+    }
+}
+
+/// UTM zone number for a position, including the Norway and Svalbard exceptions
+pub fn utm_zone(lng: f64, lat: f64) -> u8 {
+    let lng = wrap_lng(lng);
+
+    // Southwest Norway
+    if (56.0..64.0).contains(&lat) && (3.0..12.0).contains(&lng) {
+        return 32;
+    }
+
+    // Svalbard
+    if (72.0..84.0).contains(&lat) {
+        if (0.0..9.0).contains(&lng) {
+            return 31;
+        } else if (9.0..21.0).contains(&lng) {
+            return 33;
+        } else if (21.0..33.0).contains(&lng) {
+            return 35;
+        } else if (33.0..42.0).contains(&lng) {
+            return 37;
+        }
+    }
+
+    (((lng + 180.0) / 6.0).floor() as i32).clamp(0, 59) as u8 + 1
+}
+
+/// Latitude band letter for a latitude (None outside the UTM range of 80°S to 84°N)
+pub fn utm_band(lat: f64) -> Option<char> {
+    if !(-80.0..=84.0).contains(&lat) {
+        return None;
+    }
+    let index = (((lat + 80.0) / 8.0).floor() as usize).min(LAT_BANDS.len() - 1);
+    Some(LAT_BANDS[index] as char)
+}
+
+/// Longitude of the central meridian of a zone
+pub fn zone_central_meridian(zone: u8) -> f64 {
+    zone as f64 * 6.0 - 183.0
+}
+
+/// Convert geographic coordinates to UTM in the position's own zone
+pub fn to_utm(lng: f64, lat: f64) -> Option<UtmCoord> {
+    to_utm_in_zone(lng, lat, utm_zone(lng, lat))
+}
+
+/// Convert geographic coordinates to UTM in a specific zone (useful for drawing
+/// a continuous grid slightly beyond the zone boundary)
+pub fn to_utm_in_zone(lng: f64, lat: f64, zone: u8) -> Option<UtmCoord> {
+    let band = utm_band(lat)?;
+
+    let e2 = WGS84_F * (2.0 - WGS84_F);
+    let ep2 = e2 / (1.0 - e2);
+
+    let phi = lat.to_radians();
+    let dlambda = wrap_lng(lng - zone_central_meridian(zone)).to_radians();
+
+    let sin_phi = phi.sin();
+    let cos_phi = phi.cos();
+    let tan_phi = phi.tan();
+
+    let n = WGS84_A / (1.0 - e2 * sin_phi * sin_phi).sqrt();
+    let t = tan_phi * tan_phi;
+    let c = ep2 * cos_phi * cos_phi;
+    let a = cos_phi * dlambda;
+    let m = meridian_arc(phi, e2);
+
+    let easting = FALSE_EASTING
+        + UTM_K0 * n * (a
+            + (1.0 - t + c) * a.powi(3) / 6.0
+            + (5.0 - 18.0 * t + t * t + 72.0 * c - 58.0 * ep2) * a.powi(5) / 120.0);
+
+    let mut northing = UTM_K0 * (m + n * tan_phi * (a * a / 2.0
+        + (5.0 - t + 9.0 * c + 4.0 * c * c) * a.powi(4) / 24.0
+        + (61.0 - 58.0 * t + t * t + 600.0 * c - 330.0 * ep2) * a.powi(6) / 720.0));
+
+    let north = lat >= 0.0;
+    if !north {
+        northing += FALSE_NORTHING_SOUTH;
+    }
+
+    Some(UtmCoord { zone, band, easting, northing, north })
+}
+
+/// Convert UTM coordinates to geographic coordinates, returns (lng, lat)
+pub fn from_utm(zone: u8, north: bool, easting: f64, northing: f64) -> (f64, f64) {
+    let e2 = WGS84_F * (2.0 - WGS84_F);
+    let ep2 = e2 / (1.0 - e2);
+    let e1 = (1.0 - (1.0 - e2).sqrt()) / (1.0 + (1.0 - e2).sqrt());
+
+    let x = easting - FALSE_EASTING;
+    let y = if north { northing } else { northing - FALSE_NORTHING_SOUTH };
+
+    // Footpoint latitude
+    let m = y / UTM_K0;
+    let mu = m / (WGS84_A * (1.0 - e2 / 4.0 - 3.0 * e2 * e2 / 64.0 - 5.0 * e2.powi(3) / 256.0));
+    let phi1 = mu
+        + (3.0 * e1 / 2.0 - 27.0 * e1.powi(3) / 32.0) * (2.0 * mu).sin()
+        + (21.0 * e1 * e1 / 16.0 - 55.0 * e1.powi(4) / 32.0) * (4.0 * mu).sin()
+        + (151.0 * e1.powi(3) / 96.0) * (6.0 * mu).sin()
+        + (1097.0 * e1.powi(4) / 512.0) * (8.0 * mu).sin();
+
+    let sin_phi1 = phi1.sin();
+    let cos_phi1 = phi1.cos();
+    let tan_phi1 = phi1.tan();
+
+    let n1 = WGS84_A / (1.0 - e2 * sin_phi1 * sin_phi1).sqrt();
+    let t1 = tan_phi1 * tan_phi1;
+    let c1 = ep2 * cos_phi1 * cos_phi1;
+    let r1 = WGS84_A * (1.0 - e2) / (1.0 - e2 * sin_phi1 * sin_phi1).powf(1.5);
+    let d = x / (n1 * UTM_K0);
+
+    let lat = phi1 - (n1 * tan_phi1 / r1) * (d * d / 2.0
+        - (5.0 + 3.0 * t1 + 10.0 * c1 - 4.0 * c1 * c1 - 9.0 * ep2) * d.powi(4) / 24.0
+        + (61.0 + 90.0 * t1 + 298.0 * c1 + 45.0 * t1 * t1 - 252.0 * ep2 - 3.0 * c1 * c1) * d.powi(6) / 720.0);
+
+    let lng = (d
+        - (1.0 + 2.0 * t1 + c1) * d.powi(3) / 6.0
+        + (5.0 - 2.0 * c1 + 28.0 * t1 - 3.0 * c1 * c1 + 8.0 * ep2 + 24.0 * t1 * t1) * d.powi(5) / 120.0)
+        / cos_phi1;
+
+    (wrap_lng(zone_central_meridian(zone) + lng.to_degrees()), lat.to_degrees())
+}
+
+/// MGRS 100km square identifier (two letters) for a UTM position
+pub fn mgrs_square_id(utm: &UtmCoord) -> String {
+    let col_set = MGRS_COL_SETS[(utm.zone as usize - 1) % 3];
+    let col = ((utm.easting / 100000.0).floor() as usize).clamp(1, 8) - 1;
+
+    let row = ((utm.northing / 100000.0).floor() as usize + mgrs_row_offset(utm.zone)) % MGRS_ROW_LETTERS.len();
+
+    format!("{}{}", col_set[col] as char, MGRS_ROW_LETTERS[row] as char)
+}
+
+/// Even zones start their row lettering at F
+fn mgrs_row_offset(zone: u8) -> usize {
+    if zone.is_multiple_of(2) { 5 } else { 0 }
+}
+
+/// Parse a UTM position like "10S 551079 4181098" (zone and latitude band,
+/// easting, northing in meters), returns (lng, lat)
+pub fn parse_utm(text: &str) -> Option<(f64, f64)> {
+    let mut parts = text.split_whitespace();
+    let (zone, band) = parse_zone_band(parts.next()?)?;
+    let easting: f64 = parts.next()?.parse().ok()?;
+    let northing: f64 = parts.next()?.parse().ok()?;
+    if parts.next().is_some() || !(0.0..1_000_000.0).contains(&easting) || !(0.0..10_000_000.0).contains(&northing) {
+        return None;
+    }
+    Some(from_utm(zone, band >= 'N', easting, northing))
+}
+
+/// Parse an MGRS grid reference like "10SEG 51153 80432" or "10SEG5115380432"
+/// (1 to 5 digits per axis), returns (lng, lat) of the south-west corner of
+/// the referenced square, so `to_mgrs` at the same precision gives it back
+pub fn from_mgrs(text: &str) -> Option<(f64, f64)> {
+    let text: String = text.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_ascii_uppercase();
+    let letters_at = text.find(|c: char| c.is_ascii_alphabetic())?;
+    let (zone, band) = parse_zone_band(text.get(..letters_at + 1)?)?;
+    let rest = text.get(letters_at + 1..)?.as_bytes();
+    let (&col_letter, &row_letter, digits) = match rest {
+        [col, row, digits @ ..] => (col, row, digits),
+        _ => return None,
+    };
+    if digits.len() % 2 != 0 || digits.len() > 10 || !digits.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+
+    let col = MGRS_COL_SETS[(zone as usize - 1) % 3].iter().position(|&c| c == col_letter)?;
+    let row = MGRS_ROW_LETTERS.iter().position(|&c| c == row_letter)?;
+    let precision = digits.len() / 2;
+    let scale = 10_f64.powi(5 - precision as i32);
+    let number = |digits: &[u8]| digits.iter().fold(0.0, |n, d| n * 10.0 + (d - b'0') as f64) * scale;
+
+    let easting = (col + 1) as f64 * 100000.0 + number(&digits[..precision]);
+    let row_100km = (row + MGRS_ROW_LETTERS.len() - mgrs_row_offset(zone)) % MGRS_ROW_LETTERS.len();
+    let mut northing = row_100km as f64 * 100000.0 + number(&digits[precision..]);
+    // Row letters repeat every 2000km; take the cycle that falls in the band
+    let band_index = LAT_BANDS.iter().position(|&b| b as char == band)?;
+    let min_northing = BAND_MIN_NORTHING[band_index] as f64 * 100000.0;
+    while northing < min_northing {
+        northing += 2_000_000.0;
+    }
+    Some(from_utm(zone, band >= 'N', easting, northing))
+}
+
+/// Zone number and latitude band from a designation like "10S"
+fn parse_zone_band(text: &str) -> Option<(u8, char)> {
+    let band = text.chars().last()?.to_ascii_uppercase();
+    let zone: u8 = text[..text.len() - band.len_utf8()].parse().ok()?;
+    if !(1..=60).contains(&zone) || !LAT_BANDS.contains(&(band as u8)) || !band.is_ascii() {
+        return None;
+    }
+    Some((zone, band))
+}
+
+/// Format a position as an MGRS string with the given number of digits per
+/// axis (1 = 10km, 5 = 1m precision), e.g. "10SEG 51153 80432"
+pub fn to_mgrs(lng: f64, lat: f64, digits: usize) -> Option<String> {
+    let utm = to_utm(lng, lat)?;
+    let digits = digits.clamp(1, 5);
+    let divisor = 10_f64.powi(5 - digits as i32);
+    let e = ((utm.easting % 100000.0) / divisor).floor() as u64;
+    let n = ((utm.northing % 100000.0) / divisor).floor() as u64;
+    Some(format!(
+        "{}{}{} {:0width$} {:0width$}",
+        utm.zone,
+        utm.band,
+        mgrs_square_id(&utm),
+        e,
+        n,
+        width = digits
+    ))
+}
+
+/// Meridian arc length from the equator to latitude `phi` (radians)
+fn meridian_arc(phi: f64, e2: f64) -> f64 {
+    let e4 = e2 * e2;
+    let e6 = e4 * e2;
+    WGS84_A * ((1.0 - e2 / 4.0 - 3.0 * e4 / 64.0 - 5.0 * e6 / 256.0) * phi
+        - (3.0 * e2 / 8.0 + 3.0 * e4 / 32.0 + 45.0 * e6 / 1024.0) * (2.0 * phi).sin()
+        + (15.0 * e4 / 256.0 + 45.0 * e6 / 1024.0) * (4.0 * phi).sin()
+        - (35.0 * e6 / 3072.0) * (6.0 * phi).sin())
+}
+
+/// Wrap a longitude into [-180, 180)
+fn wrap_lng(lng: f64) -> f64 {
+    (lng + 180.0).rem_euclid(360.0) - 180.0
+}